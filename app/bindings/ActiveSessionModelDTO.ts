@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ActiveSessionModelDTO = { id: string, user_agent: string | null, ip_address: string | null, expires_at: string, last_seen_at: string, created_at: string, current: boolean, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ApiTokenModelDTO = { id: string, name: string, scope: number, expires_at: string | null, last_used_at: string | null, created_at: string, };
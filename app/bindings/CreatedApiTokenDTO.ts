@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ApiTokenModelDTO } from "./ApiTokenModelDTO";
+
+/**
+ * The token is only shown once, when it is created
+ */
+export type CreatedApiTokenDTO = { api_token: ApiTokenModelDTO, token: string, };
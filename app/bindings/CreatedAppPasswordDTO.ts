@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AppPasswordModelDTO } from "./AppPasswordModelDTO";
+
+/**
+ * The password is only shown once, when it is created
+ */
+export type CreatedAppPasswordDTO = { app_password: AppPasswordModelDTO, password: string, };
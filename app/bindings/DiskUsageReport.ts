@@ -3,4 +3,4 @@ import type { FileModelDTO } from "./FileModelDTO";
 import type { FileTypeSumDataDTO } from "./FileTypeSumDataDTO";
 import type { UsageSumDataDTO } from "./UsageSumDataDTO";
 
-export type DiskUsageReport = { active_storage: UsageSumDataDTO, bin_storage: UsageSumDataDTO, by_file_type: Array<FileTypeSumDataDTO>, large_files: Array<FileModelDTO>, };
+export type DiskUsageReport = { active_storage: UsageSumDataDTO, bin_storage: UsageSumDataDTO, version_storage: UsageSumDataDTO, by_file_type: Array<FileTypeSumDataDTO>, large_files: Array<FileModelDTO>, };
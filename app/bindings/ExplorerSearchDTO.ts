@@ -2,4 +2,8 @@
 import type { FileModelDTO } from "./FileModelDTO";
 import type { FolderModelDTO } from "./FolderModelDTO";
 
-export type ExplorerSearchDTO = { files: Array<FileModelDTO>, folders: Array<FolderModelDTO>, };
+export type ExplorerSearchDTO = { files: Array<FileModelDTO>, folders: Array<FolderModelDTO>, 
+/**
+ * HTML excerpts of the matched content by file id, matches are wrapped in `<mark>`
+ */
+snippets: { [key: string]: string }, };
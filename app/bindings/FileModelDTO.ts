@@ -1,4 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { JsonValue } from "./serde_json/JsonValue";
+import type { TagModelDTO } from "./TagModelDTO";
 
-export type FileModelDTO = { id: string, user_id: string, file_name: string, file_size: number, file_type: number, mime_type: string, metadata: JsonValue | null, parent_folder_id: string | null, preview_status: number | null, favorite: boolean, created_at: string, updated_at: string, deleted_at: string | null, };
+export type FileModelDTO = { id: string, user_id: string, file_name: string, file_size: number, file_type: number, mime_type: string, metadata: JsonValue | null, parent_folder_id: string | null, preview_status: number | null, favorite: boolean, created_at: string, updated_at: string, deleted_at: string | null, 
+/**
+ * Only filled in for the owner, see `TagService::tag_files`
+ */
+tags: Array<TagModelDTO>, 
+/**
+ * Only filled in for files in the bin which are purged automatically
+ */
+days_until_purge: number | null, };
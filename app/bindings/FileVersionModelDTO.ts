@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FileVersionModelDTO = { id: string, file_id: string, file_size: number, mime_type: string, modified_at: string, created_at: string, };
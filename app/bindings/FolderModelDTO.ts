@@ -1,3 +1,12 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TagModelDTO } from "./TagModelDTO";
 
-export type FolderModelDTO = { id: string, user_id: string, folder_name: string, parent_id: string | null, favorite: boolean, color: string | null, created_at: string, updated_at: string, };
+export type FolderModelDTO = { id: string, user_id: string, folder_name: string, parent_id: string | null, favorite: boolean, color: string | null, created_at: string, updated_at: string, deleted_at: string | null, 
+/**
+ * Only filled in for the owner, see `TagService::tag_folders`
+ */
+tags: Array<TagModelDTO>, 
+/**
+ * Only filled in for folders in the bin which are purged automatically
+ */
+days_until_purge: number | null, };
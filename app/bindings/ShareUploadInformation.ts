@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ShareUploadInformation = { folder_name: string, 
+/**
+ * Visitors cannot see the content of the folder
+ */
+write_only: boolean, size_remaining: number | null, count_remaining: number | null, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FileModelDTO } from "./FileModelDTO";
+import type { FolderModelDTO } from "./FolderModelDTO";
+import type { TagModelDTO } from "./TagModelDTO";
+
+export type TaggedItemsResponse = { tag: TagModelDTO, folders: Array<FolderModelDTO>, files: Array<FileModelDTO>, };
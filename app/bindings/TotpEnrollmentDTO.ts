@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Shown once when enrolling, the URI is meant to be displayed as QR code
+ */
+export type TotpEnrollmentDTO = { secret: string, provisioning_uri: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Recovery codes are only shown once, when they are generated
+ */
+export type TotpRecoveryCodesDTO = { recovery_codes: Array<string>, };
@@ -1,3 +1,8 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ZipFileInformation } from "./ZipFileInformation";
 
-export type ZipInformation = { name: string, folders: Array<ZipInformation>, files: Array<string>, };
+export type ZipInformation = { name: string, modified: string | null, folders: Array<ZipInformation>, files: Array<string>, 
+/**
+ * Size and time of each file, in the same order as `files`
+ */
+file_details: Array<ZipFileInformation>, };
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS tus_uploads
+(
+    id               BIGINT PRIMARY KEY,
+    user_id          BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    file_name        TEXT        NOT NULL,
+    mime_type        TEXT        NOT NULL,
+    parent_folder_id BIGINT REFERENCES folder (id) ON DELETE CASCADE,
+
+    upload_length    BIGINT      NOT NULL,
+    upload_offset    BIGINT      NOT NULL DEFAULT 0,
+
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER update_tus_uploads_modtime
+    BEFORE UPDATE
+    ON tus_uploads
+    FOR EACH ROW
+EXECUTE PROCEDURE update_modified_column();
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS blobs
+(
+    hash       TEXT PRIMARY KEY,
+    size       BIGINT      NOT NULL,
+    ref_count  INT         NOT NULL DEFAULT 1,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Files uploaded before deduplication keep their content under their own id
+ALTER TABLE files ADD COLUMN IF NOT EXISTS blob_hash TEXT REFERENCES blobs (hash);
+
+CREATE INDEX IF NOT EXISTS files_blob_hash_index ON files (blob_hash);
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS file_versions
+(
+    id          BIGINT PRIMARY KEY,
+    file_id     BIGINT      NOT NULL REFERENCES files (id) ON DELETE CASCADE,
+    user_id     BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    blob_hash   TEXT        NOT NULL REFERENCES blobs (hash),
+    file_size   BIGINT      NOT NULL,
+    mime_type   TEXT        NOT NULL,
+
+    -- Last time the content of this version was the current content of the file
+    modified_at TIMESTAMPTZ NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS file_versions_file_id_index ON file_versions (file_id);
+
+-- NULL disables the respective limit
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS version_retention_count INT DEFAULT 10,
+    ADD COLUMN IF NOT EXISTS version_retention_days  INT DEFAULT 30;
@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS app_passwords
+(
+    id            BIGINT PRIMARY KEY,
+    user_id       BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    name          TEXT        NOT NULL,
+    -- App passwords are random, so a fast hash is enough to look them up
+    password_hash TEXT        NOT NULL UNIQUE,
+
+    last_used_at  TIMESTAMPTZ,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS dav_locks
+(
+    token          UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id        BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    -- Locks apply to the path of a resource, not to the resource itself
+    path           TEXT        NOT NULL,
+    exclusive      BOOLEAN     NOT NULL,
+    depth_infinity BOOLEAN     NOT NULL,
+    owner          TEXT,
+
+    timeout        INT         NOT NULL,
+    expires_at     TIMESTAMPTZ NOT NULL,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS dav_locks_user_id_index ON dav_locks (user_id);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    id           BIGINT PRIMARY KEY,
+    user_id      BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    name         TEXT        NOT NULL,
+    -- Tokens are random, so a fast hash is enough to look them up
+    token_hash   TEXT        NOT NULL UNIQUE,
+    scope        SMALLINT    NOT NULL DEFAULT 0,
+
+    expires_at   TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS api_tokens_user_id_index ON api_tokens (user_id);
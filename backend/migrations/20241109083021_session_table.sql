@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS sessions
+(
+    id           TEXT PRIMARY KEY,
+    -- Identifies the session in the API, the id itself is the cookie value and never leaves the server
+    uuid         UUID        NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    -- Sessions of anonymous visitors only hold share access
+    user_id      BIGINT REFERENCES users (id) ON DELETE CASCADE,
+
+    data         JSONB       NOT NULL,
+    user_agent   TEXT,
+    ip_address   TEXT,
+
+    expires_at   TIMESTAMPTZ NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS sessions_user_id_index ON sessions (user_id);
+CREATE INDEX IF NOT EXISTS sessions_expires_at_index ON sessions (expires_at);
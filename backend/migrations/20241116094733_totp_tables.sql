@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS totp
+(
+    user_id        BIGINT PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+
+    -- Base32 encoded, as shown to the authenticator app
+    secret         TEXT        NOT NULL,
+    -- Set once the user verified a first code, until then the enrollment is pending
+    enabled        BOOLEAN     NOT NULL DEFAULT false,
+    -- Time step of the last accepted code, codes can't be used twice
+    last_used_step BIGINT,
+
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE OR REPLACE TRIGGER update_totp_modtime
+    BEFORE UPDATE
+    ON totp
+    FOR EACH ROW
+EXECUTE PROCEDURE update_modified_column();
+
+CREATE TABLE IF NOT EXISTS totp_recovery_codes
+(
+    id         BIGINT PRIMARY KEY,
+    user_id    BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    code_hash  TEXT        NOT NULL,
+    used_at    TIMESTAMPTZ,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS totp_recovery_codes_user_id_index ON totp_recovery_codes (user_id);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS oidc_links
+(
+    id         BIGINT PRIMARY KEY,
+    user_id    BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    -- The subject is only unique within the identity provider which issued it
+    issuer     TEXT        NOT NULL,
+    subject    TEXT        NOT NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    UNIQUE (issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS oidc_links_user_id_index ON oidc_links (user_id);
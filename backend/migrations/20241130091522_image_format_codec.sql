@@ -0,0 +1,14 @@
+ALTER TABLE image_formats
+    ADD COLUMN IF NOT EXISTS codec int2 NOT NULL DEFAULT 0;
+
+-- Reprocessing could insert a format of a file twice
+DELETE
+FROM image_formats a
+    USING image_formats b
+WHERE a.file_id = b.file_id
+  AND a.format = b.format
+  AND a.codec = b.codec
+  AND a.id < b.id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS image_formats_file_format_codec_index
+    ON image_formats (file_id, format, codec);
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS jobs
+(
+    id           BIGINT PRIMARY KEY,
+    operation_id BIGINT      NOT NULL REFERENCES operations (id) ON DELETE CASCADE,
+
+    job_type     INT2        NOT NULL,
+    payload      JSONB       NOT NULL,
+
+    attempts     INT         NOT NULL DEFAULT 0,
+    max_attempts INT         NOT NULL,
+    last_error   TEXT,
+
+    -- Failed attempts are retried once this is reached
+    run_at       TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Set while a worker runs the job
+    locked_at    TIMESTAMPTZ,
+    -- Set once all attempts failed, the job is kept for inspection
+    dead_at      TIMESTAMPTZ,
+
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS jobs_run_at_index ON jobs (run_at) WHERE dead_at IS NULL;
+CREATE INDEX IF NOT EXISTS jobs_operation_id_index ON jobs (operation_id);
+
+CREATE TRIGGER update_jobs_modtime
+    BEFORE UPDATE
+    ON jobs
+    FOR EACH ROW
+EXECUTE PROCEDURE update_modified_column();
@@ -0,0 +1,6 @@
+ALTER TABLE operations
+    ADD COLUMN IF NOT EXISTS progress_done   INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS progress_total  INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS progress_errors INT NOT NULL DEFAULT 0,
+    -- Name of the item the operation last worked on
+    ADD COLUMN IF NOT EXISTS progress_item   TEXT;
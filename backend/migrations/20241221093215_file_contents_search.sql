@@ -0,0 +1,16 @@
+-- Text of editable files and documents, kept apart from files so listing files doesn't load it
+CREATE TABLE IF NOT EXISTS file_contents
+(
+    file_id     BIGINT PRIMARY KEY REFERENCES files (id) ON DELETE CASCADE,
+    content     TEXT        NOT NULL,
+    content_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', content)) STORED,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS file_contents_content_tsv_index ON file_contents USING GIN (content_tsv);
+
+CREATE TRIGGER update_file_contents_modtime
+    BEFORE UPDATE
+    ON file_contents
+    FOR EACH ROW
+EXECUTE PROCEDURE update_modified_column();
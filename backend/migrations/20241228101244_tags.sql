@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS tags
+(
+    id         BIGINT PRIMARY KEY,
+    user_id    BIGINT      NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name       TEXT        NOT NULL,
+    color      VARCHAR(7),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS tags_user_id_name_index ON tags (user_id, LOWER(name));
+
+CREATE TRIGGER update_tags_modtime
+    BEFORE UPDATE
+    ON tags
+    FOR EACH ROW
+EXECUTE PROCEDURE update_modified_column();
+
+CREATE TABLE IF NOT EXISTS files_on_tag
+(
+    tag_id     BIGINT REFERENCES tags (id) ON DELETE CASCADE,
+    file_id    BIGINT REFERENCES files (id) ON DELETE CASCADE,
+    PRIMARY KEY (tag_id, file_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS files_on_tag_file_id ON files_on_tag (file_id);
+
+CREATE TABLE IF NOT EXISTS folders_on_tag
+(
+    tag_id     BIGINT REFERENCES tags (id) ON DELETE CASCADE,
+    folder_id  BIGINT REFERENCES folder (id) ON DELETE CASCADE,
+    PRIMARY KEY (tag_id, folder_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS folders_on_tag_folder_id ON folders_on_tag (folder_id);
@@ -0,0 +1,5 @@
+ALTER TABLE users
+    -- Days files stay in the bin before they are purged, NULL uses BIN_RETENTION_DAYS and 0 keeps them
+    ADD COLUMN IF NOT EXISTS bin_retention_days INT;
+
+CREATE INDEX IF NOT EXISTS files_deleted_at_index ON files (deleted_at) WHERE deleted_at IS NOT NULL;
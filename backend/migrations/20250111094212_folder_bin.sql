@@ -0,0 +1,10 @@
+ALTER TABLE folder
+    ADD COLUMN IF NOT EXISTS deleted_at   TIMESTAMPTZ,
+    -- Folder the item was moved to the bin with, NULL if it was moved there on its own
+    ADD COLUMN IF NOT EXISTS deleted_with BIGINT REFERENCES folder (id) ON DELETE SET NULL;
+
+ALTER TABLE files
+    ADD COLUMN IF NOT EXISTS deleted_with BIGINT REFERENCES folder (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS folder_deleted_with_index ON folder (deleted_with) WHERE deleted_with IS NOT NULL;
+CREATE INDEX IF NOT EXISTS files_deleted_with_index ON files (deleted_with) WHERE deleted_with IS NOT NULL;
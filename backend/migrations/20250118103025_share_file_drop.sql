@@ -0,0 +1,8 @@
+ALTER TABLE shares
+    -- 0: read only, 1: visitors can also upload, 2: visitors can only upload (file drop)
+    ADD COLUMN IF NOT EXISTS share_mode         INT2   DEFAULT 0 NOT NULL,
+    -- Limits for uploads through the share, NULL if there is no limit
+    ADD COLUMN IF NOT EXISTS upload_size_limit  BIGINT,
+    ADD COLUMN IF NOT EXISTS upload_count_limit INT,
+    ADD COLUMN IF NOT EXISTS uploaded_size      BIGINT DEFAULT 0 NOT NULL,
+    ADD COLUMN IF NOT EXISTS uploaded_count     INT    DEFAULT 0 NOT NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE totp
+    -- Codes which were tried since the last accepted one, counted across all sessions
+    ADD COLUMN IF NOT EXISTS failed_attempts INT DEFAULT 0 NOT NULL,
+    -- No codes are checked until then, set after every few failed attempts
+    ADD COLUMN IF NOT EXISTS locked_until    TIMESTAMPTZ;
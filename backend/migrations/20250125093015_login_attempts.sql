@@ -0,0 +1,5 @@
+ALTER TABLE users
+    -- Passwords which were tried since the last accepted one, through the API and WebDAV
+    ADD COLUMN IF NOT EXISTS failed_login_attempts INT DEFAULT 0 NOT NULL,
+    -- No passwords are checked until then, set after every few failed attempts
+    ADD COLUMN IF NOT EXISTS login_locked_until    TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+//! Archives which are written while they are downloaded, and read from stored files
+
+pub mod reader;
+pub mod seven_zip_reader;
+pub mod tar_reader;
+pub mod tar_stream;
+pub mod zip_reader;
+pub mod zip_stream;
@@ -0,0 +1,140 @@
+use std::io::{self, Read};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+
+use crate::archive::seven_zip_reader::SevenZipReader;
+use crate::archive::tar_reader::{TarCompression, TarReader};
+use crate::archive::zip_reader::ZipReader;
+
+/// Entry of an archive under a path which stays inside the folder it is extracted to
+#[derive(Clone, Debug)]
+pub struct ArchiveEntryInfo {
+    pub path: String,
+    pub size: u64,
+    pub is_directory: bool,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Called with every entry and its content, returns whether the next entry should be read
+pub type EntryVisitor<'a> = dyn FnMut(ArchiveEntryInfo, &mut dyn Read) -> io::Result<bool> + 'a;
+
+/// Reads an archive in local storage, entries with unsafe paths are left out
+pub trait ArchiveReader: Send {
+    /// Entries in the order of the archive
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntryInfo>>;
+
+    /// Hands every entry with its content to the visitor until it returns `false`,
+    /// directories come with an empty content
+    fn read_entries(&mut self, visit: &mut EntryVisitor) -> io::Result<()>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarBz2,
+    SevenZip,
+}
+
+/// Extensions with the format, compressed tars are checked before `.tar` itself
+const FORMATS_BY_EXTENSION: [(&str, ArchiveFormat); 8] = [
+    (".tar.gz", ArchiveFormat::TarGz),
+    (".tar.bz2", ArchiveFormat::TarBz2),
+    (".zip", ArchiveFormat::Zip),
+    (".tar", ArchiveFormat::Tar),
+    (".tgz", ArchiveFormat::TarGz),
+    (".tbz2", ArchiveFormat::TarBz2),
+    (".tbz", ArchiveFormat::TarBz2),
+    (".7z", ArchiveFormat::SevenZip),
+];
+
+const FORMATS_BY_MIME: [(&str, ArchiveFormat); 8] = [
+    ("application/zip", ArchiveFormat::Zip),
+    ("application/x-zip-compressed", ArchiveFormat::Zip),
+    ("application/tar", ArchiveFormat::Tar),
+    ("application/x-tar", ArchiveFormat::Tar),
+    ("application/gzip", ArchiveFormat::TarGz),
+    ("application/x-gzip", ArchiveFormat::TarGz),
+    ("application/x-bzip2", ArchiveFormat::TarBz2),
+    ("application/x-7z-compressed", ArchiveFormat::SevenZip),
+];
+
+impl ArchiveFormat {
+    /// Format by the extension of the name, the mime type is used for names without one
+    pub fn detect(file_name: &str, mime_type: &str) -> Option<ArchiveFormat> {
+        let lowercase_name = file_name.to_lowercase();
+
+        FORMATS_BY_EXTENSION
+            .iter()
+            .find(|(extension, _)| lowercase_name.ends_with(extension))
+            .or_else(|| FORMATS_BY_MIME.iter().find(|(mime, _)| *mime == mime_type))
+            .map(|(_, format)| *format)
+    }
+
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn ArchiveReader>> {
+        Ok(match self {
+            ArchiveFormat::Zip => Box::new(ZipReader::open(path)?),
+            ArchiveFormat::Tar => Box::new(TarReader::new(path, TarCompression::None)),
+            ArchiveFormat::TarGz => Box::new(TarReader::new(path, TarCompression::Gzip)),
+            ArchiveFormat::TarBz2 => Box::new(TarReader::new(path, TarCompression::Bzip2)),
+            ArchiveFormat::SevenZip => Box::new(SevenZipReader::open(path)?),
+        })
+    }
+
+    /// 7z archives can only be listed
+    pub fn can_read_entries(&self) -> bool {
+        !matches!(self, ArchiveFormat::SevenZip)
+    }
+
+    /// Name of the archive without the extension of the format
+    pub fn strip_extension<'a>(&self, file_name: &'a str) -> &'a str {
+        let lowercase_name = file_name.to_lowercase();
+
+        FORMATS_BY_EXTENSION
+            .iter()
+            .filter(|(_, format)| format == self)
+            .find(|(extension, _)| lowercase_name.ends_with(extension))
+            .and_then(|(extension, _)| file_name.get(..file_name.len() - extension.len()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(file_name)
+    }
+}
+
+/// Relative path of an entry with `/` as separator. Absolute paths, drive letters and `..`
+/// could place files outside of the target folder ("zip slip"), such entries are rejected.
+pub fn sanitize_entry_path(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+
+    if name.starts_with('/') || name.contains(':') || name.contains('\0') {
+        return None;
+    }
+
+    let mut segments = vec![];
+    for segment in name.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            segment if segment.len() > 255 => return None,
+            segment => segments.push(segment),
+        }
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some(segments.join("/"))
+}
+
+/// Path of the entry if it is safe to use, unsafe entries are logged and skipped
+pub(crate) fn checked_entry_path(name: &str) -> Option<String> {
+    let path = sanitize_entry_path(name);
+
+    if path.is_none() {
+        tracing::warn!("Skipping archive entry with unsafe path {}", name);
+    }
+
+    path
+}
@@ -0,0 +1,637 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use lzma_rust2::{Lzma2Reader, LzmaReader};
+
+use crate::archive::reader::{checked_entry_path, ArchiveEntryInfo, ArchiveReader, EntryVisitor};
+
+const SIGNATURE: [u8; 6] = [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c];
+const SIGNATURE_HEADER_SIZE: u64 = 32;
+/// Headers are read into memory, larger ones are taken as corrupt
+const HEADER_LIMIT: u64 = 64 * 1024 * 1024;
+
+const ID_END: u64 = 0x00;
+const ID_HEADER: u64 = 0x01;
+const ID_ARCHIVE_PROPERTIES: u64 = 0x02;
+const ID_MAIN_STREAMS_INFO: u64 = 0x04;
+const ID_FILES_INFO: u64 = 0x05;
+const ID_PACK_INFO: u64 = 0x06;
+const ID_UNPACK_INFO: u64 = 0x07;
+const ID_SUBSTREAMS_INFO: u64 = 0x08;
+const ID_SIZE: u64 = 0x09;
+const ID_CRC: u64 = 0x0a;
+const ID_FOLDER: u64 = 0x0b;
+const ID_CODERS_UNPACK_SIZE: u64 = 0x0c;
+const ID_NUM_UNPACK_STREAM: u64 = 0x0d;
+const ID_EMPTY_STREAM: u64 = 0x0e;
+const ID_EMPTY_FILE: u64 = 0x0f;
+const ID_NAME: u64 = 0x11;
+const ID_MODIFIED: u64 = 0x14;
+const ID_ENCODED_HEADER: u64 = 0x17;
+
+const CODER_COPY: &[u8] = &[0x00];
+const CODER_LZMA: &[u8] = &[0x03, 0x01, 0x01];
+const CODER_LZMA2: &[u8] = &[0x21];
+
+/// Seconds between the start of Windows file times (1601) and the unix epoch
+const FILE_TIME_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// Lists 7z archives. Only the header is decoded, the content can't be read because of the
+/// many coders 7z supports, so `read_entries` fails with `Unsupported`.
+pub struct SevenZipReader {
+    entries: Vec<ArchiveEntryInfo>,
+}
+
+impl SevenZipReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut signature_header = [0u8; SIGNATURE_HEADER_SIZE as usize];
+        file.read_exact(&mut signature_header)?;
+        if signature_header[..6] != SIGNATURE {
+            return Err(invalid_data("Not a 7z archive"));
+        }
+
+        let mut start_header = HeaderReader::new(&signature_header[12..]);
+        let next_header_offset = start_header.read_u64()?;
+        let next_header_size = start_header.read_u64()?;
+        let next_header_crc = start_header.read_u32()?;
+
+        if next_header_size == 0 {
+            return Ok(SevenZipReader { entries: vec![] });
+        }
+
+        let header = read_at(
+            &mut file,
+            SIGNATURE_HEADER_SIZE.saturating_add(next_header_offset),
+            next_header_size,
+        )?;
+        if crc32fast::hash(&header) != next_header_crc {
+            return Err(invalid_data("Invalid header checksum"));
+        }
+
+        let mut reader = HeaderReader::new(&header);
+        let header = match reader.read_number()? {
+            ID_HEADER => header,
+            // The header itself is compressed, its streams info tells where it is
+            ID_ENCODED_HEADER => {
+                let streams = reader.read_streams_info()?;
+                let decoded = decode_first_folder(&mut file, &streams)?;
+
+                if decoded.first().map(|id| *id as u64) != Some(ID_HEADER) {
+                    return Err(invalid_data("Invalid encoded header"));
+                }
+                decoded
+            }
+            _ => return Err(invalid_data("Invalid header")),
+        };
+
+        let mut reader = HeaderReader::new(&header[1..]);
+        let entries = reader.read_header()?;
+
+        Ok(SevenZipReader { entries })
+    }
+}
+
+impl ArchiveReader for SevenZipReader {
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntryInfo>> {
+        Ok(self.entries.clone())
+    }
+
+    fn read_entries(&mut self, _visit: &mut EntryVisitor) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Entries of 7z archives can't be read",
+        ))
+    }
+}
+
+struct Coder {
+    id: Vec<u8>,
+    properties: Vec<u8>,
+}
+
+struct Folder {
+    coders: Vec<Coder>,
+    unpack_size: u64,
+    has_crc: bool,
+}
+
+#[derive(Default)]
+struct StreamsInfo {
+    pack_position: u64,
+    pack_sizes: Vec<u64>,
+    folders: Vec<Folder>,
+    /// Sizes of the files in the folders, in the order of the files with content
+    file_sizes: Vec<u64>,
+}
+
+/// Decodes the first folder of the streams, as used for the encoded header
+fn decode_first_folder(file: &mut BufReader<File>, streams: &StreamsInfo) -> io::Result<Vec<u8>> {
+    let (Some(folder), Some(pack_size)) = (streams.folders.first(), streams.pack_sizes.first())
+    else {
+        return Err(invalid_data("Encoded header without content"));
+    };
+
+    if folder.unpack_size > HEADER_LIMIT {
+        return Err(invalid_data("Header is too large"));
+    }
+
+    let packed = read_at(
+        file,
+        SIGNATURE_HEADER_SIZE.saturating_add(streams.pack_position),
+        *pack_size,
+    )?;
+
+    let [coder] = folder.coders.as_slice() else {
+        return Err(unsupported("Header uses several coders"));
+    };
+
+    // The dictionary never has to be larger than the output, so a crafted dictionary size
+    // can't make the decoder allocate more than the header limit
+    let dict_limit = folder.unpack_size.max(4096) as u32;
+    let decoder: Box<dyn Read + '_> = match coder.id.as_slice() {
+        CODER_COPY => Box::new(packed.as_slice()),
+        CODER_LZMA => {
+            // 7z keeps the properties of the stream in the coder instead of in front of it
+            let [props, dict_size @ ..] = coder.properties.as_slice() else {
+                return Err(invalid_data("Invalid LZMA properties"));
+            };
+            let dict_size = u32::from_le_bytes(
+                dict_size
+                    .try_into()
+                    .map_err(|_| invalid_data("Invalid LZMA properties"))?,
+            );
+
+            Box::new(LzmaReader::new_with_props(
+                packed.as_slice(),
+                folder.unpack_size,
+                *props,
+                dict_size.min(dict_limit),
+                None,
+            )?)
+        }
+        CODER_LZMA2 => {
+            let Some(dict_size) = coder
+                .properties
+                .first()
+                .and_then(|props| lzma2_dict_size(*props))
+            else {
+                return Err(invalid_data("Invalid LZMA2 properties"));
+            };
+
+            Box::new(Lzma2Reader::new(
+                packed.as_slice(),
+                dict_size.min(dict_limit),
+                None,
+            ))
+        }
+        _ => return Err(unsupported("Header uses an unsupported coder")),
+    };
+
+    // Decoding stops right after the declared size, a small stream can't expand beyond it
+    let mut decoded = Vec::with_capacity(folder.unpack_size as usize);
+    decoder
+        .take(folder.unpack_size + 1)
+        .read_to_end(&mut decoded)?;
+
+    if decoded.len() as u64 != folder.unpack_size {
+        return Err(invalid_data("Invalid encoded header size"));
+    }
+
+    Ok(decoded)
+}
+
+/// Dictionary size encoded in the property byte of LZMA2
+fn lzma2_dict_size(props: u8) -> Option<u32> {
+    match props {
+        0..=39 => Some((2 | (props as u32 & 1)) << (props / 2 + 11)),
+        40 => Some(u32::MAX),
+        _ => None,
+    }
+}
+
+fn read_at(file: &mut BufReader<File>, position: u64, size: u64) -> io::Result<Vec<u8>> {
+    if size > HEADER_LIMIT {
+        return Err(invalid_data("Header is too large"));
+    }
+
+    file.seek(SeekFrom::Start(position))?;
+    let mut data = vec![0u8; size as usize];
+    file.read_exact(&mut data)?;
+
+    Ok(data)
+}
+
+/// Reads the properties of a header, the layout is described in `7zFormat.txt` of the 7-Zip
+/// sources
+struct HeaderReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> HeaderReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        HeaderReader { data }
+    }
+
+    fn read_bytes(&mut self, length: u64) -> io::Result<&'a [u8]> {
+        if length > self.data.len() as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let (bytes, rest) = self.data.split_at(length as usize);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    /// Numbers take 1 to 9 bytes, the leading ones of the first byte tell how many follow
+    fn read_number(&mut self) -> io::Result<u64> {
+        let first = self.read_u8()?;
+        let mut mask = 0x80u8;
+        let mut value = 0u64;
+
+        for index in 0..8 {
+            if first & mask == 0 {
+                let high = (first & mask.wrapping_sub(1)) as u64;
+                return Ok(value | (high << (8 * index)));
+            }
+
+            value |= (self.read_u8()? as u64) << (8 * index);
+            mask >>= 1;
+        }
+
+        Ok(value)
+    }
+
+    /// Counts which are used to allocate, they can't be more than the bytes left
+    fn read_count(&mut self) -> io::Result<usize> {
+        let count = self.read_number()?;
+        if count > self.data.len() as u64 {
+            return Err(invalid_data("Invalid count in header"));
+        }
+
+        Ok(count as usize)
+    }
+
+    fn read_bits(&mut self, count: usize) -> io::Result<Vec<bool>> {
+        let bytes = self.read_bytes(count.div_ceil(8) as u64)?;
+
+        Ok((0..count)
+            .map(|index| bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+            .collect())
+    }
+
+    /// Bits which can be given as "all defined"
+    fn read_defined_bits(&mut self, count: usize) -> io::Result<Vec<bool>> {
+        match self.read_u8()? {
+            0 => self.read_bits(count),
+            _ => Ok(vec![true; count]),
+        }
+    }
+
+    fn skip_digests(&mut self, count: usize) -> io::Result<Vec<bool>> {
+        let defined = self.read_defined_bits(count)?;
+        self.read_bytes(4 * defined.iter().filter(|defined| **defined).count() as u64)?;
+
+        Ok(defined)
+    }
+
+    fn expect(&mut self, id: u64) -> io::Result<()> {
+        if self.read_number()? != id {
+            return Err(invalid_data("Unexpected property in header"));
+        }
+
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> io::Result<Vec<ArchiveEntryInfo>> {
+        let mut id = self.read_number()?;
+
+        if id == ID_ARCHIVE_PROPERTIES {
+            while self.read_number()? != ID_END {
+                let size = self.read_number()?;
+                self.read_bytes(size)?;
+            }
+            id = self.read_number()?;
+        }
+
+        let mut streams = StreamsInfo::default();
+        if id == ID_MAIN_STREAMS_INFO {
+            streams = self.read_streams_info()?;
+            id = self.read_number()?;
+        }
+
+        if id == ID_FILES_INFO {
+            return self.read_files_info(&streams);
+        }
+
+        match id {
+            ID_END => Ok(vec![]),
+            _ => Err(unsupported("Header uses unsupported properties")),
+        }
+    }
+
+    fn read_streams_info(&mut self) -> io::Result<StreamsInfo> {
+        let mut streams = StreamsInfo::default();
+        let mut id = self.read_number()?;
+
+        if id == ID_PACK_INFO {
+            self.read_pack_info(&mut streams)?;
+            id = self.read_number()?;
+        }
+
+        if id == ID_UNPACK_INFO {
+            self.read_unpack_info(&mut streams)?;
+            id = self.read_number()?;
+        }
+
+        // Without substreams every folder holds a single file
+        streams.file_sizes = streams.folders.iter().map(|f| f.unpack_size).collect();
+        if id == ID_SUBSTREAMS_INFO {
+            self.read_substreams_info(&mut streams)?;
+            id = self.read_number()?;
+        }
+
+        match id {
+            ID_END => Ok(streams),
+            _ => Err(invalid_data("Unexpected property in streams info")),
+        }
+    }
+
+    fn read_pack_info(&mut self, streams: &mut StreamsInfo) -> io::Result<()> {
+        streams.pack_position = self.read_number()?;
+        let count = self.read_count()?;
+
+        loop {
+            match self.read_number()? {
+                ID_END => return Ok(()),
+                ID_SIZE => {
+                    streams.pack_sizes = (0..count)
+                        .map(|_| self.read_number())
+                        .collect::<io::Result<_>>()?;
+                }
+                ID_CRC => {
+                    self.skip_digests(count)?;
+                }
+                _ => return Err(invalid_data("Unexpected property in pack info")),
+            }
+        }
+    }
+
+    fn read_unpack_info(&mut self, streams: &mut StreamsInfo) -> io::Result<()> {
+        self.expect(ID_FOLDER)?;
+        let count = self.read_count()?;
+
+        if self.read_u8()? != 0 {
+            return Err(unsupported("Folders in external streams"));
+        }
+
+        let mut output_counts = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (folder, bound_outputs) = self.read_folder()?;
+            streams.folders.push(folder);
+            output_counts.push(bound_outputs);
+        }
+
+        self.expect(ID_CODERS_UNPACK_SIZE)?;
+        for (folder, (outputs, bound_outputs)) in streams.folders.iter_mut().zip(output_counts) {
+            // The size of the folder is the one of the output which isn't bound to a coder
+            for output in 0..outputs {
+                let size = self.read_number()?;
+                if !bound_outputs.contains(&output) {
+                    folder.unpack_size = size;
+                }
+            }
+        }
+
+        loop {
+            match self.read_number()? {
+                ID_END => return Ok(()),
+                ID_CRC => {
+                    let defined = self.skip_digests(streams.folders.len())?;
+                    for (folder, defined) in streams.folders.iter_mut().zip(defined) {
+                        folder.has_crc = defined;
+                    }
+                }
+                _ => return Err(invalid_data("Unexpected property in unpack info")),
+            }
+        }
+    }
+
+    /// Folder with the number of outputs of its coders and which of them are bound to inputs
+    fn read_folder(&mut self) -> io::Result<(Folder, (u64, Vec<u64>))> {
+        let coder_count = self.read_count()?;
+        let mut coders = Vec::with_capacity(coder_count);
+        let mut inputs = 0u64;
+        let mut outputs = 0u64;
+
+        for _ in 0..coder_count {
+            let flags = self.read_u8()?;
+            let id = self.read_bytes((flags & 0x0f) as u64)?.to_vec();
+
+            if flags & 0x10 != 0 {
+                inputs = inputs.saturating_add(self.read_number()?);
+                outputs = outputs.saturating_add(self.read_number()?);
+            } else {
+                inputs += 1;
+                outputs += 1;
+            }
+
+            let properties = match flags & 0x20 != 0 {
+                true => {
+                    let size = self.read_number()?;
+                    self.read_bytes(size)?.to_vec()
+                }
+                false => vec![],
+            };
+
+            coders.push(Coder { id, properties });
+        }
+
+        let bind_pairs = outputs.saturating_sub(1);
+        let mut bound_outputs = vec![];
+        for _ in 0..bind_pairs {
+            self.read_number()?;
+            bound_outputs.push(self.read_number()?);
+        }
+
+        let packed_streams = inputs.saturating_sub(bind_pairs);
+        if packed_streams > 1 {
+            for _ in 0..packed_streams {
+                self.read_number()?;
+            }
+        }
+
+        let folder = Folder {
+            coders,
+            unpack_size: 0,
+            has_crc: false,
+        };
+
+        Ok((folder, (outputs, bound_outputs)))
+    }
+
+    fn read_substreams_info(&mut self, streams: &mut StreamsInfo) -> io::Result<()> {
+        let mut stream_counts = vec![1u64; streams.folders.len()];
+        let mut id = self.read_number()?;
+
+        if id == ID_NUM_UNPACK_STREAM {
+            for count in stream_counts.iter_mut() {
+                *count = self.read_count()? as u64;
+            }
+            id = self.read_number()?;
+        }
+
+        streams.file_sizes.clear();
+        for (folder, count) in streams.folders.iter().zip(&stream_counts) {
+            if *count == 0 {
+                continue;
+            }
+
+            // The size of the last file is what is left of the folder
+            let mut sum = 0u64;
+            if id == ID_SIZE {
+                for _ in 1..*count {
+                    let size = self.read_number()?;
+                    sum = sum.saturating_add(size);
+                    streams.file_sizes.push(size);
+                }
+            }
+            streams
+                .file_sizes
+                .push(folder.unpack_size.saturating_sub(sum));
+        }
+        if id == ID_SIZE {
+            id = self.read_number()?;
+        }
+
+        loop {
+            match id {
+                ID_END => return Ok(()),
+                ID_CRC => {
+                    let digests = streams
+                        .folders
+                        .iter()
+                        .zip(&stream_counts)
+                        .filter(|(folder, count)| **count != 1 || !folder.has_crc)
+                        .map(|(_, count)| *count as usize)
+                        .sum();
+                    self.skip_digests(digests)?;
+                }
+                _ => return Err(invalid_data("Unexpected property in substreams info")),
+            }
+            id = self.read_number()?;
+        }
+    }
+
+    fn read_files_info(&mut self, streams: &StreamsInfo) -> io::Result<Vec<ArchiveEntryInfo>> {
+        let count = self.read_count()?;
+        let mut names = vec![String::new(); count];
+        let mut empty_streams = vec![false; count];
+        let mut empty_files = vec![];
+        let mut modified = vec![None; count];
+
+        loop {
+            let id = self.read_number()?;
+            if id == ID_END {
+                break;
+            }
+
+            let size = self.read_number()?;
+            let mut property = HeaderReader::new(self.read_bytes(size)?);
+
+            match id {
+                ID_EMPTY_STREAM => {
+                    empty_streams = property.read_bits(count)?;
+                }
+                ID_EMPTY_FILE => {
+                    let empty_count = empty_streams.iter().filter(|empty| **empty).count();
+                    empty_files = property.read_bits(empty_count)?;
+                }
+                ID_NAME => {
+                    if property.read_u8()? != 0 {
+                        return Err(unsupported("Names in external streams"));
+                    }
+
+                    let units = property
+                        .data
+                        .chunks_exact(2)
+                        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                        .collect::<Vec<_>>();
+                    for (name, units) in names.iter_mut().zip(units.split(|unit| *unit == 0)) {
+                        *name = String::from_utf16_lossy(units);
+                    }
+                }
+                ID_MODIFIED => {
+                    let defined = property.read_defined_bits(count)?;
+                    if property.read_u8()? != 0 {
+                        return Err(unsupported("Times in external streams"));
+                    }
+
+                    for (time, defined) in modified.iter_mut().zip(defined) {
+                        if defined {
+                            *time = file_time(property.read_u64()?);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut file_sizes = streams.file_sizes.iter();
+        let mut empty_files = empty_files.into_iter();
+        let mut entries = Vec::with_capacity(count);
+
+        for ((name, empty_stream), modified) in names.iter().zip(empty_streams).zip(modified) {
+            // Entries without content are folders unless they are marked as empty file
+            let (size, is_directory) = match empty_stream {
+                true => (0, !empty_files.next().unwrap_or(false)),
+                false => (*file_sizes.next().unwrap_or(&0), false),
+            };
+
+            if let Some(path) = checked_entry_path(name) {
+                entries.push(ArchiveEntryInfo {
+                    path,
+                    size,
+                    is_directory,
+                    modified,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Windows file times count 100 nanoseconds since 1601
+fn file_time(time: u64) -> Option<DateTime<Utc>> {
+    let seconds = (time / 10_000_000) as i64 - FILE_TIME_EPOCH_OFFSET;
+    let nanoseconds = (time % 10_000_000) as u32 * 100;
+
+    DateTime::from_timestamp(seconds, nanoseconds)
+}
+
+fn invalid_data(error: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+fn unsupported(error: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, error.to_string())
+}
@@ -0,0 +1,304 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use bzip2::read::MultiBzDecoder;
+use chrono::DateTime;
+use flate2::read::MultiGzDecoder;
+
+use crate::archive::reader::{checked_entry_path, ArchiveEntryInfo, ArchiveReader, EntryVisitor};
+
+const BLOCK_SIZE: u64 = 512;
+/// Long names and pax headers are read into memory, larger ones are taken as corrupt
+const EXTENDED_HEADER_LIMIT: u64 = 1024 * 1024;
+
+const NAME: (usize, usize) = (0, 100);
+const SIZE: (usize, usize) = (124, 136);
+const MODIFIED: (usize, usize) = (136, 148);
+const CHECKSUM: (usize, usize) = (148, 156);
+const TYPE_FLAG: usize = 156;
+const MAGIC: (usize, usize) = (257, 263);
+const PREFIX: (usize, usize) = (345, 500);
+
+#[derive(Clone, Copy, Debug)]
+pub enum TarCompression {
+    None,
+    Gzip,
+    Bzip2,
+}
+
+/// Reads tar archives, also compressed ones. Tars have no index, so every listing reads the
+/// whole archive from the start.
+pub struct TarReader {
+    path: PathBuf,
+    compression: TarCompression,
+}
+
+/// Values of a pax extended header which apply to the next entry
+#[derive(Default)]
+struct PaxHeader {
+    path: Option<String>,
+    size: Option<u64>,
+    modified: Option<i64>,
+}
+
+impl TarReader {
+    pub fn new(path: &Path, compression: TarCompression) -> Self {
+        TarReader {
+            path: path.to_path_buf(),
+            compression,
+        }
+    }
+
+    fn open_stream(&self) -> io::Result<Box<dyn Read>> {
+        let file = BufReader::new(File::open(&self.path)?);
+
+        Ok(match self.compression {
+            TarCompression::None => Box::new(file),
+            TarCompression::Gzip => Box::new(MultiGzDecoder::new(file)),
+            TarCompression::Bzip2 => Box::new(MultiBzDecoder::new(file)),
+        })
+    }
+}
+
+impl ArchiveReader for TarReader {
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntryInfo>> {
+        let mut entries = vec![];
+
+        read_tar_entries(&mut self.open_stream()?, &mut |info, _| {
+            entries.push(info);
+            Ok(true)
+        })?;
+
+        Ok(entries)
+    }
+
+    fn read_entries(&mut self, visit: &mut EntryVisitor) -> io::Result<()> {
+        read_tar_entries(&mut self.open_stream()?, visit)
+    }
+}
+
+/// Walks the headers of the archive. Links, devices and other special entries are skipped.
+fn read_tar_entries(reader: &mut dyn Read, visit: &mut EntryVisitor) -> io::Result<()> {
+    let mut block = [0u8; BLOCK_SIZE as usize];
+    let mut long_name: Option<String> = None;
+    let mut pax = PaxHeader::default();
+
+    // The end is marked by zero blocks, archives which just stop are accepted as well
+    while read_block(reader, &mut block)? {
+        if block.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        verify_checksum(&block)?;
+
+        let type_flag = block[TYPE_FLAG];
+        let mut size = parse_number(field(&block, SIZE))?;
+
+        match type_flag {
+            b'L' => {
+                let name = read_extended_header(reader, size)?;
+                long_name = Some(
+                    String::from_utf8_lossy(&name)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                );
+                continue;
+            }
+            b'x' => {
+                pax = parse_pax_header(&read_extended_header(reader, size)?);
+                continue;
+            }
+            _ => {}
+        }
+
+        let name = pax
+            .path
+            .take()
+            .or(long_name.take())
+            .unwrap_or_else(|| header_name(&block));
+        if let Some(pax_size) = pax.size.take() {
+            size = pax_size;
+        }
+        let modified = match pax.modified.take() {
+            Some(modified) => modified,
+            None => parse_number(field(&block, MODIFIED))? as i64,
+        };
+
+        let is_directory = type_flag == b'5' || (type_flag == b'\0' && name.ends_with('/'));
+        let is_file = matches!(type_flag, b'0' | b'\0' | b'7') && !is_directory;
+
+        let mut content = reader.take(size);
+
+        if is_directory || is_file {
+            if let Some(path) = checked_entry_path(&name) {
+                let info = ArchiveEntryInfo {
+                    path,
+                    size: if is_directory { 0 } else { size },
+                    is_directory,
+                    modified: DateTime::from_timestamp(modified, 0),
+                };
+
+                let keep_reading = if is_directory {
+                    visit(info, &mut io::empty())?
+                } else {
+                    visit(info, &mut content)?
+                };
+
+                if !keep_reading {
+                    return Ok(());
+                }
+            }
+        }
+
+        // Whatever the visitor left is skipped, followed by the padding to the next block
+        io::copy(&mut content, &mut io::sink())?;
+        if content.limit() > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        skip_padding(reader, size)?;
+    }
+
+    Ok(())
+}
+
+/// Fills the block, `false` if the archive ended before it
+fn read_block(reader: &mut dyn Read, block: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(block) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn skip_padding(reader: &mut dyn Read, size: u64) -> io::Result<()> {
+    let padding = (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE;
+    io::copy(&mut reader.take(padding), &mut io::sink())?;
+
+    Ok(())
+}
+
+fn read_extended_header(reader: &mut dyn Read, size: u64) -> io::Result<Vec<u8>> {
+    if size > EXTENDED_HEADER_LIMIT {
+        return Err(invalid_data("Extended header is too large"));
+    }
+
+    let mut data = vec![0u8; size as usize];
+    reader.read_exact(&mut data)?;
+    skip_padding(reader, size)?;
+
+    Ok(data)
+}
+
+fn field(block: &[u8], (start, end): (usize, usize)) -> &[u8] {
+    &block[start..end]
+}
+
+fn field_string(block: &[u8], range: (usize, usize)) -> String {
+    let value = field(block, range);
+    let length = value
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(value.len());
+
+    String::from_utf8_lossy(&value[..length]).to_string()
+}
+
+/// Name of the entry, POSIX archives split long names into a prefix and the name
+fn header_name(block: &[u8]) -> String {
+    let name = field_string(block, NAME);
+
+    if field(block, MAGIC) != b"ustar\0" {
+        return name;
+    }
+
+    match field_string(block, PREFIX) {
+        prefix if prefix.is_empty() => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// Numbers are octal text, GNU tar stores large ones as base-256 with the high bit set
+fn parse_number(value: &[u8]) -> io::Result<u64> {
+    if value.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return value
+            .iter()
+            .enumerate()
+            .try_fold(0u64, |number, (index, byte)| {
+                let byte = if index == 0 { byte & 0x7f } else { *byte };
+                number
+                    .checked_mul(256)
+                    .map(|number| number + byte as u64)
+                    .ok_or_else(|| invalid_data("Number in header is too large"))
+            });
+    }
+
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(text, 8).map_err(|_| invalid_data("Invalid number in header"))
+}
+
+/// The checksum is the sum of the header bytes with the checksum field taken as spaces,
+/// some old archivers summed signed bytes
+fn verify_checksum(block: &[u8]) -> io::Result<()> {
+    let expected = parse_number(field(block, CHECKSUM))?;
+    let (start, end) = CHECKSUM;
+
+    let (unsigned, signed) =
+        block
+            .iter()
+            .enumerate()
+            .fold((0u64, 0i64), |(unsigned, signed), (index, byte)| {
+                let byte = if (start..end).contains(&index) {
+                    b' '
+                } else {
+                    *byte
+                };
+                (unsigned + byte as u64, signed + byte as i8 as i64)
+            });
+
+    if expected != unsigned && expected as i64 != signed {
+        return Err(invalid_data("Invalid header checksum"));
+    }
+
+    Ok(())
+}
+
+/// Records are `<length> <key>=<value>\n`, the length counts the whole record
+fn parse_pax_header(data: &[u8]) -> PaxHeader {
+    let mut header = PaxHeader::default();
+    let mut rest = data;
+
+    while let Some(space) = rest.iter().position(|byte| *byte == b' ') {
+        let Some(length) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|length| length.parse::<usize>().ok())
+            .filter(|length| *length > space + 1 && *length <= rest.len())
+        else {
+            break;
+        };
+
+        let record = String::from_utf8_lossy(&rest[space + 1..length - 1]);
+        if let Some((key, value)) = record.split_once('=') {
+            match key {
+                "path" => header.path = Some(value.to_string()),
+                "size" => header.size = value.parse().ok(),
+                // Times can have fractions of a second
+                "mtime" => header.modified = value.split('.').next().and_then(|s| s.parse().ok()),
+                _ => {}
+            }
+        }
+
+        rest = &rest[length..];
+    }
+
+    header
+}
+
+fn invalid_data(error: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
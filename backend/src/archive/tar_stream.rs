@@ -0,0 +1,214 @@
+use std::io::{self, Write};
+
+use chrono::{DateTime, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const BLOCK_SIZE: usize = 512;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+const TYPE_FILE: u8 = b'0';
+const TYPE_DIRECTORY: u8 = b'5';
+const TYPE_PAX_HEADER: u8 = b'x';
+const FILE_MODE: u64 = 0o644;
+const DIRECTORY_MODE: u64 = 0o755;
+
+/// Names up to this length fit into the header, longer ones go into a pax header
+const NAME_LIMIT: usize = 100;
+/// Largest size the 11 octal digits of the header can hold
+const SIZE_LIMIT: u64 = 0o77777777777;
+
+/// Writes a tar archive, optionally compressed with gzip, while it is sent. Names and sizes
+/// beyond the limits of the ustar header are stored in pax headers.
+pub struct TarStreamWriter<W> {
+    writer: W,
+    /// Compressed data collects in the vector of the encoder until it is sent
+    gzip: Option<GzEncoder<Vec<u8>>>,
+}
+
+impl<W: AsyncWrite + Unpin> TarStreamWriter<W> {
+    pub fn new(writer: W, gzip: bool) -> Self {
+        TarStreamWriter {
+            writer,
+            gzip: gzip.then(|| GzEncoder::new(vec![], Compression::default())),
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self.gzip.as_mut() {
+            Some(encoder) => {
+                encoder.write_all(bytes)?;
+                let compressed = std::mem::take(encoder.get_mut());
+                self.writer.write_all(&compressed).await
+            }
+            None => self.writer.write_all(bytes).await,
+        }
+    }
+
+    async fn write_padding(&mut self, size: u64) -> io::Result<()> {
+        let padding = (BLOCK_SIZE - (size % BLOCK_SIZE as u64) as usize) % BLOCK_SIZE;
+        self.write(&[0; BLOCK_SIZE][..padding]).await
+    }
+
+    async fn write_header(
+        &mut self,
+        name: &str,
+        type_flag: u8,
+        mode: u64,
+        size: u64,
+        modified: DateTime<Utc>,
+    ) -> io::Result<()> {
+        let mut records = vec![];
+        if name.len() > NAME_LIMIT || !name.is_ascii() {
+            records.extend(pax_record("path", name));
+        }
+        if size > SIZE_LIMIT {
+            records.extend(pax_record("size", &size.to_string()));
+        }
+
+        if !records.is_empty() {
+            let pax_name = format!("PaxHeaders/{}", ascii_name(name));
+            let pax_header = header(
+                &pax_name,
+                TYPE_PAX_HEADER,
+                FILE_MODE,
+                records.len() as u64,
+                modified,
+            );
+            self.write(&pax_header).await?;
+            self.write(&records).await?;
+            self.write_padding(records.len() as u64).await?;
+        }
+
+        let header = header(
+            &ascii_name(name),
+            type_flag,
+            mode,
+            size.min(SIZE_LIMIT),
+            modified,
+        );
+        self.write(&header).await
+    }
+
+    pub async fn add_directory(&mut self, path: &str, modified: DateTime<Utc>) -> io::Result<()> {
+        let name = format!("{}/", path.trim_end_matches('/'));
+
+        self.write_header(&name, TYPE_DIRECTORY, DIRECTORY_MODE, 0, modified)
+            .await
+    }
+
+    /// Copies the content into the archive. The size is part of the header which is sent first,
+    /// so content of another size fails the archive.
+    pub async fn add_file<R: AsyncRead + Unpin>(
+        &mut self,
+        path: &str,
+        modified: DateTime<Utc>,
+        size: u64,
+        content: &mut R,
+    ) -> io::Result<()> {
+        self.write_header(path, TYPE_FILE, FILE_MODE, size, modified)
+            .await?;
+
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut written = 0u64;
+
+        loop {
+            let read = content.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+
+            if written + read as u64 > size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is larger than expected", path),
+                ));
+            }
+
+            self.write(&buffer[..read]).await?;
+            written += read as u64;
+        }
+
+        if written < size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} is smaller than expected", path),
+            ));
+        }
+
+        self.write_padding(size).await
+    }
+
+    /// Writes the two zero blocks which end the archive
+    pub async fn finish(mut self) -> io::Result<W> {
+        self.write(&[0; 2 * BLOCK_SIZE]).await?;
+
+        if let Some(encoder) = self.gzip.take() {
+            let compressed = encoder.finish()?;
+            self.writer.write_all(&compressed).await?;
+        }
+
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+fn header(name: &str, type_flag: u8, mode: u64, size: u64, modified: DateTime<Utc>) -> Vec<u8> {
+    let mut header = vec![0u8; BLOCK_SIZE];
+
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    put_octal(&mut header[100..108], mode);
+    put_octal(&mut header[108..116], 0);
+    put_octal(&mut header[116..124], 0);
+    put_octal(&mut header[124..136], size);
+    put_octal(&mut header[136..148], modified.timestamp().max(0) as u64);
+    header[156] = type_flag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is calculated with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|byte| *byte as u64).sum::<u64>();
+    put_octal(&mut header[148..155], checksum);
+
+    header
+}
+
+/// Zero padded octal number which ends with a NUL
+fn put_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{:0width$o}", value, width = digits);
+
+    field[..digits].copy_from_slice(&text.as_bytes()[text.len() - digits..]);
+    field[digits] = 0;
+}
+
+/// Name for the header itself, readers without pax support see a shortened ASCII name
+fn ascii_name(name: &str) -> String {
+    let ascii = name
+        .chars()
+        .map(|c| if c.is_ascii() && c != '\0' { c } else { '_' })
+        .collect::<String>();
+
+    match ascii.len() > NAME_LIMIT {
+        true => ascii[ascii.len() - NAME_LIMIT..].to_string(),
+        false => ascii,
+    }
+}
+
+/// `<length> <key>=<value>\n`, where the length counts the whole record including itself
+fn pax_record(key: &str, value: &str) -> Vec<u8> {
+    let base = key.len() + value.len() + 3;
+    let mut length = base;
+
+    loop {
+        let total = base + length.to_string().len();
+        if total == length {
+            break;
+        }
+        length = total;
+    }
+
+    format!("{} {}={}\n", length, key, value).into_bytes()
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use zip::ZipArchive;
+
+use crate::archive::reader::{checked_entry_path, ArchiveEntryInfo, ArchiveReader, EntryVisitor};
+
+/// Reads entries of a zip archive in local storage, the archive is parsed on every open
+pub struct ZipReader {
+    archive: ZipArchive<File>,
+}
+
+impl ZipReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let archive = ZipArchive::new(File::open(path)?)?;
+
+        Ok(ZipReader { archive })
+    }
+}
+
+impl ArchiveReader for ZipReader {
+    fn entries(&mut self) -> io::Result<Vec<ArchiveEntryInfo>> {
+        Ok((0..self.archive.len())
+            .filter_map(|index| {
+                let entry = self.archive.by_index_raw(index).ok()?;
+
+                Some(ArchiveEntryInfo {
+                    path: checked_entry_path(entry.name())?,
+                    size: entry.size(),
+                    is_directory: entry.is_dir(),
+                    modified: entry.last_modified().and_then(to_utc),
+                })
+            })
+            .collect())
+    }
+
+    /// The checksum of an entry is verified once it is read to the end
+    fn read_entries(&mut self, visit: &mut EntryVisitor) -> io::Result<()> {
+        for index in 0..self.archive.len() {
+            let mut entry = self.archive.by_index(index)?;
+            let Some(path) = checked_entry_path(entry.name()) else {
+                continue;
+            };
+
+            let info = ArchiveEntryInfo {
+                path,
+                size: entry.size(),
+                is_directory: entry.is_dir(),
+                modified: entry.last_modified().and_then(to_utc),
+            };
+
+            let keep_reading = if info.is_directory {
+                visit(info, &mut io::empty())?
+            } else {
+                visit(info, &mut entry as &mut dyn Read)?
+            };
+
+            if !keep_reading {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Zip stores local time without a zone, it is taken as UTC
+fn to_utc(time: zip::DateTime) -> Option<DateTime<Utc>> {
+    NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+        .and_hms_opt(
+            time.hour() as u32,
+            time.minute() as u32,
+            time.second() as u32,
+        )
+        .map(|time| time.and_utc())
+}
@@ -0,0 +1,390 @@
+use std::io::{self, Write};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use crc32fast::Hasher;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// Upper byte of "version made by", the external attributes hold unix permissions
+const MADE_BY_UNIX: u16 = 3 << 8;
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+const FILE_ATTRIBUTES: u32 = 0o100644 << 16;
+const DIRECTORY_ATTRIBUTES: u32 = (0o40755 << 16) | 0x10;
+
+/// Sizes, offsets and counts from this value on are only stored in the ZIP64 records
+const ZIP64_LIMIT: u64 = u32::MAX as u64;
+const ZIP64_COUNT_LIMIT: usize = u16::MAX as usize;
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZipCompression {
+    Stored,
+    Deflate,
+}
+
+impl ZipCompression {
+    fn method(&self) -> u16 {
+        match self {
+            ZipCompression::Stored => METHOD_STORED,
+            ZipCompression::Deflate => METHOD_DEFLATED,
+        }
+    }
+
+    /// Largest size the content can take in the archive, deflate can grow incompressible data
+    fn max_compressed_size(&self, size: u64) -> u64 {
+        match self {
+            ZipCompression::Stored => size,
+            ZipCompression::Deflate => size
+                .saturating_add(size >> 12)
+                .saturating_add(size >> 14)
+                .saturating_add(13),
+        }
+    }
+}
+
+struct CentralDirectoryEntry {
+    name: String,
+    time: u16,
+    date: u16,
+    method: u16,
+    crc32: u32,
+    size: u64,
+    compressed_size: u64,
+    offset: u64,
+    /// The local header has a ZIP64 extra field and the data descriptor 8 byte sizes
+    zip64: bool,
+    directory: bool,
+}
+
+/// Writes a zip archive front to back, so it can be sent while it is created. Sizes and checksums
+/// follow the contents in data descriptors.
+pub struct ZipStreamWriter<W> {
+    writer: W,
+    compression: ZipCompression,
+    offset: u64,
+    entries: Vec<CentralDirectoryEntry>,
+}
+
+impl<W: AsyncWrite + Unpin> ZipStreamWriter<W> {
+    pub fn new(writer: W, compression: ZipCompression) -> Self {
+        ZipStreamWriter {
+            writer,
+            compression,
+            offset: 0,
+            entries: vec![],
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes).await?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    pub async fn add_directory(&mut self, path: &str, modified: DateTime<Utc>) -> io::Result<()> {
+        let name = format!("{}/", path.trim_end_matches('/'));
+        let (time, date) = dos_date_time(modified);
+        let offset = self.offset;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION_DEFAULT);
+        put_u16(&mut header, FLAG_UTF8);
+        put_u16(&mut header, METHOD_STORED);
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u32(&mut header, 0);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name.as_bytes());
+        self.write(&header).await?;
+
+        self.entries.push(CentralDirectoryEntry {
+            name,
+            time,
+            date,
+            method: METHOD_STORED,
+            crc32: 0,
+            size: 0,
+            compressed_size: 0,
+            offset,
+            zip64: false,
+            directory: true,
+        });
+
+        Ok(())
+    }
+
+    /// Copies the content into the archive. Files expected to reach 4 GiB are written in the
+    /// ZIP64 format, the expected size has to be known up front as the header is sent first.
+    pub async fn add_file<R: AsyncRead + Unpin>(
+        &mut self,
+        path: &str,
+        modified: DateTime<Utc>,
+        expected_size: u64,
+        content: &mut R,
+    ) -> io::Result<()> {
+        let name = path.to_string();
+        let method = self.compression.method();
+        let zip64 = self.compression.max_compressed_size(expected_size) >= ZIP64_LIMIT;
+        let (time, date) = dos_date_time(modified);
+        let offset = self.offset;
+
+        let mut header = Vec::with_capacity(50 + name.len());
+        put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+        put_u16(
+            &mut header,
+            if zip64 {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            },
+        );
+        put_u16(&mut header, FLAG_DATA_DESCRIPTOR | FLAG_UTF8);
+        put_u16(&mut header, method);
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        put_u32(&mut header, 0);
+        if zip64 {
+            put_u32(&mut header, u32::MAX);
+            put_u32(&mut header, u32::MAX);
+            put_u16(&mut header, name.len() as u16);
+            put_u16(&mut header, 20);
+            header.extend_from_slice(name.as_bytes());
+            put_u16(&mut header, ZIP64_EXTRA_FIELD_ID);
+            put_u16(&mut header, 16);
+            put_u64(&mut header, 0);
+            put_u64(&mut header, 0);
+        } else {
+            put_u32(&mut header, 0);
+            put_u32(&mut header, 0);
+            put_u16(&mut header, name.len() as u16);
+            put_u16(&mut header, 0);
+            header.extend_from_slice(name.as_bytes());
+        }
+        self.write(&header).await?;
+
+        let mut hasher = Hasher::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let data_start = self.offset;
+        // Compressed data collects in the vector of the encoder until it is sent
+        let mut encoder = match self.compression {
+            ZipCompression::Stored => None,
+            ZipCompression::Deflate => Some(DeflateEncoder::new(vec![], Compression::default())),
+        };
+
+        loop {
+            let read = content.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+
+            match encoder.as_mut() {
+                Some(encoder) => {
+                    encoder.write_all(&buffer[..read])?;
+                    let compressed = std::mem::take(encoder.get_mut());
+                    self.write(&compressed).await?;
+                }
+                None => self.write(&buffer[..read]).await?,
+            }
+        }
+
+        if let Some(encoder) = encoder {
+            self.write(&encoder.finish()?).await?;
+        }
+        let compressed_size = self.offset - data_start;
+
+        if !zip64 && (size >= ZIP64_LIMIT || compressed_size >= ZIP64_LIMIT) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is larger than expected", name),
+            ));
+        }
+
+        let crc32 = hasher.finalize();
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, crc32);
+        if zip64 {
+            put_u64(&mut descriptor, compressed_size);
+            put_u64(&mut descriptor, size);
+        } else {
+            put_u32(&mut descriptor, compressed_size as u32);
+            put_u32(&mut descriptor, size as u32);
+        }
+        self.write(&descriptor).await?;
+
+        self.entries.push(CentralDirectoryEntry {
+            name,
+            time,
+            date,
+            method,
+            crc32,
+            size,
+            compressed_size,
+            offset,
+            zip64,
+            directory: false,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the central directory, the archive is only readable once it is finished
+    pub async fn finish(mut self) -> io::Result<W> {
+        let central_directory_offset = self.offset;
+        let entries = std::mem::take(&mut self.entries);
+
+        for entry in &entries {
+            let mut extra = vec![];
+            if entry.size >= ZIP64_LIMIT {
+                put_u64(&mut extra, entry.size);
+            }
+            if entry.compressed_size >= ZIP64_LIMIT {
+                put_u64(&mut extra, entry.compressed_size);
+            }
+            if entry.offset >= ZIP64_LIMIT {
+                put_u64(&mut extra, entry.offset);
+            }
+
+            let mut header = Vec::with_capacity(50 + entry.name.len() + extra.len());
+            put_u32(&mut header, CENTRAL_DIRECTORY_HEADER_SIGNATURE);
+            put_u16(&mut header, MADE_BY_UNIX | VERSION_ZIP64);
+            put_u16(
+                &mut header,
+                if entry.zip64 || !extra.is_empty() {
+                    VERSION_ZIP64
+                } else {
+                    VERSION_DEFAULT
+                },
+            );
+            put_u16(
+                &mut header,
+                if entry.directory {
+                    FLAG_UTF8
+                } else {
+                    FLAG_DATA_DESCRIPTOR | FLAG_UTF8
+                },
+            );
+            put_u16(&mut header, entry.method);
+            put_u16(&mut header, entry.time);
+            put_u16(&mut header, entry.date);
+            put_u32(&mut header, entry.crc32);
+            put_u32(&mut header, entry.compressed_size.min(ZIP64_LIMIT) as u32);
+            put_u32(&mut header, entry.size.min(ZIP64_LIMIT) as u32);
+            put_u16(&mut header, entry.name.len() as u16);
+            put_u16(
+                &mut header,
+                if extra.is_empty() {
+                    0
+                } else {
+                    extra.len() as u16 + 4
+                },
+            );
+            put_u16(&mut header, 0);
+            put_u16(&mut header, 0);
+            put_u16(&mut header, 0);
+            put_u32(
+                &mut header,
+                if entry.directory {
+                    DIRECTORY_ATTRIBUTES
+                } else {
+                    FILE_ATTRIBUTES
+                },
+            );
+            put_u32(&mut header, entry.offset.min(ZIP64_LIMIT) as u32);
+            header.extend_from_slice(entry.name.as_bytes());
+            if !extra.is_empty() {
+                put_u16(&mut header, ZIP64_EXTRA_FIELD_ID);
+                put_u16(&mut header, extra.len() as u16);
+                header.extend_from_slice(&extra);
+            }
+            self.write(&header).await?;
+        }
+
+        let central_directory_size = self.offset - central_directory_offset;
+        let count = entries.len();
+
+        let mut end = vec![];
+        if count >= ZIP64_COUNT_LIMIT
+            || central_directory_offset >= ZIP64_LIMIT
+            || central_directory_size >= ZIP64_LIMIT
+        {
+            let zip64_end_offset = self.offset;
+
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+            put_u64(&mut end, 44);
+            put_u16(&mut end, MADE_BY_UNIX | VERSION_ZIP64);
+            put_u16(&mut end, VERSION_ZIP64);
+            put_u32(&mut end, 0);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, count as u64);
+            put_u64(&mut end, count as u64);
+            put_u64(&mut end, central_directory_size);
+            put_u64(&mut end, central_directory_offset);
+
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, zip64_end_offset);
+            put_u32(&mut end, 1);
+        }
+
+        put_u32(&mut end, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, count.min(ZIP64_COUNT_LIMIT) as u16);
+        put_u16(&mut end, count.min(ZIP64_COUNT_LIMIT) as u16);
+        put_u32(&mut end, central_directory_size.min(ZIP64_LIMIT) as u32);
+        put_u32(&mut end, central_directory_offset.min(ZIP64_LIMIT) as u32);
+        put_u16(&mut end, 0);
+        self.write(&end).await?;
+
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+}
+
+/// MS-DOS time and date, which can't go back further than 1980
+fn dos_date_time(time: DateTime<Utc>) -> (u16, u16) {
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+
+    let dos_time = (time.hour() << 11) | (time.minute() << 5) | (time.second() / 2);
+    let dos_date = (((time.year() - 1980).min(127) as u32) << 9) | (time.month() << 5) | time.day();
+
+    (dos_time as u16, dos_date as u16)
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
@@ -4,5 +4,9 @@ pub const FALLBACK_STORAGE_LIMIT: i64 = 10 * 1024 * 1024 * 1024;
 /* Session constants */
 pub const SESSION_USER_ID_KEY: &str = "user_id";
 pub const SESSION_NAME: &str = "comet-trail";
+pub const SESSION_USER_AGENT_KEY: &str = "user_agent";
+pub const SESSION_IP_ADDRESS_KEY: &str = "ip_address";
+pub const SESSION_PENDING_TWO_FACTOR_KEY: &str = "pending_two_factor";
+pub const SESSION_OIDC_LOGIN_KEY: &str = "oidc_login";
 pub const QUICK_SHARE_FOLDER_NAME: &str = "Quick Share";
 pub const MAX_QUICK_SHARE_FILES: i8 = 100;
@@ -0,0 +1,55 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::document::pdftoppm::PdftoppmRenderer;
+use crate::document::pdftotext::PdftotextExtractor;
+
+pub mod pdftoppm;
+pub mod pdftotext;
+
+pub type KosmosPageRenderer = Arc<dyn PageRenderer>;
+pub type KosmosTextExtractor = Arc<dyn TextExtractor>;
+
+/// Rasterizes pages of PDF documents, which are turned into their formats.
+#[async_trait]
+pub trait PageRenderer: Send + Sync {
+    /// The first page, scaled to fit into `max_size` and encoded as PNG.
+    async fn first_page(&self, path: &Path, max_size: u32) -> io::Result<Vec<u8>>;
+}
+
+/// Extracts the text of PDF documents, which is added to the search index.
+#[async_trait]
+pub trait TextExtractor: Send + Sync {
+    async fn text(&self, path: &Path) -> io::Result<String>;
+}
+
+/// PDFs only get previews if poppler is installed, the location of pdftoppm can be set in
+/// `PDFTOPPM_PATH`
+pub fn init() -> Option<KosmosPageRenderer> {
+    let renderer = PdftoppmRenderer::from_env();
+
+    if !renderer.is_available() {
+        tracing::warn!(name: "bootstrap", "pdftoppm not found, PDFs get no previews");
+        return None;
+    }
+
+    tracing::info!(name: "bootstrap", "Using pdftoppm for PDF previews");
+    Some(Arc::new(renderer))
+}
+
+/// PDFs are only searchable by their content if poppler is installed, the location of pdftotext
+/// can be set in `PDFTOTEXT_PATH`
+pub fn init_text_extractor() -> Option<KosmosTextExtractor> {
+    let extractor = PdftotextExtractor::from_env();
+
+    if !extractor.is_available() {
+        tracing::warn!(name: "bootstrap", "pdftotext not found, PDFs are only searchable by name");
+        return None;
+    }
+
+    tracing::info!(name: "bootstrap", "Using pdftotext for PDF search");
+    Some(Arc::new(extractor))
+}
@@ -0,0 +1,60 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::document::PageRenderer;
+use crate::storage;
+use crate::utils::process;
+
+/// Runs taking longer are killed, malformed PDFs can keep poppler busy indefinitely
+const DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+
+pub struct PdftoppmRenderer {
+    pdftoppm: PathBuf,
+    timeout: Duration,
+}
+
+impl PdftoppmRenderer {
+    pub fn from_env() -> Self {
+        PdftoppmRenderer {
+            pdftoppm: PathBuf::from(
+                std::env::var("PDFTOPPM_PATH").unwrap_or("pdftoppm".to_string()),
+            ),
+            timeout: process::timeout_from_env("POPPLER_TIMEOUT_SECONDS", DEFAULT_TIMEOUT_SECONDS),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        process::is_available(&self.pdftoppm, "-v")
+    }
+}
+
+#[async_trait]
+impl PageRenderer for PdftoppmRenderer {
+    async fn first_page(&self, path: &Path, max_size: u32) -> io::Result<Vec<u8>> {
+        // pdftoppm appends the extension to the output root itself
+        let output_root = storage::unique_temp_path("page");
+        let output_path = output_root.with_extension("png");
+
+        let rendered = process::run(
+            Command::new(&self.pdftoppm)
+                .args(["-f", "1", "-l", "1", "-singlefile", "-png", "-scale-to"])
+                .arg(max_size.to_string())
+                .arg(path)
+                .arg(&output_root),
+            self.timeout,
+        )
+        .await;
+
+        let page = match rendered {
+            Ok(_) => tokio::fs::read(&output_path).await,
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&output_path).await;
+
+        page
+    }
+}
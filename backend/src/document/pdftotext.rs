@@ -0,0 +1,49 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::document::TextExtractor;
+use crate::utils::process;
+
+/// Runs taking longer are killed, malformed PDFs can keep poppler busy indefinitely
+const DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+
+pub struct PdftotextExtractor {
+    pdftotext: PathBuf,
+    timeout: Duration,
+}
+
+impl PdftotextExtractor {
+    pub fn from_env() -> Self {
+        PdftotextExtractor {
+            pdftotext: PathBuf::from(
+                std::env::var("PDFTOTEXT_PATH").unwrap_or("pdftotext".to_string()),
+            ),
+            timeout: process::timeout_from_env("POPPLER_TIMEOUT_SECONDS", DEFAULT_TIMEOUT_SECONDS),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        process::is_available(&self.pdftotext, "-v")
+    }
+}
+
+#[async_trait]
+impl TextExtractor for PdftotextExtractor {
+    async fn text(&self, path: &Path) -> io::Result<String> {
+        // "-" writes the text to stdout
+        let output = process::run(
+            Command::new(&self.pdftotext)
+                .args(["-q", "-enc", "UTF-8"])
+                .arg(path)
+                .arg("-"),
+            self.timeout,
+        )
+        .await?;
+
+        Ok(String::from_utf8_lossy(&output).to_string())
+    }
+}
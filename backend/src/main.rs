@@ -1,8 +1,8 @@
 use axum::http::header::{
-    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_EXPOSE_HEADERS, CONTENT_DISPOSITION,
-    CONTENT_TYPE,
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_EXPOSE_HEADERS, AUTHORIZATION,
+    CONTENT_DISPOSITION, CONTENT_TYPE, LOCATION,
 };
-use axum::http::{HeaderValue, Method};
+use axum::http::{HeaderName, HeaderValue, Method};
 use std::net::SocketAddr;
 use tower_http::cors::CorsLayer;
 
@@ -15,12 +15,16 @@ pub mod routes;
 pub mod services;
 pub mod session;
 
+mod archive;
 mod constants;
+mod document;
 mod folders;
 mod router;
 mod runtimes;
 mod state;
+mod storage;
 mod utils;
+mod video;
 mod webauthn;
 
 #[tokio::main]
@@ -58,28 +62,62 @@ async fn main() {
             Method::DELETE,
             Method::PATCH,
             Method::PUT,
+            Method::HEAD,
         ])
         .allow_origin(cors_origin)
         .allow_headers([
             CONTENT_TYPE,
+            AUTHORIZATION,
             ACCESS_CONTROL_ALLOW_CREDENTIALS,
             ACCESS_CONTROL_EXPOSE_HEADERS,
+            HeaderName::from_static("tus-resumable"),
+            HeaderName::from_static("upload-length"),
+            HeaderName::from_static("upload-offset"),
+            HeaderName::from_static("upload-metadata"),
+        ])
+        .expose_headers([
+            CONTENT_DISPOSITION,
+            LOCATION,
+            HeaderName::from_static("tus-resumable"),
+            HeaderName::from_static("tus-version"),
+            HeaderName::from_static("upload-length"),
+            HeaderName::from_static("upload-offset"),
         ])
-        .expose_headers([CONTENT_DISPOSITION])
         .allow_credentials(true);
 
-    let session_layer = session::init();
-
     let db = db::init().await;
 
+    let session_layer = session::init(&db);
+
     let webauthn = webauthn::init();
 
+    let storage = storage::init();
+
+    let frame_extractor = video::init();
+
+    let page_renderer = document::init();
+
+    let text_extractor = document::init_text_extractor();
+
     tracing::info!(name: "bootstrap", "Starting server");
 
-    let state = state::init(&db, &webauthn);
+    let state = state::init(
+        &db,
+        &webauthn,
+        &storage,
+        &frame_extractor,
+        &page_renderer,
+        &text_extractor,
+    );
 
     state.operation_service.startup_prepare().await;
     state.file_service.startup_prepare().await;
+    state.upload_service.startup_prepare().await;
+    state.version_service.startup_prepare().await;
+    state.dav_service.startup_prepare().await;
+    state.active_session_service.startup_prepare().await;
+    state.job_service.startup_prepare(&state).await;
+    state.bin_service.startup_prepare().await;
 
     let router = router::init(cors, session_layer, state);
 
@@ -92,7 +130,10 @@ async fn main() {
 
     tracing::info!(name: "bootstrap", "Listening on {}", socket_addr);
 
-    axum::serve(listener, router.into_make_service())
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
         .await
         .unwrap()
 }
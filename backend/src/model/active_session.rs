@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::Uuid;
+use sqlx::FromRow;
+use ts_rs::TS;
+
+// Start: Active Session Model
+#[derive(Clone, FromRow, Debug)]
+pub struct ActiveSessionModel {
+    pub uuid: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Whether this is the session of the request which listed the sessions
+    pub current: bool,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct ActiveSessionModelDTO {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub current: bool,
+}
+
+impl From<ActiveSessionModel> for ActiveSessionModelDTO {
+    fn from(model: ActiveSessionModel) -> Self {
+        ActiveSessionModelDTO {
+            id: model.uuid.to_string(),
+            user_agent: model.user_agent,
+            ip_address: model.ip_address,
+            expires_at: model.expires_at,
+            last_seen_at: model.last_seen_at,
+            created_at: model.created_at,
+            current: model.current,
+        }
+    }
+}
+// End: Active Session Model
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::model::internal::token_scope::TokenScope;
+use crate::services::session_service::UserId;
+
+// Start: API Token Model
+#[derive(Clone, FromRow, Debug)]
+pub struct ApiTokenModel {
+    pub id: i64,
+    pub user_id: UserId,
+    pub name: String,
+    pub token_hash: String,
+    pub scope: TokenScope,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct ApiTokenModelDTO {
+    pub id: String,
+    pub name: String,
+    pub scope: i16,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiTokenModel> for ApiTokenModelDTO {
+    fn from(model: ApiTokenModel) -> Self {
+        ApiTokenModelDTO {
+            id: model.id.to_string(),
+            name: model.name,
+            scope: model.scope as i16,
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// The token is only shown once, when it is created
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct CreatedApiTokenDTO {
+    pub api_token: ApiTokenModelDTO,
+    pub token: String,
+}
+// End: API Token Model
@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::services::session_service::UserId;
+
+// Start: App Password Model
+#[derive(Clone, FromRow, Debug)]
+pub struct AppPasswordModel {
+    pub id: i64,
+    pub user_id: UserId,
+    pub name: String,
+    pub password_hash: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct AppPasswordModelDTO {
+    pub id: String,
+    pub name: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AppPasswordModel> for AppPasswordModelDTO {
+    fn from(model: AppPasswordModel) -> Self {
+        AppPasswordModelDTO {
+            id: model.id.to_string(),
+            name: model.name,
+            last_used_at: model.last_used_at,
+            created_at: model.created_at,
+        }
+    }
+}
+
+/// The password is only shown once, when it is created
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct CreatedAppPasswordDTO {
+    pub app_password: AppPasswordModelDTO,
+    pub password: String,
+}
+// End: App Password Model
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use sqlx::types::Uuid;
+use crate::services::session_service::UserId;
+
+// Start: Dav Lock Model
+#[derive(Clone, FromRow, Debug)]
+pub struct DavLockModel {
+    pub token: Uuid,
+    pub user_id: UserId,
+    pub path: String,
+    pub exclusive: bool,
+    pub depth_infinity: bool,
+    pub owner: Option<String>,
+    pub timeout: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DavLockModel {
+    pub fn lock_token(&self) -> String {
+        format!("opaquelocktoken:{}", self.token)
+    }
+
+    /// Whether the lock applies to the resource at the path
+    pub fn covers(&self, path: &str) -> bool {
+        self.path == path || (self.depth_infinity && is_descendant(path, &self.path))
+    }
+}
+
+/// Whether the path lies below the collection path
+pub fn is_descendant(path: &str, collection: &str) -> bool {
+    collection == "/" && path != "/"
+        || path
+            .strip_prefix(collection)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+// End: Dav Lock Model
@@ -4,7 +4,9 @@ use sqlx::types::{JsonValue, Uuid};
 use sqlx::FromRow;
 use ts_rs::TS;
 use crate::model::internal::file_type::FileType;
+use crate::model::tag::TagModelDTO;
 use crate::services::session_service::UserId;
+use crate::storage;
 
 // Start: File Model
 #[derive(Clone, FromRow, Debug, Serialize)]
@@ -22,12 +24,26 @@ pub struct FileModel {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub blob_hash: Option<String>,
+    pub deleted_with: Option<i64>,
 }
 
 impl FileModel {
     pub fn is_valid_to_edit_content(&self) -> bool {
         FileType::FILE_TYPES_FOR_UPDATE.contains(&self.file_type)
     }
+
+    pub fn content_key(&self) -> String {
+        storage::content_key(self.id, self.blob_hash.as_deref())
+    }
+
+    /// Strong ETag of the content, files stored before deduplication have no hash to use
+    pub fn etag(&self) -> String {
+        match &self.blob_hash {
+            Some(hash) => format!("\"{}\"", hash),
+            None => format!("\"{}-{}\"", self.id, self.updated_at.timestamp_millis()),
+        }
+    }
 }
 
 #[derive(Serialize, TS)]
@@ -47,6 +63,11 @@ pub struct FileModelDTO {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Only filled in for the owner, see `TagService::tag_files`
+    pub tags: Vec<TagModelDTO>,
+    /// Only filled in for files in the bin which are purged automatically
+    #[ts(type = "number | null")]
+    pub days_until_purge: Option<i64>,
 }
 
 impl From<FileModel> for FileModelDTO {
@@ -65,6 +86,8 @@ impl From<FileModel> for FileModelDTO {
             created_at: model.created_at,
             updated_at: model.updated_at,
             deleted_at: model.deleted_at,
+            tags: vec![],
+            days_until_purge: None,
         }
     }
 }
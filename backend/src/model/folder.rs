@@ -3,6 +3,7 @@ use serde::Serialize;
 use sqlx::FromRow;
 use sqlx::types::Uuid;
 use ts_rs::TS;
+use crate::model::tag::TagModelDTO;
 use crate::services::session_service::UserId;
 
 // Start: Folder Model
@@ -16,6 +17,8 @@ pub struct FolderModel {
     pub color: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_with: Option<i64>,
 }
 
 #[derive(Serialize, TS)]
@@ -29,6 +32,12 @@ pub struct FolderModelDTO {
     pub color: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Only filled in for the owner, see `TagService::tag_folders`
+    pub tags: Vec<TagModelDTO>,
+    /// Only filled in for folders in the bin which are purged automatically
+    #[ts(type = "number | null")]
+    pub days_until_purge: Option<i64>,
 }
 
 impl From<FolderModel> for FolderModelDTO {
@@ -42,6 +51,9 @@ impl From<FolderModel> for FolderModelDTO {
             color: model.color,
             created_at: model.created_at,
             updated_at: model.updated_at,
+            deleted_at: model.deleted_at,
+            tags: vec![],
+            days_until_purge: None,
         }
     }
 }
@@ -127,9 +139,15 @@ pub struct Directory {
     pub id: i64,
     pub folder_name: String,
     pub user_id: UserId,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub path: Vec<String>,
     pub files: Vec<i64>,
     pub file_names: Vec<String>,
+    pub file_blob_hashes: Vec<Option<String>>,
+    pub file_sizes: Vec<i64>,
+    pub file_updated_at: Vec<DateTime<Utc>>,
 }
 // End: Directory
 
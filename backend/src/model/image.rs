@@ -11,4 +11,6 @@ pub struct ImageFormatModel {
     pub width: i32,
     pub height: i32,
     pub created_at: DateTime<Utc>,
+    /// `ImageCodec`, or `ExcerptCodec` for excerpts
+    pub codec: i16,
 }
@@ -0,0 +1,50 @@
+use crate::utils::http::parse_accept;
+
+/// Encodings of document excerpts. They share the codec column with `ImageCodec`, so their
+/// values continue after it.
+#[repr(i16)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExcerptCodec {
+    /// Highlighted with inline styles
+    Html = 3,
+    Text = 4,
+}
+
+impl From<i16> for ExcerptCodec {
+    fn from(num: i16) -> Self {
+        Self::new(num)
+    }
+}
+
+impl ExcerptCodec {
+    pub fn new(num: i16) -> Self {
+        match num {
+            4 => ExcerptCodec::Text,
+            _ => ExcerptCodec::Html,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExcerptCodec::Html => "text/html",
+            ExcerptCodec::Text => "text/plain",
+        }
+    }
+
+    /// HTML is served unless the `Accept` header of the client prefers plain text over it
+    pub fn negotiate(accept: Option<&str>) -> ExcerptCodec {
+        let ranges = accept.map(parse_accept).unwrap_or_default();
+        let quality = |codec: ExcerptCodec| {
+            ranges
+                .iter()
+                .find(|(media_type, _)| media_type == codec.mime_type())
+                .map_or(0.0, |(_, quality)| *quality)
+        };
+
+        if quality(ExcerptCodec::Text) > quality(ExcerptCodec::Html) {
+            ExcerptCodec::Text
+        } else {
+            ExcerptCodec::Html
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use sqlx::Type;
+
+use crate::utils::http::parse_accept;
+
+#[repr(i16)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Type)]
+pub enum ImageCodec {
+    Jpeg = 0,
+    WebP = 1,
+    Avif = 2,
+}
+
+impl From<i16> for ImageCodec {
+    fn from(num: i16) -> Self {
+        Self::new(num)
+    }
+}
+
+impl ImageCodec {
+    pub fn new(num: i16) -> Self {
+        match num {
+            1 => ImageCodec::WebP,
+            2 => ImageCodec::Avif,
+            _ => ImageCodec::Jpeg,
+        }
+    }
+
+    pub fn codec_by_name(name: &str) -> Option<Self> {
+        match name {
+            "jpeg" | "jpg" => Some(ImageCodec::Jpeg),
+            "webp" => Some(ImageCodec::WebP),
+            "avif" => Some(ImageCodec::Avif),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageCodec::Jpeg => "image/jpeg",
+            ImageCodec::WebP => "image/webp",
+            ImageCodec::Avif => "image/avif",
+        }
+    }
+
+    /// Picks the codec the client prefers by its `Accept` header. JPEG is used if the header
+    /// is missing or accepts none of the available codecs, since every client can show it,
+    /// or the first available codec if there is no JPEG.
+    pub fn negotiate(accept: Option<&str>, available: &[ImageCodec]) -> ImageCodec {
+        let fallback = match available.first() {
+            Some(codec) if !available.contains(&ImageCodec::Jpeg) => *codec,
+            _ => ImageCodec::Jpeg,
+        };
+
+        let Some(accept) = accept else {
+            return fallback;
+        };
+
+        let ranges = parse_accept(accept);
+
+        available
+            .iter()
+            .filter_map(|codec| {
+                // An exact media type wins over a wildcard, whatever its quality
+                let exact = ranges
+                    .iter()
+                    .find(|(media_type, _)| media_type == codec.mime_type());
+                let wildcard = ranges
+                    .iter()
+                    .find(|(media_type, _)| media_type == "image/*" || media_type == "*/*");
+
+                let (quality, is_exact) = match (exact, wildcard) {
+                    (Some((_, quality)), _) => (*quality, true),
+                    (None, Some((_, quality))) => (*quality, false),
+                    (None, None) => return None,
+                };
+
+                (quality > 0.0).then_some((*codec, quality, is_exact))
+            })
+            .max_by(|(a, a_quality, a_exact), (b, b_quality, b_exact)| {
+                a_quality
+                    .total_cmp(b_quality)
+                    .then(a_exact.cmp(b_exact))
+                    // Newer codecs are smaller at the same quality
+                    .then((*a as i16).cmp(&(*b as i16)))
+            })
+            .map(|(codec, _, _)| codec)
+            .unwrap_or(fallback)
+    }
+}
@@ -6,6 +6,12 @@ use crate::response::error_handling::AppError;
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Type)]
 pub enum ImageFormat {
     Thumbnail = 0,
+    Preview = 1,
+    Large = 2,
+    /// Storyboard of a video, frames spread over its duration in a grid
+    Sprite = 3,
+    /// Beginning of a text document, highlighted if it is code
+    Excerpt = 4,
 }
 
 impl From<i16> for ImageFormat {
@@ -17,26 +23,50 @@ impl From<i16> for ImageFormat {
 impl ImageFormat {
     pub fn format_by_id_unsafe(num: i16) -> Self {
         match num {
+            1 => ImageFormat::Preview,
+            2 => ImageFormat::Large,
+            3 => ImageFormat::Sprite,
+            4 => ImageFormat::Excerpt,
             _ => ImageFormat::Thumbnail,
         }
     }
     pub fn format_by_id_save(num: i16) -> Result<Self, AppError> {
-        Ok(Self::format_by_id_unsafe(num))
+        match num {
+            0..=4 => Ok(Self::format_by_id_unsafe(num)),
+            _ => Err(AppError::BadRequest {
+                error: Some("Invalid image format".to_string()),
+            }),
+        }
     }
 
     pub fn id_by_format(num: ImageFormat) -> i16 {
         match num {
             ImageFormat::Thumbnail => 0,
+            ImageFormat::Preview => 1,
+            ImageFormat::Large => 2,
+            ImageFormat::Sprite => 3,
+            ImageFormat::Excerpt => 4,
         }
     }
 
+    /// Default width of the format, can be overridden in `IMAGE_FORMATS`. For sprites this is
+    /// the width of a single frame, excerpts are measured in characters instead.
     pub fn width_by_format(&self) -> u32 {
         match self {
             ImageFormat::Thumbnail => 256,
+            ImageFormat::Preview => 1280,
+            ImageFormat::Large => 2560,
+            ImageFormat::Sprite => 160,
+            ImageFormat::Excerpt => 120,
         }
     }
 
-    pub const IMAGE_FORMATS: [ImageFormat; 1] = [
-        ImageFormat::Thumbnail,
-    ];
-}
\ No newline at end of file
+    pub fn format_by_name(name: &str) -> Option<Self> {
+        match name {
+            "thumbnail" => Some(ImageFormat::Thumbnail),
+            "preview" => Some(ImageFormat::Preview),
+            "large" => Some(ImageFormat::Large),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use serde::Serialize;
+use sqlx::Type;
+
+use crate::model::internal::operation_type::OperationType;
+
+#[repr(i16)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Type)]
+pub enum JobType {
+    ImageProcessing = 0,
+    ContentIndexing = 1,
+    BinPurge = 2,
+    ArchiveExtraction = 3,
+}
+
+impl From<i16> for JobType {
+    fn from(num: i16) -> Self {
+        Self::new(num)
+    }
+}
+
+impl JobType {
+    pub fn new(num: i16) -> JobType {
+        match num {
+            1 => JobType::ContentIndexing,
+            2 => JobType::BinPurge,
+            3 => JobType::ArchiveExtraction,
+            _ => JobType::ImageProcessing,
+        }
+    }
+
+    /// Type of the operation the job is shown as to its user
+    pub fn operation_type(&self) -> OperationType {
+        match self {
+            JobType::ImageProcessing => OperationType::ImageProcessing,
+            JobType::ContentIndexing => OperationType::ContentIndexing,
+            JobType::BinPurge => OperationType::BinPurge,
+            JobType::ArchiveExtraction => OperationType::ArchiveExtraction,
+        }
+    }
+}
@@ -1,7 +1,12 @@
 pub mod file_type;
 pub mod preview_status;
 pub mod image_format;
+pub mod image_codec;
+pub mod excerpt_codec;
 pub mod operation_type;
 pub mod operation_status;
 pub mod share_type;
-pub mod zip;
\ No newline at end of file
+pub mod share_mode;
+pub mod zip;
+pub mod token_scope;
+pub mod job_type;
\ No newline at end of file
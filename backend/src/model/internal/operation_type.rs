@@ -6,6 +6,9 @@ use sqlx::Type;
 pub enum OperationType {
     General = 0,
     ImageProcessing = 1,
+    ContentIndexing = 2,
+    BinPurge = 3,
+    ArchiveExtraction = 4,
 }
 
 impl From<i16> for OperationType {
@@ -18,6 +21,9 @@ impl OperationType {
     pub fn new(num: i16) -> OperationType {
         match num {
             1 => OperationType::ImageProcessing,
+            2 => OperationType::ContentIndexing,
+            3 => OperationType::BinPurge,
+            4 => OperationType::ArchiveExtraction,
             _ => OperationType::General,
         }
     }
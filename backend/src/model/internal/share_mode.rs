@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+
+/// What visitors of a folder share can do
+#[repr(i16)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, Type)]
+pub enum ShareMode {
+    #[default]
+    ReadOnly = 0,
+    /// Visitors can browse the folder and upload into it
+    Upload = 1,
+    /// Visitors can only upload, the content of the folder stays hidden
+    DropOnly = 2,
+}
+
+impl From<i16> for ShareMode {
+    fn from(num: i16) -> Self {
+        Self::new(num)
+    }
+}
+
+impl ShareMode {
+    pub fn new(num: i16) -> ShareMode {
+        match num {
+            1 => ShareMode::Upload,
+            2 => ShareMode::DropOnly,
+            _ => ShareMode::ReadOnly,
+        }
+    }
+
+    pub fn accepts_uploads(&self) -> bool {
+        matches!(self, ShareMode::Upload | ShareMode::DropOnly)
+    }
+}
@@ -0,0 +1,96 @@
+use axum::http::Method;
+use serde::Serialize;
+use sqlx::Type;
+
+/// Routes which manage credentials can only be used with the session of a login
+const SESSION_ONLY_PATHS: [&str; 7] = [
+    "/api/v1/auth/user/api-token",
+    "/api/v1/auth/user/app-password",
+    "/api/v1/auth/user/password",
+    "/api/v1/auth/user/session",
+    "/api/v1/auth/user/totp",
+    "/api/v1/auth/passkey",
+    "/api/v1/auth/oidc",
+];
+
+/// Routes a token for uploads can use, `{id}` stands for a numeric path segment. Routes are
+/// listed one by one, so routes added later are not allowed by accident.
+const UPLOAD_ONLY_ROUTES: [(Method, &str); 11] = [
+    (Method::GET, "/api/v1/auth"),
+    (Method::POST, "/api/v1/auth/file/upload"),
+    (Method::POST, "/api/v1/auth/file/upload/{id}"),
+    (Method::POST, "/api/v1/auth/file/tus"),
+    (Method::HEAD, "/api/v1/auth/file/tus/{id}"),
+    (Method::PATCH, "/api/v1/auth/file/tus/{id}"),
+    (Method::DELETE, "/api/v1/auth/file/tus/{id}"),
+    // Uploads can create folders to upload into and list them, but can't read any content
+    (Method::POST, "/api/v1/auth/folder"),
+    (Method::POST, "/api/v1/auth/folder/{id}"),
+    (Method::GET, "/api/v1/auth/folder/all"),
+    (Method::GET, "/api/v1/auth/folder/all/{id}"),
+];
+
+#[repr(i16)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Type)]
+pub enum TokenScope {
+    Full = 0,
+    ReadOnly = 1,
+    UploadOnly = 2,
+}
+
+impl From<i16> for TokenScope {
+    fn from(num: i16) -> Self {
+        Self::new(num)
+    }
+}
+
+impl TokenScope {
+    pub fn new(num: i16) -> TokenScope {
+        match num {
+            1 => TokenScope::ReadOnly,
+            2 => TokenScope::UploadOnly,
+            _ => TokenScope::Full,
+        }
+    }
+
+    /// Checks if a request with the method to the path is allowed with a token of this scope
+    pub fn allows(&self, method: &Method, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+
+        if SESSION_ONLY_PATHS
+            .iter()
+            .any(|session_path| path.starts_with(session_path))
+        {
+            return false;
+        }
+
+        let reading = method == Method::GET || method == Method::HEAD;
+
+        match self {
+            TokenScope::Full => true,
+            TokenScope::ReadOnly => reading,
+            TokenScope::UploadOnly => UPLOAD_ONLY_ROUTES.iter().any(|(route_method, route)| {
+                (route_method == method || (reading && route_method == Method::GET))
+                    && matches_route(route, path)
+            }),
+        }
+    }
+}
+
+fn matches_route(route: &str, path: &str) -> bool {
+    let mut route_segments = route.split('/');
+    let mut path_segments = path.split('/');
+
+    loop {
+        match (route_segments.next(), path_segments.next()) {
+            (None, None) => return true,
+            (Some("{id}"), Some(segment)) => {
+                if segment.is_empty() || !segment.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return false;
+                }
+            }
+            (Some(route_segment), Some(segment)) if route_segment == segment => {}
+            _ => return false,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use ts_rs::TS;
 
@@ -5,29 +6,51 @@ use ts_rs::TS;
 #[ts(export)]
 pub struct ZipInformation {
     pub name: String,
+    pub modified: Option<DateTime<Utc>>,
     pub folders: Vec<ZipInformation>,
     pub files: Vec<String>,
+    /// Size and time of each file, in the same order as `files`
+    pub file_details: Vec<ZipFileInformation>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct ZipFileInformation {
+    pub name: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
 }
 
 impl ZipInformation {
     pub fn new(name: &str) -> Self {
         ZipInformation {
             name: name.to_string(),
+            modified: None,
             folders: Vec::new(),
             files: Vec::new(),
+            file_details: Vec::new(),
         }
     }
 
-    pub fn add_path(&mut self, path_parts: &[&str]) {
+    /// Adds a file, or a folder if the last part is empty. The size and time belong to the
+    /// last part, folders on the way are created without a time.
+    pub fn add_path(&mut self, path_parts: &[&str], size: u64, modified: Option<DateTime<Utc>>) {
         if path_parts.is_empty() {
             return;
         }
 
         if path_parts.len() == 1 {
             if path_parts[0].is_empty() {
+                self.modified = modified;
                 return;
             }
             self.files.push(path_parts[0].to_string());
+            self.file_details.push(ZipFileInformation {
+                name: path_parts[0].to_string(),
+                size,
+                modified,
+            });
         } else {
             let folder_name = path_parts[0];
             let folder = self.folders.iter_mut().find(|f| f.name == folder_name);
@@ -40,7 +63,7 @@ impl ZipInformation {
                 }
             };
 
-            folder.add_path(&path_parts[1..]);
+            folder.add_path(&path_parts[1..], size, modified);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,55 @@
+use crate::model::internal::job_type::JobType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::JsonValue;
+use sqlx::FromRow;
+
+// Start: Job Model
+#[derive(Clone, FromRow, Debug)]
+pub struct JobModel {
+    pub id: i64,
+    pub operation_id: i64,
+    pub job_type: JobType,
+    pub payload: JsonValue,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub run_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub dead_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+// End: Job Model
+
+#[derive(Serialize, Deserialize)]
+pub struct ImageProcessingPayload {
+    pub user_id: i64,
+    pub file_ids: Vec<i64>,
+    /// Operation which failed before and is recovered by this job
+    pub started_by_operation: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContentIndexingPayload {
+    pub user_id: i64,
+    pub file_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BinPurgePayload {
+    pub user_id: i64,
+    pub file_ids: Vec<i64>,
+    /// Purged with everything in them
+    #[serde(default)]
+    pub folder_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveExtractionPayload {
+    pub user_id: i64,
+    /// The archive, which may belong to another user if it was shared
+    pub file_id: i64,
+    /// Folder the folder named after the archive is created in
+    pub parent_folder_id: Option<i64>,
+}
@@ -8,4 +8,14 @@ pub mod role;
 pub mod usage;
 pub mod album;
 pub mod passkey;
+pub mod upload;
+pub mod version;
+pub mod app_password;
+pub mod dav;
+pub mod api_token;
+pub mod active_session;
+pub mod totp;
+pub mod oidc;
+pub mod job;
+pub mod tag;
 pub mod internal;
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::services::session_service::UserId;
+
+// Start: OIDC Link Model
+#[derive(Clone, FromRow, Debug)]
+pub struct OidcLinkModel {
+    pub id: i64,
+    pub user_id: UserId,
+    pub issuer: String,
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct OidcLinkModelDTO {
+    pub id: String,
+    pub issuer: String,
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OidcLinkModel> for OidcLinkModelDTO {
+    fn from(model: OidcLinkModel) -> Self {
+        OidcLinkModelDTO {
+            id: model.id.to_string(),
+            issuer: model.issuer,
+            subject: model.subject,
+            created_at: model.created_at,
+        }
+    }
+}
+// End: OIDC Link Model
+
+/// The parts of the discovery document of the identity provider which are used
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcTokenResponse {
+    pub id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcIdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    /// Either a single client id or a list of them
+    pub aud: serde_json::Value,
+    pub exp: i64,
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub claims: HashMap<String, serde_json::Value>,
+}
+
+/// State of a login between the redirect to the identity provider and its callback
+#[derive(Serialize, Deserialize)]
+pub struct OidcLoginState {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
@@ -18,6 +18,10 @@ pub struct OperationModel {
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub progress_done: i32,
+    pub progress_total: i32,
+    pub progress_errors: i32,
+    pub progress_item: Option<String>,
 }
 
 #[derive(Serialize, TS)]
@@ -31,6 +35,10 @@ pub struct OperationModelDTO {
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub progress_done: i32,
+    pub progress_total: i32,
+    pub progress_errors: i32,
+    pub progress_item: Option<String>,
 }
 
 impl From<OperationModel> for OperationModelDTO {
@@ -44,6 +52,10 @@ impl From<OperationModel> for OperationModelDTO {
             started_at: model.started_at,
             ended_at: model.ended_at,
             updated_at: model.updated_at,
+            progress_done: model.progress_done,
+            progress_total: model.progress_total,
+            progress_errors: model.progress_errors,
+            progress_item: model.progress_item,
         }
     }
 }
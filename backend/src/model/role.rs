@@ -24,6 +24,7 @@ impl Role {
                 permissions.insert(Permission::ReadUser);
                 permissions.insert(Permission::DeleteUser);
                 permissions.insert(Permission::UpdateUser);
+                permissions.insert(Permission::ProcessImages);
                 permissions
             }
             Role::User => {
@@ -52,4 +53,5 @@ pub enum Permission {
     ListUser,
     DeleteUser,
     UpdateUser,
+    ProcessImages,
 }
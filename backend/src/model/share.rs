@@ -4,6 +4,7 @@ use serde::Serialize;
 use sqlx::types::Uuid;
 use sqlx::FromRow;
 use ts_rs::TS;
+use crate::model::internal::share_mode::ShareMode;
 use crate::model::internal::share_type::ShareType;
 
 // Start: Share Model
@@ -24,6 +25,11 @@ pub struct ShareModel {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub share_mode: ShareMode,
+    pub upload_size_limit: Option<i64>,
+    pub upload_count_limit: Option<i32>,
+    pub uploaded_size: i64,
+    pub uploaded_count: i32,
 }
 
 #[derive(Serialize, TS)]
@@ -44,6 +50,13 @@ pub struct ShareModelDTO {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub share_mode: i16,
+    #[ts(type = "number | null")]
+    pub upload_size_limit: Option<i64>,
+    pub upload_count_limit: Option<i32>,
+    #[ts(type = "number")]
+    pub uploaded_size: i64,
+    pub uploaded_count: i32,
 }
 
 impl From<ShareModel> for ShareModelDTO {
@@ -65,6 +78,11 @@ impl From<ShareModel> for ShareModelDTO {
             created_at: model.created_at,
             expires_at: model.expires_at,
             updated_at: model.updated_at,
+            share_mode: model.share_mode as i16,
+            upload_size_limit: model.upload_size_limit,
+            upload_count_limit: model.upload_count_limit,
+            uploaded_size: model.uploaded_size,
+            uploaded_count: model.uploaded_count,
         }
     }
 }
@@ -86,6 +104,11 @@ pub struct ExtendedShareModel {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub share_mode: i16,
+    pub upload_size_limit: Option<i64>,
+    pub upload_count_limit: Option<i32>,
+    pub uploaded_size: i64,
+    pub uploaded_count: i32,
     pub share_target_username: Option<String>,
 }
 
@@ -108,6 +131,13 @@ pub struct ExtendedShareModelDTO {
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
     pub updated_at: DateTime<Utc>,
+    pub share_mode: i16,
+    #[ts(type = "number | null")]
+    pub upload_size_limit: Option<i64>,
+    pub upload_count_limit: Option<i32>,
+    #[ts(type = "number")]
+    pub uploaded_size: i64,
+    pub uploaded_count: i32,
 }
 
 impl From<ExtendedShareModel> for ExtendedShareModelDTO {
@@ -130,6 +160,11 @@ impl From<ExtendedShareModel> for ExtendedShareModelDTO {
             created_at: model.created_at,
             expires_at: model.expires_at,
             updated_at: model.updated_at,
+            share_mode: model.share_mode,
+            upload_size_limit: model.upload_size_limit,
+            upload_count_limit: model.upload_count_limit,
+            uploaded_size: model.uploaded_size,
+            uploaded_count: model.uploaded_count,
         }
     }
 }
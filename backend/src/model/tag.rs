@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::services::session_service::UserId;
+
+// Start: Tag Model
+#[derive(Clone, FromRow, Debug, Serialize)]
+pub struct TagModel {
+    pub id: i64,
+    pub user_id: UserId,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize, TS)]
+#[ts(export)]
+pub struct TagModelDTO {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<TagModel> for TagModelDTO {
+    fn from(model: TagModel) -> Self {
+        TagModelDTO {
+            id: model.id.to_string(),
+            name: model.name,
+            color: model.color,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+// End: Tag Model
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::services::session_service::UserId;
+
+// Start: TOTP Model
+#[derive(Clone, FromRow, Debug)]
+pub struct TotpModel {
+    pub user_id: UserId,
+    pub secret: String,
+    pub enabled: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub failed_attempts: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct TotpStatusDTO {
+    pub enabled: bool,
+    #[ts(type = "number")]
+    pub recovery_codes_remaining: i64,
+}
+
+/// Shown once when enrolling, the URI is meant to be displayed as QR code
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct TotpEnrollmentDTO {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// Recovery codes are only shown once, when they are generated
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct TotpRecoveryCodesDTO {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct TwoFactorRequiredDTO {
+    pub two_factor_required: bool,
+}
+// End: TOTP Model
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use crate::services::session_service::UserId;
+
+// Start: Tus Upload Model
+#[derive(Clone, FromRow, Debug)]
+pub struct TusUploadModel {
+    pub id: i64,
+    pub user_id: UserId,
+    pub file_name: String,
+    pub mime_type: String,
+    pub parent_folder_id: Option<i64>,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+// End: Tus Upload Model
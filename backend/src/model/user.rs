@@ -17,6 +17,13 @@ pub struct UserModel {
     pub uuid: Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version_retention_count: Option<i32>,
+    pub version_retention_days: Option<i32>,
+    pub bin_retention_days: Option<i32>,
+    #[serde(skip)]
+    pub failed_login_attempts: i32,
+    #[serde(skip)]
+    pub login_locked_until: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, TS)]
@@ -29,6 +36,9 @@ pub struct UserModelDTO {
     #[ts(type = "number")]
     pub storage_limit: i64,
     pub role: i16,
+    pub version_retention_count: Option<i32>,
+    pub version_retention_days: Option<i32>,
+    pub bin_retention_days: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -42,6 +52,9 @@ impl From<UserModel> for UserModelDTO {
             email: user.email,
             storage_limit: user.storage_limit,
             role: user.role,
+            version_retention_count: user.version_retention_count,
+            version_retention_days: user.version_retention_days,
+            bin_retention_days: user.bin_retention_days,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use ts_rs::TS;
+use crate::model::file::FileModel;
+use crate::services::session_service::UserId;
+
+// Start: File Version Model
+#[derive(Clone, FromRow, Debug)]
+pub struct FileVersionModel {
+    pub id: i64,
+    pub file_id: i64,
+    pub user_id: UserId,
+    pub blob_hash: String,
+    pub file_size: i64,
+    pub mime_type: String,
+    pub modified_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FileVersionModel {
+    /// The file as it was when this version was its content
+    pub fn as_file(&self, file: &FileModel) -> FileModel {
+        FileModel {
+            file_size: self.file_size,
+            mime_type: self.mime_type.clone(),
+            updated_at: self.modified_at,
+            blob_hash: Some(self.blob_hash.clone()),
+            ..file.clone()
+        }
+    }
+}
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct FileVersionModelDTO {
+    pub id: String,
+    pub file_id: String,
+    #[ts(type = "number")]
+    pub file_size: i64,
+    pub mime_type: String,
+    pub modified_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<FileVersionModel> for FileVersionModelDTO {
+    fn from(model: FileVersionModel) -> Self {
+        FileVersionModelDTO {
+            id: model.id.to_string(),
+            file_id: model.file_id.to_string(),
+            file_size: model.file_size,
+            mime_type: model.mime_type,
+            modified_at: model.modified_at,
+            created_at: model.created_at,
+        }
+    }
+}
+// End: File Version Model
@@ -22,59 +22,44 @@ pub struct ErrorResponse {
     pub(crate) error: Option<String>,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let status_code;
-        let body;
-
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::UserNotFound => {
-                status_code = StatusCode::NOT_FOUND;
-                body = "User not found".to_string();
-            }
-            Self::InternalError => {
-                status_code = StatusCode::INTERNAL_SERVER_ERROR;
-                body = "Internal server error".to_string();
-            }
-            Self::NotLoggedIn => {
-                status_code = StatusCode::UNAUTHORIZED;
-                body = "Not logged in".to_string();
-            }
-            Self::BadRequest { error } => {
-                status_code = StatusCode::BAD_REQUEST;
-                body = error.unwrap_or("".to_string());
-            }
-            Self::NotAllowed { error } => {
-                status_code = StatusCode::FORBIDDEN;
-                body = error;
-            }
-            Self::DataConflict { error } => {
-                status_code = StatusCode::CONFLICT;
-                body = error;
-            }
-            Self::NotFound { error } => {
-                status_code = StatusCode::NOT_FOUND;
-                body = error;
-            }
-            Self::Forbidden { error } => {
-                status_code = StatusCode::FORBIDDEN;
-                body = error.unwrap_or("".to_string());
-            }
-            Self::UnprocessableEntity { error } => {
-                status_code = StatusCode::UNPROCESSABLE_ENTITY;
-                body = error;
-            }
-            Self::Gone { error } => {
-                status_code = StatusCode::GONE;
-                body = error;
-            }
-            Self::Locked { error } => {
-                status_code = StatusCode::LOCKED;
-                body = error;
+            Self::UserNotFound => write!(f, "User not found"),
+            Self::InternalError => write!(f, "Internal server error"),
+            Self::NotLoggedIn => write!(f, "Not logged in"),
+            Self::BadRequest { error } | Self::Forbidden { error } => {
+                write!(f, "{}", error.as_deref().unwrap_or(""))
             }
+            Self::NotAllowed { error }
+            | Self::DataConflict { error }
+            | Self::NotFound { error }
+            | Self::UnprocessableEntity { error }
+            | Self::Gone { error }
+            | Self::Locked { error } => write!(f, "{}", error),
         }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            Self::UserNotFound => StatusCode::NOT_FOUND,
+            Self::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotLoggedIn => StatusCode::UNAUTHORIZED,
+            Self::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            Self::NotAllowed { .. } => StatusCode::FORBIDDEN,
+            Self::DataConflict { .. } => StatusCode::CONFLICT,
+            Self::NotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Self::UnprocessableEntity { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Gone { .. } => StatusCode::GONE,
+            Self::Locked { .. } => StatusCode::LOCKED,
+        };
 
-        let response_body = ErrorResponse { error: Some(body) };
+        let response_body = ErrorResponse {
+            error: Some(self.to_string()),
+        };
 
         (status_code, serde_json::to_string(&response_body).unwrap()).into_response()
     }
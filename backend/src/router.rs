@@ -1,8 +1,12 @@
 use crate::session::KosmosSession;
 use crate::state::AppState;
-use axum::extract::DefaultBodyLimit;
-use axum::routing::{delete, get, patch, post, put};
+use axum::extract::{DefaultBodyLimit, Request};
+use axum::http::header::ACCESS_CONTROL_REQUEST_METHOD;
+use axum::http::Method;
+use axum::middleware::{from_fn, map_response};
+use axum::routing::{any, delete, get, head, patch, post, put};
 use axum::Router;
+use tower::{service_fn, ServiceExt};
 use tower_http::cors::CorsLayer;
 use tower_http::trace;
 use tower_http::trace::TraceLayer;
@@ -26,10 +30,22 @@ fn get_folder_router() -> KosmosRouter {
             "/:folder_id/color",
             patch(crate::routes::api::v1::auth::folder::update::recolor_folder),
         )
+        .route(
+            "/:folder_id/restore",
+            post(crate::routes::api::v1::auth::folder::bin::restore_folder),
+        )
         .route(
             "/all",
             get(crate::routes::api::v1::auth::folder::get_folders),
         )
+        .route(
+            "/all/deleted",
+            get(crate::routes::api::v1::auth::folder::bin::get_deleted_folders),
+        )
+        .route(
+            "/all/deleted/:folder_id",
+            get(crate::routes::api::v1::auth::folder::bin::get_deleted_folders),
+        )
         .route(
             "/all/:folder_id",
             get(crate::routes::api::v1::auth::folder::get_folders),
@@ -52,6 +68,25 @@ fn get_image_router() -> KosmosRouter {
         )
 }
 
+fn get_tus_router() -> KosmosRouter {
+    Router::new()
+        .route(
+            "/",
+            post(crate::routes::api::v1::auth::file::upload::create_tus_upload)
+                .options(crate::routes::api::v1::auth::file::upload::get_tus_options),
+        )
+        .route(
+            "/:upload_id",
+            head(crate::routes::api::v1::auth::file::upload::get_tus_upload_offset)
+                .patch(crate::routes::api::v1::auth::file::upload::append_tus_upload)
+                .delete(crate::routes::api::v1::auth::file::upload::delete_tus_upload),
+        )
+        .layer(DefaultBodyLimit::disable())
+        .layer(map_response(
+            crate::routes::api::v1::auth::file::upload::add_tus_resumable_header,
+        ))
+}
+
 fn get_file_router() -> KosmosRouter {
     Router::new()
         .route(
@@ -75,6 +110,23 @@ fn get_file_router() -> KosmosRouter {
             "/:file_id/action/:operation_type",
             get(crate::routes::api::v1::auth::download::handle_raw_file),
         )
+        .route(
+            "/:file_id/versions",
+            get(crate::routes::api::v1::auth::file::version::get_file_versions)
+                .delete(crate::routes::api::v1::auth::file::version::prune_file_versions),
+        )
+        .route(
+            "/:file_id/versions/:version_id",
+            delete(crate::routes::api::v1::auth::file::version::delete_file_version),
+        )
+        .route(
+            "/:file_id/versions/:version_id/action/:operation_type",
+            get(crate::routes::api::v1::auth::file::version::handle_raw_file_version),
+        )
+        .route(
+            "/:file_id/versions/:version_id/restore",
+            post(crate::routes::api::v1::auth::file::version::restore_file_version),
+        )
         .route(
             "/:file_id/bin",
             post(crate::routes::api::v1::auth::file::bin::mark_file_for_deletion),
@@ -108,6 +160,10 @@ fn get_file_router() -> KosmosRouter {
             "/all/deleted",
             get(crate::routes::api::v1::auth::file::get_deleted_files),
         )
+        .route(
+            "/all/deleted/:folder_id",
+            get(crate::routes::api::v1::auth::file::get_deleted_files),
+        )
         .route(
             "/all/:folder_id",
             get(crate::routes::api::v1::auth::file::get_files),
@@ -120,8 +176,21 @@ fn get_file_router() -> KosmosRouter {
             "/zip/:file_id",
             get(crate::routes::api::v1::auth::file::zip::get_zip_information),
         )
+        .route(
+            "/zip/:file_id/entry/:operation_type",
+            get(crate::routes::api::v1::auth::file::zip::get_zip_entry),
+        )
+        .route(
+            "/zip/:file_id/image/:format",
+            get(crate::routes::api::v1::auth::file::zip::get_zip_entry_image),
+        )
+        .route(
+            "/zip/:file_id/extract",
+            post(crate::routes::api::v1::auth::file::zip::extract_zip),
+        )
         .layer(DefaultBodyLimit::disable())
         .nest("/image", get_image_router())
+        .nest("/tus", get_tus_router())
 }
 
 fn get_download_router() -> KosmosRouter {
@@ -154,6 +223,54 @@ fn get_user_router() -> KosmosRouter {
             "/password",
             patch(crate::routes::api::v1::auth::user::update::update_user_password),
         )
+        .route(
+            "/versions",
+            patch(crate::routes::api::v1::auth::user::update::update_version_retention),
+        )
+        .route(
+            "/bin",
+            patch(crate::routes::api::v1::auth::user::update::update_bin_retention),
+        )
+        .route(
+            "/app-password",
+            get(crate::routes::api::v1::auth::user::app_password::get_app_passwords)
+                .post(crate::routes::api::v1::auth::user::app_password::create_app_password),
+        )
+        .route(
+            "/app-password/:app_password_id",
+            delete(crate::routes::api::v1::auth::user::app_password::delete_app_password),
+        )
+        .route(
+            "/totp",
+            get(crate::routes::api::v1::auth::user::totp::get_totp_status)
+                .post(crate::routes::api::v1::auth::user::totp::start_totp_enrollment)
+                .delete(crate::routes::api::v1::auth::user::totp::disable_totp),
+        )
+        .route(
+            "/totp/verify",
+            post(crate::routes::api::v1::auth::user::totp::enable_totp),
+        )
+        .route(
+            "/totp/recovery-codes",
+            post(crate::routes::api::v1::auth::user::totp::regenerate_recovery_codes),
+        )
+        .route(
+            "/session",
+            get(crate::routes::api::v1::auth::user::session::get_active_sessions),
+        )
+        .route(
+            "/session/:session_id",
+            delete(crate::routes::api::v1::auth::user::session::delete_active_session),
+        )
+        .route(
+            "/api-token",
+            get(crate::routes::api::v1::auth::user::api_token::get_api_tokens)
+                .post(crate::routes::api::v1::auth::user::api_token::create_api_token),
+        )
+        .route(
+            "/api-token/:api_token_id",
+            delete(crate::routes::api::v1::auth::user::api_token::delete_api_token),
+        )
         .nest("/usage", get_usage_router())
 }
 
@@ -169,13 +286,23 @@ fn get_multi_router() -> KosmosRouter {
             "/bin",
             post(crate::routes::api::v1::auth::file::bin::mark_files_for_deletion),
         )
+        .route(
+            "/tag",
+            put(crate::routes::api::v1::auth::tag::update::multi_tag)
+                .delete(crate::routes::api::v1::auth::tag::update::multi_untag),
+        )
 }
 
 fn get_operation_router() -> KosmosRouter {
-    Router::new().route(
-        "/all",
-        get(crate::routes::api::v1::auth::operation::get_all_operations),
-    )
+    Router::new()
+        .route(
+            "/all",
+            get(crate::routes::api::v1::auth::operation::get_all_operations),
+        )
+        .route(
+            "/events",
+            get(crate::routes::api::v1::auth::operation::get_operation_events),
+        )
 }
 
 fn get_share_router() -> KosmosRouter {
@@ -252,6 +379,14 @@ fn get_admin_router() -> KosmosRouter {
             "/user/:user_id/usage",
             get(crate::routes::api::v1::auth::admin::user::get_user_usage),
         )
+        .route(
+            "/user/:user_id/totp",
+            delete(crate::routes::api::v1::auth::admin::user::reset_user_totp),
+        )
+        .route(
+            "/image/backfill",
+            post(crate::routes::api::v1::auth::admin::image::backfill_image_formats),
+        )
 }
 
 fn get_search_router() -> KosmosRouter {
@@ -274,6 +409,21 @@ fn get_favorite_router() -> KosmosRouter {
         )
 }
 
+fn get_tag_router() -> KosmosRouter {
+    Router::new()
+        .route(
+            "/",
+            get(crate::routes::api::v1::auth::tag::read::get_tags)
+                .post(crate::routes::api::v1::auth::tag::create::create_tag),
+        )
+        .route(
+            "/:tag_id",
+            get(crate::routes::api::v1::auth::tag::read::get_tagged_items)
+                .patch(crate::routes::api::v1::auth::tag::update::update_tag)
+                .delete(crate::routes::api::v1::auth::tag::delete::delete_tag),
+        )
+}
+
 fn get_album_router() -> KosmosRouter {
     Router::new()
         .route(
@@ -337,6 +487,26 @@ fn get_passkey_auth_router() -> KosmosRouter {
         )
 }
 
+fn get_oidc_auth_router() -> KosmosRouter {
+    Router::new()
+        .route(
+            "/login",
+            get(crate::routes::api::v1::auth::oidc::login::oidc_login),
+        )
+        .route(
+            "/callback",
+            get(crate::routes::api::v1::auth::oidc::login::oidc_callback),
+        )
+        .route(
+            "/link",
+            get(crate::routes::api::v1::auth::oidc::get_oidc_links),
+        )
+        .route(
+            "/link/:link_id",
+            delete(crate::routes::api::v1::auth::oidc::delete_oidc_link),
+        )
+}
+
 fn get_quick_share_router() -> KosmosRouter {
     Router::new()
 }
@@ -345,9 +515,14 @@ fn get_auth_router() -> KosmosRouter {
     Router::new()
         .route("/", get(crate::routes::api::v1::auth::auth))
         .route("/login", post(crate::routes::api::v1::auth::login))
+        .route(
+            "/login/totp",
+            post(crate::routes::api::v1::auth::login_two_factor),
+        )
         .route("/register", post(crate::routes::api::v1::auth::register))
         .route("/logout", post(crate::routes::api::v1::auth::logout))
         .nest("/passkey", get_passkey_auth_router())
+        .nest("/oidc", get_oidc_auth_router())
         .nest("/search", get_search_router())
         .nest("/share", get_share_router())
         .nest("/file", get_file_router())
@@ -356,6 +531,7 @@ fn get_auth_router() -> KosmosRouter {
         .nest("/download", get_download_router())
         .nest("/multi", get_multi_router())
         .nest("/album", get_album_router())
+        .nest("/tag", get_tag_router())
         .nest("/quick", get_quick_share_router())
         .nest("/operation", get_operation_router())
         .nest("/user", get_user_router())
@@ -380,6 +556,18 @@ fn get_public_share_router() -> KosmosRouter {
             "/file/:share_id/zip",
             get(crate::routes::api::v1::auth::file::zip::access_zip_share),
         )
+        .route(
+            "/file/:share_id/zip/entry/:operation_type",
+            get(crate::routes::api::v1::auth::file::zip::get_share_zip_entry),
+        )
+        .route(
+            "/file/:share_id/zip/image/:format",
+            get(crate::routes::api::v1::auth::file::zip::get_share_zip_entry_image),
+        )
+        .route(
+            "/file/:share_id/zip/extract",
+            post(crate::routes::api::v1::auth::file::zip::extract_share_zip),
+        )
         .route(
             "/folder/:share_id",
             get(crate::routes::api::v1::share::access_folder_share),
@@ -388,6 +576,14 @@ fn get_public_share_router() -> KosmosRouter {
             "/folder/:share_id/multi",
             post(crate::routes::api::v1::auth::download::multi_share_download),
         )
+        .route(
+            "/folder/:share_id/drop",
+            get(crate::routes::api::v1::share::get_share_upload_information),
+        )
+        .route(
+            "/folder/:share_id/upload",
+            post(crate::routes::api::v1::share::upload_to_share).layer(DefaultBodyLimit::disable()),
+        )
         .route(
             "/folder/:share_id/:access_type/:access_id",
             get(crate::routes::api::v1::share::access_folder_share_item),
@@ -396,6 +592,18 @@ fn get_public_share_router() -> KosmosRouter {
             "/folder/:share_id/File/:file_id/action/:operation_type",
             get(crate::routes::api::v1::auth::download::handle_raw_file_share_through_folder),
         )
+        .route(
+            "/folder/:share_id/Zip/:file_id/entry/:operation_type",
+            get(crate::routes::api::v1::auth::file::zip::get_share_zip_entry_through_folder),
+        )
+        .route(
+            "/folder/:share_id/Zip/:file_id/image/:format",
+            get(crate::routes::api::v1::auth::file::zip::get_share_zip_entry_image_through_folder),
+        )
+        .route(
+            "/folder/:share_id/Zip/:file_id/extract",
+            post(crate::routes::api::v1::auth::file::zip::extract_share_zip_through_folder),
+        )
         .route(
             "/folder/:share_id/image/:file_id/:format",
             get(
@@ -417,15 +625,43 @@ fn get_public_share_router() -> KosmosRouter {
         .route("/unlock", post(crate::routes::api::v1::share::unlock_share))
 }
 
+fn get_dav_router() -> KosmosRouter {
+    Router::new()
+        .route("/dav", any(crate::routes::dav::handle_dav))
+        .route("/dav/", any(crate::routes::dav::handle_dav))
+        .route("/dav/*path", any(crate::routes::dav::handle_dav))
+        .layer(DefaultBodyLimit::disable())
+}
+
 pub fn init(cors: CorsLayer, session_layer: KosmosSession, state: AppState) -> Router {
     let api_router = Router::new()
         .nest("/auth", get_auth_router())
         .nest("/s", get_public_share_router());
 
-    Router::new()
+    let api_router = Router::new()
         .nest("/api/v1", api_router)
-        .layer(session_layer)
-        .layer(cors)
+        .layer(from_fn(crate::session::track_session_client))
+        .layer(session_layer);
+
+    // The CORS layer answers every OPTIONS request as a preflight, but tus clients also send
+    // OPTIONS requests without CORS to discover what the server supports
+    let cors_router = api_router.clone().layer(cors).with_state(state.clone());
+    let api_router = api_router.with_state(state.clone());
+    let api_service = service_fn(move |request: Request| {
+        let is_preflight = request.method() == Method::OPTIONS
+            && request.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+
+        if request.method() == Method::OPTIONS && !is_preflight {
+            api_router.clone().oneshot(request)
+        } else {
+            cors_router.clone().oneshot(request)
+        }
+    });
+
+    Router::new()
+        .fallback_service(api_service)
+        // WebDAV clients authenticate on every request and don't use the session
+        .merge(get_dav_router())
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
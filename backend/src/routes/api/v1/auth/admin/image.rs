@@ -0,0 +1,31 @@
+use axum::extract::State;
+use itertools::Itertools;
+use crate::session::AuthSession;
+
+use crate::model::role::Permission;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::routes::api::v1::auth::file::upload::start_image_processing;
+use crate::state::KosmosState;
+
+/// Generates the formats missing after the configured formats or codecs changed, with one
+/// image processing operation per owner
+pub async fn backfill_image_formats(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> ResponseResult {
+    state
+        .permission_service
+        .verify_permission(&session, Permission::ProcessImages)
+        .await?;
+
+    let files = state.image_service.get_files_missing_formats().await?;
+    let total_files = files.len();
+
+    for (user_id, file_ids) in files.into_iter().into_group_map() {
+        start_image_processing(&state, user_id, file_ids).await?;
+    }
+
+    Ok(AppSuccess::OK {
+        data: Some(total_files.to_string()),
+    })
+}
@@ -2,7 +2,7 @@ use axum::extract::State;
 use axum::Json;
 use axum_valid::Valid;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use validator::Validate;
 
 use crate::model::role::Permission;
@@ -22,7 +22,7 @@ pub struct AdminCreateUser {
 
 pub async fn create_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Valid(Json(payload)): Valid<Json<AdminCreateUser>>,
 ) -> ResponseResult {
     state
@@ -3,11 +3,11 @@ use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::state::KosmosState;
 use axum::extract::{Path, State};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 pub async fn delete_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(user_id): Path<i64>,
 ) -> ResponseResult {
     let admin = state
@@ -2,8 +2,10 @@ pub use create::*;
 pub use read::*;
 pub use delete::*;
 pub use update::*;
+pub use totp::*;
 
 mod create;
 mod read;
 mod delete;
 mod update;
+mod totp;
\ No newline at end of file
@@ -1,6 +1,6 @@
 use axum::extract::{Path, State};
 use axum::Json;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::role::Permission;
 use crate::model::user::UserModelDTO;
@@ -10,7 +10,7 @@ use crate::state::KosmosState;
 
 pub async fn get_all_users(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<Vec<UserModelDTO>>, AppError> {
     state
         .permission_service
@@ -26,7 +26,7 @@ pub async fn get_all_users(
 
 pub async fn get_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(user_id): Path<i64>,
 ) -> Result<Json<UserModelDTO>, AppError> {
     state
@@ -41,7 +41,7 @@ pub async fn get_user(
 
 pub async fn get_user_usage(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(user_id): Path<i64>,
 ) -> Result<Json<DiskUsageStats>, AppError> {
     state
@@ -0,0 +1,24 @@
+use axum::extract::{Path, State};
+use crate::session::AuthSession;
+
+use crate::model::role::Permission;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::state::KosmosState;
+
+/// Removes the second factor of a user who lost access to it, they can log in with the password again
+pub async fn reset_user_totp(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(user_id): Path<i64>,
+) -> ResponseResult {
+    state
+        .permission_service
+        .verify_permission(&session, Permission::UpdateUser)
+        .await?;
+
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    state.totp_service.disable(user.id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -1,7 +1,7 @@
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::role::{Permission, Role};
 use crate::response::error_handling::AppError;
@@ -22,7 +22,7 @@ pub struct AdminUpdateUserPayload {
 
 pub async fn update_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(user_id): Path<i64>,
     Json(payload): Json<AdminUpdateUserPayload>,
 ) -> ResponseResult {
@@ -1,7 +1,7 @@
 use axum::extract::State;
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::album::AlbumModelDTO;
 use crate::response::error_handling::AppError;
@@ -16,7 +16,7 @@ pub struct CreateAlbumPayload {
 
 pub async fn create_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<CreateAlbumPayload>,
 ) -> Result<Json<AlbumModelDTO>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -1,7 +1,7 @@
 use axum::extract::{Path, Query, State};
 use axum::Json;
 use serde::{Deserialize, Serialize};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::album::AlbumModelDTO;
 use crate::model::file::FileModelDTO;
@@ -13,7 +13,7 @@ use crate::state::KosmosState;
 
 pub async fn get_albums(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<Vec<AlbumModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
@@ -30,7 +30,7 @@ pub async fn get_albums(
 
 pub async fn get_albums_for_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> Result<Json<Vec<AlbumModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -71,7 +71,7 @@ pub struct AvailableAlbumsForFileResponse {
 
 pub async fn get_available_albums_for_files(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<GetAvailableAlbumsPayload>,
 ) -> Result<Json<AvailableAlbumsForFileResponse>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -122,7 +122,7 @@ pub struct AlbumResponse {
 
 pub async fn get_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(album_id): Path<i64>,
 ) -> Result<Json<AlbumResponse>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -142,7 +142,7 @@ pub async fn get_album(
 
 pub async fn get_available_files(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Query(params): Query<GetFilesByType>,
 ) -> Result<Json<Vec<FileModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
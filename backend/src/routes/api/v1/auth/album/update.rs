@@ -1,7 +1,7 @@
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
@@ -25,7 +25,7 @@ impl UpdateAlbumPayload {
 
 pub async fn update_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<UpdateAlbumPayload>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -62,7 +62,7 @@ impl FilesToAlbumActionPayload {
 
 pub async fn link_files_to_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(album_id): Path<i64>,
     Json(payload): Json<FilesToAlbumActionPayload>,
 ) -> ResponseResult {
@@ -104,7 +104,7 @@ pub async fn link_files_to_album(
 
 pub async fn unlink_file_from_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(album_id): Path<i64>,
     Json(payload): Json<FilesToAlbumActionPayload>,
 ) -> ResponseResult {
@@ -156,7 +156,7 @@ impl UpdateAlbumPreviewPayload {
 
 pub async fn update_album_preview(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(album_id): Path<i64>,
     Json(payload): Json<UpdateAlbumPreviewPayload>,
 ) -> ResponseResult {
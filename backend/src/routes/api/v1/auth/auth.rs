@@ -1,6 +1,6 @@
 use axum::extract::State;
 use axum::Json;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::user::UserModelDTO;
 use crate::response::error_handling::AppError;
@@ -9,9 +9,9 @@ use crate::state::KosmosState;
 
 pub async fn auth(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<UserModelDTO>, AppError> {
-    if let Some(user_id) = SessionService::get_user_id(&session).await {
+    if let Some(user_id) = SessionService::get_auth_user_id(&session).await {
         let auth_user = state.user_service.get_user(user_id).await?;
 
         match auth_user {
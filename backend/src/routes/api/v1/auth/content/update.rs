@@ -5,7 +5,7 @@ use crate::state::KosmosState;
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Deserialize)]
 pub struct UpdateFileContentPayload {
@@ -14,7 +14,7 @@ pub struct UpdateFileContentPayload {
 
 pub async fn update_file_contents(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
     Json(payload): Json<UpdateFileContentPayload>,
 ) -> ResponseResult {
@@ -29,7 +29,13 @@ pub async fn update_file_contents(
 
     state
         .file_service
-        .update_file_content(file_id, payload.content)
+        .update_file_content(&file, payload.content.clone())
+        .await?;
+
+    // Editor saves are small, so they are indexed right away instead of queueing a job
+    state
+        .search_service
+        .index_content(file.id, &payload.content)
         .await?;
 
     Ok(AppSuccess::UPDATED)
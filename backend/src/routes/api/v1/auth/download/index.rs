@@ -1,20 +1,24 @@
-use std::io::{BufWriter, SeekFrom, Write};
-use std::str::FromStr;
+use std::collections::HashSet;
+use std::io;
 
-use axum::body::Body;
+use axum::body::{Body, Bytes};
 use axum::extract::{Path, State};
 use axum::http::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
 use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde::Deserialize;
-use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, DuplexStream};
 use tokio_util::io::ReaderStream;
-use tower_sessions::Session;
-use zip::write::{FileOptions, SimpleFileOptions};
-use zip::ZipWriter;
+use crate::session::AuthSession;
 
+use crate::archive::tar_stream::TarStreamWriter;
+use crate::archive::zip_stream::{ZipCompression, ZipStreamWriter};
 use crate::model::file::FileModel;
 use crate::model::folder::Directory;
 use crate::model::share::ExtendedShareModel;
@@ -25,6 +29,18 @@ use crate::routes::api::v1::share::{
 };
 use crate::services::session_service::{SessionService, UserId};
 use crate::state::{AppState, KosmosState};
+use crate::storage;
+use crate::storage::KosmosStorage;
+use crate::utils::http::{
+    attachment_disposition, etag_matches, http_date, parse_http_date, parse_range, RangeRequest,
+};
+
+/// Archived data waiting to be sent, writing pauses while the client is behind
+const ARCHIVE_BUFFER_SIZE: usize = 256 * 1024;
+const MULTIPART_BOUNDARY_LENGTH: usize = 32;
+/// Folders of a multi download with the metadata the archive format can't hold
+const MANIFEST_FILE_NAME: &str = ".kosmos-manifest.json";
+const MANIFEST_VERSION: u32 = 1;
 
 #[derive(Deserialize)]
 pub enum RawFileAction {
@@ -60,117 +76,233 @@ pub async fn get_raw_file(
             })?,
     };
 
-    let file_path =
-        std::path::Path::new(&std::env::var("UPLOAD_LOCATION").unwrap()).join(file.id.to_string());
+    get_raw_content(request_headers, state, &file, operation_type).await
+}
 
-    let mut system_file = File::open(file_path)
-        .await
-        .map_err(|_| AppError::NotFound {
-            error: "File to download not found".to_string(),
-        })?;
+/// Serves the stored content of the file. Conditional requests are answered with
+/// `304 Not Modified`, ranges with `206 Partial Content` or `416 Range Not Satisfiable`.
+pub async fn get_raw_content(
+    request_headers: &mut HeaderMap,
+    state: &AppState,
+    file: &FileModel,
+    operation_type: RawFileAction,
+) -> Result<(StatusCode, HeaderMap<HeaderValue>, Body), AppError> {
+    let file_key = file.content_key();
 
-    let metadata = &system_file
-        .metadata()
+    let file_size = state
+        .storage
+        .size(&file_key)
         .await
         .map_err(|_| AppError::NotFound {
             error: "File to download not found".to_string(),
         })?;
 
-    let disposition = match operation_type {
-        RawFileAction::Download => format!("attachment; filename=\"{}\"", file.file_name),
-        RawFileAction::Serve => String::from("inline"),
-    };
+    let etag = file.etag();
 
     let mut response_headers = HeaderMap::new();
-    add_header(
-        &mut response_headers,
-        header::CONTENT_TYPE,
-        file.mime_type.as_str(),
-    );
-    add_header(
-        &mut response_headers,
-        header::CONTENT_DISPOSITION,
-        disposition.clone().as_str(),
-    );
-    add_header(
-        &mut response_headers,
-        header::ETAG,
-        &format!("\"{}\"", file.id),
-    );
+    add_header(&mut response_headers, header::ETAG, &etag);
     add_header(&mut response_headers, header::CACHE_CONTROL, "no-cache");
     add_header(&mut response_headers, header::PRAGMA, "no-cache");
     add_header(
         &mut response_headers,
         header::LAST_MODIFIED,
-        file.updated_at.to_rfc3339().as_str(),
+        &http_date(&file.updated_at),
     );
 
-    // If Range header is present, try to serve partial content to client
-    let body = if let Some(range) = request_headers.get(RANGE) {
-        // Parse the range and skip beginning ""bytes="
-        let range_str = &range.to_str().unwrap_or("")[6..];
-        let ranges: Vec<&str> = range_str.split('-').collect();
-
-        let range_start = u64::from_str(ranges[0]).unwrap_or(0);
-
-        // If end isn't specified, read to the end of the file
-        let range_end = if ranges.get(1).is_some() && !ranges[1].is_empty() {
-            u64::from_str(ranges[1]).unwrap_or(0)
-        } else {
-            metadata.len() - 1
-        };
+    if is_not_modified(request_headers, &etag, &file.updated_at) {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers, Body::empty()));
+    }
 
-        let mut file_slice = vec![0u8; (range_end - range_start + 1) as usize];
+    let disposition = match operation_type {
+        RawFileAction::Download => format!("attachment; filename=\"{}\"", file.file_name),
+        RawFileAction::Serve => String::from("inline"),
+    };
 
-        system_file
-            .seek(SeekFrom::Start(range_start))
-            .await
-            .map_err(|e| {
-                tracing::error!("Error seeking file: {}", e);
-                AppError::InternalError
-            })?;
+    add_header(
+        &mut response_headers,
+        header::CONTENT_DISPOSITION,
+        disposition.as_str(),
+    );
+    add_header(&mut response_headers, header::ACCEPT_RANGES, "bytes");
 
-        system_file.read_exact(&mut file_slice).await.map_err(|e| {
-            tracing::error!("Error reading file slice: {}", e);
-            AppError::InternalError
-        })?;
+    let range_request = match request_headers.get(RANGE).and_then(|range| range.to_str().ok()) {
+        Some(range) if is_range_current(request_headers, &etag, &file.updated_at) => {
+            parse_range(range, file_size)
+        }
+        _ => RangeRequest::Full,
+    };
 
-        // Set Content-Range header to response
-        let _ = HeaderValue::try_from(format!(
-            "bytes {}-{}/{}",
-            range_start,
-            range_end,
-            metadata.len()
-        ))
-        .map(|value| {
-            response_headers.insert(CONTENT_RANGE, value);
-        });
+    match range_request {
+        RangeRequest::Full => {
+            add_header(
+                &mut response_headers,
+                header::CONTENT_TYPE,
+                file.mime_type.as_str(),
+            );
+            response_headers.insert(CONTENT_LENGTH, HeaderValue::from(file_size));
+
+            let system_file = state
+                .storage
+                .get(&file_key)
+                .await
+                .map_err(|_| AppError::NotFound {
+                    error: "File to download not found".to_string(),
+                })?;
+
+            Ok((
+                StatusCode::OK,
+                response_headers,
+                Body::from_stream(ReaderStream::new(system_file)),
+            ))
+        }
+        RangeRequest::Unsatisfiable => {
+            add_header(
+                &mut response_headers,
+                CONTENT_RANGE,
+                &format!("bytes */{}", file_size),
+            );
+
+            Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                response_headers,
+                Body::empty(),
+            ))
+        }
+        RangeRequest::Partial(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+
+            add_header(
+                &mut response_headers,
+                header::CONTENT_TYPE,
+                file.mime_type.as_str(),
+            );
+            add_header(
+                &mut response_headers,
+                CONTENT_RANGE,
+                &range.content_range(file_size),
+            );
+            response_headers.insert(CONTENT_LENGTH, HeaderValue::from(range.length()));
+
+            let system_file = state
+                .storage
+                .get_range(&file_key, range.start, range.length())
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error reading file range: {}", e);
+                    AppError::InternalError
+                })?;
+
+            Ok((
+                StatusCode::PARTIAL_CONTENT,
+                response_headers,
+                Body::from_stream(ReaderStream::new(system_file)),
+            ))
+        }
+        RangeRequest::Partial(ranges) => {
+            let boundary: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(MULTIPART_BOUNDARY_LENGTH)
+                .map(char::from)
+                .collect();
+
+            // Every part is the header, the range and a line break, the ranges are only read
+            // once the part is sent
+            let parts = ranges
+                .iter()
+                .map(|range| {
+                    let part_header = format!(
+                        "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                        boundary,
+                        file.mime_type,
+                        range.content_range(file_size)
+                    );
+                    (*range, Bytes::from(part_header))
+                })
+                .collect::<Vec<_>>();
+            let closing = Bytes::from(format!("--{}--\r\n", boundary));
+
+            let content_length = parts
+                .iter()
+                .map(|(range, part_header)| part_header.len() as u64 + range.length() + 2)
+                .sum::<u64>()
+                + closing.len() as u64;
+
+            add_header(
+                &mut response_headers,
+                header::CONTENT_TYPE,
+                &format!("multipart/byteranges; boundary={}", boundary),
+            );
+            response_headers.insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+
+            let storage = state.storage.clone();
+            let body = stream::iter(parts)
+                .then(move |(range, part_header)| {
+                    let storage = storage.clone();
+                    let file_key = file_key.clone();
+                    async move {
+                        let content = storage
+                            .get_range(&file_key, range.start, range.length())
+                            .await
+                            .inspect_err(|e| tracing::error!("Error reading file range: {}", e))?;
+
+                        Ok::<_, io::Error>(
+                            stream::once(async move { Ok(part_header) })
+                                .chain(ReaderStream::new(content))
+                                .chain(stream::once(async { Ok(Bytes::from_static(b"\r\n")) })),
+                        )
+                    }
+                })
+                .try_flatten()
+                .chain(stream::once(async move { Ok(closing) }));
+
+            Ok((
+                StatusCode::PARTIAL_CONTENT,
+                response_headers,
+                Body::from_stream(body),
+            ))
+        }
+    }
+}
 
-        // Set Content-Length header to response
-        let _ = HeaderValue::try_from(file_slice.len().to_string()).map(|value| {
-            response_headers.insert(CONTENT_LENGTH, value);
-        });
+/// `If-None-Match` takes precedence, `If-Modified-Since` is only evaluated without it
+fn is_not_modified(
+    request_headers: &HeaderMap,
+    etag: &str,
+    last_modified: &DateTime<Utc>,
+) -> bool {
+    if let Some(if_none_match) = request_headers.get(header::IF_NONE_MATCH) {
+        return if_none_match
+            .to_str()
+            .is_ok_and(|if_none_match| etag_matches(if_none_match, etag, true));
+    }
 
-        Body::from(file_slice)
-    } else {
-        response_headers.insert(CONTENT_LENGTH, HeaderValue::from(metadata.len()));
-        let stream = ReaderStream::new(system_file);
+    request_headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(parse_http_date)
+        .is_some_and(|since| last_modified.timestamp() <= since.timestamp())
+}
 
-        Body::from_stream(stream)
+/// A range only applies if the `If-Range` validator still matches, otherwise the client gets
+/// the whole new content
+fn is_range_current(request_headers: &HeaderMap, etag: &str, last_modified: &DateTime<Utc>) -> bool {
+    let Some(if_range) = request_headers.get(header::IF_RANGE) else {
+        return true;
+    };
+    let Ok(if_range) = if_range.to_str() else {
+        return false;
     };
 
-    // If Content-Range header is present, return partial content to client
-    if response_headers.get(CONTENT_RANGE).is_some() {
-        Ok((StatusCode::PARTIAL_CONTENT, response_headers, body))
-    } else {
-        Ok((StatusCode::OK, response_headers, body))
+    match parse_http_date(if_range) {
+        Some(date) => date.timestamp() == last_modified.timestamp(),
+        None => etag_matches(if_range, etag, false),
     }
 }
 
 pub async fn handle_raw_file(
     mut headers: HeaderMap,
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path((file_id, operation_type)): Path<(i64, RawFileAction)>,
 ) -> Result<Response, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -184,7 +316,7 @@ pub async fn handle_raw_file(
 pub async fn handle_raw_file_share(
     mut headers: HeaderMap,
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path((share_uuid, operation_type)): Path<(String, RawFileAction)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid.clone(), true).await?;
@@ -205,7 +337,7 @@ pub async fn handle_raw_file_share(
 pub async fn handle_raw_file_share_through_folder(
     mut headers: HeaderMap,
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path((share_uuid, file_id, operation_type)): Path<(String, i64, RawFileAction)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, true).await?;
@@ -236,7 +368,7 @@ pub async fn handle_raw_file_share_through_folder(
 pub async fn handle_raw_file_share_through_album(
     mut headers: HeaderMap,
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path((share_uuid, file_id, operation_type)): Path<(String, i64, RawFileAction)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, true).await?;
@@ -262,16 +394,50 @@ pub async fn handle_raw_file_share_through_album(
 pub struct MultiDownloadRequest {
     pub files: Vec<String>,
     pub folders: Vec<String>,
+    #[serde(default)]
+    pub format: MultiDownloadFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub enum MultiDownloadFormat {
+    #[serde(rename = "zip-deflate")]
+    ZipDeflate,
+    #[default]
+    #[serde(rename = "zip-stored")]
+    ZipStored,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+impl MultiDownloadFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            MultiDownloadFormat::ZipDeflate | MultiDownloadFormat::ZipStored => "zip",
+            MultiDownloadFormat::Tar => "tar",
+            MultiDownloadFormat::TarGz => "tar.gz",
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            MultiDownloadFormat::ZipDeflate | MultiDownloadFormat::ZipStored => "application/zip",
+            MultiDownloadFormat::Tar => "application/x-tar",
+            MultiDownloadFormat::TarGz => "application/gzip",
+        }
+    }
 }
 
 pub struct MultiDownloadParsed {
     pub files: Vec<i64>,
     pub folders: Vec<i64>,
+    pub format: MultiDownloadFormat,
 }
 
 pub async fn multi_download(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(request_data): Json<MultiDownloadRequest>,
 ) -> Result<Response, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -282,14 +448,21 @@ pub async fn multi_download(
         .get_folder_structure(request.folders, Some(user_id))
         .await?;
 
-    let response =
-        handle_multi_download(state, request.files, folder_structure, Some(user_id), None).await;
+    let response = handle_multi_download(
+        state,
+        request.files,
+        folder_structure,
+        request.format,
+        Some(user_id),
+        None,
+    )
+    .await;
     response
 }
 
 pub async fn multi_share_download(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_uuid): Path<String>,
     Json(request_data): Json<MultiDownloadRequest>,
 ) -> Result<Response, AppError> {
@@ -314,8 +487,15 @@ pub async fn multi_share_download(
         .get_folder_structure(request.folders, None)
         .await?;
 
-    let response =
-        handle_multi_download(state, request.files, folder_structure, None, Some(share)).await;
+    let response = handle_multi_download(
+        state,
+        request.files,
+        folder_structure,
+        request.format,
+        None,
+        Some(share),
+    )
+    .await;
     response
 }
 
@@ -339,39 +519,142 @@ fn parse_multi_download_payload(
             .map_err(|_| AppError::UnprocessableEntity {
                 error: "Invalid folder id".to_string(),
             })?,
+        format: request_data.format,
     };
     Ok(request)
 }
 
+/// An item of a multi download and the path it gets in the archive
+enum ArchiveEntry {
+    Directory {
+        path: String,
+        modified: DateTime<Utc>,
+    },
+    File {
+        path: String,
+        content_key: String,
+        size: u64,
+        modified: DateTime<Utc>,
+    },
+    Manifest {
+        content: Vec<u8>,
+    },
+}
+
+/// Lists every folder, so empty ones and their colors survive formats without metadata
+#[derive(Serialize)]
+struct ArchiveManifest {
+    version: u32,
+    created_at: DateTime<Utc>,
+    folders: Vec<ArchiveManifestFolder>,
+}
+
+#[derive(Serialize)]
+struct ArchiveManifestFolder {
+    path: String,
+    color: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+/// Writer for the format chosen for a multi download
+enum MultiDownloadWriter {
+    Zip(ZipStreamWriter<DuplexStream>),
+    Tar(TarStreamWriter<DuplexStream>),
+}
+
+impl MultiDownloadWriter {
+    fn new(format: MultiDownloadFormat, writer: DuplexStream) -> Self {
+        match format {
+            MultiDownloadFormat::ZipDeflate => {
+                Self::Zip(ZipStreamWriter::new(writer, ZipCompression::Deflate))
+            }
+            MultiDownloadFormat::ZipStored => {
+                Self::Zip(ZipStreamWriter::new(writer, ZipCompression::Stored))
+            }
+            MultiDownloadFormat::Tar => Self::Tar(TarStreamWriter::new(writer, false)),
+            MultiDownloadFormat::TarGz => Self::Tar(TarStreamWriter::new(writer, true)),
+        }
+    }
+
+    async fn add_directory(&mut self, path: &str, modified: DateTime<Utc>) -> io::Result<()> {
+        match self {
+            Self::Zip(zip) => zip.add_directory(path, modified).await,
+            Self::Tar(tar) => tar.add_directory(path, modified).await,
+        }
+    }
+
+    async fn add_file<R: AsyncRead + Unpin>(
+        &mut self,
+        path: &str,
+        modified: DateTime<Utc>,
+        size: u64,
+        content: &mut R,
+    ) -> io::Result<()> {
+        match self {
+            Self::Zip(zip) => zip.add_file(path, modified, size, content).await,
+            Self::Tar(tar) => tar.add_file(path, modified, size, content).await,
+        }
+    }
+
+    async fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Zip(zip) => zip.finish().await.map(|_| ()),
+            Self::Tar(tar) => tar.finish().await.map(|_| ()),
+        }
+    }
+}
+
+/// Files with the same path get a counter appended to their name, as they would overwrite each
+/// other when extracted
+fn unique_path(paths: &mut HashSet<String>, path: String) -> String {
+    if paths.insert(path.clone()) {
+        return path;
+    }
+
+    let (stem, extension) = match path.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && !stem.ends_with('/') => {
+            (stem.to_string(), format!(".{}", extension))
+        }
+        _ => (path.clone(), String::new()),
+    };
+
+    (1..)
+        .map(|counter| format!("{} ({}){}", stem, counter, extension))
+        .find(|candidate| paths.insert(candidate.clone()))
+        .unwrap_or(path)
+}
+
 async fn handle_multi_download(
     state: AppState,
     files: Vec<i64>,
     folder_structure: Vec<Directory>,
+    format: MultiDownloadFormat,
     user_id: Option<UserId>,
     share: Option<ExtendedShareModel>,
 ) -> Result<Response, AppError> {
-    let upload_location = std::env::var("UPLOAD_LOCATION").unwrap();
-    let upload_path = std::path::Path::new(&upload_location);
-    let temp_location = std::path::Path::new(&upload_location).join("temp");
-    let temp_path = std::path::Path::new(&temp_location);
-
-    let file_name = format!(
-        "Kosmos_Archive_{}.zip",
-        chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S")
-    );
-    let temp_zip_path = temp_path.join(&file_name);
-    let temp_zip_path_str = temp_zip_path.to_str().unwrap();
-
-    let _ = tokio::fs::remove_file(temp_zip_path_str).await;
+    // A single folder is downloaded under its own name
+    let selected_folders = folder_structure
+        .iter()
+        .filter(|dir| dir.path.is_empty())
+        .collect::<Vec<_>>();
+    let archive_name = match selected_folders.as_slice() {
+        [folder] if files.is_empty() => folder.folder_name.clone(),
+        _ => format!(
+            "Kosmos_Archive_{}",
+            chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S")
+        ),
+    };
+    let file_name = format!("{}.{}", archive_name, format.extension());
 
-    let file = std::fs::File::create(temp_zip_path_str).map_err(|e| {
-        tracing::error!("Error creating zip file: {}", e);
-        AppError::InternalError
-    })?;
+    // Access is checked before anything is sent, as the status can't change once the archive streams
+    let mut paths = HashSet::new();
+    let mut entries = vec![];
 
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
-    let mut writer = BufWriter::new(&file);
-    let mut zip = ZipWriter::new(&mut writer);
+    // Files can't take the name of the manifest
+    if !folder_structure.is_empty() {
+        paths.insert(MANIFEST_FILE_NAME.to_string());
+    }
 
     for file_id in files {
         let database_file = multi_download_get_file(&state, file_id, &user_id, &share)
@@ -380,83 +663,135 @@ async fn handle_multi_download(
                 error: "File not found".to_string(),
             })?;
 
-        let path_to_file = upload_path.join(database_file.id.to_string());
-
-        if let Ok(mut file) = File::open(&path_to_file).await {
-            write_file_to_zip(options, &mut zip, &database_file.file_name, &mut file).await?;
-        }
+        entries.push(ArchiveEntry::File {
+            path: unique_path(&mut paths, database_file.file_name.clone()),
+            content_key: database_file.content_key(),
+            size: database_file.file_size as u64,
+            modified: database_file.updated_at,
+        });
     }
 
-    for dir in &folder_structure {
-        let mut dir_paths = vec![];
-        dir_paths.push((&dir.path.join("/")).to_owned());
-        dir_paths.push((&dir.folder_name).to_owned());
+    let mut folder_structure = folder_structure;
+    folder_structure.sort_by(|a, b| (&a.path, &a.folder_name).cmp(&(&b.path, &b.folder_name)));
 
-        let path_in_zip = dir_paths.join("/");
-
-        for i in 0..dir.files.len() {
-            let file_id: &i64 = &dir.files[i];
-            let file_name: &String = &dir.file_names[i];
+    let mut manifest_folders = vec![];
 
-            let path_to_file = upload_path.join(file_id.to_string());
-
-            if let Ok(mut file) = File::open(&path_to_file).await {
-                // Ignore error as this can fail when a folder with the name already exists
-                let _ = zip.add_directory(&path_in_zip, options);
-
-                let file_in_zip = format!("{}/{}", path_in_zip, file_name);
+    for dir in &folder_structure {
+        let path_in_zip = dir
+            .path
+            .iter()
+            .chain(std::iter::once(&dir.folder_name))
+            .join("/");
+
+        if paths.insert(format!("{}/", path_in_zip)) {
+            entries.push(ArchiveEntry::Directory {
+                path: path_in_zip.clone(),
+                modified: dir.updated_at,
+            });
+            manifest_folders.push(ArchiveManifestFolder {
+                path: path_in_zip.clone(),
+                color: dir.color.clone(),
+                created_at: dir.created_at,
+                updated_at: dir.updated_at,
+            });
+        }
 
-                write_file_to_zip(options, &mut zip, &file_in_zip, &mut file).await?;
-            } else {
-                return Err(AppError::NotFound {
-                    error: "File not found".to_string(),
-                });
-            }
+        for i in 0..dir.files.len() {
+            entries.push(ArchiveEntry::File {
+                path: unique_path(&mut paths, format!("{}/{}", path_in_zip, dir.file_names[i])),
+                content_key: storage::content_key(dir.files[i], dir.file_blob_hashes[i].as_deref()),
+                size: dir.file_sizes[i] as u64,
+                modified: dir.file_updated_at[i],
+            });
         }
     }
 
-    zip.finish().map_err(|e| {
-        tracing::error!("Error finishing zip: {}", e);
-        AppError::InternalError
-    })?;
-
-    writer.flush().map_err(|e| {
-        tracing::error!("Error flushing zip data to file: {}", e);
-        AppError::InternalError
-    })?;
+    if !manifest_folders.is_empty() {
+        let manifest = ArchiveManifest {
+            version: MANIFEST_VERSION,
+            created_at: Utc::now(),
+            folders: manifest_folders,
+        };
+        let content = serde_json::to_vec_pretty(&manifest).map_err(|e| {
+            tracing::error!("Error while creating archive manifest: {}", e);
+            AppError::InternalError
+        })?;
 
-    let data = File::open(temp_zip_path_str).await.map_err(|e| {
-        tracing::error!("Error reading zip file: {}", e);
-        AppError::InternalError
-    })?;
+        entries.push(ArchiveEntry::Manifest { content });
+    }
 
-    let meta_data = data.metadata().await.map_err(|e| {
-        tracing::error!("Error getting metadata of zip file: {}", e);
-        AppError::InternalError
-    })?;
+    let (reader, writer) = tokio::io::duplex(ARCHIVE_BUFFER_SIZE);
+    let archive = tokio::spawn(write_archive(state.storage.clone(), entries, writer, format));
 
-    let stream = ReaderStream::new(data);
-    let body = Body::from_stream(stream);
+    // A failed archive ends the body with an error, so the download is aborted instead of
+    // leaving a truncated archive
+    let failure = stream::once(archive).filter_map(|result| async move {
+        match result {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(Err(e)),
+            Err(e) => Some(Err(io::Error::other(e))),
+        }
+    });
+    let body = Body::from_stream(ReaderStream::new(reader).chain(failure));
 
     let header = [
-        (header::CONTENT_TYPE, "application/zip".to_string()),
-        (CONTENT_LENGTH, meta_data.len().to_string()),
-        (
-            header::CONTENT_DISPOSITION,
-            format!("attachment; filename={}", file_name),
-        ),
+        (header::CONTENT_TYPE, format.content_type().to_string()),
+        (header::CONTENT_DISPOSITION, attachment_disposition(&file_name)),
     ];
 
-    // This way of doing it is not ideal, but it seems to work, this can maybe break in the future
+    Ok((header, body).into_response())
+}
 
-    // The tokio remove file function uses the underlying 'unlink' syscall
-    // which causes the file to be marked as deleted until no process has any handles for it
-    // the stream is closed when the request is finished and the file is deleted
+async fn write_archive(
+    storage: KosmosStorage,
+    entries: Vec<ArchiveEntry>,
+    writer: DuplexStream,
+    format: MultiDownloadFormat,
+) -> io::Result<()> {
+    let mut archive = MultiDownloadWriter::new(format, writer);
+
+    for entry in entries {
+        match entry {
+            ArchiveEntry::Directory { path, modified } => {
+                archive.add_directory(&path, modified).await?
+            }
+            ArchiveEntry::Manifest { content } => {
+                archive
+                    .add_file(
+                        MANIFEST_FILE_NAME,
+                        Utc::now(),
+                        content.len() as u64,
+                        &mut content.as_slice(),
+                    )
+                    .await?
+            }
+            ArchiveEntry::File {
+                path,
+                content_key,
+                size,
+                modified,
+            } => {
+                let mut content = storage.get(&content_key).await.map_err(|e| {
+                    tracing::error!("Error reading {} for archive: {}", path, e);
+                    e
+                })?;
+
+                archive
+                    .add_file(&path, modified, size, &mut content)
+                    .await
+                    .map_err(|e| {
+                        if e.kind() != io::ErrorKind::BrokenPipe {
+                            tracing::error!("Error writing {} to archive: {}", path, e);
+                        }
+                        e
+                    })?;
+            }
+        }
+    }
 
-    let response: Result<Response<Body>, AppError> = Ok((header, body).into_response());
+    archive.finish().await?;
 
-    let _ = tokio::fs::remove_file(temp_zip_path_str).await;
-    response
+    Ok(())
 }
 
 async fn multi_download_get_file(
@@ -488,26 +823,3 @@ async fn multi_download_get_file(
 
     return Ok(None);
 }
-
-async fn write_file_to_zip(
-    options: FileOptions<'_, ()>,
-    zip: &mut ZipWriter<&mut BufWriter<&std::fs::File>>,
-    file_name: &str,
-    file: &mut File,
-) -> Result<(), AppError> {
-    zip.start_file(file_name, options).map_err(|e| {
-        tracing::error!("Error adding file to zip: {}", e);
-        AppError::InternalError
-    })?;
-    let mut buffer = vec![0; 1024];
-    while let Ok(n) = file.read(&mut buffer).await {
-        if n == 0 {
-            break;
-        }
-        zip.write_all(&buffer[..n]).map_err(|e| {
-            tracing::error!("Error writing file to zip: {}", e);
-            AppError::InternalError
-        })?;
-    }
-    Ok(())
-}
@@ -1,7 +1,7 @@
 use axum::extract::State;
 use axum::Json;
 use serde::Serialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use ts_rs::TS;
 use crate::model::file::FileModelDTO;
 use crate::model::folder::FolderModelDTO;
@@ -18,25 +18,16 @@ pub struct FavoritesResponse {
 
 pub async fn get_favorites(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<FavoritesResponse>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
+    let folders = state.folder_service.get_favorites(user_id).await?;
+    let files = state.file_service.get_favorites(user_id).await?;
+
     let favorites = FavoritesResponse {
-        folders: state
-            .folder_service
-            .get_favorites(user_id)
-            .await?
-            .into_iter()
-            .map(FolderModelDTO::from)
-            .collect(),
-        files: state
-            .file_service
-            .get_favorites(user_id)
-            .await?
-            .into_iter()
-            .map(FileModelDTO::from)
-            .collect(),
+        folders: state.tag_service.tag_folders(folders).await?,
+        files: state.tag_service.tag_files(files).await?,
     };
 
     Ok(Json(favorites))
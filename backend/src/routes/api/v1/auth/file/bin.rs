@@ -5,11 +5,11 @@ use crate::state::KosmosState;
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 pub async fn mark_file_for_deletion(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -39,6 +39,9 @@ pub async fn mark_file_for_deletion(
 #[derive(Deserialize)]
 pub struct MarkFilesForDeletion {
     pub files: Vec<String>,
+    /// Moved to the bin with everything in them
+    #[serde(default)]
+    pub folders: Vec<String>,
 }
 
 impl MarkFilesForDeletion {
@@ -52,27 +55,57 @@ impl MarkFilesForDeletion {
             })
             .collect()
     }
+
+    pub fn get_folder_ids(&self) -> Result<Vec<i64>, AppError> {
+        self.folders
+            .iter()
+            .map(|s| {
+                s.parse::<i64>().map_err(|_| AppError::BadRequest {
+                    error: Some("Invalid folder id".to_string()),
+                })
+            })
+            .collect()
+    }
 }
 
 pub async fn mark_files_for_deletion(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<MarkFilesForDeletion>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
 
     let file_ids = payload.get_file_ids()?;
+    let folder_ids = payload.get_folder_ids()?;
+
+    for folder_id in &folder_ids {
+        state
+            .folder_service
+            .check_folder_exists_by_id(*folder_id, user_id)
+            .await?
+            .ok_or(AppError::NotFound {
+                error: "Folder not found".to_string(),
+            })?;
+    }
+
     state
         .file_service
         .mark_files_for_deletion(file_ids, user_id)
         .await?;
 
+    for folder_id in folder_ids {
+        state
+            .folder_service
+            .mark_folder_for_deletion(folder_id, user_id)
+            .await?;
+    }
+
     Ok(AppSuccess::UPDATED)
 }
 
 pub async fn restore_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -98,7 +131,7 @@ pub async fn restore_file(
 
 pub async fn permanently_delete_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -127,9 +160,16 @@ pub async fn permanently_delete_file(
     Ok(AppSuccess::DELETED)
 }
 
-pub async fn clear_bin(State(state): KosmosState, session: Session) -> ResponseResult {
+pub async fn clear_bin(State(state): KosmosState, session: AuthSession) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
 
+    for folder in state.folder_service.get_deleted_folders(user_id, None).await? {
+        state
+            .folder_service
+            .permanently_delete_folder(folder.id, user_id, &state.file_service)
+            .await?;
+    }
+
     state.file_service.clear_bin(user_id).await?;
     Ok(AppSuccess::OK { data: None })
 }
@@ -3,11 +3,11 @@ use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::SessionService;
 use crate::state::KosmosState;
 use axum::extract::{Path, State};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 pub async fn favorite_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
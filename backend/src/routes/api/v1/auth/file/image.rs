@@ -2,11 +2,15 @@ use crate::model::file::FileModel;
 use crate::model::internal::image_format::ImageFormat;
 use crate::model::internal::operation_status::OperationStatus;
 use axum::extract::{Path, State};
+use axum::http::header::{
+    ACCEPT, CONTENT_SECURITY_POLICY, CONTENT_TYPE, VARY, X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::HeaderMap;
 use axum::response::{IntoResponse, Response};
-use std::path::Path as StdPath;
-use std::sync::Arc;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use crate::model::internal::file_type::FileType;
+use crate::model::internal::excerpt_codec::ExcerptCodec;
+use crate::model::internal::image_codec::ImageCodec;
 use crate::model::internal::preview_status::PreviewStatus;
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
@@ -14,59 +18,96 @@ use crate::routes::api::v1::share::{
     get_share_access_for_folder_items, get_share_album_data, get_share_file,
     is_allowed_to_access_share, AccessShareItemType,
 };
-use crate::runtimes::IMAGE_PROCESSING_RUNTIME;
 use crate::services::file_service::FileService;
 use crate::services::image_service::ImageService;
 use crate::services::session_service::SessionService;
-use crate::state::KosmosState;
+use crate::state::{AppState, KosmosState};
+use crate::storage;
 
+/// Serves the format in the codec the `Accept` header of the client prefers
 async fn get_image_format_data(
+    state: &AppState,
     format: ImageFormat,
     file_data: &FileModel,
-) -> Result<(Vec<u8>, [(&str, &str); 1]), AppError> {
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
     let file_type_res = FileService::get_file_type(&file_data.mime_type, &file_data.file_name);
 
-    if file_type_res.file_type != FileType::Image && file_type_res.file_type != FileType::RawImage {
+    if file_type_res.file_type != FileType::Image
+        && file_type_res.file_type != FileType::RawImage
+        && file_type_res.file_type != FileType::Video
+        && file_type_res.file_type != FileType::Document
+    {
         return Err(AppError::BadRequest {
-            error: Some("File is not an image".to_string()),
+            error: Some("File has no formats".to_string()),
         });
     }
 
-    // Check that the file exists on disk
-    let upload_location = std::env::var("UPLOAD_LOCATION").unwrap();
-
     let file_should_have_formats = file_data.file_type != FileType::RawImage;
+    let storage = &state.storage;
+
+    let accept = headers.get(ACCEPT).and_then(|accept| accept.to_str().ok());
 
-    let format_path = if file_should_have_formats {
-        StdPath::new(&upload_location)
-            .join("formats")
-            .join(ImageService::make_image_format_name(file_data.id, format))
+    let (format_key, content_type) = if !file_should_have_formats {
+        (file_data.content_key(), file_data.mime_type.clone())
+    } else if format == ImageFormat::Excerpt {
+        let codec = ExcerptCodec::negotiate(accept);
+
+        (
+            storage::format_key(&ImageService::make_excerpt_format_name(file_data.id, codec)),
+            format!("{}; charset=utf-8", codec.mime_type()),
+        )
     } else {
-        StdPath::new(&upload_location).join(file_data.id.to_string())
+        let available = state.image_service.get_codecs(file_data.id, format).await?;
+        let codec = ImageCodec::negotiate(accept, &available);
+
+        (
+            storage::format_key(&ImageService::make_image_format_name(
+                file_data.id,
+                format,
+                codec,
+            )),
+            codec.mime_type().to_string(),
+        )
     };
 
-    if !format_path.exists() {
+    // Check that the file exists in storage
+    let format_exists = storage.exists(&format_key).await.map_err(|e| {
+        tracing::error!("Error checking image file: {}", e);
+        AppError::InternalError
+    })?;
+
+    if !format_exists {
         return Err(AppError::NotFound {
             error: "Format not found".to_string(),
         });
     }
 
-    let image = tokio::fs::read(format_path).await.map_err(|e| {
+    let image = storage.get_bytes(&format_key).await.map_err(|e| {
         tracing::error!("Error reading image file: {}", e);
         AppError::InternalError
     })?;
 
-    let headers = if file_should_have_formats {
-        [("Content-Type", "image/jpeg")]
-    } else {
-        [("Content-Type", file_data.mime_type.as_str())]
-    };
-    Ok((image, headers))
+    // Formats hold user content, excerpts and SVGs must not run scripts on this origin
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (VARY, "Accept".to_string()),
+            (
+                CONTENT_SECURITY_POLICY,
+                "default-src 'none'; style-src 'unsafe-inline'; sandbox".to_string(),
+            ),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        image,
+    )
+        .into_response())
 }
 
 pub async fn get_image_by_format(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
+    headers: HeaderMap,
     Path((file_id, format)): Path<(i64, i16)>,
 ) -> Result<Response, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -80,14 +121,13 @@ pub async fn get_image_by_format(
 
     let format = ImageFormat::format_by_id_save(format)?;
 
-    let (image, headers) = get_image_format_data(format, &file_data).await?;
-
-    Ok((headers, image).into_response())
+    get_image_format_data(&state, format, &file_data, &headers).await
 }
 
 pub async fn get_share_image_by_format_through_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
+    headers: HeaderMap,
     Path((share_uuid, file_id, format)): Path<(String, i64, i16)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid.clone(), false).await?;
@@ -104,14 +144,13 @@ pub async fn get_share_image_by_format_through_folder(
     let share_file_data = get_share_file(&state, Some(file_id)).await?;
     let format = ImageFormat::format_by_id_save(format)?;
 
-    let (image, headers) = get_image_format_data(format, &share_file_data.file).await?;
-
-    Ok((headers, image).into_response())
+    get_image_format_data(&state, format, &share_file_data.file, &headers).await
 }
 
 pub async fn get_share_image_by_format_through_album(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
+    headers: HeaderMap,
     Path((share_uuid, file_id, format)): Path<(String, i64, i16)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid.clone(), true).await?;
@@ -130,28 +169,25 @@ pub async fn get_share_image_by_format_through_album(
     let file = state.file_service.get_file(file_id, None).await?;
     let format = ImageFormat::format_by_id_save(format)?;
 
-    let (image, headers) = get_image_format_data(format, &file).await?;
-
-    Ok((headers, image).into_response())
+    get_image_format_data(&state, format, &file, &headers).await
 }
 
 pub async fn get_share_image_by_format(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
+    headers: HeaderMap,
     Path((share_uuid, format)): Path<(String, i16)>,
 ) -> Result<Response, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid.clone(), false).await?;
     let share_file_data = get_share_file(&state, share.file_id).await?;
     let format = ImageFormat::format_by_id_save(format)?;
 
-    let (image, headers) = get_image_format_data(format, &share_file_data.file).await?;
-
-    Ok((headers, image).into_response())
+    get_image_format_data(&state, format, &share_file_data.file, &headers).await
 }
 
 pub async fn reprocess_images_from_operation(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(operation_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -191,23 +227,15 @@ pub async fn reprocess_images_from_operation(
     };
 
     if !metadata.is_empty() {
-        let image_service_clone = state.image_service.clone();
-
         state
             .file_service
             .update_preview_status_for_file_ids(&metadata, PreviewStatus::Processing)
             .await?;
 
-        IMAGE_PROCESSING_RUNTIME.spawn(async move {
-            let _ = image_service_clone
-                .generate_all_formats(
-                    metadata,
-                    user_id.clone(),
-                    Arc::new(state.clone()),
-                    Some(operation.id),
-                )
-                .await;
-        });
+        state
+            .job_service
+            .enqueue_image_processing(user_id, metadata, Some(operation.id))
+            .await?;
     }
 
     Ok(AppSuccess::OK { data: None })
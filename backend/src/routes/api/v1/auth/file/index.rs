@@ -3,7 +3,7 @@ use axum::extract::{Path, Query, State};
 use axum::Json;
 use axum_valid::Valid;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use validator::Validate;
 
 use crate::model::file::FileModelDTO;
@@ -77,7 +77,7 @@ impl GetFilesSortParams<SortByFolders> {
 
 pub async fn get_files(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Query(sort_params): Query<GetFilesSortParams<SortByFiles>>,
     folder_id: Result<Path<i64>, PathRejection>,
 ) -> Result<Json<Vec<FileModelDTO>>, AppError> {
@@ -91,10 +91,8 @@ pub async fn get_files(
     let files = state
         .file_service
         .get_files(user_id, folder, false, sort_params)
-        .await?
-        .into_iter()
-        .map(FileModelDTO::from)
-        .collect();
+        .await?;
+    let files = state.tag_service.tag_files(files).await?;
 
     Ok(Json(files))
 }
@@ -117,7 +115,7 @@ impl GetRecentFilesParams {
 
 pub async fn get_recent_files(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Query(params): Query<GetRecentFilesParams>,
 ) -> Result<Json<Vec<FileModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -135,15 +133,42 @@ pub async fn get_recent_files(
 
 pub async fn get_deleted_files(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
+    folder_id: Result<Path<i64>, PathRejection>,
 ) -> Result<Json<Vec<FileModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    let folder_id = match folder_id {
+        Ok(Path(folder_id)) => Some(folder_id),
+        Err(_) => None,
+    };
+
+    if let Some(folder_id) = folder_id {
+        state
+            .folder_service
+            .get_deleted_folder(folder_id, user_id)
+            .await?
+            .ok_or(AppError::NotFound {
+                error: "Folder not in bin".to_string(),
+            })?;
+    }
+
     let files = state
         .file_service
-        .get_marked_deleted_files(user_id)
+        .get_marked_deleted_files(user_id, folder_id)
         .await?
         .into_iter()
-        .map(FileModelDTO::from)
+        .map(|file| {
+            let deleted_at = file.deleted_at;
+            let mut dto = FileModelDTO::from(file);
+            dto.days_until_purge = deleted_at.and_then(|deleted_at| {
+                state
+                    .bin_service
+                    .get_days_until_purge(deleted_at, user.bin_retention_days)
+            });
+            dto
+        })
         .collect();
 
     Ok(Json(files))
@@ -167,7 +192,7 @@ impl GetFilesByType {
 
 pub async fn get_file_by_type(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_type): Path<i16>,
     Query(params): Query<GetFilesByType>,
 ) -> Result<Json<Vec<FileModelDTO>>, AppError> {
@@ -206,7 +231,7 @@ impl CreateMarkdownFilePayload {
 
 pub async fn create_markdown_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<CreateMarkdownFilePayload>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -241,7 +266,7 @@ pub struct MoveParams {
 
 pub async fn move_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
     params: Option<Query<MoveParams>>,
 ) -> ResponseResult {
@@ -297,7 +322,7 @@ pub struct RenameParams {
 
 pub async fn rename_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
     Valid(Json(params)): Valid<Json<RenameParams>>,
 ) -> ResponseResult {
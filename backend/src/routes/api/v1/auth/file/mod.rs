@@ -5,4 +5,5 @@ pub mod image;
 pub mod bin;
 pub mod upload;
 pub mod favorite;
-pub mod zip;
\ No newline at end of file
+pub mod zip;
+pub mod version;
\ No newline at end of file
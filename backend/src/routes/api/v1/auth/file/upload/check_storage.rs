@@ -9,7 +9,11 @@ pub async fn check_user_storage_limit(
     let storage_used = usage_service
         .get_user_storage_usage(user_id, None)
         .await?
-        .get_sum();
+        .get_sum()
+        + usage_service
+            .get_version_storage_usage(user_id)
+            .await?
+            .get_sum();
 
     if limit - storage_used < 0 {
         return Err(AppError::BadRequest {
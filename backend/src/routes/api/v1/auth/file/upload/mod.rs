@@ -1,7 +1,9 @@
 pub use upload::*;
+pub use tus::*;
 
 mod upload;
-mod folder_segments;
-mod check_storage;
-mod stream;
+mod tus;
+pub mod folder_segments;
+pub mod check_storage;
+pub mod stream;
 mod quick_share_destination;
\ No newline at end of file
@@ -3,34 +3,36 @@ use axum::body::Bytes;
 use axum::BoxError;
 use std::io;
 use tokio_util::io::StreamReader;
-use tokio::io::BufWriter;
-use tokio::fs::File;
+use crate::response::error_handling::AppError;
+use crate::services::blob_service::{BlobService, StoredBlob};
 
-pub async fn stream_to_file<S, E>(path: &str, name: &str, stream: S) -> Result<u64, String>
+pub async fn stream_to_blob<S, E>(blob_service: &BlobService, stream: S) -> Result<StoredBlob, AppError>
 where
-    S: Stream<Item = Result<Bytes, E>>,
+    S: Stream<Item = Result<Bytes, E>> + Send,
     E: Into<BoxError>,
 {
-    async {
-        // Convert the stream into an `AsyncRead`.
-        let body_with_io_error = stream.map_err(|err| io::Error::new(io::ErrorKind::Other, err));
-        let body_reader = StreamReader::new(body_with_io_error);
-        futures::pin_mut!(body_reader);
+    // Convert the stream into an `AsyncRead`.
+    let body_with_io_error = stream.map_err(io::Error::other);
+    let body_reader = StreamReader::new(body_with_io_error);
+    futures::pin_mut!(body_reader);
 
-        // Create the file. `File` implements `AsyncWrite`.
-        let path = std::path::Path::new(path).join(name);
-        let mut file = BufWriter::new(File::create(&path).await?);
+    // The content is hashed while it is written, so identical uploads share one blob
+    blob_service.store(&mut body_reader).await
+}
 
-        // Copy the body into the file.
-        match tokio::io::copy(&mut body_reader, &mut file).await {
-            Err(e) => {
-                tracing::error!("Error copying file from stream: {}", e);
-                let _ = tokio::fs::remove_file(&path).await;
-                return Err(e);
-            }
-            Ok(len) => Ok(len),
-        }
-    }
-    .await
-    .map_err(|err| err.to_string())
-}
\ No newline at end of file
+/// Streams at most `limit` bytes into a blob, `None` if the content is larger
+pub async fn stream_to_blob_limited<S, E>(
+    blob_service: &BlobService,
+    stream: S,
+    limit: u64,
+) -> Result<Option<StoredBlob>, AppError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send,
+    E: Into<BoxError>,
+{
+    let body_with_io_error = stream.map_err(io::Error::other);
+    let body_reader = StreamReader::new(body_with_io_error);
+    futures::pin_mut!(body_reader);
+
+    blob_service.store_limited(&mut body_reader, limit).await
+}
@@ -0,0 +1,500 @@
+use crate::model::internal::file_type::FileType;
+use crate::model::upload::TusUploadModel;
+use crate::response::error_handling::AppError;
+use crate::routes::api::v1::auth::file::index::FILE_SIZE_LIMIT;
+use crate::routes::api::v1::auth::file::upload::{
+    check_storage, folder_segments, start_content_indexing, start_image_processing,
+};
+use crate::services::file_service::FileService;
+use crate::services::search_service::SearchService;
+use crate::services::session_service::SessionService;
+use crate::services::upload_service::UploadService;
+use crate::session::AuthSession;
+use crate::state::{AppState, KosmosState};
+use axum::body::Body;
+use axum::extract::{OriginalUri, Path, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, LOCATION};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::prelude::*;
+use futures::TryStreamExt;
+use std::collections::HashMap;
+use std::io;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tokio_util::io::StreamReader;
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_CONTENT_TYPE: &str = "application/offset+octet-stream";
+const TUS_EXTENSIONS: &str = "creation,termination";
+
+const TUS_RESUMABLE: &str = "Tus-Resumable";
+const TUS_VERSION_HEADER: &str = "Tus-Version";
+const TUS_EXTENSION: &str = "Tus-Extension";
+const TUS_MAX_SIZE: &str = "Tus-Max-Size";
+const UPLOAD_LENGTH: &str = "Upload-Length";
+const UPLOAD_OFFSET: &str = "Upload-Offset";
+const UPLOAD_METADATA: &str = "Upload-Metadata";
+
+/// Every tus response has to state the protocol version, including errors
+pub async fn add_tus_resumable_header(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    response
+}
+
+fn is_supported_version(headers: &HeaderMap) -> bool {
+    headers
+        .get(TUS_RESUMABLE)
+        .is_some_and(|version| version == TUS_VERSION)
+}
+
+fn unsupported_version_response() -> Response {
+    (
+        StatusCode::PRECONDITION_FAILED,
+        [(TUS_VERSION_HEADER, TUS_VERSION)],
+    )
+        .into_response()
+}
+
+fn parse_header_number(headers: &HeaderMap, name: &str) -> Result<i64, AppError> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .ok_or(AppError::BadRequest {
+            error: Some(format!("Missing or invalid {} header", name)),
+        })
+}
+
+/// Parses the `Upload-Metadata` header, a comma separated list of keys with base64 encoded values
+fn parse_upload_metadata(headers: &HeaderMap) -> Result<HashMap<String, String>, AppError> {
+    let invalid_metadata = || AppError::BadRequest {
+        error: Some("Invalid Upload-Metadata header".to_string()),
+    };
+
+    let mut metadata = HashMap::new();
+
+    let Some(header) = headers.get(UPLOAD_METADATA) else {
+        return Ok(metadata);
+    };
+
+    let header = header.to_str().map_err(|_| invalid_metadata())?;
+
+    for pair in header.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, encoded)) => {
+                let decoded = BASE64_STANDARD
+                    .decode(encoded.trim())
+                    .map_err(|_| invalid_metadata())?;
+                (
+                    key,
+                    String::from_utf8(decoded).map_err(|_| invalid_metadata())?,
+                )
+            }
+            None => (pair, "".to_string()),
+        };
+        metadata.insert(key.to_string(), value);
+    }
+
+    Ok(metadata)
+}
+
+/// Storage a new upload of the user can use, unfinished uploads already hold a part of it
+async fn get_upload_storage_remaining(state: &AppState, user_id: i64) -> Result<i64, AppError> {
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    Ok(
+        check_storage::check_user_storage_limit(&state.usage_service, user.id, user.storage_limit)
+            .await?
+            - state.upload_service.get_reserved_storage(user.id).await?,
+    )
+}
+
+/// Lets clients discover the supported version, extensions and the largest upload they can create
+pub async fn get_tus_options(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let max_size = match get_upload_storage_remaining(&state, user_id).await {
+        Ok(remaining) => remaining.max(0),
+        Err(AppError::BadRequest { .. }) => 0,
+        Err(e) => return Err(e),
+    };
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            (TUS_VERSION_HEADER, TUS_VERSION.to_string()),
+            (TUS_EXTENSION, TUS_EXTENSIONS.to_string()),
+            (TUS_MAX_SIZE, max_size.to_string()),
+        ],
+    )
+        .into_response())
+}
+
+pub async fn create_tus_upload(
+    State(state): KosmosState,
+    session: AuthSession,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    if !is_supported_version(&headers) {
+        return Ok(unsupported_version_response());
+    }
+
+    let upload_length = parse_header_number(&headers, UPLOAD_LENGTH)?;
+    let metadata = parse_upload_metadata(&headers)?;
+
+    let file_name = metadata
+        .get("filename")
+        .filter(|name| !name.is_empty())
+        .ok_or(AppError::BadRequest {
+            error: Some("Missing filename in Upload-Metadata".to_string()),
+        })?
+        .to_owned();
+
+    if file_name.len() > 255 {
+        return Err(AppError::BadRequest {
+            error: Some("File name is too long".to_string()),
+        });
+    }
+
+    let mime_type = metadata
+        .get("filetype")
+        .filter(|mime| !mime.is_empty())
+        .map(|mime| mime.to_owned())
+        .unwrap_or("application/octet-stream".to_string());
+
+    let parent_folder_id = match metadata.get("folder_id") {
+        Some(folder_id) if !folder_id.is_empty() => {
+            let folder_id = folder_id.parse::<i64>().map_err(|_| AppError::BadRequest {
+                error: Some("Invalid folder_id in Upload-Metadata".to_string()),
+            })?;
+
+            state
+                .folder_service
+                .check_folder_exists_by_id(folder_id, user_id)
+                .await?
+                .ok_or(AppError::NotFound {
+                    error: "Folder not found".to_string(),
+                })?;
+
+            Some(folder_id)
+        }
+        _ => None,
+    };
+
+    if upload_length > get_upload_storage_remaining(&state, user_id).await? {
+        return Err(AppError::BadRequest {
+            error: Some("Storage limit exceeded".to_string()),
+        });
+    }
+
+    let upload_id = state.get_safe_id()?;
+
+    let upload = state
+        .upload_service
+        .create_upload(
+            upload_id,
+            user_id,
+            file_name,
+            mime_type,
+            parent_folder_id,
+            upload_length,
+        )
+        .await?;
+
+    let location = format!("{}/{}", uri.path().trim_end_matches('/'), upload_id);
+
+    // Empty files are complete as soon as they are created
+    if upload.upload_length == 0 {
+        finish_tus_upload(&state, upload).await?;
+    }
+
+    Ok((StatusCode::CREATED, [(LOCATION, location)]).into_response())
+}
+
+pub async fn get_tus_upload_offset(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(upload_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    if !is_supported_version(&headers) {
+        return Ok(unsupported_version_response());
+    }
+
+    let upload = state.upload_service.get_upload(upload_id, user_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (UPLOAD_OFFSET, upload.upload_offset.to_string()),
+            (UPLOAD_LENGTH, upload.upload_length.to_string()),
+            (CACHE_CONTROL.as_str(), "no-store".to_string()),
+        ],
+    )
+        .into_response())
+}
+
+pub async fn append_tus_upload(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(upload_id): Path<i64>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    if !is_supported_version(&headers) {
+        return Ok(unsupported_version_response());
+    }
+
+    if headers
+        .get(CONTENT_TYPE)
+        .is_none_or(|ct| ct != TUS_CONTENT_TYPE)
+    {
+        return Ok(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response());
+    }
+
+    let upload_offset = parse_header_number(&headers, UPLOAD_OFFSET)?;
+
+    // Only the owner can claim the upload
+    state.upload_service.get_upload(upload_id, user_id).await?;
+
+    // Claimed before the offset is read, so a concurrent request can't append at the same offset
+    let _claim = state
+        .upload_service
+        .claim_upload(upload_id)
+        .ok_or(AppError::Locked {
+            error: "Upload is already receiving data".to_string(),
+        })?;
+    let upload = state.upload_service.get_upload(upload_id, user_id).await?;
+
+    if upload_offset != upload.upload_offset {
+        return Err(AppError::DataConflict {
+            error: "Upload-Offset does not match the current offset".to_string(),
+        });
+    }
+
+    // All data was received before but the file could not be created, a request without
+    // data at the final offset tries it again
+    if upload.upload_offset == upload.upload_length {
+        let upload_length = upload.upload_length;
+        finish_tus_upload(&state, upload).await?;
+
+        return Ok((
+            StatusCode::NO_CONTENT,
+            [(UPLOAD_OFFSET, upload_length.to_string())],
+        )
+            .into_response());
+    }
+
+    let path = UploadService::get_partial_upload_path(upload.id);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error opening partial upload {}: {}", upload.id, e);
+            AppError::InternalError
+        })?;
+
+    // Discard data which was written after the last confirmed offset
+    file.set_len(upload.upload_offset as u64)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error truncating partial upload {}: {}", upload.id, e);
+            AppError::InternalError
+        })?;
+
+    file.seek(io::SeekFrom::Start(upload.upload_offset as u64))
+        .await
+        .map_err(|e| {
+            tracing::error!("Error seeking partial upload {}: {}", upload.id, e);
+            AppError::InternalError
+        })?;
+
+    let mut file = BufWriter::new(file);
+
+    let remaining = (upload.upload_length - upload.upload_offset) as u64;
+    let mut body_reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+
+    let copy_result = tokio::io::copy(&mut (&mut body_reader).take(remaining), &mut file).await;
+
+    // Keep everything received so far, even if the connection dropped
+    let flush_result = file.flush().await;
+
+    // Nothing of a request which is longer than the upload is kept, it is discarded by the
+    // truncation of the next request
+    if copy_result.is_ok() {
+        let mut overflow = [0u8; 1];
+        if body_reader.read(&mut overflow).await.unwrap_or(0) > 0 {
+            return Err(AppError::BadRequest {
+                error: Some("Upload exceeds the declared Upload-Length".to_string()),
+            });
+        }
+    }
+
+    let new_offset = file
+        .get_ref()
+        .metadata()
+        .await
+        .map(|m| m.len() as i64)
+        .unwrap_or(upload.upload_offset);
+
+    if !state
+        .upload_service
+        .update_upload_offset(upload.id, upload.upload_offset, new_offset)
+        .await?
+    {
+        return Err(AppError::DataConflict {
+            error: "Upload-Offset does not match the current offset".to_string(),
+        });
+    }
+
+    if let Err(e) = copy_result.and(flush_result) {
+        tracing::error!("Error appending to upload {}: {}", upload.id, e);
+        return Err(AppError::InternalError);
+    }
+
+    if new_offset == upload.upload_length {
+        finish_tus_upload(
+            &state,
+            TusUploadModel {
+                upload_offset: new_offset,
+                ..upload
+            },
+        )
+        .await?;
+    }
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [(UPLOAD_OFFSET, new_offset.to_string())],
+    )
+        .into_response())
+}
+
+pub async fn delete_tus_upload(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(upload_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    if !is_supported_version(&headers) {
+        return Ok(unsupported_version_response());
+    }
+
+    let upload = state.upload_service.get_upload(upload_id, user_id).await?;
+    state.upload_service.delete_upload(upload.id).await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Moves a completed upload into storage and creates the file the same way a multipart upload does
+async fn finish_tus_upload(state: &AppState, upload: TusUploadModel) -> Result<(), AppError> {
+    let (file_name, parent_folder_id) = folder_segments::process_folder_segments(
+        state,
+        upload.user_id,
+        upload.parent_folder_id,
+        &mut HashMap::new(),
+        upload.file_name.clone(),
+    )
+    .await?;
+
+    let mut file_type_res = FileService::get_file_type(&upload.mime_type, &file_name);
+
+    let exists = state
+        .file_service
+        .check_file_exists_by_name(&file_name, upload.user_id, parent_folder_id)
+        .await?;
+
+    let mut partial_file = tokio::fs::File::open(UploadService::get_partial_upload_path(upload.id))
+        .await
+        .map_err(|e| {
+            tracing::error!("Error opening finished upload {}: {}", upload.id, e);
+            AppError::InternalError
+        })?;
+
+    let blob = state.blob_service.store(&mut partial_file).await?;
+    let len = blob.size as u64;
+
+    if file_type_res.file_type == FileType::Image && len > FILE_SIZE_LIMIT {
+        file_type_res.file_type = FileType::LargeImage;
+    }
+
+    // An existing file keeps its id and gets the upload as its newest version
+    let file_id = match exists {
+        Some(existing) => {
+            let file = state
+                .file_service
+                .get_file(existing, Some(upload.user_id))
+                .await?;
+
+            // Finishing again after the file was created doesn't add a version
+            if file.blob_hash.as_deref() == Some(blob.hash.as_str()) {
+                let _ = state.blob_service.release(&blob.hash).await;
+                state.upload_service.delete_upload(upload.id).await?;
+                return Ok(());
+            }
+
+            state
+                .file_service
+                .replace_file_content(
+                    &file,
+                    blob,
+                    file_type_res.file_type,
+                    file_type_res.normalized_mime_type,
+                )
+                .await?;
+
+            tracing::info!(
+                "File {} replaced with upload {} for user {}",
+                existing,
+                upload.id,
+                upload.user_id
+            );
+            existing
+        }
+        None => {
+            state
+                .file_service
+                .create_file(
+                    upload.user_id,
+                    upload.id,
+                    file_name,
+                    parent_folder_id,
+                    &file_type_res,
+                    blob,
+                )
+                .await?
+        }
+    };
+
+    state.upload_service.delete_upload(upload.id).await?;
+
+    if state
+        .image_service
+        .supports_file_type(file_type_res.file_type)
+    {
+        start_image_processing(state, upload.user_id, vec![file_id]).await?;
+    }
+
+    if SearchService::supports_file_type(file_type_res.file_type) {
+        start_content_indexing(state, upload.user_id, vec![file_id]).await?;
+    }
+
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::constants::MAX_QUICK_SHARE_FILES;
 use crate::model::internal::file_type::FileType;
 use crate::model::internal::preview_status::PreviewStatus;
+use crate::model::internal::share_mode::ShareMode;
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::routes::api::v1::auth::file::index::FILE_SIZE_LIMIT;
@@ -8,10 +9,10 @@ use crate::routes::api::v1::auth::file::upload::{
     check_storage, folder_segments, quick_share_destination, stream,
 };
 use crate::routes::api::v1::share::create::ShareFolderPublicRequest;
-use crate::runtimes::IMAGE_PROCESSING_RUNTIME;
 use crate::services::file_service::FileService;
-use crate::services::session_service::SessionService;
-use crate::state::KosmosState;
+use crate::services::search_service::SearchService;
+use crate::services::session_service::{SessionService, UserId};
+use crate::state::{AppState, KosmosState};
 use crate::utils::auth;
 use axum::extract::rejection::PathRejection;
 use axum::extract::{Multipart, Path, Query, State};
@@ -19,8 +20,7 @@ use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::str::FromStr;
-use std::sync::Arc;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Deserialize)]
 pub struct FileUploadParams {
@@ -62,7 +62,7 @@ impl FileUploadParams {
 
 pub async fn upload_file(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Query(params): Query<FileUploadParams>,
     folder_id: Result<Path<i64>, PathRejection>,
     mut multipart: Multipart,
@@ -79,13 +79,9 @@ pub async fn upload_file(
         check_storage::check_user_storage_limit(&state.usage_service, user.id, user.storage_limit)
             .await?;
 
-    let location = std::env::var("UPLOAD_LOCATION").map_err(|e| {
-        tracing::error!("Error getting upload location: {}", e);
-        AppError::InternalError
-    })?;
-
     let mut folder_cache: HashMap<String, i64> = HashMap::new();
     let mut pending_image_formats: Vec<i64> = Vec::new();
+    let mut pending_content_index: Vec<i64> = Vec::new();
 
     let quick_share_destination = if params.is_quick_share() {
         Some(
@@ -145,25 +141,13 @@ pub async fn upload_file(
             .check_file_exists_by_name(&file_name, user.id, relative_parent_folder)
             .await?;
 
-        if let Some(file) = exists {
-            state
-                .file_service
-                .permanently_delete_file(file, None)
-                .await?;
-
-            tracing::info!(
-                "File {} deleted for replacement {} for user {}",
-                file,
-                id,
-                user.id
-            );
-        }
-
-        match stream::stream_to_file(&location, &id.to_string(), field).await {
-            Ok(len) => {
+        match stream::stream_to_blob(&state.blob_service, field).await {
+            Ok(blob) => {
+                let len = blob.size as u64;
                 storage_remaining -= len as i64;
 
                 if storage_remaining < 0 {
+                    let _ = state.blob_service.release(&blob.hash).await;
                     return Err(AppError::BadRequest {
                         error: Some("Storage limit exceeded".to_string()),
                     })?;
@@ -173,26 +157,53 @@ pub async fn upload_file(
                     file_type_res.file_type = FileType::LargeImage;
                 }
 
-                state
-                    .file_service
-                    .create_file(
-                        user.id,
-                        id,
-                        file_name,
-                        len as i64,
-                        file_type_res.file_type,
-                        file_type_res.normalized_mime_type,
-                        relative_parent_folder,
-                    )
-                    .await?;
-
-                if file_type_res.file_type == FileType::Image {
-                    pending_image_formats.push(id);
+                // An existing file keeps its id and gets the upload as its newest version
+                let file_id = match exists {
+                    Some(existing) => {
+                        let file = state.file_service.get_file(existing, Some(user.id)).await?;
+                        state
+                            .file_service
+                            .replace_file_content(
+                                &file,
+                                blob,
+                                file_type_res.file_type,
+                                file_type_res.normalized_mime_type,
+                            )
+                            .await?;
+
+                        tracing::info!(
+                            "File {} replaced with a new version for user {}",
+                            existing,
+                            user.id
+                        );
+                        existing
+                    }
+                    None => {
+                        state
+                            .file_service
+                            .create_file(
+                                user.id,
+                                id,
+                                file_name,
+                                relative_parent_folder,
+                                &file_type_res,
+                                blob,
+                            )
+                            .await?
+                    }
+                };
+
+                if state.image_service.supports_file_type(file_type_res.file_type) {
+                    pending_image_formats.push(file_id);
+                }
+
+                if SearchService::supports_file_type(file_type_res.file_type) {
+                    pending_content_index.push(file_id);
                 }
             }
             Err(err) => {
-                tracing::error!("Error uploading file {}: {}", id, err);
-                return Err(AppError::InternalError);
+                tracing::error!("Error uploading file {}", id);
+                return Err(err);
             }
         };
 
@@ -207,6 +218,9 @@ pub async fn upload_file(
             password: params.get_hash()?,
             limit: params.limit,
             expires_at: params.get_expiry()?,
+            mode: ShareMode::ReadOnly,
+            upload_size_limit: None,
+            upload_count_limit: None,
         };
         Some(
             state
@@ -220,28 +234,51 @@ pub async fn upload_file(
 
     tracing::debug!("Pending {}", pending_image_formats.len());
 
-    if !pending_image_formats.is_empty() {
-        let image_service_clone = state.image_service.clone();
-        tracing::debug!("Generating {} formats", pending_image_formats.len());
-
-        state
-            .file_service
-            .update_preview_status_for_file_ids(&pending_image_formats, PreviewStatus::Processing)
-            .await?;
-
-        IMAGE_PROCESSING_RUNTIME.spawn(async move {
-            let _ = image_service_clone
-                .generate_all_formats(
-                    pending_image_formats,
-                    user.id,
-                    Arc::new(state.clone()),
-                    None,
-                )
-                .await;
-        });
-    }
+    start_image_processing(&state, user.id, pending_image_formats).await?;
+    start_content_indexing(&state, user.id, pending_content_index).await?;
 
     Ok(AppSuccess::OK {
         data: share.map(|s| s.uuid.to_string()),
     })
 }
+
+pub async fn start_image_processing(
+    state: &AppState,
+    user_id: UserId,
+    file_ids: Vec<i64>,
+) -> Result<(), AppError> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+
+    tracing::debug!("Generating {} formats", file_ids.len());
+
+    state
+        .file_service
+        .update_preview_status_for_file_ids(&file_ids, PreviewStatus::Processing)
+        .await?;
+
+    state
+        .job_service
+        .enqueue_image_processing(user_id, file_ids, None)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn start_content_indexing(
+    state: &AppState,
+    user_id: UserId,
+    file_ids: Vec<i64>,
+) -> Result<(), AppError> {
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+
+    state
+        .job_service
+        .enqueue_content_indexing(user_id, file_ids)
+        .await?;
+
+    Ok(())
+}
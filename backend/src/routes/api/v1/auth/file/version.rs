@@ -0,0 +1,97 @@
+use crate::model::version::FileVersionModelDTO;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::routes::api::v1::auth::download::{get_raw_content, RawFileAction};
+use crate::routes::api::v1::auth::file::upload::{start_content_indexing, start_image_processing};
+use crate::services::search_service::SearchService;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use crate::session::AuthSession;
+
+pub async fn get_file_versions(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(file_id): Path<i64>,
+) -> Result<Json<Vec<FileVersionModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = state.file_service.get_file(file_id, Some(user_id)).await?;
+
+    let versions = state
+        .version_service
+        .get_versions(file.id)
+        .await?
+        .into_iter()
+        .map(FileVersionModelDTO::from)
+        .collect();
+
+    Ok(Json(versions))
+}
+
+pub async fn handle_raw_file_version(
+    mut headers: HeaderMap,
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((file_id, version_id, operation_type)): Path<(i64, i64, RawFileAction)>,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = state.file_service.get_file(file_id, Some(user_id)).await?;
+    let version = state.version_service.get_version(file.id, version_id).await?;
+
+    let raw_response =
+        get_raw_content(&mut headers, &state, &version.as_file(&file), operation_type).await?;
+
+    Ok(raw_response.into_response())
+}
+
+pub async fn restore_file_version(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((file_id, version_id)): Path<(i64, i64)>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = state.file_service.get_file(file_id, Some(user_id)).await?;
+    let version = state.version_service.get_version(file.id, version_id).await?;
+
+    let file_type = state.file_service.restore_version(&file, &version).await?;
+
+    if state.image_service.supports_file_type(file_type) {
+        start_image_processing(&state, user_id, vec![file.id]).await?;
+    }
+
+    if SearchService::supports_file_type(file_type) {
+        start_content_indexing(&state, user_id, vec![file.id]).await?;
+    }
+
+    Ok(AppSuccess::UPDATED)
+}
+
+pub async fn delete_file_version(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((file_id, version_id)): Path<(i64, i64)>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = state.file_service.get_file(file_id, Some(user_id)).await?;
+    let version = state.version_service.get_version(file.id, version_id).await?;
+
+    state.version_service.delete_version(version.id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
+
+pub async fn prune_file_versions(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(file_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = state.file_service.get_file(file_id, Some(user_id)).await?;
+
+    state.version_service.delete_versions_for_file(file.id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
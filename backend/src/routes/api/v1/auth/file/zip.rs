@@ -1,77 +1,42 @@
 use crate::model::file::FileModel;
+use crate::model::internal::file_type::FileType;
+use crate::model::internal::image_format::ImageFormat;
 use crate::response::error_handling::AppError;
-use crate::services::session_service::SessionService;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::routes::api::v1::auth::download::RawFileAction;
+use crate::routes::api::v1::auth::file::upload::check_storage::check_user_storage_limit;
+use crate::routes::api::v1::auth::file::FILE_SIZE_LIMIT;
+use crate::services::archive_service::ArchiveService;
+use crate::services::file_service::FileService;
+use crate::services::image_service::ImageService;
+use crate::services::session_service::{SessionService, UserId};
 use crate::state::{AppState, KosmosState};
-use axum::extract::{Path, State};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::header::{
+    CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS,
+};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
-use std::fs::File;
+use serde::Deserialize;
 use tokio::task::spawn_blocking;
-use tower_sessions::Session;
-use zip::ZipArchive;
+use crate::session::AuthSession;
 use crate::model::internal::zip::ZipInformation;
-use crate::routes::api::v1::share::{get_share_file, is_allowed_to_access_share};
-
-fn build_zip_information(paths: Vec<String>) -> ZipInformation {
-    let mut root = ZipInformation::new("root");
-
-    for path in paths {
-        let parts: Vec<&str> = path.split('/').collect();
-        root.add_path(&parts);
-    }
-
-    root
-}
+use crate::routes::api::v1::share::{
+    get_share_access_for_folder_items, get_share_file, is_allowed_to_access_share,
+    AccessShareItemType,
+};
 
 pub async fn get_zip_information_for_file(
     state: AppState,
     file_model: FileModel,
 ) -> Result<ZipInformation, AppError> {
-    if !file_model.file_type.is_archive() && !file_model.file_name.ends_with(".zip") {
-        return Err(AppError::BadRequest {
-            error: Some("File is not an zip file".to_string()),
-        });
-    };
-
-    let file = spawn_blocking(move || {
-        File::open(
-            state
-                .file_service
-                .upload_path
-                .join(file_model.id.to_string()),
-        )
-            .map_err(|e| {
-                tracing::error!("Error while loading file {}: {}", file_model.id, e);
-                AppError::InternalError
-            })
-    })
-        .await
-        .map_err(|_| AppError::InternalError)??;
-
-    let mut archive = ZipArchive::new(file).map_err(|e| {
-        tracing::error!(
-            "Error while loading file as archive {}: {}",
-            file_model.id,
-            e
-        );
-        AppError::InternalError
-    })?;
-
-    let mut files_strings: Vec<String> = Vec::new();
-
-    for i in 0..archive.len() {
-        if let Ok(file) = archive.by_index(i) {
-            let name = file.name().trim_start_matches('/').to_string();
-            files_strings.push(name);
-        }
-    }
-
-    let zip_info = build_zip_information(files_strings);
-    Ok(zip_info)
+    state.archive_service.get_information(&file_model).await
 }
 
 pub async fn get_zip_information(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> Result<Json<ZipInformation>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -84,7 +49,7 @@ pub async fn get_zip_information(
 
 pub async fn access_zip_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_uuid): Path<String>,
 ) -> Result<Json<ZipInformation>, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, false).await?;
@@ -96,3 +61,279 @@ pub async fn access_zip_share(
 
     Ok(Json(zip_info))
 }
+
+#[derive(Deserialize)]
+pub struct ZipEntryParams {
+    /// Path of the entry inside the archive, as listed in `ZipInformation`
+    pub path: String,
+}
+
+#[derive(Deserialize)]
+pub struct ExtractZipPayload {
+    /// Folder the archive is extracted to, the folder of the archive if not set
+    pub folder_id: Option<String>,
+}
+
+/// Streams the decompressed entry, the archive is never extracted to storage
+async fn get_zip_entry_data(
+    state: &AppState,
+    file: &FileModel,
+    path: String,
+    operation_type: RawFileAction,
+) -> Result<Response, AppError> {
+    let (entry, content) = state.archive_service.stream_entry(file, path).await?;
+
+    let file_name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+    let mime_type = mime_guess::from_path(file_name).first_or_octet_stream();
+    let disposition = match operation_type {
+        RawFileAction::Download => format!("attachment; filename=\"{}\"", file_name),
+        RawFileAction::Serve => String::from("inline"),
+    };
+
+    // Entries are not checked like uploads, they must not run scripts on this origin
+    Ok((
+        [
+            (CONTENT_TYPE, mime_type.to_string()),
+            (CONTENT_DISPOSITION, disposition),
+            (CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        Body::from_stream(content),
+    )
+        .into_response())
+}
+
+/// Renders the format of an image entry on demand
+async fn get_zip_entry_image_data(
+    state: &AppState,
+    file: &FileModel,
+    path: String,
+    format: i16,
+) -> Result<Response, AppError> {
+    let format = ImageFormat::format_by_id_save(format)?;
+    if !matches!(
+        format,
+        ImageFormat::Thumbnail | ImageFormat::Preview | ImageFormat::Large
+    ) {
+        return Err(AppError::BadRequest {
+            error: Some("Entries only have image formats".to_string()),
+        });
+    }
+
+    let mime_type = mime_guess::from_path(&path).first_or_octet_stream();
+    if FileService::get_file_type(mime_type.as_ref(), &path).file_type != FileType::Image {
+        return Err(AppError::BadRequest {
+            error: Some("Entry is not an image".to_string()),
+        });
+    }
+
+    let (_, image) = state
+        .archive_service
+        .read_entry(file, path, FILE_SIZE_LIMIT)
+        .await?;
+
+    let image = spawn_blocking(move || ImageService::render_format_on_demand(&image, format))
+        .await
+        .map_err(|_| AppError::InternalError)??;
+
+    Ok(([(CONTENT_TYPE, "image/jpeg")], image).into_response())
+}
+
+/// Queues the extraction for the user, the entries have to fit into the storage left
+async fn start_zip_extraction(
+    state: &AppState,
+    user_id: UserId,
+    file: &FileModel,
+    folder_id: Option<i64>,
+) -> ResponseResult {
+    if !ArchiveService::get_format(file)?.can_read_entries() {
+        return Err(AppError::BadRequest {
+            error: Some("Archive can only be listed".to_string()),
+        });
+    }
+
+    if let Some(folder_id) = folder_id {
+        state
+            .folder_service
+            .check_folder_exists_by_id(folder_id, user_id)
+            .await?
+            .ok_or(AppError::NotFound {
+                error: "Folder not found".to_string(),
+            })?;
+    }
+
+    let user = state.user_service.get_auth_user(user_id).await?;
+    let storage_remaining =
+        check_user_storage_limit(&state.usage_service, user.id, user.storage_limit).await?;
+
+    let extracted_size = state
+        .archive_service
+        .get_entries(file)
+        .await?
+        .iter()
+        .map(|entry| entry.size as i64)
+        .sum::<i64>();
+
+    if extracted_size > storage_remaining {
+        return Err(AppError::BadRequest {
+            error: Some("Storage limit exceeded".to_string()),
+        });
+    }
+
+    let operation = state
+        .job_service
+        .enqueue_archive_extraction(user_id, file.id, folder_id)
+        .await?;
+
+    Ok(AppSuccess::CREATED {
+        id: Some(operation.id.to_string()),
+    })
+}
+
+fn parse_folder_id(payload: &ExtractZipPayload) -> Result<Option<i64>, AppError> {
+    payload
+        .folder_id
+        .as_ref()
+        .map(|id| {
+            id.parse::<i64>().map_err(|_| AppError::BadRequest {
+                error: Some("Invalid folder id".to_string()),
+            })
+        })
+        .transpose()
+}
+
+pub async fn get_zip_entry(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((file_id, operation_type)): Path<(i64, RawFileAction)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file_model = state.file_service.get_file(file_id, Some(user_id)).await?;
+
+    get_zip_entry_data(&state, &file_model, params.path, operation_type).await
+}
+
+pub async fn get_zip_entry_image(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((file_id, format)): Path<(i64, i16)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file_model = state.file_service.get_file(file_id, Some(user_id)).await?;
+
+    get_zip_entry_image_data(&state, &file_model, params.path, format).await
+}
+
+pub async fn extract_zip(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(file_id): Path<i64>,
+    Json(payload): Json<ExtractZipPayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file_model = state.file_service.get_file(file_id, Some(user_id)).await?;
+
+    let folder_id = match parse_folder_id(&payload)? {
+        Some(folder_id) => Some(folder_id),
+        None => file_model.parent_folder_id,
+    };
+
+    start_zip_extraction(&state, user_id, &file_model, folder_id).await
+}
+
+pub async fn get_share_zip_entry(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((share_uuid, operation_type)): Path<(String, RawFileAction)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let share = is_allowed_to_access_share(&state, &session, share_uuid, true).await?;
+    let share_file = get_share_file(&state, share.file_id).await?;
+
+    get_zip_entry_data(&state, &share_file.file, params.path, operation_type).await
+}
+
+pub async fn get_share_zip_entry_image(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((share_uuid, format)): Path<(String, i16)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let share = is_allowed_to_access_share(&state, &session, share_uuid, false).await?;
+    let share_file = get_share_file(&state, share.file_id).await?;
+
+    get_zip_entry_image_data(&state, &share_file.file, params.path, format).await
+}
+
+/// Extracts a shared archive into the files of the logged-in user
+pub async fn extract_share_zip(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(share_uuid): Path<String>,
+    Json(payload): Json<ExtractZipPayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let share = is_allowed_to_access_share(&state, &session, share_uuid, true).await?;
+    let share_file = get_share_file(&state, share.file_id).await?;
+
+    start_zip_extraction(&state, user_id, &share_file.file, parse_folder_id(&payload)?).await
+}
+
+/// Archive in a shared folder, `NotAllowed` if it is not inside the share
+async fn get_share_zip_through_folder(
+    state: &AppState,
+    session: &AuthSession,
+    share_uuid: String,
+    file_id: i64,
+    count_as_use: bool,
+) -> Result<FileModel, AppError> {
+    let share = is_allowed_to_access_share(state, session, share_uuid, count_as_use).await?;
+
+    let can_access_with_share =
+        get_share_access_for_folder_items(state, &AccessShareItemType::Zip, file_id, &share)
+            .await?;
+
+    if !can_access_with_share {
+        return Err(AppError::NotAllowed {
+            error: "Not allowed".to_string(),
+        })?;
+    }
+
+    Ok(get_share_file(state, Some(file_id)).await?.file)
+}
+
+pub async fn get_share_zip_entry_through_folder(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((share_uuid, file_id, operation_type)): Path<(String, i64, RawFileAction)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let file = get_share_zip_through_folder(&state, &session, share_uuid, file_id, true).await?;
+
+    get_zip_entry_data(&state, &file, params.path, operation_type).await
+}
+
+pub async fn get_share_zip_entry_image_through_folder(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((share_uuid, file_id, format)): Path<(String, i64, i16)>,
+    Query(params): Query<ZipEntryParams>,
+) -> Result<Response, AppError> {
+    let file = get_share_zip_through_folder(&state, &session, share_uuid, file_id, false).await?;
+
+    get_zip_entry_image_data(&state, &file, params.path, format).await
+}
+
+pub async fn extract_share_zip_through_folder(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path((share_uuid, file_id)): Path<(String, i64)>,
+    Json(payload): Json<ExtractZipPayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let file = get_share_zip_through_folder(&state, &session, share_uuid, file_id, true).await?;
+
+    start_zip_extraction(&state, user_id, &file, parse_folder_id(&payload)?).await
+}
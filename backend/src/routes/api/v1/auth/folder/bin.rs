@@ -0,0 +1,73 @@
+use axum::extract::rejection::PathRejection;
+use axum::extract::{Path, State};
+use axum::Json;
+
+use crate::model::folder::FolderModelDTO;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::session::AuthSession;
+use crate::state::KosmosState;
+
+pub async fn get_deleted_folders(
+    State(state): KosmosState,
+    session: AuthSession,
+    folder_id: Result<Path<i64>, PathRejection>,
+) -> Result<Json<Vec<FolderModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    let parent_id = match folder_id {
+        Ok(Path(folder_id)) => Some(folder_id),
+        Err(_) => None,
+    };
+
+    if let Some(parent_id) = parent_id {
+        state
+            .folder_service
+            .get_deleted_folder(parent_id, user_id)
+            .await?
+            .ok_or(AppError::NotFound {
+                error: "Folder not in bin".to_string(),
+            })?;
+    }
+
+    let folders = state
+        .folder_service
+        .get_deleted_folders(user_id, parent_id)
+        .await?
+        .into_iter()
+        .map(|folder| {
+            let deleted_at = folder.deleted_at;
+            let mut dto = FolderModelDTO::from(folder);
+            dto.days_until_purge = deleted_at.and_then(|deleted_at| {
+                state
+                    .bin_service
+                    .get_days_until_purge(deleted_at, user.bin_retention_days)
+            });
+            dto
+        })
+        .collect();
+
+    Ok(Json(folders))
+}
+
+pub async fn restore_folder(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(folder_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let folder = state
+        .folder_service
+        .get_deleted_folder(folder_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound {
+            error: "Folder not in bin".to_string(),
+        })?;
+
+    state.folder_service.restore_folder(&folder).await?;
+
+    Ok(AppSuccess::UPDATED)
+}
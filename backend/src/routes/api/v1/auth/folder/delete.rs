@@ -1,4 +1,3 @@
-use crate::model::internal::file_type::FileType;
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::{SessionService, UserId};
@@ -6,7 +5,7 @@ use crate::state::{AppState, KosmosState};
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Deserialize)]
 pub struct MultiDeleteRawBody {
@@ -21,7 +20,7 @@ pub struct MultiDeleteBody {
 
 pub async fn multi_delete(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(body): Json<MultiDeleteRawBody>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -52,7 +51,7 @@ pub async fn multi_delete(
     };
 
     for folder_id in body.folders {
-        delete_folder_with_structure(&state, folder_id, user_id).await?;
+        remove_folder(&state, folder_id, user_id).await?;
     }
 
     for file_id in body.files {
@@ -72,15 +71,27 @@ pub async fn multi_delete(
     Ok(AppSuccess::DELETED)
 }
 
-async fn delete_folder_with_structure(
-    state: &AppState,
-    folder_id: i64,
-    user_id: UserId,
-) -> ResponseResult {
+/// Moves the folder with its structure to the bin, a folder which already is in the bin is
+/// deleted permanently
+async fn remove_folder(state: &AppState, folder_id: i64, user_id: UserId) -> ResponseResult {
     if state
         .folder_service
         .check_folder_exists_by_id(folder_id, user_id)
         .await?
+        .is_some()
+    {
+        state
+            .folder_service
+            .mark_folder_for_deletion(folder_id, user_id)
+            .await?;
+
+        return Ok(AppSuccess::DELETED);
+    }
+
+    if state
+        .folder_service
+        .get_deleted_folder(folder_id, user_id)
+        .await?
         .is_none()
     {
         return Err(AppError::NotFound {
@@ -88,59 +99,20 @@ async fn delete_folder_with_structure(
         });
     }
 
-    let structure = state
+    state
         .folder_service
-        .get_deletion_directories(folder_id, user_id)
+        .permanently_delete_folder(folder_id, user_id, &state.file_service)
         .await?;
 
-    for folder in structure {
-        for i in 0..folder.file_ids.len() {
-            state
-                .file_service
-                .permanently_delete_file(
-                    folder.file_ids[i],
-                    Some(FileType::new(folder.file_types[i])),
-                )
-                .await?;
-        }
-
-        state.folder_service.delete_folder(folder.id).await?;
-    }
-
-    state.folder_service.delete_folder(folder_id).await?;
-
     Ok(AppSuccess::DELETED)
 }
 
 pub async fn delete_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(folder_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
 
-    if state
-        .folder_service
-        .check_folder_exists_by_id(folder_id, user_id)
-        .await?
-        .is_none()
-    {
-        return Err(AppError::NotFound {
-            error: "Folder not found".to_string(),
-        });
-    }
-
-    if state
-        .folder_service
-        .check_folder_contains_elements(folder_id)
-        .await?
-    {
-        return Err(AppError::DataConflict {
-            error: "Folder is not empty".to_string(),
-        });
-    }
-
-    state.folder_service.delete_folder(folder_id).await?;
-
-    Ok(AppSuccess::DELETED)
+    remove_folder(&state, folder_id, user_id).await
 }
@@ -3,7 +3,7 @@ use axum::extract::{Path, Query, State};
 use axum::Json;
 use axum_valid::Valid;
 use serde::{Deserialize, Serialize};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use ts_rs::TS;
 use validator::Validate;
 
@@ -31,7 +31,7 @@ pub struct FolderResponse {
 
 pub async fn get_folders(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Query(sort_params): Query<GetFilesSortParams<SortByFolders>>,
     folder_id: Result<Path<i64>, PathRejection>,
 ) -> Result<Json<FolderResponse>, AppError> {
@@ -42,13 +42,11 @@ pub async fn get_folders(
         Err(_) => None,
     };
 
-    let folders: Vec<FolderModelDTO> = state
+    let folders = state
         .folder_service
         .get_folders(user_id, parent, sort_params)
-        .await?
-        .into_iter()
-        .map(FolderModelDTO::from)
-        .collect::<Vec<_>>();
+        .await?;
+    let folders: Vec<FolderModelDTO> = state.tag_service.tag_folders(folders).await?;
 
     let folder: Option<FolderModelDTO> = if let Some(folder) = parent {
         Some(FolderModelDTO::from(
@@ -87,7 +85,7 @@ pub struct FolderRequest {
 
 pub async fn create_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     folder_id: Result<Path<i64>, PathRejection>,
     Valid(Json(payload)): Valid<Json<FolderRequest>>,
 ) -> ResponseResult {
@@ -118,7 +116,7 @@ pub async fn create_folder(
 
 pub async fn move_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(folder_id): Path<i64>,
     params: Option<Query<MoveParams>>,
 ) -> ResponseResult {
@@ -217,7 +215,7 @@ impl MultiMovePayload {
 
 pub async fn multi_move(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<MultiMovePayload>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -250,7 +248,7 @@ pub async fn multi_move(
 
 pub async fn rename_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(folder_id): Path<i64>,
     Valid(Json(payload)): Valid<Json<RenameParams>>,
 ) -> ResponseResult {
@@ -1,6 +1,7 @@
 pub use index::*;
 
 mod index;
+pub mod bin;
 pub mod delete;
 pub mod favorite;
 pub mod update;
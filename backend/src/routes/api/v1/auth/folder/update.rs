@@ -1,12 +1,11 @@
-use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::SessionService;
 use crate::state::KosmosState;
+use crate::utils::validation;
 use axum::extract::{Path, State};
 use axum::Json;
-use regex::Regex;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Deserialize)]
 pub struct FolderRecolorPayload {
@@ -15,7 +14,7 @@ pub struct FolderRecolorPayload {
 
 pub async fn recolor_folder(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(folder_id): Path<i64>,
     Json(payload): Json<FolderRecolorPayload>,
 ) -> ResponseResult {
@@ -31,13 +30,7 @@ pub async fn recolor_folder(
         return Ok(AppSuccess::UPDATED);
     };
 
-    let color_test = Regex::new(r"^#([0-9a-f]{6})$").unwrap();
-
-    if !color_test.is_match(color) {
-        return Err(AppError::BadRequest {
-            error: Some("Invalid hex color".to_string()),
-        });
-    }
+    validation::validate_color(color)?;
 
     state
         .folder_service
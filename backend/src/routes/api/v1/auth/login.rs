@@ -1,29 +1,35 @@
 use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::Deserialize;
 use tower_sessions::Session;
 
-use crate::constants::SESSION_USER_ID_KEY;
+use crate::model::totp::TwoFactorRequiredDTO;
 use crate::model::user::UserModelDTO;
 use crate::response::error_handling::AppError;
 use crate::services::session_service::SessionService;
 use crate::state::AppState;
-use crate::utils::auth;
 #[derive(Deserialize)]
 pub struct LoginCredentials {
     username: String,
     password: String,
 }
 
+#[derive(Deserialize)]
+pub struct TwoFactorPayload {
+    code: String,
+}
+
 pub async fn login(
     State(state): State<AppState>,
     session: Session,
     Json(payload): Json<LoginCredentials>,
-) -> Result<Json<UserModelDTO>, AppError> {
+) -> Result<Response, AppError> {
     let user_id = SessionService::get_user_id(&session).await;
     if let Some(user_id) = user_id {
         let user = state.user_service.get_auth_user(user_id).await?;
-        return Ok(Json(user.into()));
+        return Ok(Json(UserModelDTO::from(user)).into_response());
     }
 
     let found_user = state
@@ -40,7 +46,10 @@ pub async fn login(
         }
     };
 
-    let is_password_valid = auth::verify_password(payload.password.as_str(), &user.password_hash)?;
+    let is_password_valid = state
+        .user_service
+        .verify_login_password(&user, &payload.password)
+        .await?;
 
     if !is_password_valid {
         Err(AppError::Forbidden {
@@ -48,13 +57,49 @@ pub async fn login(
         })?;
     }
 
-    session
-        .insert(SESSION_USER_ID_KEY, user.id)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to insert session: {}", e);
-            AppError::InternalError
-        })?;
+    // The session is only logged in once the second factor was verified as well
+    if state.totp_service.is_enabled(user.id).await? {
+        SessionService::set_pending_two_factor(&session, user.id).await?;
+
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(TwoFactorRequiredDTO {
+                two_factor_required: true,
+            }),
+        )
+            .into_response());
+    }
+
+    SessionService::log_in(&session, user.id).await?;
+
+    Ok(Json(UserModelDTO::from(user)).into_response())
+}
+
+/// Second step of a login, with a code of the authenticator app or a recovery code
+pub async fn login_two_factor(
+    State(state): State<AppState>,
+    session: Session,
+    Json(payload): Json<TwoFactorPayload>,
+) -> Result<Json<UserModelDTO>, AppError> {
+    let pending = SessionService::get_pending_two_factor(&session).await?;
+
+    if !state
+        .totp_service
+        .verify(pending.user_id, &payload.code)
+        .await?
+    {
+        SessionService::record_failed_two_factor(&session, pending).await?;
+
+        return Err(AppError::Forbidden {
+            error: Some("Invalid code".to_string()),
+        });
+    }
+
+    SessionService::clear_pending_two_factor(&session).await?;
+
+    let user = state.user_service.get_auth_user(pending.user_id).await?;
+
+    SessionService::log_in(&session, user.id).await?;
 
     Ok(Json(user.into()))
 }
@@ -17,5 +17,7 @@ pub mod operation;
 pub mod admin;
 pub mod favorite;
 pub mod album;
+pub mod tag;
 pub mod passkey;
+pub mod oidc;
 pub mod content;
\ No newline at end of file
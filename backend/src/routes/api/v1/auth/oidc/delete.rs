@@ -0,0 +1,18 @@
+use axum::extract::{Path, State};
+use crate::session::AuthSession;
+
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+
+pub async fn delete_oidc_link(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(link_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state.oidc_service.delete_link(user_id, link_id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -0,0 +1,143 @@
+use axum::extract::{Query, State};
+use axum::response::Redirect;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+use tower_sessions::Session;
+
+use crate::response::error_handling::AppError;
+use crate::services::session_service::{SessionService, UserId};
+use crate::services::user_service::UserService;
+use crate::state::KosmosState;
+use crate::utils::{auth, validation};
+
+#[derive(Deserialize)]
+pub struct OidcCallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// Sends the browser to the identity provider. A logged-in user links the identity to
+/// their account instead of logging in.
+pub async fn oidc_login(
+    State(state): KosmosState,
+    session: Session,
+) -> Result<Redirect, AppError> {
+    let (authorization_url, login_state) = state.oidc_service.start_login().await?;
+
+    SessionService::set_oidc_login(&session, login_state).await?;
+
+    Ok(Redirect::to(&authorization_url))
+}
+
+pub async fn oidc_callback(
+    State(state): KosmosState,
+    session: Session,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Redirect, AppError> {
+    let config = state.oidc_service.config()?;
+    let login_state = SessionService::take_oidc_login(&session).await?;
+
+    if let Some(error) = query.error {
+        return Err(AppError::BadRequest {
+            error: Some(query.error_description.unwrap_or(error)),
+        });
+    }
+
+    let (Some(code), Some(returned_state)) = (query.code, query.state) else {
+        return Err(AppError::BadRequest {
+            error: Some("Missing code or state".to_string()),
+        });
+    };
+
+    if returned_state != login_state.state {
+        return Err(AppError::BadRequest {
+            error: Some("Invalid state".to_string()),
+        });
+    }
+
+    let claims = state.oidc_service.finish_login(&code, &login_state).await?;
+    let role = state.oidc_service.get_role_from_claims(&claims)?;
+
+    let link = state
+        .oidc_service
+        .get_link(&config.issuer, &claims.sub)
+        .await?;
+
+    let user_id: UserId = match (link, SessionService::get_user_id(&session).await) {
+        (Some(link), Some(user_id)) if link.user_id != user_id => {
+            return Err(AppError::DataConflict {
+                error: "The identity is already linked to another user".to_string(),
+            });
+        }
+        (Some(link), _) => link.user_id,
+        (None, Some(user_id)) => {
+            state
+                .oidc_service
+                .create_link(user_id, &config.issuer, &claims.sub)
+                .await?;
+
+            return Ok(Redirect::to(&config.login_redirect));
+        }
+        (None, None) => {
+            if !config.auto_provision {
+                return Err(AppError::Forbidden {
+                    error: Some("No user is linked to the identity".to_string()),
+                });
+            }
+
+            let username = state
+                .oidc_service
+                .get_username_from_claims(&claims)?
+                .ok_or(AppError::BadRequest {
+                    error: Some("The identity provider did not send a username".to_string()),
+                })?;
+            let username = validation::verify_username(&username)?;
+
+            // An existing user has to link the identity from their account
+            if state
+                .user_service
+                .get_user_by_username_optional(&username)
+                .await?
+                .is_some()
+            {
+                return Err(AppError::DataConflict {
+                    error: "User already exists".to_string(),
+                });
+            }
+
+            // The password is never handed out, the user logs in through the identity provider
+            let password: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(64)
+                .map(char::from)
+                .collect();
+
+            let user_id = state
+                .user_service
+                .create_user(
+                    username,
+                    auth::hash_password(&password)?,
+                    UserService::default_storage_limit(),
+                )
+                .await?;
+
+            state
+                .oidc_service
+                .create_link(user_id, &config.issuer, &claims.sub)
+                .await?;
+
+            user_id
+        }
+    };
+
+    if let Some(role) = role {
+        state.user_service.update_role(user_id, role).await?;
+    }
+
+    SessionService::log_in(&session, user_id).await?;
+
+    Ok(Redirect::to(&config.login_redirect))
+}
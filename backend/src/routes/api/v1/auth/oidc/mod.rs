@@ -0,0 +1,6 @@
+pub use read::*;
+pub use delete::*;
+
+pub mod login;
+mod read;
+mod delete;
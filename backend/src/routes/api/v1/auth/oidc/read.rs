@@ -0,0 +1,23 @@
+use crate::model::oidc::OidcLinkModelDTO;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+use axum::extract::State;
+use axum::Json;
+use crate::session::AuthSession;
+
+pub async fn get_oidc_links(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<Vec<OidcLinkModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let links = state
+        .oidc_service
+        .get_links(user_id)
+        .await?
+        .into_iter()
+        .map(OidcLinkModelDTO::from)
+        .collect();
+
+    Ok(Json(links))
+}
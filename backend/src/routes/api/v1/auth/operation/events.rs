@@ -0,0 +1,62 @@
+use std::convert::Infallible;
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use crate::session::AuthSession;
+
+use crate::model::internal::operation_status::OperationStatus;
+use crate::model::operation::{OperationModel, OperationModelDTO};
+use crate::response::error_handling::AppError;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+
+/// Pending operations report `progress` events, finished ones a final `done` event
+fn operation_event(operation: OperationModel) -> Event {
+    let name = match operation.operation_status {
+        OperationStatus::Pending => "progress",
+        _ => "done",
+    };
+
+    Event::default()
+        .event(name)
+        .json_data(OperationModelDTO::from(operation))
+        .unwrap_or_else(|e| {
+            tracing::error!("Error serializing operation event: {}", e);
+            Event::default().event("error")
+        })
+}
+
+pub async fn get_operation_events(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    // Subscribe first so no update between the snapshot and the stream is lost
+    let receiver = state.operation_service.subscribe();
+
+    let pending = state
+        .operation_service
+        .get_operations_by_user_id(user_id, 20)
+        .await?
+        .into_iter()
+        .filter(|operation| operation.operation_status == OperationStatus::Pending)
+        .map(|operation| Ok(operation_event(operation)))
+        .collect::<Vec<_>>();
+
+    let updates = stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(operation) if operation.user_id == user_id => {
+                    return Some((Ok(operation_event(operation)), receiver));
+                }
+                // Clients catch up with the next update of the operation
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(stream::iter(pending).chain(updates)).keep_alive(KeepAlive::default()))
+}
@@ -1,6 +1,6 @@
 use axum::extract::State;
 use axum::Json;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::model::operation::OperationModelDTO;
 use crate::response::error_handling::AppError;
@@ -9,7 +9,7 @@ use crate::state::KosmosState;
 
 pub async fn get_all_operations(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<Vec<OperationModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
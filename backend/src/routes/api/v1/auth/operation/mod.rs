@@ -1,3 +1,5 @@
+pub use events::*;
 pub use index::*;
 
+mod events;
 mod index;
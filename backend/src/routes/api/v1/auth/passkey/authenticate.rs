@@ -1,4 +1,3 @@
-use crate::constants::SESSION_USER_ID_KEY;
 use crate::model::user::UserModelDTO;
 use crate::response::error_handling::AppError;
 use crate::services::session_service::SessionService;
@@ -75,13 +74,7 @@ pub async fn authentication_complete(
         .get_user_from_passkey_credential_id(auth_result.cred_id())
         .await?;
 
-    session
-        .insert(SESSION_USER_ID_KEY, user.id)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to insert session by passkey auth: {}", e);
-            AppError::InternalError
-        })?;
+    SessionService::log_in(&session, user.id).await?;
 
     Ok(Json(user.into()))
 }
@@ -1,5 +1,5 @@
 use axum::extract::{Path, State};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::SessionService;
@@ -7,7 +7,7 @@ use crate::state::KosmosState;
 
 pub async fn delete_passkey(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(passkey_id): Path<i32>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
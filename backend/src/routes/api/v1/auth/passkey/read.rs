@@ -4,11 +4,11 @@ use crate::services::session_service::SessionService;
 use crate::state::KosmosState;
 use axum::extract::State;
 use axum::Json;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 pub async fn get_passkeys(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<Vec<PasskeyModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
     let passkeys = state
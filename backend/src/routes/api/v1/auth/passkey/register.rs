@@ -1,7 +1,7 @@
 use axum::extract::State;
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use webauthn_rs::prelude::{CreationChallengeResponse, RegisterPublicKeyCredential};
 
 use crate::response::error_handling::AppError;
@@ -16,7 +16,7 @@ pub struct PasskeyRegisterStart {
 
 pub async fn register_start(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<PasskeyRegisterStart>,
 ) -> Result<Json<CreationChallengeResponse>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -56,7 +56,7 @@ pub async fn register_start(
 
 pub async fn register_complete(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<RegisterPublicKeyCredential>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
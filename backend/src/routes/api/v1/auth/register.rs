@@ -3,11 +3,10 @@ use axum::response::IntoResponse;
 use axum::Json;
 use axum_valid::Valid;
 
-use crate::constants::FALLBACK_STORAGE_LIMIT;
 use crate::model::role::Role;
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::AppSuccess;
-use crate::services::user_service::RegisterCredentials;
+use crate::services::user_service::{RegisterCredentials, UserService};
 use crate::state::KosmosState;
 use crate::utils::auth;
 
@@ -44,10 +43,7 @@ pub async fn register(
 
     let password_hash = auth::hash_password(&payload.password)?;
 
-    let default_storage_limit = match std::env::var("DEFAULT_STORAGE_LIMIT") {
-        Ok(env) => env.parse::<i64>().unwrap_or(FALLBACK_STORAGE_LIMIT),
-        Err(_) => FALLBACK_STORAGE_LIMIT,
-    };
+    let default_storage_limit = UserService::default_storage_limit();
 
     let id = state
         .user_service
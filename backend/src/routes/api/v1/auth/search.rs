@@ -1,29 +1,52 @@
 use axum::extract::{Query, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::response::error_handling::AppError;
-use crate::services::search_service::ExplorerSearchDTO;
+use crate::routes::api::v1::auth::file::{SortByFiles, SortOrder};
+use crate::services::search_service::{ExplorerSearchDTO, SearchOptions};
 use crate::services::session_service::SessionService;
 use crate::state::AppState;
 
+const DEFAULT_LIMIT: i64 = 25;
+const MAX_LIMIT: i64 = 100;
+
 #[derive(Deserialize)]
 pub struct Pagination {
     q: String,
+    sort_by: Option<SortByFiles>,
+    sort_order: Option<SortOrder>,
+    page: Option<i64>,
+    limit: Option<i64>,
 }
 
 pub async fn search(
     State(state): State<AppState>,
-    session: Session,
+    session: AuthSession,
     Query(pagination): Query<Pagination>,
 ) -> Result<Json<ExplorerSearchDTO>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
-    let data = state.search_service.search_explorer(user_id, pagination.q).await?;
+    let options = SearchOptions {
+        sort_by: pagination.sort_by,
+        sort_order: pagination.sort_order,
+        limit: pagination.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        page: pagination.page.unwrap_or(0).max(0),
+    };
+
+    let data = state
+        .search_service
+        .search_explorer(user_id, pagination.q, options)
+        .await?;
     let data_dto = ExplorerSearchDTO {
-        files: data.files.into_iter().map(|f| f.into()).collect(),
-        folders: data.folders.into_iter().map(|f| f.into()).collect(),
+        files: state.tag_service.tag_files(data.files).await?,
+        folders: state.tag_service.tag_folders(data.folders).await?,
+        snippets: data
+            .snippets
+            .into_iter()
+            .map(|(file_id, snippet)| (file_id.to_string(), snippet))
+            .collect(),
     };
 
     Ok(Json(data_dto))
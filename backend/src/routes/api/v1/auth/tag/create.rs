@@ -0,0 +1,54 @@
+use axum::extract::State;
+use axum::Json;
+use serde::Deserialize;
+use crate::session::AuthSession;
+
+use crate::model::tag::TagModelDTO;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+use crate::utils::validation;
+
+#[derive(Deserialize)]
+pub struct TagPayload {
+    pub name: String,
+    pub color: Option<String>,
+}
+
+impl TagPayload {
+    /// Commas separate tags in search filters, so they can't be part of a name
+    pub fn get_name(&self) -> Result<&str, AppError> {
+        let name = self.name.trim();
+
+        if name.is_empty() || name.len() > 64 || name.contains(',') {
+            return Err(AppError::BadRequest {
+                error: Some("Tag name must be 1 to 64 characters without commas".to_string()),
+            });
+        }
+
+        Ok(name)
+    }
+
+    pub fn get_color(&self) -> Result<Option<&str>, AppError> {
+        if let Some(color) = &self.color {
+            validation::validate_color(color)?;
+        }
+
+        Ok(self.color.as_deref())
+    }
+}
+
+pub async fn create_tag(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<TagModelDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let tag = state
+        .tag_service
+        .create_tag(user_id, payload.get_name()?, payload.get_color()?)
+        .await?;
+
+    Ok(Json(tag.into()))
+}
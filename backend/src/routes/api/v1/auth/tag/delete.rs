@@ -0,0 +1,20 @@
+use axum::extract::{Path, State};
+use crate::session::AuthSession;
+
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+
+pub async fn delete_tag(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(tag_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let tag = state.tag_service.get_tag(user_id, tag_id).await?;
+
+    state.tag_service.delete_tag(user_id, tag.id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -0,0 +1,4 @@
+pub mod read;
+pub mod create;
+pub mod update;
+pub mod delete;
@@ -0,0 +1,56 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Serialize;
+use ts_rs::TS;
+use crate::session::AuthSession;
+
+use crate::model::file::FileModelDTO;
+use crate::model::folder::FolderModelDTO;
+use crate::model::tag::TagModelDTO;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct TaggedItemsResponse {
+    tag: TagModelDTO,
+    folders: Vec<FolderModelDTO>,
+    files: Vec<FileModelDTO>,
+}
+
+pub async fn get_tags(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<Vec<TagModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let tags = state
+        .tag_service
+        .get_tags(user_id)
+        .await?
+        .into_iter()
+        .map(TagModelDTO::from)
+        .collect();
+
+    Ok(Json(tags))
+}
+
+pub async fn get_tagged_items(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(tag_id): Path<i64>,
+) -> Result<Json<TaggedItemsResponse>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let tag = state.tag_service.get_tag(user_id, tag_id).await?;
+
+    let folders = state.tag_service.get_folders_by_tag(tag.id).await?;
+    let files = state.tag_service.get_files_by_tag(tag.id).await?;
+
+    Ok(Json(TaggedItemsResponse {
+        tag: tag.into(),
+        folders: state.tag_service.tag_folders(folders).await?,
+        files: state.tag_service.tag_files(files).await?,
+    }))
+}
@@ -0,0 +1,98 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::Deserialize;
+use crate::session::AuthSession;
+
+use crate::model::tag::TagModelDTO;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::routes::api::v1::auth::tag::create::TagPayload;
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+
+pub async fn update_tag(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(tag_id): Path<i64>,
+    Json(payload): Json<TagPayload>,
+) -> Result<Json<TagModelDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let tag = state
+        .tag_service
+        .update_tag(user_id, tag_id, payload.get_name()?, payload.get_color()?)
+        .await?;
+
+    Ok(Json(tag.into()))
+}
+
+#[derive(Deserialize)]
+pub struct MultiTagPayload {
+    tags: Vec<String>,
+    files: Vec<String>,
+    folders: Vec<String>,
+}
+
+impl MultiTagPayload {
+    fn parse_ids(ids: &[String], kind: &str) -> Result<Vec<i64>, AppError> {
+        ids.iter()
+            .map(|id| {
+                id.parse::<i64>().map_err(|_| AppError::BadRequest {
+                    error: Some(format!("Invalid {} id", kind)),
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_tag_ids(&self) -> Result<Vec<i64>, AppError> {
+        Self::parse_ids(&self.tags, "tag")
+    }
+
+    pub fn get_file_ids(&self) -> Result<Vec<i64>, AppError> {
+        Self::parse_ids(&self.files, "file")
+    }
+
+    pub fn get_folder_ids(&self) -> Result<Vec<i64>, AppError> {
+        Self::parse_ids(&self.folders, "folder")
+    }
+}
+
+pub async fn multi_tag(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<MultiTagPayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .tag_service
+        .tag_items(
+            user_id,
+            &payload.get_tag_ids()?,
+            &payload.get_file_ids()?,
+            &payload.get_folder_ids()?,
+        )
+        .await?;
+
+    Ok(AppSuccess::UPDATED)
+}
+
+pub async fn multi_untag(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<MultiTagPayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .tag_service
+        .untag_items(
+            user_id,
+            &payload.get_tag_ids()?,
+            &payload.get_file_ids()?,
+            &payload.get_folder_ids()?,
+        )
+        .await?;
+
+    Ok(AppSuccess::UPDATED)
+}
@@ -0,0 +1,83 @@
+use crate::model::api_token::{ApiTokenModelDTO, CreatedApiTokenDTO};
+use crate::model::internal::token_scope::TokenScope;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::session::AuthSession;
+use crate::state::KosmosState;
+use axum::extract::{Path, State};
+use axum::Json;
+use axum_valid::Valid;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct CreateApiTokenPayload {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: String,
+    /// 0 = full access, 1 = read-only, 2 = upload-only
+    #[validate(range(min = 0, max = 2, message = "Unknown scope"))]
+    pub scope: Option<i16>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn get_api_tokens(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<Vec<ApiTokenModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let api_tokens = state
+        .api_token_service
+        .get_api_tokens(user_id)
+        .await?
+        .into_iter()
+        .map(ApiTokenModelDTO::from)
+        .collect();
+
+    Ok(Json(api_tokens))
+}
+
+pub async fn create_api_token(
+    State(state): KosmosState,
+    session: AuthSession,
+    Valid(Json(payload)): Valid<Json<CreateApiTokenPayload>>,
+) -> Result<Json<CreatedApiTokenDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    if payload.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AppError::BadRequest {
+            error: Some("Expiry has to be in the future".to_string()),
+        });
+    }
+
+    let (api_token, token) = state
+        .api_token_service
+        .create_api_token(
+            user_id,
+            payload.name,
+            TokenScope::new(payload.scope.unwrap_or(TokenScope::Full as i16)),
+            payload.expires_at,
+        )
+        .await?;
+
+    Ok(Json(CreatedApiTokenDTO {
+        api_token: ApiTokenModelDTO::from(api_token),
+        token,
+    }))
+}
+
+pub async fn delete_api_token(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(api_token_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .api_token_service
+        .delete_api_token(user_id, api_token_id)
+        .await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -0,0 +1,65 @@
+use crate::model::app_password::{AppPasswordModelDTO, CreatedAppPasswordDTO};
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::state::KosmosState;
+use axum::extract::{Path, State};
+use axum::Json;
+use axum_valid::Valid;
+use serde::Deserialize;
+use crate::session::AuthSession;
+use validator::Validate;
+
+#[derive(Deserialize, Validate)]
+pub struct CreateAppPasswordPayload {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: String,
+}
+
+pub async fn get_app_passwords(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<Vec<AppPasswordModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let app_passwords = state
+        .app_password_service
+        .get_app_passwords(user_id)
+        .await?
+        .into_iter()
+        .map(AppPasswordModelDTO::from)
+        .collect();
+
+    Ok(Json(app_passwords))
+}
+
+pub async fn create_app_password(
+    State(state): KosmosState,
+    session: AuthSession,
+    Valid(Json(payload)): Valid<Json<CreateAppPasswordPayload>>,
+) -> Result<Json<CreatedAppPasswordDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let (app_password, password) = state
+        .app_password_service
+        .create_app_password(user_id, payload.name)
+        .await?;
+
+    Ok(Json(CreatedAppPasswordDTO {
+        app_password: AppPasswordModelDTO::from(app_password),
+        password,
+    }))
+}
+
+pub async fn delete_app_password(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(app_password_id): Path<i64>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .app_password_service
+        .delete_app_password(user_id, app_password_id)
+        .await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -5,7 +5,7 @@ use crate::state::KosmosState;
 use crate::utils::auth;
 use axum::extract::State;
 use axum::Json;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(serde::Deserialize)]
 pub struct DeleteSelfUserRequest {
@@ -14,7 +14,7 @@ pub struct DeleteSelfUserRequest {
 
 pub async fn delete_self(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<DeleteSelfUserRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
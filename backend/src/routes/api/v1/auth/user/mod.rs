@@ -1,3 +1,7 @@
+pub mod api_token;
+pub mod app_password;
 pub mod delete;
+pub mod session;
+pub mod totp;
 pub mod update;
 pub mod usage;
\ No newline at end of file
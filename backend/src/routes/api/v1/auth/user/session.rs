@@ -0,0 +1,40 @@
+use crate::model::active_session::ActiveSessionModelDTO;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::SessionService;
+use crate::session::AuthSession;
+use crate::state::KosmosState;
+use axum::extract::{Path, State};
+use axum::Json;
+use sqlx::types::Uuid;
+
+pub async fn get_active_sessions(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<Vec<ActiveSessionModelDTO>>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let sessions = state
+        .active_session_service
+        .get_active_sessions(user_id, session.id().map(|id| id.to_string()))
+        .await?
+        .into_iter()
+        .map(ActiveSessionModelDTO::from)
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+pub async fn delete_active_session(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(session_id): Path<Uuid>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .active_session_service
+        .delete_active_session(user_id, session_id)
+        .await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -0,0 +1,104 @@
+use crate::model::totp::{TotpEnrollmentDTO, TotpRecoveryCodesDTO, TotpStatusDTO};
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::services::session_service::{SessionService, UserId};
+use crate::session::AuthSession;
+use crate::state::{AppState, KosmosState};
+use crate::utils::totp;
+use axum::extract::State;
+use axum::Json;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct TotpCodePayload {
+    pub code: String,
+}
+
+/// Changes to an enabled second factor need a current code or a recovery code
+async fn verify_code(state: &AppState, user_id: UserId, code: &str) -> Result<(), AppError> {
+    if !state.totp_service.verify(user_id, code).await? {
+        return Err(AppError::Forbidden {
+            error: Some("Invalid code".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+pub async fn get_totp_status(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<TotpStatusDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let enabled = state.totp_service.is_enabled(user_id).await?;
+    let recovery_codes_remaining = if enabled {
+        state
+            .totp_service
+            .get_remaining_recovery_codes(user_id)
+            .await?
+    } else {
+        0
+    };
+
+    Ok(Json(TotpStatusDTO {
+        enabled,
+        recovery_codes_remaining,
+    }))
+}
+
+pub async fn start_totp_enrollment(
+    State(state): KosmosState,
+    session: AuthSession,
+) -> Result<Json<TotpEnrollmentDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    let secret = state.totp_service.start_enrollment(user_id).await?;
+
+    Ok(Json(TotpEnrollmentDTO {
+        provisioning_uri: totp::provisioning_uri(&secret, &user.username),
+        secret,
+    }))
+}
+
+pub async fn enable_totp(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<TotpCodePayload>,
+) -> Result<Json<TotpRecoveryCodesDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    let recovery_codes = state.totp_service.enable(user_id, &payload.code).await?;
+
+    Ok(Json(TotpRecoveryCodesDTO { recovery_codes }))
+}
+
+pub async fn regenerate_recovery_codes(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<TotpCodePayload>,
+) -> Result<Json<TotpRecoveryCodesDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    verify_code(&state, user_id, &payload.code).await?;
+
+    let recovery_codes = state
+        .totp_service
+        .regenerate_recovery_codes(user_id)
+        .await?;
+
+    Ok(Json(TotpRecoveryCodesDTO { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    State(state): KosmosState,
+    session: AuthSession,
+    Json(payload): Json<TotpCodePayload>,
+) -> ResponseResult {
+    let user_id = SessionService::check_logged_in(&session).await?;
+    verify_code(&state, user_id, &payload.code).await?;
+
+    state.totp_service.disable(user_id).await?;
+
+    Ok(AppSuccess::DELETED)
+}
@@ -9,7 +9,7 @@ use axum::extract::State;
 use axum::Json;
 use axum_valid::Valid;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use validator::Validate;
 
 #[derive(Deserialize, Validate)]
@@ -21,7 +21,7 @@ pub struct UpdateUserPayload {
 
 pub async fn update_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Valid(Json(payload)): Valid<Json<UpdateUserPayload>>,
 ) -> Result<Json<UserModelDTO>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -69,7 +69,7 @@ pub struct PasswordUpdatePayload {
 
 pub async fn update_user_password(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Valid(Json(payload)): Valid<Json<PasswordUpdatePayload>>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -104,3 +104,57 @@ pub async fn update_user_password(
 
     Ok(AppSuccess::UPDATED)
 }
+
+/// Versions are kept until either limit is reached, a missing limit keeps versions indefinitely
+#[derive(Deserialize, Validate)]
+pub struct VersionRetentionPayload {
+    #[validate(range(min = 0, message = "Version count cannot be negative"))]
+    pub version_retention_count: Option<i32>,
+    #[validate(range(min = 0, message = "Version days cannot be negative"))]
+    pub version_retention_days: Option<i32>,
+}
+
+pub async fn update_version_retention(
+    State(state): KosmosState,
+    session: AuthSession,
+    Valid(Json(payload)): Valid<Json<VersionRetentionPayload>>,
+) -> Result<Json<UserModelDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .version_service
+        .update_retention(
+            user_id,
+            payload.version_retention_count,
+            payload.version_retention_days,
+        )
+        .await?;
+
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    Ok(Json(user.into()))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct BinRetentionPayload {
+    /// Not set uses the server default, 0 keeps files until the bin is cleared
+    #[validate(range(min = 0, message = "Bin days cannot be negative"))]
+    pub bin_retention_days: Option<i32>,
+}
+
+pub async fn update_bin_retention(
+    State(state): KosmosState,
+    session: AuthSession,
+    Valid(Json(payload)): Valid<Json<BinRetentionPayload>>,
+) -> Result<Json<UserModelDTO>, AppError> {
+    let user_id = SessionService::check_logged_in(&session).await?;
+
+    state
+        .bin_service
+        .update_retention(user_id, payload.bin_retention_days)
+        .await?;
+
+    let user = state.user_service.get_auth_user(user_id).await?;
+
+    Ok(Json(user.into()))
+}
@@ -1,7 +1,7 @@
 use axum::extract::State;
 use axum::Json;
 use serde::Serialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use ts_rs::TS;
 use crate::model::file::FileModelDTO;
 use crate::model::usage::{FileTypeSumDataDTO, UsageSumDataDTO};
@@ -14,6 +14,7 @@ use crate::state::{AppState, KosmosState};
 pub struct DiskUsageReport {
     active_storage: UsageSumDataDTO,
     bin_storage: UsageSumDataDTO,
+    version_storage: UsageSumDataDTO,
     by_file_type: Vec<FileTypeSumDataDTO>,
     large_files: Vec<FileModelDTO>,
 }
@@ -32,6 +33,11 @@ pub async fn get_usage_report_by_user_id(
         .get_user_storage_usage(user_id, Some(true))
         .await?
         .into();
+    let version_storage = state
+        .usage_service
+        .get_version_storage_usage(user_id)
+        .await?
+        .into();
 
     let by_file_type = state
         .usage_service
@@ -52,6 +58,7 @@ pub async fn get_usage_report_by_user_id(
     Ok(DiskUsageReport {
         active_storage,
         bin_storage,
+        version_storage,
         by_file_type,
         large_files,
     })
@@ -59,7 +66,7 @@ pub async fn get_usage_report_by_user_id(
 
 pub async fn get_usage_report(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<DiskUsageReport>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
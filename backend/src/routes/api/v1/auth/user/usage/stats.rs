@@ -1,6 +1,6 @@
 use serde::Serialize;
 use axum::extract::State;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use axum::Json;
 use ts_rs::TS;
 use crate::response::error_handling::AppError;
@@ -15,6 +15,8 @@ pub struct DiskUsageStats {
     #[ts(type = "number")]
     pub bin: i64,
     #[ts(type = "number")]
+    pub versions: i64,
+    #[ts(type = "number")]
     pub total: i64,
     #[ts(type = "number")]
     pub limit: i64,
@@ -32,12 +34,17 @@ pub async fn get_usage_stats_by_user_id(
         .usage_service
         .get_user_storage_usage(user_id, Some(true))
         .await?.get_sum();
-    let total_usage = active_usage.clone() + bin_storage.clone();
+    let version_storage = state
+        .usage_service
+        .get_version_storage_usage(user_id)
+        .await?.get_sum();
+    let total_usage = active_usage.clone() + bin_storage.clone() + version_storage;
     let storage_limit = state.user_service.get_user_storage_limit(user_id).await?;
 
     Ok(DiskUsageStats {
         active: active_usage,
         bin: bin_storage,
+        versions: version_storage,
         total: total_usage,
         limit: storage_limit,
     })
@@ -45,7 +52,7 @@ pub async fn get_usage_stats_by_user_id(
 
 pub async fn get_usage_stats(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<DiskUsageStats>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
 
@@ -1,3 +1,4 @@
+use crate::model::internal::share_mode::ShareMode;
 use crate::model::internal::share_type::ShareType;
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
@@ -8,7 +9,7 @@ use axum::extract::State;
 use axum::Json;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Deserialize)]
 pub struct ShareFilePublicRequest {
@@ -30,7 +31,7 @@ impl ShareFilePublicRequest {
 
 pub async fn share_file_public(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(mut payload): Json<ShareFilePublicRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -61,6 +62,11 @@ pub struct ShareFolderPublicRequest {
     pub(crate) password: Option<String>,
     pub(crate) limit: Option<i32>,
     pub(crate) expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) mode: ShareMode,
+    /// Total bytes and number of files visitors can upload, only used by upload shares
+    pub(crate) upload_size_limit: Option<i64>,
+    pub(crate) upload_count_limit: Option<i32>,
 }
 
 impl ShareFolderPublicRequest {
@@ -75,7 +81,7 @@ impl ShareFolderPublicRequest {
 
 pub async fn share_folder_public(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(mut payload): Json<ShareFolderPublicRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -104,6 +110,19 @@ pub async fn share_folder_public(
         });
     }
 
+    if payload.upload_size_limit.is_some_and(|limit| limit < 1)
+        || payload.upload_count_limit.is_some_and(|limit| limit < 1)
+    {
+        return Err(AppError::BadRequest {
+            error: Some("Upload limits must be positive".to_string()),
+        });
+    }
+
+    if !payload.mode.accepts_uploads() {
+        payload.upload_size_limit = None;
+        payload.upload_count_limit = None;
+    }
+
     if let Some(password) = payload.password {
         let hashed_password = auth::hash_password(password.as_str())?;
         payload.password = Some(hashed_password);
@@ -138,7 +157,7 @@ impl ShareFilePrivateRequest {
 
 pub async fn share_file_private(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<ShareFilePrivateRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -200,7 +219,7 @@ impl ShareFolderPrivateRequest {
 
 pub async fn share_folder_private(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<ShareFolderPrivateRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -277,7 +296,7 @@ impl ShareAlbumPublicRequest {
 
 pub async fn share_album_public(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(mut payload): Json<ShareAlbumPublicRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -322,7 +341,7 @@ impl ShareAlbumPrivateRequest {
 
 pub async fn share_album_private(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<ShareAlbumPrivateRequest>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
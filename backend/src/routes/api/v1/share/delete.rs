@@ -1,12 +1,12 @@
 use axum::extract::{Path, State};
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::SessionService;
 use crate::state::KosmosState;
 
 pub async fn delete_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_id): Path<i64>,
 ) -> ResponseResult {
     let user_id = SessionService::check_logged_in(&session).await?;
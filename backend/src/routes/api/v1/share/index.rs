@@ -1,6 +1,7 @@
 use crate::model::album::{AlbumModel, SharedAlbumModelDTO};
 use crate::model::file::{FileModel, ShareFileModelDTO};
 use crate::model::folder::{FolderModel, ShareFolderModelDTO, SimpleDirectoryDTO};
+use crate::model::internal::share_mode::ShareMode;
 use crate::model::share::{ExtendedShareModel, ExtendedShareModelDTO};
 use crate::response::error_handling::AppError;
 use crate::response::success_handling::{AppSuccess, ResponseResult};
@@ -10,13 +11,13 @@ use crate::utils::auth;
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Serialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use ts_rs::TS;
 use crate::routes::api::v1::auth::file::zip::get_zip_information_for_file;
 
 pub async fn get_file_shares_for_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(file_id): Path<i64>,
 ) -> Result<Json<Vec<ExtendedShareModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -34,7 +35,7 @@ pub async fn get_file_shares_for_user(
 
 pub async fn get_folder_shares_for_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(folder_id): Path<i64>,
 ) -> Result<Json<Vec<ExtendedShareModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -52,7 +53,7 @@ pub async fn get_folder_shares_for_user(
 
 pub async fn get_album_shares_for_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(album_id): Path<i64>,
 ) -> Result<Json<Vec<ExtendedShareModelDTO>>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
@@ -76,7 +77,7 @@ pub struct UnlockShareRequest {
 
 pub async fn unlock_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Json(payload): Json<UnlockShareRequest>,
 ) -> ResponseResult {
     let share = state.share_service.get_share(&payload.share_uuid).await?;
@@ -106,7 +107,7 @@ pub async fn unlock_share(
 
 pub async fn access_file_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_uuid): Path<String>,
 ) -> Result<Json<ShareFileModelDTO>, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, false).await?;
@@ -119,7 +120,7 @@ pub async fn access_file_share(
 
 pub async fn access_album_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_uuid): Path<String>,
 ) -> Result<Json<SharedAlbumData>, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, false).await?;
@@ -141,7 +142,7 @@ pub struct FolderShareData {
 
 pub async fn access_folder_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_uuid): Path<String>,
 ) -> Result<Json<FolderShareData>, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, false).await?;
@@ -174,7 +175,7 @@ pub enum AccessShareItemType {
 
 pub async fn access_folder_share_item(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path((share_uuid, access_type, access_id)): Path<(String, AccessShareItemType, i64)>,
 ) -> Result<Json<serde_json::Value>, AppError> {
     let share = is_allowed_to_access_share(&state, &session, share_uuid, true).await?;
@@ -254,13 +255,36 @@ pub async fn get_share_access_for_folder_items(
 
 pub async fn is_allowed_to_access_share(
     state: &AppState,
-    session: &Session,
+    session: &AuthSession,
     share_uuid: String,
     count_as_use: bool,
 ) -> Result<ExtendedShareModel, AppError> {
-    let logged_in_user = state.user_service.check_user_optional(&session).await?;
     let share = state.share_service.get_share(&share_uuid).await?;
 
+    // File drops only accept uploads, their content stays hidden
+    if ShareMode::from(share.share_mode) == ShareMode::DropOnly {
+        Err(AppError::NotAllowed {
+            error: "Share only accepts uploads".to_string(),
+        })?;
+    }
+
+    check_share_restrictions(state, session, &share).await?;
+
+    if count_as_use {
+        reduce_access_limit(state, &share).await?;
+    }
+
+    Ok(share)
+}
+
+/// Checks expiry, access limit, private target and password of the share
+pub async fn check_share_restrictions(
+    state: &AppState,
+    session: &AuthSession,
+    share: &ExtendedShareModel,
+) -> Result<(), AppError> {
+    let logged_in_user = state.user_service.check_user_optional(&session).await?;
+
     //Check expired
     if let Some(expiry) = share.expires_at {
         if expiry < chrono::Utc::now() {
@@ -300,11 +324,7 @@ pub async fn is_allowed_to_access_share(
         })?;
     }
 
-    if count_as_use {
-        reduce_access_limit(state, &share).await?;
-    }
-
-    Ok(share)
+    Ok(())
 }
 
 pub struct SharedFileData {
@@ -1,9 +1,11 @@
 pub use index::*;
 pub use delete::*;
 pub use update::*;
+pub use upload::*;
 
 mod index;
 mod delete;
 mod update;
+mod upload;
 pub mod create;
 pub mod shared_items;
@@ -1,7 +1,7 @@
 use axum::extract::State;
 use axum::Json;
 use serde::Serialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 use ts_rs::TS;
 use crate::model::album::AlbumModelWithShareInfoDTO;
 use crate::model::file::FileModelWithShareInfoDTO;
@@ -53,7 +53,7 @@ impl SharedItems {
 
 pub async fn get_shared_items(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<SharedItems>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
     let shared = SharedItems::get_shared_files_and_folders(&state, &user_id, false).await?;
@@ -62,7 +62,7 @@ pub async fn get_shared_items(
 
 pub async fn get_targeted_shared_items_for_user(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
 ) -> Result<Json<SharedItems>, AppError> {
     let user_id = SessionService::check_logged_in(&session).await?;
     let shared = SharedItems::get_shared_files_and_folders(&state, &user_id, true).await?;
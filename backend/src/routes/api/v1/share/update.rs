@@ -1,7 +1,7 @@
 use axum::extract::{Path, State};
 use axum::Json;
 use serde::Deserialize;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 use crate::response::success_handling::{AppSuccess, ResponseResult};
 use crate::services::session_service::SessionService;
@@ -15,7 +15,7 @@ pub struct UpdateShareRequest {
 
 pub async fn update_share(
     State(state): KosmosState,
-    session: Session,
+    session: AuthSession,
     Path(share_id): Path<i64>,
     Json(payload): Json<UpdateShareRequest>,
 ) -> ResponseResult {
@@ -0,0 +1,254 @@
+use crate::model::internal::file_type::FileType;
+use crate::model::internal::share_mode::ShareMode;
+use crate::model::share::ExtendedShareModel;
+use crate::response::error_handling::AppError;
+use crate::response::success_handling::{AppSuccess, ResponseResult};
+use crate::routes::api::v1::auth::file::upload::{
+    check_storage, start_content_indexing, start_image_processing, stream,
+};
+use crate::routes::api::v1::auth::file::FILE_SIZE_LIMIT;
+use crate::routes::api::v1::share::{
+    check_share_restrictions, get_share_folder, reduce_access_limit,
+};
+use crate::services::file_service::FileService;
+use crate::services::search_service::SearchService;
+use crate::services::session_service::UserId;
+use crate::session::AuthSession;
+use crate::state::{AppState, KosmosState};
+use axum::extract::{Multipart, Path, State};
+use axum::Json;
+use serde::Serialize;
+use ts_rs::TS;
+
+#[derive(Serialize, TS)]
+#[ts(export)]
+pub struct ShareUploadInformation {
+    folder_name: String,
+    /// Visitors cannot see the content of the folder
+    write_only: bool,
+    #[ts(type = "number | null")]
+    size_remaining: Option<i64>,
+    count_remaining: Option<i32>,
+}
+
+pub async fn get_share_upload_information(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(share_uuid): Path<String>,
+) -> Result<Json<ShareUploadInformation>, AppError> {
+    let share = is_allowed_to_upload_to_share(&state, &session, share_uuid, false).await?;
+
+    let folder = get_share_folder(&state, share.folder_id).await?;
+    let _ = state.share_service.handle_share_access(share.id).await;
+
+    Ok(Json(ShareUploadInformation {
+        folder_name: folder.folder.folder_name,
+        write_only: ShareMode::from(share.share_mode) == ShareMode::DropOnly,
+        size_remaining: share
+            .upload_size_limit
+            .map(|limit| (limit - share.uploaded_size).max(0)),
+        count_remaining: share
+            .upload_count_limit
+            .map(|limit| (limit - share.uploaded_count).max(0)),
+    }))
+}
+
+/// Uploads of visitors are stored in the shared folder and charged to the owner of the share
+pub async fn upload_to_share(
+    State(state): KosmosState,
+    session: AuthSession,
+    Path(share_uuid): Path<String>,
+    mut multipart: Multipart,
+) -> ResponseResult {
+    let share = is_allowed_to_upload_to_share(&state, &session, share_uuid, true).await?;
+    let folder = get_share_folder(&state, share.folder_id).await?.folder;
+    let owner = state.user_service.get_auth_user(share.user_id).await?;
+
+    let mut storage_remaining = check_storage::check_user_storage_limit(
+        &state.usage_service,
+        owner.id,
+        owner.storage_limit,
+    )
+    .await?;
+
+    // Remaining limits of the share, the counters in the database are checked again per file
+    // in case of concurrent uploads
+    let mut share_size_remaining = share
+        .upload_size_limit
+        .map(|limit| limit - share.uploaded_size);
+    let mut share_count_remaining = share
+        .upload_count_limit
+        .map(|limit| limit - share.uploaded_count);
+
+    let mut pending_image_formats: Vec<i64> = Vec::new();
+    let mut pending_content_index: Vec<i64> = Vec::new();
+
+    while let Ok(Some(field)) = multipart.next_field().await {
+        // Visitors cannot create folders, only the name of the file is kept
+        let file_name = match field
+            .file_name()
+            .and_then(|name| name.rsplit(['/', '\\']).next())
+        {
+            Some(name) if name.len() > 255 => {
+                return Err(AppError::BadRequest {
+                    error: Some("File name is too long".to_string()),
+                });
+            }
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ => continue,
+        };
+
+        if share_count_remaining.is_some_and(|remaining| remaining < 1) {
+            return Err(share_limit_reached());
+        }
+
+        let id = state.get_safe_id()?;
+
+        let ct = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+
+        let mut file_type_res = FileService::get_file_type(&ct, &file_name);
+
+        // The upload stops as soon as it is larger than the owner's storage or the share allows
+        let share_is_limiting = share_size_remaining.is_some_and(|size| size < storage_remaining);
+        let budget = share_size_remaining
+            .unwrap_or(i64::MAX)
+            .min(storage_remaining)
+            .max(0);
+
+        let blob = match stream::stream_to_blob_limited(&state.blob_service, field, budget as u64)
+            .await?
+        {
+            Some(blob) => blob,
+            None if share_is_limiting => return Err(share_limit_reached()),
+            None => {
+                return Err(AppError::BadRequest {
+                    error: Some("Storage limit exceeded".to_string()),
+                })
+            }
+        };
+        let len = blob.size;
+
+        if file_type_res.file_type == FileType::Image && len as u64 > FILE_SIZE_LIMIT {
+            file_type_res.file_type = FileType::LargeImage;
+        }
+
+        // Existing files are never replaced by visitors
+        let file_name = match available_file_name(&state, owner.id, folder.id, file_name).await {
+            Ok(file_name) => file_name,
+            Err(e) => {
+                let _ = state.blob_service.release(&blob.hash).await;
+                return Err(e);
+            }
+        };
+
+        if !state.share_service.add_share_upload(share.id, len).await? {
+            let _ = state.blob_service.release(&blob.hash).await;
+            return Err(share_limit_reached());
+        }
+
+        let file_id = match state
+            .file_service
+            .create_file(
+                owner.id,
+                id,
+                file_name,
+                Some(folder.id),
+                &file_type_res,
+                blob,
+            )
+            .await
+        {
+            Ok(file_id) => file_id,
+            // The blob is released by `create_file` already
+            Err(e) => {
+                let _ = state.share_service.remove_share_upload(share.id, len).await;
+                return Err(e);
+            }
+        };
+
+        storage_remaining -= len;
+        share_size_remaining = share_size_remaining.map(|size| size - len);
+        share_count_remaining = share_count_remaining.map(|count| count - 1);
+
+        tracing::info!("File {} uploaded through share {}", file_id, share.id);
+
+        if state
+            .image_service
+            .supports_file_type(file_type_res.file_type)
+        {
+            pending_image_formats.push(file_id);
+        }
+
+        if SearchService::supports_file_type(file_type_res.file_type) {
+            pending_content_index.push(file_id);
+        }
+    }
+
+    start_image_processing(&state, owner.id, pending_image_formats).await?;
+    start_content_indexing(&state, owner.id, pending_content_index).await?;
+
+    Ok(AppSuccess::OK { data: None })
+}
+
+fn share_limit_reached() -> AppError {
+    AppError::BadRequest {
+        error: Some("Upload limit of the share reached".to_string()),
+    }
+}
+
+/// Same checks as for reading a share, but only folder shares which accept uploads are allowed
+pub async fn is_allowed_to_upload_to_share(
+    state: &AppState,
+    session: &AuthSession,
+    share_uuid: String,
+    count_as_use: bool,
+) -> Result<ExtendedShareModel, AppError> {
+    let share = state.share_service.get_share(&share_uuid).await?;
+
+    if share.folder_id.is_none() || !ShareMode::from(share.share_mode).accepts_uploads() {
+        Err(AppError::NotAllowed {
+            error: "Share does not accept uploads".to_string(),
+        })?;
+    }
+
+    check_share_restrictions(state, session, &share).await?;
+
+    if count_as_use {
+        reduce_access_limit(state, &share).await?;
+    }
+
+    Ok(share)
+}
+
+/// Name which is not used in the folder yet, a counter is appended to taken names
+async fn available_file_name(
+    state: &AppState,
+    user_id: UserId,
+    folder_id: i64,
+    file_name: String,
+) -> Result<String, AppError> {
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            (stem.to_string(), format!(".{}", extension))
+        }
+        _ => (file_name.clone(), String::new()),
+    };
+
+    let mut candidate = file_name;
+    let mut counter = 1;
+
+    while state
+        .file_service
+        .check_file_exists_by_name(&candidate, user_id, Some(folder_id))
+        .await?
+        .is_some()
+    {
+        candidate = format!("{} ({}){}", stem, counter, extension);
+        counter += 1;
+    }
+
+    Ok(candidate)
+}
@@ -0,0 +1,68 @@
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use base64::prelude::*;
+
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+/// WebDAV clients can't log in through the API, so every request carries HTTP Basic credentials.
+/// Both the account password and app passwords of the user are accepted, users with a second
+/// factor have to use an app password. Failed account passwords are throttled like logins.
+pub async fn authenticate(state: &AppState, headers: &HeaderMap) -> Result<Option<UserId>, AppError> {
+    let Some((username, password)) = parse_basic_credentials(headers) else {
+        return Ok(None);
+    };
+
+    if let Some(user_id) = state
+        .app_password_service
+        .get_user_id_by_app_password(&username, &password)
+        .await?
+    {
+        return Ok(Some(user_id));
+    }
+
+    let Some(user) = state
+        .user_service
+        .get_user_by_username_optional(&username)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    if state.totp_service.is_enabled(user.id).await? {
+        return Ok(None);
+    }
+
+    if state
+        .user_service
+        .verify_login_password(&user, &password)
+        .await?
+    {
+        Ok(Some(user.id))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+
+    let decoded = String::from_utf8(BASE64_STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+
+    Some((username.to_string(), password.to_string()))
+}
+
+pub fn unauthorized_response() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(WWW_AUTHENTICATE, "Basic realm=\"Kosmos\", charset=\"UTF-8\"")],
+    )
+        .into_response()
+}
@@ -0,0 +1,366 @@
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::model::dav::is_descendant;
+use crate::model::file::FileModel;
+use crate::response::error_handling::AppError;
+use crate::routes::dav::content::{get_storage_remaining, store_file};
+use crate::routes::dav::lock::DavLocks;
+use crate::routes::dav::path::{resolve, resolve_collection, DavPath, DavResource};
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+pub async fn mkcol(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    if !body.is_empty() {
+        return Ok(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response());
+    }
+
+    let Some(folder_name) = path.name() else {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    };
+
+    if resolve(state, user_id, path).await?.is_some() {
+        return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response());
+    }
+
+    let Some(parent_id) = resolve_collection(state, user_id, &path.parent()).await? else {
+        return Ok(StatusCode::CONFLICT.into_response());
+    };
+
+    DavLocks::load(state, user_id, headers)
+        .await?
+        .check(&path.parent(), false)?;
+
+    state
+        .folder_service
+        .create_folder(user_id, folder_name.to_string(), parent_id)
+        .await?;
+
+    Ok(StatusCode::CREATED.into_response())
+}
+
+/// Files and folders are moved to the bin, so they can still be restored
+async fn remove_resource(
+    state: &AppState,
+    user_id: UserId,
+    resource: DavResource,
+) -> Result<(), AppError> {
+    match resource {
+        DavResource::File(file) => {
+            state
+                .file_service
+                .mark_file_for_deletion(file.id, user_id)
+                .await
+        }
+        DavResource::Folder(folder) => {
+            state
+                .folder_service
+                .mark_folder_for_deletion(folder.id, user_id)
+                .await
+        }
+        DavResource::Root => Err(AppError::Forbidden {
+            error: Some("The root can't be removed".to_string()),
+        }),
+    }
+}
+
+pub async fn delete(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    if path.is_root() {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let Some(resource) = resolve(state, user_id, path).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let locks = DavLocks::load(state, user_id, headers).await?;
+    locks.check(path, true)?;
+    locks.check(&path.parent(), false)?;
+
+    remove_resource(state, user_id, resource).await?;
+    state
+        .dav_service
+        .delete_locks_at(user_id, &path.lock_path())
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+struct FolderCopy {
+    source_id: i64,
+    /// Index of the copy of the parent folder, the first folder is copied into the destination
+    parent: Option<usize>,
+    name: String,
+    color: Option<String>,
+    files: Vec<FileModel>,
+}
+
+/// Collects the folders and files to copy, without members if the depth is 0
+async fn plan_folder_copy(
+    state: &AppState,
+    user_id: UserId,
+    folder_id: i64,
+    folder_name: &str,
+    color: Option<String>,
+    with_members: bool,
+) -> Result<Vec<FolderCopy>, AppError> {
+    let mut folders = vec![FolderCopy {
+        source_id: folder_id,
+        parent: None,
+        name: folder_name.to_string(),
+        color,
+        files: vec![],
+    }];
+
+    if !with_members {
+        return Ok(folders);
+    }
+
+    let mut index = 0;
+
+    while index < folders.len() {
+        let source_id = folders[index].source_id;
+
+        folders[index].files = state
+            .file_service
+            .get_child_files(user_id, Some(source_id))
+            .await?;
+
+        for child in state
+            .folder_service
+            .get_child_folders(user_id, Some(source_id))
+            .await?
+        {
+            folders.push(FolderCopy {
+                source_id: child.id,
+                parent: Some(index),
+                name: child.folder_name,
+                color: child.color,
+                files: vec![],
+            });
+        }
+
+        index += 1;
+    }
+
+    Ok(folders)
+}
+
+async fn copy_folders(
+    state: &AppState,
+    user_id: UserId,
+    folders: Vec<FolderCopy>,
+    parent_id: Option<i64>,
+) -> Result<(), AppError> {
+    let mut copied_ids: Vec<i64> = Vec::with_capacity(folders.len());
+
+    for folder in folders {
+        let parent_id = folder.parent.map_or(parent_id, |index| Some(copied_ids[index]));
+
+        let folder_id = state
+            .folder_service
+            .create_folder(user_id, folder.name, parent_id)
+            .await?;
+
+        if folder.color.is_some() {
+            state
+                .folder_service
+                .update_folder_color(user_id, folder_id, folder.color.as_ref())
+                .await?;
+        }
+
+        for file in folder.files {
+            let blob = state.file_service.copy_content(&file).await?;
+            store_file(
+                state,
+                user_id,
+                Some(folder_id),
+                &file.file_name,
+                None,
+                blob,
+                &file.mime_type,
+            )
+            .await?;
+        }
+
+        copied_ids.push(folder_id);
+    }
+
+    Ok(())
+}
+
+/// MOVE and COPY, which share how the destination is resolved and overwritten
+pub async fn transfer(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    copy: bool,
+) -> Result<Response, AppError> {
+    let Some(destination) = DavPath::from_destination(headers)? else {
+        return Ok(StatusCode::BAD_GATEWAY.into_response());
+    };
+
+    let overwrite = headers
+        .get("Overwrite")
+        .is_none_or(|value| !value.as_bytes().eq_ignore_ascii_case(b"F"));
+
+    let with_members = match headers.get("Depth").and_then(|value| value.to_str().ok()) {
+        None => true,
+        Some(depth) if depth.eq_ignore_ascii_case("infinity") => true,
+        Some("0") if copy => false,
+        Some(_) => {
+            return Err(AppError::BadRequest {
+                error: Some("Invalid Depth header".to_string()),
+            })
+        }
+    };
+
+    if path.is_root() || destination.is_root() || &destination == path {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let Some(source) = resolve(state, user_id, path).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    // A collection can't end up inside of itself
+    if source.is_collection() && is_descendant(&destination.lock_path(), &path.lock_path()) {
+        return Ok(StatusCode::FORBIDDEN.into_response());
+    }
+
+    let (Some(name), Some(parent_id)) = (
+        destination.name(),
+        resolve_collection(state, user_id, &destination.parent()).await?,
+    ) else {
+        return Ok(StatusCode::CONFLICT.into_response());
+    };
+
+    let existing = resolve(state, user_id, &destination).await?;
+
+    if existing.is_some() && !overwrite {
+        return Ok(StatusCode::PRECONDITION_FAILED.into_response());
+    }
+
+    let locks = DavLocks::load(state, user_id, headers).await?;
+    if !copy {
+        locks.check(path, true)?;
+        locks.check(&path.parent(), false)?;
+    }
+    locks.check(&destination, true)?;
+    if existing.is_none() {
+        locks.check(&destination.parent(), false)?;
+    }
+
+    let status = if existing.is_some() {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::CREATED
+    };
+
+    // Copies take up storage, a move only changes where the content is found
+    if copy {
+        let copy_size = match &source {
+            DavResource::File(file) => file.file_size,
+            DavResource::Folder(folder) => {
+                plan_folder_copy(state, user_id, folder.id, name, None, with_members)
+                    .await?
+                    .iter()
+                    .flat_map(|folder| &folder.files)
+                    .map(|file| file.file_size)
+                    .sum()
+            }
+            DavResource::Root => 0,
+        };
+
+        if copy_size > get_storage_remaining(state, user_id).await? {
+            return Ok(StatusCode::INSUFFICIENT_STORAGE.into_response());
+        }
+    }
+
+    match (source, existing) {
+        // The overwritten file keeps its history and gets the content as its newest version
+        (DavResource::File(file), Some(DavResource::File(existing))) => {
+            let blob = state.file_service.copy_content(&file).await?;
+            store_file(
+                state,
+                user_id,
+                parent_id,
+                name,
+                Some(&existing),
+                blob,
+                &file.mime_type,
+            )
+            .await?;
+
+            // The source goes to the bin with its history, like a deleted file
+            if !copy {
+                remove_resource(state, user_id, DavResource::File(file)).await?;
+            }
+        }
+        (source, existing) => {
+            if let Some(existing) = existing {
+                remove_resource(state, user_id, existing).await?;
+                state
+                    .dav_service
+                    .delete_locks_at(user_id, &destination.lock_path())
+                    .await?;
+            }
+
+            match source {
+                DavResource::File(file) if copy => {
+                    let blob = state.file_service.copy_content(&file).await?;
+                    store_file(state, user_id, parent_id, name, None, blob, &file.mime_type)
+                        .await?;
+                }
+                DavResource::File(file) => {
+                    state
+                        .file_service
+                        .relocate_file(user_id, file.id, name, parent_id)
+                        .await?;
+                }
+                DavResource::Folder(folder) if copy => {
+                    let folders = plan_folder_copy(
+                        state,
+                        user_id,
+                        folder.id,
+                        name,
+                        folder.color,
+                        with_members,
+                    )
+                    .await?;
+                    copy_folders(state, user_id, folders, parent_id).await?;
+                }
+                DavResource::Folder(folder) => {
+                    state
+                        .folder_service
+                        .relocate_folder(user_id, folder.id, name, parent_id)
+                        .await?;
+                }
+                DavResource::Root => return Ok(StatusCode::FORBIDDEN.into_response()),
+            }
+        }
+    }
+
+    // Locks stay with the path, so they don't follow a moved resource
+    if !copy {
+        state
+            .dav_service
+            .delete_locks_at(user_id, &path.lock_path())
+            .await?;
+    }
+
+    Ok(status.into_response())
+}
@@ -0,0 +1,173 @@
+use axum::body::Body;
+use axum::http::header::{ALLOW, CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::model::file::FileModel;
+use crate::model::internal::file_type::FileType;
+use crate::response::error_handling::AppError;
+use crate::routes::api::v1::auth::download::{get_raw_content, RawFileAction};
+use crate::routes::api::v1::auth::file::upload::check_storage::check_user_storage_limit;
+use crate::routes::api::v1::auth::file::upload::{start_content_indexing, start_image_processing};
+use crate::routes::api::v1::auth::file::upload::stream::stream_to_blob;
+use crate::routes::api::v1::auth::file::FILE_SIZE_LIMIT;
+use crate::routes::dav::lock::DavLocks;
+use crate::routes::dav::path::{resolve, resolve_collection, DavPath, DavResource};
+use crate::services::blob_service::StoredBlob;
+use crate::services::file_service::FileService;
+use crate::services::search_service::SearchService;
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+/// Storage the user has left, files of the user are not allowed to grow beyond it
+pub async fn get_storage_remaining(state: &AppState, user_id: UserId) -> Result<i64, AppError> {
+    let user = state.user_service.get_auth_user(user_id).await?;
+    check_user_storage_limit(&state.usage_service, user.id, user.storage_limit).await
+}
+
+/// Stores the blob as file with the name in the folder. An existing file keeps its id and
+/// gets the blob as its newest version.
+pub async fn store_file(
+    state: &AppState,
+    user_id: UserId,
+    parent_folder_id: Option<i64>,
+    file_name: &str,
+    existing: Option<&FileModel>,
+    blob: StoredBlob,
+    mime_type: &str,
+) -> Result<(), AppError> {
+    let mut file_type_res = FileService::get_file_type(mime_type, file_name);
+
+    if file_type_res.file_type == FileType::Image && blob.size as u64 > FILE_SIZE_LIMIT {
+        file_type_res.file_type = FileType::LargeImage;
+    }
+
+    let file_id = match existing {
+        Some(file) => {
+            state
+                .file_service
+                .replace_file_content(
+                    file,
+                    blob,
+                    file_type_res.file_type,
+                    file_type_res.normalized_mime_type,
+                )
+                .await?;
+            file.id
+        }
+        None => {
+            state
+                .file_service
+                .create_file(
+                    user_id,
+                    state.get_safe_id()?,
+                    file_name.to_string(),
+                    parent_folder_id,
+                    &file_type_res,
+                    blob,
+                )
+                .await?
+        }
+    };
+
+    if state.image_service.supports_file_type(file_type_res.file_type) {
+        start_image_processing(state, user_id, vec![file_id]).await?;
+    }
+
+    if SearchService::supports_file_type(file_type_res.file_type) {
+        start_content_indexing(state, user_id, vec![file_id]).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn get(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    match resolve(state, user_id, path).await? {
+        Some(DavResource::File(file)) => Ok(get_raw_content(
+            &mut headers.clone(),
+            state,
+            &file,
+            RawFileAction::Serve,
+        )
+        .await?
+        .into_response()),
+        Some(_) => Ok((
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(ALLOW, "OPTIONS, PROPFIND, PROPPATCH, MKCOL, COPY, MOVE, DELETE, LOCK, UNLOCK")],
+        )
+            .into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+pub async fn put(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    let (Some(file_name), Some(parent_folder_id)) = (
+        path.name(),
+        resolve_collection(state, user_id, &path.parent()).await?,
+    ) else {
+        return Ok(StatusCode::CONFLICT.into_response());
+    };
+
+    let existing = match resolve(state, user_id, path).await? {
+        Some(DavResource::File(file)) => Some(file),
+        Some(_) => return Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+        None => None,
+    };
+
+    let locks = DavLocks::load(state, user_id, headers).await?;
+    locks.check(path, false)?;
+    if existing.is_none() {
+        locks.check(&path.parent(), false)?;
+    }
+
+    let storage_remaining = get_storage_remaining(state, user_id).await?;
+
+    let content_length = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok());
+
+    if content_length.is_some_and(|length| length > storage_remaining) {
+        return Ok(StatusCode::INSUFFICIENT_STORAGE.into_response());
+    }
+
+    let blob = stream_to_blob(&state.blob_service, body.into_data_stream()).await?;
+
+    if blob.size > storage_remaining {
+        let _ = state.blob_service.release(&blob.hash).await;
+        return Ok(StatusCode::INSUFFICIENT_STORAGE.into_response());
+    }
+
+    let mime_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream");
+
+    store_file(
+        state,
+        user_id,
+        parent_folder_id,
+        file_name,
+        existing.as_ref(),
+        blob,
+        mime_type,
+    )
+    .await?;
+
+    if existing.is_some() {
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Ok(StatusCode::CREATED.into_response())
+    }
+}
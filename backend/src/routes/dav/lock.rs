@@ -0,0 +1,282 @@
+use axum::body::Bytes;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sqlx::types::Uuid;
+
+use crate::model::dav::{is_descendant, DavLockModel};
+use crate::response::error_handling::AppError;
+use crate::routes::dav::content;
+use crate::routes::dav::path::{resolve, resolve_collection, DavPath};
+use crate::routes::dav::xml::{error_response, escape, parse_lockinfo, xml_response};
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+const DEFAULT_LOCK_TIMEOUT: i32 = 60 * 60;
+const MAX_LOCK_TIMEOUT: i32 = 24 * 60 * 60;
+
+pub const SUPPORTED_LOCK: &str = "<D:lockentry><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>\
+<D:lockentry><D:lockscope><D:shared/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry>";
+
+/// Lock tokens in a header, they are sent as `<opaquelocktoken:...>` in `If` and `Lock-Token`
+fn parse_lock_tokens(value: &str) -> Vec<Uuid> {
+    value
+        .split('<')
+        .filter_map(|part| part.split_once('>'))
+        .filter_map(|(token, _)| token.strip_prefix("opaquelocktoken:"))
+        .filter_map(|token| Uuid::parse_str(token).ok())
+        .collect()
+}
+
+/// The active locks of the user together with the lock tokens the request submitted
+pub struct DavLocks {
+    locks: Vec<DavLockModel>,
+    submitted: Vec<Uuid>,
+}
+
+impl DavLocks {
+    pub async fn load(
+        state: &AppState,
+        user_id: UserId,
+        headers: &HeaderMap,
+    ) -> Result<Self, AppError> {
+        let submitted = headers
+            .get("If")
+            .and_then(|value| value.to_str().ok())
+            .map(parse_lock_tokens)
+            .unwrap_or_default();
+
+        Ok(DavLocks {
+            locks: state.dav_service.get_locks(user_id).await?,
+            submitted,
+        })
+    }
+
+    /// Fails if the resource is locked by a lock the request did not submit the token of.
+    /// Removing or moving a collection also requires the tokens of locks on its members.
+    pub fn check(&self, path: &DavPath, with_members: bool) -> Result<(), AppError> {
+        let lock_path = path.lock_path();
+
+        let locked = self.locks.iter().any(|lock| {
+            (lock.covers(&lock_path) || (with_members && is_descendant(&lock.path, &lock_path)))
+                && !self.submitted.contains(&lock.token)
+        });
+
+        if locked {
+            return Err(AppError::Locked {
+                error: format!("{} is locked", lock_path),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn covering(&self, path: &DavPath) -> Vec<&DavLockModel> {
+        let lock_path = path.lock_path();
+        self.locks
+            .iter()
+            .filter(|lock| lock.covers(&lock_path))
+            .collect()
+    }
+}
+
+pub fn lock_discovery(locks: &[&DavLockModel]) -> String {
+    locks.iter().map(|lock| active_lock(lock)).collect()
+}
+
+fn active_lock(lock: &DavLockModel) -> String {
+    let scope = if lock.exclusive { "exclusive" } else { "shared" };
+    let depth = if lock.depth_infinity { "infinity" } else { "0" };
+    let owner = lock
+        .owner
+        .as_ref()
+        .map(|owner| format!("<D:owner>{}</D:owner>", owner))
+        .unwrap_or_default();
+    let root = DavPath::from_lock_path(&lock.path).href(false);
+
+    format!(
+        "<D:activelock><D:locktype><D:write/></D:locktype><D:lockscope><D:{}/></D:lockscope>\
+<D:depth>{}</D:depth>{}<D:timeout>Second-{}</D:timeout>\
+<D:locktoken><D:href>{}</D:href></D:locktoken><D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock>",
+        scope,
+        depth,
+        owner,
+        lock.timeout,
+        lock.lock_token(),
+        escape(&root)
+    )
+}
+
+fn lock_response(status: StatusCode, lock: &DavLockModel, with_token: bool) -> Response {
+    let mut response = xml_response(
+        status,
+        format!(
+            "<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery>{}</D:lockdiscovery></D:prop>",
+            active_lock(lock)
+        ),
+    );
+
+    if with_token {
+        if let Ok(value) = HeaderValue::from_str(&format!("<{}>", lock.lock_token())) {
+            response.headers_mut().insert("Lock-Token", value);
+        }
+    }
+
+    response
+}
+
+/// The first supported value of the `Timeout` header, e.g. `Second-600` or `Infinite`
+fn parse_timeout(headers: &HeaderMap) -> i32 {
+    headers
+        .get("Timeout")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value.split(',').map(str::trim).find_map(|timeout| {
+                if timeout.eq_ignore_ascii_case("Infinite") {
+                    Some(MAX_LOCK_TIMEOUT)
+                } else {
+                    timeout
+                        .strip_prefix("Second-")
+                        .and_then(|seconds| seconds.parse::<i64>().ok())
+                        .map(|seconds| seconds.clamp(1, MAX_LOCK_TIMEOUT as i64) as i32)
+                }
+            })
+        })
+        .unwrap_or(DEFAULT_LOCK_TIMEOUT)
+}
+
+pub async fn lock(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let locks = DavLocks::load(state, user_id, headers).await?;
+    let timeout = parse_timeout(headers);
+
+    // Without a body the request refreshes a lock the client holds
+    if body.iter().all(u8::is_ascii_whitespace) {
+        let Some(held) = locks
+            .covering(path)
+            .into_iter()
+            .find(|lock| locks.submitted.contains(&lock.token))
+        else {
+            return Ok(error_response(
+                StatusCode::PRECONDITION_FAILED,
+                "lock-token-submitted",
+            ));
+        };
+
+        return match state
+            .dav_service
+            .refresh_lock(user_id, held.token, timeout)
+            .await?
+        {
+            Some(lock) => Ok(lock_response(StatusCode::OK, &lock, false)),
+            None => Ok(error_response(
+                StatusCode::PRECONDITION_FAILED,
+                "lock-token-submitted",
+            )),
+        };
+    }
+
+    let lock_info = parse_lockinfo(&body)?;
+
+    let depth_infinity = match headers.get("Depth").and_then(|value| value.to_str().ok()) {
+        None => true,
+        Some(depth) if depth.eq_ignore_ascii_case("infinity") => true,
+        Some("0") => false,
+        Some(_) => {
+            return Err(AppError::BadRequest {
+                error: Some("Depth of a lock has to be 0 or infinity".to_string()),
+            })
+        }
+    };
+
+    let lock_path = path.lock_path();
+
+    let conflicting = locks.locks.iter().any(|lock| {
+        (lock.covers(&lock_path) || (depth_infinity && is_descendant(&lock.path, &lock_path)))
+            && (lock.exclusive || lock_info.exclusive)
+    });
+
+    if conflicting {
+        return Ok(error_response(StatusCode::LOCKED, "no-conflicting-lock"));
+    }
+
+    // Locking an unmapped path creates an empty file, so the client can write to it
+    let status = match resolve(state, user_id, path).await? {
+        Some(_) => StatusCode::OK,
+        None => {
+            let (Some(file_name), Some(parent_folder_id)) = (
+                path.name(),
+                resolve_collection(state, user_id, &path.parent()).await?,
+            ) else {
+                return Ok(StatusCode::CONFLICT.into_response());
+            };
+
+            locks.check(&path.parent(), false)?;
+
+            let blob = state.blob_service.store_bytes(vec![]).await?;
+            content::store_file(
+                state,
+                user_id,
+                parent_folder_id,
+                file_name,
+                None,
+                blob,
+                "application/octet-stream",
+            )
+            .await?;
+
+            StatusCode::CREATED
+        }
+    };
+
+    let lock = state
+        .dav_service
+        .create_lock(
+            user_id,
+            &lock_path,
+            lock_info.exclusive,
+            depth_infinity,
+            lock_info.owner,
+            timeout,
+        )
+        .await?;
+
+    Ok(lock_response(status, &lock, true))
+}
+
+pub async fn unlock(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+) -> Result<Response, AppError> {
+    let token = headers
+        .get("Lock-Token")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_lock_tokens(value).into_iter().next())
+        .ok_or(AppError::BadRequest {
+            error: Some("Missing Lock-Token header".to_string()),
+        })?;
+
+    let lock_path = path.lock_path();
+
+    let in_scope = state
+        .dav_service
+        .get_locks(user_id)
+        .await?
+        .iter()
+        .any(|lock| lock.token == token && lock.covers(&lock_path));
+
+    if !in_scope || !state.dav_service.delete_lock(user_id, token).await? {
+        return Ok(error_response(
+            StatusCode::CONFLICT,
+            "lock-token-matches-request-uri",
+        ));
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
@@ -0,0 +1,89 @@
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::State;
+use axum::http::header::ALLOW;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+
+use crate::response::error_handling::AppError;
+use crate::routes::dav::path::DavPath;
+use crate::state::{AppState, KosmosState};
+
+pub mod auth;
+pub mod collection;
+pub mod content;
+pub mod lock;
+pub mod path;
+pub mod propfind;
+pub mod xml;
+
+const DAV_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, PROPPATCH, MKCOL, COPY, MOVE, LOCK, UNLOCK";
+
+/// XML request bodies are small, anything larger is rejected
+const XML_BODY_LIMIT: usize = 1024 * 1024;
+
+async fn read_xml_body(body: Body) -> Result<Bytes, AppError> {
+    to_bytes(body, XML_BODY_LIMIT)
+        .await
+        .map_err(|_| AppError::BadRequest {
+            error: Some("Request body too large".to_string()),
+        })
+}
+
+async fn handle_method(
+    state: &AppState,
+    method: &Method,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    let Some(user_id) = auth::authenticate(state, headers).await? else {
+        return Ok(auth::unauthorized_response());
+    };
+
+    match method.as_str() {
+        "GET" | "HEAD" => content::get(state, user_id, path, headers).await,
+        "PUT" => content::put(state, user_id, path, headers, body).await,
+        "PROPFIND" => {
+            propfind::propfind(state, user_id, path, headers, read_xml_body(body).await?).await
+        }
+        "PROPPATCH" => {
+            propfind::proppatch(state, user_id, path, headers, read_xml_body(body).await?).await
+        }
+        "MKCOL" => {
+            collection::mkcol(state, user_id, path, headers, read_xml_body(body).await?).await
+        }
+        "DELETE" => collection::delete(state, user_id, path, headers).await,
+        "MOVE" => collection::transfer(state, user_id, path, headers, false).await,
+        "COPY" => collection::transfer(state, user_id, path, headers, true).await,
+        "LOCK" => lock::lock(state, user_id, path, headers, read_xml_body(body).await?).await,
+        "UNLOCK" => lock::unlock(state, user_id, path, headers).await,
+        _ => Ok((StatusCode::METHOD_NOT_ALLOWED, [(ALLOW, DAV_METHODS)]).into_response()),
+    }
+}
+
+/// Entry point of the WebDAV server, the methods are mapped onto the folder hierarchy of the user
+pub async fn handle_dav(
+    State(state): KosmosState,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    if method == Method::OPTIONS {
+        return (
+            StatusCode::OK,
+            [("DAV", "1, 2"), ("Allow", DAV_METHODS), ("MS-Author-Via", "DAV")],
+        )
+            .into_response();
+    }
+
+    let Some(path) = DavPath::from_uri_path(uri.path()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match handle_method(&state, &method, &path, &headers, body).await {
+        Ok(response) => response,
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,186 @@
+use axum::http::HeaderMap;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::model::file::FileModel;
+use crate::model::folder::FolderModel;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+pub const DAV_PREFIX: &str = "/dav";
+
+/// Characters which are kept as they are in the segments of an href
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Path of a resource below the WebDAV root, with decoded segments
+#[derive(Clone, Debug, PartialEq)]
+pub struct DavPath {
+    segments: Vec<String>,
+}
+
+impl DavPath {
+    /// Parses the path of a request URI, paths outside the WebDAV root are rejected
+    pub fn from_uri_path(path: &str) -> Option<Self> {
+        let relative = path.strip_prefix(DAV_PREFIX)?;
+
+        if !relative.is_empty() && !relative.starts_with('/') {
+            return None;
+        }
+
+        let mut segments = Vec::new();
+
+        for segment in relative.split('/').filter(|segment| !segment.is_empty()) {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?.to_string();
+
+            if segment == "." || segment == ".." || segment.contains('/') {
+                return None;
+            }
+
+            segments.push(segment);
+        }
+
+        Some(DavPath { segments })
+    }
+
+    /// Parses the `Destination` header of MOVE and COPY, which is usually an absolute URI.
+    /// Returns `None` if the destination is not on this server.
+    pub fn from_destination(headers: &HeaderMap) -> Result<Option<Self>, AppError> {
+        let destination = headers
+            .get("Destination")
+            .and_then(|value| value.to_str().ok())
+            .ok_or(AppError::BadRequest {
+                error: Some("Missing Destination header".to_string()),
+            })?;
+
+        let path = match destination.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |index| &rest[index..]),
+            None => destination,
+        };
+
+        Ok(Self::from_uri_path(path))
+    }
+
+    pub fn from_lock_path(path: &str) -> Self {
+        DavPath {
+            segments: path
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.segments.last().map(String::as_str)
+    }
+
+    pub fn parent(&self) -> DavPath {
+        DavPath {
+            segments: self.segments[..self.segments.len().saturating_sub(1)].to_vec(),
+        }
+    }
+
+    pub fn join(&self, name: &str) -> DavPath {
+        let mut segments = self.segments.clone();
+        segments.push(name.to_string());
+        DavPath { segments }
+    }
+
+    /// Normalized form of the path which locks are recorded for
+    pub fn lock_path(&self) -> String {
+        format!("/{}", self.segments.join("/"))
+    }
+
+    pub fn href(&self, collection: bool) -> String {
+        let mut href = DAV_PREFIX.to_string();
+
+        for segment in &self.segments {
+            href.push('/');
+            href.extend(utf8_percent_encode(segment, SEGMENT));
+        }
+
+        if collection || self.is_root() {
+            href.push('/');
+        }
+
+        href
+    }
+}
+
+pub enum DavResource {
+    Root,
+    Folder(FolderModel),
+    File(FileModel),
+}
+
+impl DavResource {
+    pub fn is_collection(&self) -> bool {
+        !matches!(self, DavResource::File(_))
+    }
+
+    /// The folder which the members of a collection are stored in, the root has none
+    pub fn collection_id(&self) -> Option<Option<i64>> {
+        match self {
+            DavResource::Root => Some(None),
+            DavResource::Folder(folder) => Some(Some(folder.id)),
+            DavResource::File(_) => None,
+        }
+    }
+}
+
+/// Walks the folder hierarchy of the user along the path. Files in the bin are not part of it.
+pub async fn resolve(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+) -> Result<Option<DavResource>, AppError> {
+    let Some((name, folders)) = path.segments.split_last() else {
+        return Ok(Some(DavResource::Root));
+    };
+
+    let mut parent_id = None;
+
+    for folder_name in folders {
+        match state
+            .folder_service
+            .get_folder_by_name(user_id, parent_id, folder_name)
+            .await?
+        {
+            Some(folder) => parent_id = Some(folder.id),
+            None => return Ok(None),
+        }
+    }
+
+    if let Some(folder) = state
+        .folder_service
+        .get_folder_by_name(user_id, parent_id, name)
+        .await?
+    {
+        return Ok(Some(DavResource::Folder(folder)));
+    }
+
+    Ok(state
+        .file_service
+        .get_file_by_name(user_id, parent_id, name)
+        .await?
+        .map(DavResource::File))
+}
+
+/// Resolves the path to the folder its members are stored in, `None` if it is no collection
+pub async fn resolve_collection(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+) -> Result<Option<Option<i64>>, AppError> {
+    Ok(resolve(state, user_id, path)
+        .await?
+        .and_then(|resource| resource.collection_id()))
+}
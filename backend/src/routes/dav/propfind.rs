@@ -0,0 +1,212 @@
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::response::error_handling::AppError;
+use crate::routes::dav::lock::{lock_discovery, DavLocks, SUPPORTED_LOCK};
+use crate::routes::dav::path::{resolve, DavPath, DavResource};
+use crate::routes::dav::xml::{
+    error_response, escape, multistatus_response, parse_propfind, parse_proppatch, status_line,
+    PropName, PropfindRequest, DAV_NAMESPACE,
+};
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+use crate::utils::http::http_date;
+
+/// Live properties which are returned for `allprop` and `propname`
+const LIVE_PROPERTIES: [&str; 9] = [
+    "displayname",
+    "resourcetype",
+    "creationdate",
+    "getlastmodified",
+    "getcontentlength",
+    "getcontenttype",
+    "getetag",
+    "supportedlock",
+    "lockdiscovery",
+];
+
+/// Value of a live property of the resource, `None` if the resource does not have it
+fn property_value(
+    resource: &DavResource,
+    path: &DavPath,
+    locks: &DavLocks,
+    name: &str,
+) -> Option<String> {
+    let (created_at, updated_at) = match resource {
+        DavResource::Root => (None, None),
+        DavResource::Folder(folder) => (Some(folder.created_at), Some(folder.updated_at)),
+        DavResource::File(file) => (Some(file.created_at), Some(file.updated_at)),
+    };
+
+    match (name, resource) {
+        ("displayname", _) => Some(escape(path.name().unwrap_or("")).to_string()),
+        ("resourcetype", DavResource::File(_)) => Some(String::new()),
+        ("resourcetype", _) => Some("<D:collection/>".to_string()),
+        ("creationdate", _) => created_at.map(|date| date.to_rfc3339()),
+        ("getlastmodified", _) => updated_at.as_ref().map(http_date),
+        ("getcontentlength", DavResource::File(file)) => Some(file.file_size.to_string()),
+        ("getcontenttype", DavResource::File(file)) => Some(escape(&file.mime_type).to_string()),
+        ("getetag", DavResource::File(file)) => Some(file.etag()),
+        ("supportedlock", _) => Some(SUPPORTED_LOCK.to_string()),
+        ("lockdiscovery", _) => Some(lock_discovery(&locks.covering(path))),
+        _ => None,
+    }
+}
+
+fn propstat(props: &str, status: StatusCode) -> String {
+    format!(
+        "<D:propstat><D:prop>{}</D:prop><D:status>{}</D:status></D:propstat>",
+        props,
+        status_line(status)
+    )
+}
+
+fn resource_response(
+    resource: &DavResource,
+    path: &DavPath,
+    locks: &DavLocks,
+    request: &PropfindRequest,
+) -> String {
+    let mut found = String::new();
+    let mut missing = String::new();
+
+    match request {
+        PropfindRequest::AllProp | PropfindRequest::PropName => {
+            for name in LIVE_PROPERTIES {
+                let Some(value) = property_value(resource, path, locks, name) else {
+                    continue;
+                };
+
+                let prop = PropName {
+                    namespace: DAV_NAMESPACE.to_string(),
+                    name: name.to_string(),
+                };
+
+                if matches!(request, PropfindRequest::PropName) {
+                    found.push_str(&prop.to_xml(""));
+                } else {
+                    found.push_str(&prop.to_xml(&value));
+                }
+            }
+        }
+        PropfindRequest::Prop(props) => {
+            for prop in props {
+                let value = if prop.namespace == DAV_NAMESPACE {
+                    property_value(resource, path, locks, &prop.name)
+                } else {
+                    None
+                };
+
+                match value {
+                    Some(value) => found.push_str(&prop.to_xml(&value)),
+                    None => missing.push_str(&prop.to_xml("")),
+                }
+            }
+        }
+    }
+
+    let mut response = format!(
+        "<D:response><D:href>{}</D:href>",
+        escape(&path.href(resource.is_collection()))
+    );
+
+    if !found.is_empty() {
+        response.push_str(&propstat(&found, StatusCode::OK));
+    }
+    if !missing.is_empty() {
+        response.push_str(&propstat(&missing, StatusCode::NOT_FOUND));
+    }
+
+    response.push_str("</D:response>");
+    response
+}
+
+pub async fn propfind(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    // Listing a whole tree at once is not supported, clients have to walk it
+    let with_members = match headers.get("Depth").and_then(|value| value.to_str().ok()) {
+        Some("0") => false,
+        Some("1") => true,
+        _ => {
+            return Ok(error_response(
+                StatusCode::FORBIDDEN,
+                "propfind-finite-depth",
+            ))
+        }
+    };
+
+    let request = parse_propfind(&body)?;
+
+    let Some(resource) = resolve(state, user_id, path).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let locks = DavLocks::load(state, user_id, headers).await?;
+    let mut responses = resource_response(&resource, path, &locks, &request);
+
+    if let (true, Some(collection_id)) = (with_members, resource.collection_id()) {
+        for folder in state
+            .folder_service
+            .get_child_folders(user_id, collection_id)
+            .await?
+        {
+            let child_path = path.join(&folder.folder_name);
+            responses.push_str(&resource_response(
+                &DavResource::Folder(folder),
+                &child_path,
+                &locks,
+                &request,
+            ));
+        }
+
+        for file in state
+            .file_service
+            .get_child_files(user_id, collection_id)
+            .await?
+        {
+            let child_path = path.join(&file.file_name);
+            responses.push_str(&resource_response(
+                &DavResource::File(file),
+                &child_path,
+                &locks,
+                &request,
+            ));
+        }
+    }
+
+    Ok(multistatus_response(responses))
+}
+
+/// Dead properties are not stored, so every change is rejected
+pub async fn proppatch(
+    state: &AppState,
+    user_id: UserId,
+    path: &DavPath,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let Some(resource) = resolve(state, user_id, path).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    DavLocks::load(state, user_id, headers)
+        .await?
+        .check(path, false)?;
+
+    let props: String = parse_proppatch(&body)?
+        .iter()
+        .map(|prop| prop.to_xml(""))
+        .collect();
+
+    Ok(multistatus_response(format!(
+        "<D:response><D:href>{}</D:href>{}</D:response>",
+        escape(&path.href(resource.is_collection())),
+        propstat(&props, StatusCode::FORBIDDEN)
+    )))
+}
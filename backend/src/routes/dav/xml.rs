@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+
+use crate::response::error_handling::AppError;
+
+pub const DAV_NAMESPACE: &str = "DAV:";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn is_dav(&self, name: &str) -> bool {
+        self.namespace == DAV_NAMESPACE && self.name == name
+    }
+
+    /// Element of the property, properties outside of the DAV namespace declare their own
+    pub fn to_xml(&self, value: &str) -> String {
+        let (open, close) = if self.namespace == DAV_NAMESPACE {
+            (format!("D:{}", self.name), format!("D:{}", self.name))
+        } else {
+            (
+                format!("X:{} xmlns:X=\"{}\"", self.name, escape(&self.namespace)),
+                format!("X:{}", self.name),
+            )
+        };
+
+        if value.is_empty() {
+            format!("<{}/>", open)
+        } else {
+            format!("<{}>{}</{}>", open, value, close)
+        }
+    }
+}
+
+struct XmlElement {
+    depth: usize,
+    name: PropName,
+}
+
+pub fn escape(value: &str) -> Cow<'_, str> {
+    quick_xml::escape::escape(value)
+}
+
+fn invalid_xml() -> AppError {
+    AppError::BadRequest {
+        error: Some("Invalid XML body".to_string()),
+    }
+}
+
+fn resolve_name(namespace: ResolveResult, local_name: &[u8]) -> PropName {
+    let namespace = match namespace {
+        ResolveResult::Bound(namespace) => String::from_utf8_lossy(namespace.as_ref()).to_string(),
+        _ => String::new(),
+    };
+
+    PropName {
+        namespace,
+        name: String::from_utf8_lossy(local_name).to_string(),
+    }
+}
+
+/// All elements of the document in order, with their nesting depth
+fn parse_elements(body: &[u8]) -> Result<Vec<XmlElement>, AppError> {
+    let mut reader = NsReader::from_reader(body);
+    let mut elements = Vec::new();
+    let mut depth = 0;
+
+    loop {
+        match reader.read_resolved_event().map_err(|_| invalid_xml())? {
+            (namespace, Event::Start(element)) => {
+                depth += 1;
+                elements.push(XmlElement {
+                    depth,
+                    name: resolve_name(namespace, element.local_name().as_ref()),
+                });
+            }
+            (namespace, Event::Empty(element)) => {
+                elements.push(XmlElement {
+                    depth: depth + 1,
+                    name: resolve_name(namespace, element.local_name().as_ref()),
+                });
+            }
+            (_, Event::End(_)) => depth -= 1,
+            (_, Event::Eof) => break,
+            _ => {}
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Children of every `DAV:prop` element, as used by PROPFIND and PROPPATCH
+fn prop_children(elements: &[XmlElement]) -> Vec<PropName> {
+    let mut props = Vec::new();
+
+    for (index, element) in elements.iter().enumerate() {
+        if !element.name.is_dav("prop") {
+            continue;
+        }
+
+        props.extend(
+            elements[index + 1..]
+                .iter()
+                .take_while(|child| child.depth > element.depth)
+                .filter(|child| child.depth == element.depth + 1)
+                .map(|child| child.name.clone()),
+        );
+    }
+
+    props
+}
+
+pub enum PropfindRequest {
+    AllProp,
+    PropName,
+    Prop(Vec<PropName>),
+}
+
+pub fn parse_propfind(body: &[u8]) -> Result<PropfindRequest, AppError> {
+    // An empty body is the same as asking for all properties
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(PropfindRequest::AllProp);
+    }
+
+    let elements = parse_elements(body)?;
+
+    if elements.iter().any(|element| element.name.is_dav("propname")) {
+        return Ok(PropfindRequest::PropName);
+    }
+
+    if elements.iter().any(|element| element.name.is_dav("prop")) {
+        return Ok(PropfindRequest::Prop(prop_children(&elements)));
+    }
+
+    Ok(PropfindRequest::AllProp)
+}
+
+pub fn parse_proppatch(body: &[u8]) -> Result<Vec<PropName>, AppError> {
+    Ok(prop_children(&parse_elements(body)?))
+}
+
+pub struct LockInfo {
+    pub exclusive: bool,
+    /// Content of the owner element, elements declare their namespace themselves
+    pub owner: Option<String>,
+}
+
+pub fn parse_lockinfo(body: &[u8]) -> Result<LockInfo, AppError> {
+    let mut reader = NsReader::from_reader(body);
+    let mut lock_info = LockInfo {
+        exclusive: true,
+        owner: None,
+    };
+
+    loop {
+        match reader.read_resolved_event().map_err(|_| invalid_xml())? {
+            (namespace, Event::Start(element)) => {
+                let name = resolve_name(namespace, element.local_name().as_ref());
+
+                if name.is_dav("owner") {
+                    let owner = read_owner(&mut reader)?;
+                    lock_info.owner = Some(owner).filter(|owner| !owner.is_empty());
+                } else if name.is_dav("shared") {
+                    lock_info.exclusive = false;
+                }
+            }
+            (namespace, Event::Empty(element)) => {
+                let name = resolve_name(namespace, element.local_name().as_ref());
+
+                if name.is_dav("shared") {
+                    lock_info.exclusive = false;
+                }
+            }
+            (_, Event::Eof) => break,
+            _ => {}
+        }
+    }
+
+    Ok(lock_info)
+}
+
+/// Serializes the content of the owner element again. Prefixes declared further up in the
+/// request are unknown where the owner is returned, so every element declares its namespace
+/// as the default namespace.
+fn read_owner(reader: &mut NsReader<&[u8]>) -> Result<String, AppError> {
+    let mut owner = String::new();
+    let mut open_elements = Vec::new();
+
+    loop {
+        match reader.read_resolved_event().map_err(|_| invalid_xml())? {
+            (namespace, Event::Start(element)) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                owner.push_str(&owner_tag(namespace, &name, &element)?);
+                owner.push('>');
+                open_elements.push(name);
+            }
+            (namespace, Event::Empty(element)) => {
+                let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                owner.push_str(&owner_tag(namespace, &name, &element)?);
+                owner.push_str("/>");
+            }
+            (_, Event::End(_)) => match open_elements.pop() {
+                Some(name) => owner.push_str(&format!("</{}>", name)),
+                None => break,
+            },
+            (_, Event::Text(text)) => {
+                owner.push_str(&escape(&text.unescape().map_err(|_| invalid_xml())?));
+            }
+            (_, Event::CData(data)) => owner.push_str(&escape(&String::from_utf8_lossy(&data))),
+            (_, Event::Eof) => return Err(invalid_xml()),
+            _ => {}
+        }
+    }
+
+    Ok(owner.trim().to_string())
+}
+
+/// Start of an element in the owner, attributes in other namespaces are left out
+fn owner_tag(
+    namespace: ResolveResult,
+    name: &str,
+    element: &BytesStart,
+) -> Result<String, AppError> {
+    let namespace = match namespace {
+        ResolveResult::Bound(namespace) => String::from_utf8_lossy(namespace.as_ref()).to_string(),
+        ResolveResult::Unbound => String::new(),
+        ResolveResult::Unknown(_) => return Err(invalid_xml()),
+    };
+
+    let mut tag = format!("<{} xmlns=\"{}\"", name, escape(&namespace));
+
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(|_| invalid_xml())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+
+        if key == "xmlns" || key.contains(':') {
+            continue;
+        }
+
+        let value = attribute.unescape_value().map_err(|_| invalid_xml())?;
+        tag.push_str(&format!(" {}=\"{}\"", key, escape(&value)));
+    }
+
+    Ok(tag)
+}
+
+pub fn xml_response(status: StatusCode, content: String) -> Response {
+    (
+        status,
+        [(CONTENT_TYPE, "application/xml; charset=utf-8")],
+        format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>{}", content),
+    )
+        .into_response()
+}
+
+pub fn multistatus_response(responses: String) -> Response {
+    xml_response(
+        StatusCode::MULTI_STATUS,
+        format!("<D:multistatus xmlns:D=\"DAV:\">{}</D:multistatus>", responses),
+    )
+}
+
+/// Response body for a failed precondition, e.g. `propfind-finite-depth`
+pub fn error_response(status: StatusCode, condition: &str) -> Response {
+    xml_response(
+        status,
+        format!("<D:error xmlns:D=\"DAV:\"><D:{}/></D:error>", condition),
+    )
+}
+
+pub fn status_line(status: StatusCode) -> String {
+    format!(
+        "HTTP/1.1 {} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    )
+}
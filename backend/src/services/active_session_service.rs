@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use sqlx::types::Uuid;
+
+use crate::db::KosmosPool;
+use crate::model::active_session::ActiveSessionModel;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+const EXPIRED_SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The sessions of logged-in users as kept by the session store
+#[derive(Clone)]
+pub struct ActiveSessionService {
+    db_pool: KosmosPool,
+}
+
+impl ActiveSessionService {
+    pub fn new(db_pool: KosmosPool) -> Self {
+        ActiveSessionService { db_pool }
+    }
+
+    pub async fn get_active_sessions(
+        &self,
+        user_id: UserId,
+        current_session_id: Option<String>,
+    ) -> Result<Vec<ActiveSessionModel>, AppError> {
+        sqlx::query_as!(
+            ActiveSessionModel,
+            r#"SELECT uuid,
+                      user_agent,
+                      ip_address,
+                      expires_at,
+                      last_seen_at,
+                      created_at,
+                      id IS NOT DISTINCT FROM $2 AS "current!"
+               FROM sessions
+               WHERE user_id = $1
+                 AND expires_at > now()
+               ORDER BY last_seen_at DESC"#,
+            user_id,
+            current_session_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting sessions for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Ends the session, its cookie is not accepted anymore afterward
+    pub async fn delete_active_session(&self, user_id: UserId, uuid: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE uuid = $1 AND user_id = $2",
+            uuid,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting session {}: {}", uuid, e);
+            AppError::InternalError
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound {
+                error: "Session not found".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM sessions WHERE expires_at <= now()")
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting expired sessions: {}", e);
+                AppError::InternalError
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn startup_prepare(&self) {
+        let _ = self.delete_expired_sessions().await;
+        tracing::info!("Deleted expired sessions");
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EXPIRED_SESSION_CLEANUP_INTERVAL);
+            // The first tick completes immediately, the cleanup above already ran
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let _ = service.delete_expired_sessions().await;
+            }
+        });
+    }
+}
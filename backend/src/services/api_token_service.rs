@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sonyflake::Sonyflake;
+
+use crate::db::KosmosPool;
+use crate::model::api_token::ApiTokenModel;
+use crate::model::internal::token_scope::TokenScope;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+const API_TOKEN_PREFIX: &str = "kosmos_";
+const API_TOKEN_LENGTH: usize = 40;
+
+#[derive(Clone)]
+pub struct ApiTokenService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+}
+
+impl ApiTokenService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
+        ApiTokenService { db_pool, sf }
+    }
+
+    fn hash_token(token: &str) -> String {
+        format!("{:x}", Sha256::digest(token.as_bytes()))
+    }
+
+    /// Creates a random token for the user, which is returned in plain text only here
+    pub async fn create_api_token(
+        &self,
+        user_id: UserId,
+        name: String,
+        scope: TokenScope,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(ApiTokenModel, String), AppError> {
+        let id = self.sf.next_id().map_err(|_| AppError::InternalError)? as i64;
+
+        let token = format!(
+            "{}{}",
+            API_TOKEN_PREFIX,
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(API_TOKEN_LENGTH)
+                .map(char::from)
+                .collect::<String>()
+        );
+
+        let api_token = sqlx::query_as!(
+            ApiTokenModel,
+            "INSERT INTO api_tokens (id, user_id, name, token_hash, scope, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *",
+            id,
+            user_id,
+            name,
+            Self::hash_token(&token),
+            scope as i16,
+            expires_at
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating api token for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok((api_token, token))
+    }
+
+    pub async fn get_api_tokens(&self, user_id: UserId) -> Result<Vec<ApiTokenModel>, AppError> {
+        sqlx::query_as!(
+            ApiTokenModel,
+            "SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY created_at DESC",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting api tokens for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn delete_api_token(&self, user_id: UserId, api_token_id: i64) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM api_tokens WHERE id = $1 AND user_id = $2",
+            api_token_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting api token {}: {}", api_token_id, e);
+            AppError::InternalError
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound {
+                error: "API token not found".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Finds the user and scope of a token which has not expired yet and marks the token as used
+    pub async fn get_token_auth(
+        &self,
+        token: &str,
+    ) -> Result<Option<(UserId, TokenScope)>, AppError> {
+        sqlx::query!(
+            "UPDATE api_tokens SET last_used_at = now()
+             WHERE token_hash = $1
+               AND (expires_at IS NULL OR expires_at > now())
+             RETURNING user_id, scope",
+            Self::hash_token(token)
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map(|row| row.map(|row| (row.user_id, TokenScope::new(row.scope))))
+        .map_err(|e| {
+            tracing::error!("Error checking api token: {}", e);
+            AppError::InternalError
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sonyflake::Sonyflake;
+
+use crate::db::KosmosPool;
+use crate::model::app_password::AppPasswordModel;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+const APP_PASSWORD_LENGTH: usize = 32;
+
+#[derive(Clone)]
+pub struct AppPasswordService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+}
+
+impl AppPasswordService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
+        AppPasswordService { db_pool, sf }
+    }
+
+    fn hash_password(password: &str) -> String {
+        format!("{:x}", Sha256::digest(password.as_bytes()))
+    }
+
+    /// Creates a random password for the user, which is returned in plain text only here
+    pub async fn create_app_password(
+        &self,
+        user_id: UserId,
+        name: String,
+    ) -> Result<(AppPasswordModel, String), AppError> {
+        let id = self.sf.next_id().map_err(|_| AppError::InternalError)? as i64;
+
+        let password = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(APP_PASSWORD_LENGTH)
+            .map(char::from)
+            .collect::<String>();
+
+        let app_password = sqlx::query_as!(
+            AppPasswordModel,
+            "INSERT INTO app_passwords (id, user_id, name, password_hash) VALUES ($1, $2, $3, $4) RETURNING *",
+            id,
+            user_id,
+            name,
+            Self::hash_password(&password)
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating app password for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok((app_password, password))
+    }
+
+    pub async fn get_app_passwords(
+        &self,
+        user_id: UserId,
+    ) -> Result<Vec<AppPasswordModel>, AppError> {
+        sqlx::query_as!(
+            AppPasswordModel,
+            "SELECT * FROM app_passwords WHERE user_id = $1 ORDER BY created_at DESC",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting app passwords for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn delete_app_password(
+        &self,
+        user_id: UserId,
+        app_password_id: i64,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM app_passwords WHERE id = $1 AND user_id = $2",
+            app_password_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting app password {}: {}", app_password_id, e);
+            AppError::InternalError
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound {
+                error: "App password not found".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Finds the user the app password was created for and marks the password as used
+    pub async fn get_user_id_by_app_password(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<UserId>, AppError> {
+        sqlx::query_scalar!(
+            "UPDATE app_passwords SET last_used_at = now()
+             FROM users
+             WHERE users.id = app_passwords.user_id
+               AND users.username = $1
+               AND app_passwords.password_hash = $2
+             RETURNING users.id",
+            username,
+            Self::hash_password(password)
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error checking app password of user {}: {}", username, e);
+            AppError::InternalError
+        })
+    }
+}
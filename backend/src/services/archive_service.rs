@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+
+use axum::body::Bytes;
+use futures::{stream, Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::spawn_blocking;
+use tokio_util::io::StreamReader;
+
+use crate::archive::reader::{ArchiveEntryInfo, ArchiveFormat};
+use crate::model::file::FileModel;
+use crate::model::internal::file_type::FileType;
+use crate::model::internal::zip::ZipInformation;
+use crate::model::job::ArchiveExtractionPayload;
+use crate::response::error_handling::AppError;
+use crate::routes::api::v1::auth::file::upload::check_storage::check_user_storage_limit;
+use crate::routes::api::v1::auth::file::upload::folder_segments::process_folder_segments;
+use crate::routes::api::v1::auth::file::upload::{start_content_indexing, start_image_processing};
+use crate::routes::api::v1::auth::file::FILE_SIZE_LIMIT;
+use crate::services::file_service::FileService;
+use crate::services::search_service::SearchService;
+use crate::state::AppState;
+use crate::storage;
+use crate::storage::{KosmosStorage, LocalCopy};
+
+/// Decompressed data is handed over in chunks of this size
+const ENTRY_CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks waiting to be consumed, reading the archive pauses while the consumer is behind
+const ENTRY_CHUNKS_BUFFERED: usize = 4;
+
+type EntryContent = mpsc::Receiver<io::Result<Bytes>>;
+
+/// Sends what is written through a channel, so archives read on a blocking thread can be
+/// consumed by async code
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn content_stream(content: EntryContent) -> impl Stream<Item = io::Result<Bytes>> {
+    stream::unfold(content, |mut content| async move {
+        content.recv().await.map(|chunk| (chunk, content))
+    })
+}
+
+/// Lists, streams and extracts the entries of archives which are stored as files
+#[derive(Clone)]
+pub struct ArchiveService {
+    storage: KosmosStorage,
+}
+
+impl ArchiveService {
+    pub fn new(storage: KosmosStorage) -> Self {
+        ArchiveService { storage }
+    }
+
+    pub fn get_format(file: &FileModel) -> Result<ArchiveFormat, AppError> {
+        ArchiveFormat::detect(&file.file_name, &file.mime_type).ok_or(AppError::BadRequest {
+            error: Some("File is not a supported archive".to_string()),
+        })
+    }
+
+    /// Archives need random access, so remote objects are copied to a local file first
+    async fn open(&self, file: &FileModel) -> Result<(LocalCopy, ArchiveFormat), AppError> {
+        let format = Self::get_format(file)?;
+
+        let local_copy = storage::local_copy(&self.storage, &file.content_key())
+            .await
+            .map_err(|e| {
+                tracing::error!("Error while loading file {}: {}", file.id, e);
+                AppError::InternalError
+            })?;
+
+        Ok((local_copy, format))
+    }
+
+    fn unreadable_archive(file_id: i64, error: io::Error) -> AppError {
+        // Formats which can only be listed
+        if error.kind() == io::ErrorKind::Unsupported {
+            return AppError::BadRequest {
+                error: Some(error.to_string()),
+            };
+        }
+
+        tracing::error!("Error while loading file as archive {}: {}", file_id, error);
+        AppError::UnprocessableEntity {
+            error: "Archive can't be read".to_string(),
+        }
+    }
+
+    pub async fn get_entries(&self, file: &FileModel) -> Result<Vec<ArchiveEntryInfo>, AppError> {
+        let (local_copy, format) = self.open(file).await?;
+
+        spawn_blocking(move || format.open(&local_copy.path)?.entries())
+            .await
+            .map_err(|_| AppError::InternalError)?
+            .map_err(|e| Self::unreadable_archive(file.id, e))
+    }
+
+    pub async fn get_information(&self, file: &FileModel) -> Result<ZipInformation, AppError> {
+        let mut root = ZipInformation::new("root");
+
+        for entry in self.get_entries(file).await? {
+            let path = match entry.is_directory {
+                true => format!("{}/", entry.path),
+                false => entry.path,
+            };
+            root.add_path(
+                &path.split('/').collect::<Vec<_>>(),
+                entry.size,
+                entry.modified,
+            );
+        }
+
+        Ok(root)
+    }
+
+    /// Decompresses a single file of the archive while it is sent. Archives without an index
+    /// are read up to the entry.
+    pub async fn stream_entry(
+        &self,
+        file: &FileModel,
+        path: String,
+    ) -> Result<(ArchiveEntryInfo, impl Stream<Item = io::Result<Bytes>>), AppError> {
+        let (local_copy, format) = self.open(file).await?;
+        let (info_sender, info_receiver) = oneshot::channel();
+        let (sender, receiver) = mpsc::channel(ENTRY_CHUNKS_BUFFERED);
+
+        spawn_blocking(move || {
+            let mut info_sender = Some(info_sender);
+
+            let result = format.open(&local_copy.path).and_then(|mut reader| {
+                reader.read_entries(&mut |info, content| {
+                    if info.is_directory || info.path != path {
+                        return Ok(true);
+                    }
+
+                    let Some(info_sender) = info_sender.take() else {
+                        return Ok(false);
+                    };
+                    if info_sender.send(Ok(Some(info))).is_err() {
+                        return Ok(false);
+                    }
+
+                    let mut writer = BufWriter::with_capacity(
+                        ENTRY_CHUNK_SIZE,
+                        ChannelWriter {
+                            sender: sender.clone(),
+                        },
+                    );
+
+                    if let Err(e) = io::copy(content, &mut writer).and_then(|_| writer.flush()) {
+                        if e.kind() != io::ErrorKind::BrokenPipe {
+                            tracing::error!("Error reading {} from archive: {}", path, e);
+                            let _ = sender.blocking_send(Err(e));
+                        }
+                    }
+
+                    Ok(false)
+                })
+            });
+
+            if let Some(info_sender) = info_sender {
+                let _ = info_sender.send(result.map(|_| None));
+            }
+        });
+
+        let info = info_receiver
+            .await
+            .map_err(|_| AppError::InternalError)?
+            .map_err(|e| Self::unreadable_archive(file.id, e))?
+            .ok_or(AppError::NotFound {
+                error: "Entry not found".to_string(),
+            })?;
+
+        Ok((info, content_stream(receiver)))
+    }
+
+    /// Whole content of a file of the archive, entries beyond the limit are rejected
+    pub async fn read_entry(
+        &self,
+        file: &FileModel,
+        path: String,
+        limit: u64,
+    ) -> Result<(ArchiveEntryInfo, Vec<u8>), AppError> {
+        let too_large = || AppError::BadRequest {
+            error: Some("Entry is too large".to_string()),
+        };
+
+        let (info, content) = self.stream_entry(file, path).await?;
+        if info.size > limit {
+            return Err(too_large());
+        }
+
+        futures::pin_mut!(content);
+        let mut data = Vec::with_capacity(info.size as usize);
+
+        while let Some(chunk) = content.next().await {
+            let chunk = chunk.map_err(|e| Self::unreadable_archive(file.id, e))?;
+
+            // The size in the archive is not trusted, it could hide a much larger content
+            if data.len() as u64 + chunk.len() as u64 > limit {
+                return Err(too_large());
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok((info, data))
+    }
+
+    /// Decompresses every entry into its own channel. The next entry is only read once the
+    /// content of the previous one is consumed or dropped.
+    async fn stream_entries(
+        &self,
+        file: &FileModel,
+    ) -> Result<mpsc::Receiver<(ArchiveEntryInfo, EntryContent)>, AppError> {
+        let (local_copy, format) = self.open(file).await?;
+        let file_id = file.id;
+        let (sender, receiver) = mpsc::channel(1);
+
+        spawn_blocking(move || {
+            let result = format.open(&local_copy.path).and_then(|mut reader| {
+                reader.read_entries(&mut |info, content| {
+                    let (content_sender, content_receiver) = mpsc::channel(ENTRY_CHUNKS_BUFFERED);
+                    let path = info.path.clone();
+
+                    if sender.blocking_send((info, content_receiver)).is_err() {
+                        return Ok(false);
+                    }
+
+                    let mut writer = BufWriter::with_capacity(
+                        ENTRY_CHUNK_SIZE,
+                        ChannelWriter {
+                            sender: content_sender.clone(),
+                        },
+                    );
+
+                    if let Err(e) = io::copy(content, &mut writer).and_then(|_| writer.flush()) {
+                        if e.kind() != io::ErrorKind::BrokenPipe {
+                            tracing::error!("Error reading {} from archive: {}", path, e);
+                            let _ = content_sender.blocking_send(Err(e));
+                        }
+                    }
+
+                    Ok(true)
+                })
+            });
+
+            if let Err(e) = result {
+                tracing::error!("Error while reading archive {}: {}", file_id, e);
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    /// Recreates the archive in a new folder named after it. Folders which already exist are
+    /// merged, files which already exist get the entry as new version unless it is the same.
+    pub async fn extract(
+        &self,
+        payload: &ArchiveExtractionPayload,
+        state: &AppState,
+        operation_id: i64,
+    ) -> Result<(), AppError> {
+        let archive = state.file_service.get_file(payload.file_id, None).await?;
+        let user = state.user_service.get_auth_user(payload.user_id).await?;
+
+        let total = self.get_entries(&archive).await?.len() as i32;
+        let mut storage_remaining =
+            check_user_storage_limit(&state.usage_service, user.id, user.storage_limit).await?;
+
+        let folder_name = Self::get_format(&archive)?
+            .strip_extension(&archive.file_name)
+            .to_string();
+
+        let mut folder_cache: HashMap<String, i64> = HashMap::new();
+        let mut pending_image_formats: Vec<i64> = Vec::new();
+        let mut pending_content_index: Vec<i64> = Vec::new();
+        let mut done = 0;
+        let mut failures = 0;
+
+        let mut entries = self.stream_entries(&archive).await?;
+
+        while let Some((entry, content)) = entries.recv().await {
+            done += 1;
+            let path = format!("{}/{}", folder_name, entry.path);
+
+            if entry.is_directory {
+                process_folder_segments(
+                    state,
+                    user.id,
+                    payload.parent_folder_id,
+                    &mut folder_cache,
+                    format!("{}/", path),
+                )
+                .await?;
+                continue;
+            }
+
+            let (file_name, parent_folder_id) = process_folder_segments(
+                state,
+                user.id,
+                payload.parent_folder_id,
+                &mut folder_cache,
+                path,
+            )
+            .await?;
+
+            // Files extracted up to here are kept, like entries which fail. Extracting again
+            // after freeing space continues, as files which are the same are skipped.
+            if entry.size > storage_remaining.max(0) as u64 {
+                tracing::info!("Storage limit reached while extracting archive {}", archive.id);
+                return Err(AppError::BadRequest {
+                    error: Some("Storage limit exceeded".to_string()),
+                });
+            }
+
+            // Entries can decompress to more than they declare, such content is not stored
+            let reader = StreamReader::new(content_stream(content));
+            futures::pin_mut!(reader);
+            let blob = match state.blob_service.store_limited(&mut reader, entry.size).await {
+                Ok(Some(blob)) => blob,
+                Ok(None) | Err(_) => {
+                    failures += 1;
+                    continue;
+                }
+            };
+
+            storage_remaining -= blob.size;
+
+            let mime_type = mime_guess::from_path(&file_name)
+                .first_or_octet_stream()
+                .to_string();
+            let mut file_type_res = FileService::get_file_type(&mime_type, &file_name);
+
+            if file_type_res.file_type == FileType::Image && blob.size as u64 > FILE_SIZE_LIMIT {
+                file_type_res.file_type = FileType::LargeImage;
+            }
+
+            let exists = state
+                .file_service
+                .check_file_exists_by_name(&file_name, user.id, parent_folder_id)
+                .await?;
+
+            let file_id = match exists {
+                Some(existing) => {
+                    let file = state.file_service.get_file(existing, Some(user.id)).await?;
+
+                    // Extracting again, e.g. when the job is retried, doesn't add versions
+                    if file.blob_hash.as_deref() == Some(blob.hash.as_str()) {
+                        storage_remaining += blob.size;
+                        let _ = state.blob_service.release(&blob.hash).await;
+                        let _ = state
+                            .operation_service
+                            .update_progress(operation_id, done, total, failures, Some(file_name))
+                            .await;
+                        continue;
+                    }
+
+                    state
+                        .file_service
+                        .replace_file_content(
+                            &file,
+                            blob,
+                            file_type_res.file_type,
+                            file_type_res.normalized_mime_type,
+                        )
+                        .await?;
+                    existing
+                }
+                None => {
+                    state
+                        .file_service
+                        .create_file(
+                            user.id,
+                            state.get_safe_id()?,
+                            file_name.clone(),
+                            parent_folder_id,
+                            &file_type_res,
+                            blob,
+                        )
+                        .await?
+                }
+            };
+
+            if state.image_service.supports_file_type(file_type_res.file_type) {
+                pending_image_formats.push(file_id);
+            }
+
+            if SearchService::supports_file_type(file_type_res.file_type) {
+                pending_content_index.push(file_id);
+            }
+
+            let _ = state
+                .operation_service
+                .update_progress(operation_id, done, total, failures, Some(file_name))
+                .await;
+        }
+
+        start_image_processing(state, user.id, pending_image_formats).await?;
+        start_content_indexing(state, user.id, pending_content_index).await?;
+
+        if failures > 0 {
+            return Err(AppError::InternalError);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+
+use crate::db::KosmosPool;
+use crate::model::internal::file_type::FileType;
+use crate::model::internal::job_type::JobType;
+use crate::model::job::BinPurgePayload;
+use crate::response::error_handling::AppError;
+use crate::services::job_service::JobService;
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+const DEFAULT_BIN_RETENTION_DAYS: i32 = 30;
+const BIN_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Hours until the bin of a user is purged again after all attempts of their last purge failed
+const FAILED_PURGE_BACKOFF_HOURS: i32 = 24;
+
+/// Purges files and folders which stayed in the bin longer than the retention of their owner
+#[derive(Clone)]
+pub struct BinService {
+    db_pool: KosmosPool,
+    job_service: JobService,
+    /// Used for users without their own retention, 0 keeps files until the bin is cleared
+    default_retention_days: i32,
+}
+
+impl BinService {
+    pub fn new(db_pool: KosmosPool, job_service: JobService) -> Self {
+        let default_retention_days = env::var("BIN_RETENTION_DAYS")
+            .ok()
+            .and_then(|days| days.parse::<i32>().ok())
+            .map(|days| days.max(0))
+            .unwrap_or(DEFAULT_BIN_RETENTION_DAYS);
+
+        BinService {
+            db_pool,
+            job_service,
+            default_retention_days,
+        }
+    }
+
+    /// Days files of the user stay in the bin, `None` if they are never purged
+    pub fn get_retention_days(&self, user_retention_days: Option<i32>) -> Option<i32> {
+        match user_retention_days.unwrap_or(self.default_retention_days) {
+            0 => None,
+            days => Some(days),
+        }
+    }
+
+    /// Started days left until a file deleted at the given time is purged
+    pub fn get_days_until_purge(
+        &self,
+        deleted_at: DateTime<Utc>,
+        user_retention_days: Option<i32>,
+    ) -> Option<i64> {
+        let retention_days = self.get_retention_days(user_retention_days)?;
+        let seconds_left = (deleted_at + chrono::Duration::days(retention_days as i64)
+            - Utc::now())
+        .num_seconds()
+        .max(0);
+
+        Some((seconds_left + 86399) / 86400)
+    }
+
+    pub async fn update_retention(
+        &self,
+        user_id: UserId,
+        retention_days: Option<i32>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users SET bin_retention_days = $1 WHERE id = $2",
+            retention_days,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error updating bin retention for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    /// Expired files grouped by user, users who still have a purge queued or whose last purge
+    /// failed recently are skipped.
+    /// Files which went to the bin with their folder are purged with it.
+    async fn get_expired_files(&self) -> Result<HashMap<UserId, Vec<i64>>, AppError> {
+        let expired = sqlx::query!(
+            "SELECT f.user_id, f.id
+             FROM files f
+                      JOIN users u ON u.id = f.user_id
+             WHERE f.deleted_at IS NOT NULL
+               AND f.deleted_with IS NULL
+               AND COALESCE(u.bin_retention_days, $1) > 0
+               AND f.deleted_at < now() - make_interval(days => COALESCE(u.bin_retention_days, $1))
+               AND NOT EXISTS (SELECT 1
+                               FROM jobs j
+                                        JOIN operations o ON o.id = j.operation_id
+                               WHERE o.user_id = f.user_id
+                                 AND j.job_type = $2
+                                 AND (j.dead_at IS NULL
+                                   OR j.dead_at > now() - make_interval(hours => $3)))",
+            self.default_retention_days,
+            JobType::BinPurge as i16,
+            FAILED_PURGE_BACKOFF_HOURS
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting expired bin files: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(expired
+            .into_iter()
+            .map(|file| (file.user_id, file.id))
+            .into_group_map())
+    }
+
+    /// Expired folders grouped by user, like `get_expired_files`
+    async fn get_expired_folders(&self) -> Result<HashMap<UserId, Vec<i64>>, AppError> {
+        let expired = sqlx::query!(
+            "SELECT f.user_id, f.id
+             FROM folder f
+                      JOIN users u ON u.id = f.user_id
+             WHERE f.deleted_at IS NOT NULL
+               AND f.deleted_with IS NULL
+               AND COALESCE(u.bin_retention_days, $1) > 0
+               AND f.deleted_at < now() - make_interval(days => COALESCE(u.bin_retention_days, $1))
+               AND NOT EXISTS (SELECT 1
+                               FROM jobs j
+                                        JOIN operations o ON o.id = j.operation_id
+                               WHERE o.user_id = f.user_id
+                                 AND j.job_type = $2
+                                 AND (j.dead_at IS NULL
+                                   OR j.dead_at > now() - make_interval(hours => $3)))",
+            self.default_retention_days,
+            JobType::BinPurge as i16,
+            FAILED_PURGE_BACKOFF_HOURS
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting expired bin folders: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(expired
+            .into_iter()
+            .map(|folder| (folder.user_id, folder.id))
+            .into_group_map())
+    }
+
+    /// Queues a purge for every user with expired items in their bin
+    pub async fn schedule_purges(&self) -> Result<(), AppError> {
+        let mut expired_files = self.get_expired_files().await?;
+        let mut expired_folders = self.get_expired_folders().await?;
+
+        let user_ids = expired_files
+            .keys()
+            .chain(expired_folders.keys())
+            .copied()
+            .unique()
+            .collect::<Vec<_>>();
+
+        for user_id in user_ids {
+            let file_ids = expired_files.remove(&user_id).unwrap_or_default();
+            let folder_ids = expired_folders.remove(&user_id).unwrap_or_default();
+
+            tracing::info!(
+                "Purging {} files and {} folders from the bin of user {}",
+                file_ids.len(),
+                folder_ids.len(),
+                user_id
+            );
+            self.job_service
+                .enqueue_bin_purge(user_id, file_ids, folder_ids)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Permanently deletes the items, items restored in the meantime are kept
+    pub async fn purge(
+        &self,
+        payload: &BinPurgePayload,
+        state: &AppState,
+        operation_id: i64,
+    ) -> Result<(), AppError> {
+        let files = sqlx::query!(
+            "SELECT id, file_name, file_type FROM files WHERE id = ANY($1) AND deleted_at IS NOT NULL",
+            &payload.file_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting files to purge: {}", e);
+            AppError::InternalError
+        })?;
+
+        let folders = sqlx::query!(
+            "SELECT id, folder_name FROM folder WHERE id = ANY($1) AND deleted_at IS NOT NULL",
+            &payload.folder_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting folders to purge: {}", e);
+            AppError::InternalError
+        })?;
+
+        let total = (files.len() + folders.len()) as i32;
+        let mut done = 0;
+        let mut failures = 0;
+
+        for folder in folders {
+            if state
+                .folder_service
+                .permanently_delete_folder(folder.id, payload.user_id, &state.file_service)
+                .await
+                .is_err()
+            {
+                failures += 1;
+            }
+
+            done += 1;
+            let _ = state
+                .operation_service
+                .update_progress(
+                    operation_id,
+                    done,
+                    total,
+                    failures,
+                    Some(folder.folder_name),
+                )
+                .await;
+        }
+
+        for file in files {
+            if state
+                .file_service
+                .permanently_delete_file(file.id, Some(FileType::new(file.file_type)))
+                .await
+                .is_err()
+            {
+                failures += 1;
+            }
+
+            done += 1;
+            let _ = state
+                .operation_service
+                .update_progress(operation_id, done, total, failures, Some(file.file_name))
+                .await;
+        }
+
+        // Purged files are gone on a retry, so only the failed ones are tried again
+        if failures > 0 {
+            return Err(AppError::InternalError);
+        }
+
+        Ok(())
+    }
+
+    pub async fn startup_prepare(&self) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(BIN_PURGE_INTERVAL);
+
+            loop {
+                interval.tick().await;
+                let _ = service.schedule_purges().await;
+            }
+        });
+
+        match self.default_retention_days {
+            0 => tracing::info!("Bin is not purged by default"),
+            days => tracing::info!(
+                "Purging files which are in the bin for more than {} days",
+                days
+            ),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::io;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use sha2::{Digest, Sha256};
+use sonyflake::Sonyflake;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::db::KosmosPool;
+use crate::response::error_handling::AppError;
+use crate::storage;
+use crate::storage::KosmosStorage;
+
+/// Hashes all data read through it and fails once more than the limit was read
+struct HashingReader<'a> {
+    inner: &'a mut (dyn AsyncRead + Send + Unpin),
+    hasher: Sha256,
+    limit: u64,
+    read: u64,
+    exceeded: bool,
+}
+
+impl AsyncRead for HashingReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled_before = buf.filled().len();
+        let result = Pin::new(&mut *self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = result {
+            let read = &buf.filled()[filled_before..];
+            self.read += read.len() as u64;
+
+            if self.read > self.limit {
+                self.exceeded = true;
+                return Poll::Ready(Err(io::Error::other("Content exceeds the size limit")));
+            }
+
+            self.hasher.update(read);
+        }
+
+        result
+    }
+}
+
+pub struct StoredBlob {
+    pub hash: String,
+    pub size: i64,
+}
+
+#[derive(Clone)]
+pub struct BlobService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+    storage: KosmosStorage,
+}
+
+impl BlobService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake, storage: KosmosStorage) -> Self {
+        BlobService {
+            db_pool,
+            sf,
+            storage,
+        }
+    }
+
+    /// Stores the content of the reader once per content hash and takes a reference on the blob.
+    /// Every stored blob has to be released again when the referencing file is removed.
+    pub async fn store(
+        &self,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> Result<StoredBlob, AppError> {
+        self.store_limited(reader, u64::MAX)
+            .await?
+            .ok_or(AppError::InternalError)
+    }
+
+    /// Like `store`, but reading stops once the content is larger than the limit. Such content
+    /// is discarded and `None` returned, so untrusted uploads never take more space than allowed.
+    pub async fn store_limited(
+        &self,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+        limit: u64,
+    ) -> Result<Option<StoredBlob>, AppError> {
+        let staging_key =
+            storage::staging_key(self.sf.next_id().map_err(|_| AppError::InternalError)? as i64);
+
+        let mut hashing_reader = HashingReader {
+            inner: reader,
+            hasher: Sha256::new(),
+            limit,
+            read: 0,
+            exceeded: false,
+        };
+
+        let size = match self.storage.put(&staging_key, &mut hashing_reader).await {
+            Ok(size) => size as i64,
+            Err(_) if hashing_reader.exceeded => {
+                let _ = self.storage.delete(&staging_key).await;
+                return Ok(None);
+            }
+            Err(e) => {
+                tracing::error!("Error writing blob to storage: {}", e);
+                let _ = self.storage.delete(&staging_key).await;
+                return Err(AppError::InternalError);
+            }
+        };
+
+        let hash = format!("{:x}", hashing_reader.hasher.finalize());
+
+        let mut transaction = match self.db_pool.begin().await {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                tracing::error!("Error starting transaction: {}", e);
+                let _ = self.storage.delete(&staging_key).await;
+                return Err(AppError::InternalError);
+            }
+        };
+
+        // Concurrent uploads of the same content wait on the row until this transaction ends,
+        // so they only rely on the blob once its content is in place
+        let inserted = sqlx::query_scalar!(
+            "INSERT INTO blobs (hash, size) VALUES ($1, $2)
+             ON CONFLICT (hash) DO UPDATE SET ref_count = blobs.ref_count + 1
+             RETURNING (xmax = 0) AS inserted",
+            hash,
+            size
+        )
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error referencing blob {}: {}", hash, e);
+            AppError::InternalError
+        });
+
+        let inserted = match inserted {
+            Ok(inserted) => inserted.unwrap_or(false),
+            Err(e) => {
+                let _ = self.storage.delete(&staging_key).await;
+                return Err(e);
+            }
+        };
+
+        if !inserted {
+            // The content is already stored, the new copy is not needed
+            let _ = self.storage.delete(&staging_key).await;
+        } else if let Err(e) = self
+            .storage
+            .rename(&staging_key, &storage::blob_key(&hash))
+            .await
+        {
+            // Dropping the transaction removes the row again
+            tracing::error!("Error moving blob {} into place: {}", hash, e);
+            let _ = self.storage.delete(&staging_key).await;
+            return Err(AppError::InternalError);
+        }
+
+        transaction.commit().await.map_err(|e| {
+            tracing::error!("Error committing blob {}: {}", hash, e);
+            AppError::InternalError
+        })?;
+
+        Ok(Some(StoredBlob { hash, size }))
+    }
+
+    pub async fn store_bytes(&self, data: Vec<u8>) -> Result<StoredBlob, AppError> {
+        self.store(&mut Cursor::new(data)).await
+    }
+
+    /// Takes another reference on a blob that is already referenced by a file
+    pub async fn add_reference(&self, hash: &str) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "UPDATE blobs SET ref_count = ref_count + 1 WHERE hash = $1",
+            hash
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error referencing blob {}: {}", hash, e);
+            AppError::InternalError
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound {
+                error: "Blob not found".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Drops a reference on the blob and removes it once nothing references it anymore
+    pub async fn release(&self, hash: &str) -> Result<(), AppError> {
+        let mut transaction = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        let ref_count = sqlx::query_scalar!(
+            "UPDATE blobs SET ref_count = ref_count - 1 WHERE hash = $1 RETURNING ref_count",
+            hash
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error releasing blob {}: {}", hash, e);
+            AppError::InternalError
+        })?;
+
+        if ref_count.is_some_and(|count| count <= 0) {
+            sqlx::query!("DELETE FROM blobs WHERE hash = $1", hash)
+                .execute(&mut *transaction)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error deleting blob {}: {}", hash, e);
+                    AppError::InternalError
+                })?;
+
+            // Removed while the row is still locked, so a concurrent upload of the same
+            // content waits and stores the blob again afterwards
+            match self.storage.delete(&storage::blob_key(hash)).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    tracing::error!("Error deleting blob {} from storage: {}", hash, e);
+                    return Err(AppError::InternalError);
+                }
+                _ => {}
+            }
+        }
+
+        transaction.commit().await.map_err(|e| {
+            tracing::error!("Error committing blob release {}: {}", hash, e);
+            AppError::InternalError
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use sqlx::types::Uuid;
+
+use crate::db::KosmosPool;
+use crate::model::dav::{is_descendant, DavLockModel};
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+#[derive(Clone)]
+pub struct DavService {
+    db_pool: KosmosPool,
+}
+
+impl DavService {
+    pub fn new(db_pool: KosmosPool) -> Self {
+        DavService { db_pool }
+    }
+
+    pub async fn get_locks(&self, user_id: UserId) -> Result<Vec<DavLockModel>, AppError> {
+        sqlx::query_as!(
+            DavLockModel,
+            "SELECT * FROM dav_locks WHERE user_id = $1 AND expires_at > now()",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting locks for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn create_lock(
+        &self,
+        user_id: UserId,
+        path: &str,
+        exclusive: bool,
+        depth_infinity: bool,
+        owner: Option<String>,
+        timeout: i32,
+    ) -> Result<DavLockModel, AppError> {
+        self.delete_expired_locks().await?;
+
+        sqlx::query_as!(
+            DavLockModel,
+            "INSERT INTO dav_locks (user_id, path, exclusive, depth_infinity, owner, timeout, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6::INT, now() + make_interval(secs => $6::INT))
+             RETURNING *",
+            user_id,
+            path,
+            exclusive,
+            depth_infinity,
+            owner,
+            timeout
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating lock on {} for user {}: {}", path, user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn refresh_lock(
+        &self,
+        user_id: UserId,
+        token: Uuid,
+        timeout: i32,
+    ) -> Result<Option<DavLockModel>, AppError> {
+        sqlx::query_as!(
+            DavLockModel,
+            "UPDATE dav_locks SET timeout = $1::INT, expires_at = now() + make_interval(secs => $1::INT)
+             WHERE token = $2 AND user_id = $3 AND expires_at > now()
+             RETURNING *",
+            timeout,
+            token,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error refreshing lock {}: {}", token, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn delete_lock(&self, user_id: UserId, token: Uuid) -> Result<bool, AppError> {
+        sqlx::query!(
+            "DELETE FROM dav_locks WHERE token = $1 AND user_id = $2",
+            token,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .map_err(|e| {
+            tracing::error!("Error deleting lock {}: {}", token, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Removes the locks of a resource that no longer exists at the path, including its members
+    pub async fn delete_locks_at(&self, user_id: UserId, path: &str) -> Result<(), AppError> {
+        let tokens = self
+            .get_locks(user_id)
+            .await?
+            .into_iter()
+            .filter(|lock| lock.path == path || is_descendant(&lock.path, path))
+            .map(|lock| lock.token)
+            .collect::<Vec<_>>();
+
+        sqlx::query!("DELETE FROM dav_locks WHERE token = ANY($1)", &tokens)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting locks on {}: {}", path, e);
+                AppError::InternalError
+            })?;
+
+        Ok(())
+    }
+
+    async fn delete_expired_locks(&self) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM dav_locks WHERE expires_at <= now()")
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting expired locks: {}", e);
+                AppError::InternalError
+            })?;
+
+        Ok(())
+    }
+
+    pub async fn startup_prepare(&self) {
+        let _ = self.delete_expired_locks().await;
+        tracing::info!("Removed expired WebDAV locks");
+    }
+}
@@ -3,34 +3,44 @@ use crate::model::file::FileModel;
 use crate::model::image::ImageFormatModel;
 use crate::model::internal::file_type::FileType;
 use crate::model::internal::preview_status::PreviewStatus;
+use crate::model::version::FileVersionModel;
 use crate::response::error_handling::AppError;
+use crate::routes::api::v1::auth::file::FILE_SIZE_LIMIT;
 use crate::routes::api::v1::auth::file::{
     GetFilesSortParams, GetRecentFilesParams, SortByFiles, SortOrder,
 };
+use crate::services::blob_service::{BlobService, StoredBlob};
 use crate::services::image_service::ImageService;
 use crate::services::session_service::UserId;
+use crate::services::version_service::VersionService;
+use crate::storage;
+use crate::storage::KosmosStorage;
 use itertools::Itertools;
 use sonyflake::Sonyflake;
 use sqlx::{Execute, QueryBuilder};
-use std::path::{Path, PathBuf};
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
 
 #[derive(Clone)]
 pub struct FileService {
     db_pool: KosmosPool,
-    pub upload_path: PathBuf,
+    pub storage: KosmosStorage,
+    blob_service: BlobService,
+    version_service: VersionService,
     sf: Sonyflake,
 }
 
 impl FileService {
-    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
-        let upload_location =
-            std::env::var("UPLOAD_LOCATION").expect("File service is missing UPLOAD_LOCATION");
-        let upload_path = Path::new(&upload_location).to_path_buf();
+    pub fn new(
+        db_pool: KosmosPool,
+        sf: Sonyflake,
+        storage: KosmosStorage,
+        blob_service: BlobService,
+        version_service: VersionService,
+    ) -> Self {
         FileService {
             db_pool,
-            upload_path,
+            storage,
+            blob_service,
+            version_service,
             sf,
         }
     }
@@ -154,32 +164,33 @@ impl FileService {
         let file_type = FileService::get_file_type(mime_type, &file_name);
         let file_type = file_type.file_type;
 
-        tokio::fs::File::create(self.upload_path.join(id.to_string()))
-            .await
-            .map_err(|e| {
-                tracing::error!("Error creating empty file for user {}: {}", user_id, e);
-                AppError::InternalError
-            })?;
+        let blob = self.blob_service.store_bytes(vec![]).await?;
 
-        sqlx::query_as!(
+        let file = sqlx::query_as!(
             FileModel,
-            "INSERT INTO files (id, user_id, parent_folder_id, file_name, file_type, mime_type,file_size)
-             VALUES ($1, $2, $3, $4, $5, $6, 0)
+            "INSERT INTO files (id, user_id, parent_folder_id, file_name, file_type, mime_type, file_size, blob_hash)
+             VALUES ($1, $2, $3, $4, $5, $6, 0, $7)
              RETURNING *",
             id,
             user_id,
             parent_folder_id,
             file_name,
             file_type as i16,
-            mime_type
+            mime_type,
+            blob.hash
         )
         .fetch_one(&self.db_pool)
         .await
         .map_err(|e| {
             tracing::error!("Error creating empty file for user {}: {}", user_id, e);
-            let _ = tokio::fs::remove_file(self.upload_path.join(id.to_string()));
             AppError::InternalError
-        })
+        });
+
+        if file.is_err() {
+            let _ = self.blob_service.release(&blob.hash).await;
+        }
+
+        file
     }
 
     pub async fn get_files_by_file_type(
@@ -311,16 +322,20 @@ impl FileService {
         .map(|rows| rows.into_iter().map(FileModel::from).collect())
     }
 
+    /// Files moved to the bin on their own, or the ones in a folder in the bin if it is given
     pub async fn get_marked_deleted_files(
         &self,
         user_id: UserId,
+        folder_id: Option<i64>,
     ) -> Result<Vec<FileModel>, AppError> {
         sqlx::query_as!(
             FileModel,
             "SELECT * FROM files WHERE user_id = $1
              AND deleted_at IS NOT NULL
+             AND (($2::BIGINT IS NULL AND deleted_with IS NULL) OR parent_folder_id = $2)
              ORDER BY deleted_at DESC",
-            user_id
+            user_id,
+            folder_id
         )
         .fetch_all(&self.db_pool)
         .await
@@ -350,33 +365,40 @@ impl FileService {
         Ok(())
     }
 
+    /// Creates the file with the stored content, the file takes over the reference on the blob
     pub async fn create_file(
         &self,
         user_id: UserId,
         file_id: i64,
         file_name: String,
-        file_size: i64,
-        file_type: FileType,
-        mime_type: String,
         parent_folder_id: Option<i64>,
+        file_type: &GetFileTypeResponse,
+        blob: StoredBlob,
     ) -> Result<i64, AppError> {
-        sqlx::query!(
-            "INSERT INTO files (id, user_id, file_name, file_size, file_type, mime_type, parent_folder_id) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+        let row = sqlx::query!(
+            "INSERT INTO files (id, user_id, file_name, file_size, file_type, mime_type, parent_folder_id, blob_hash) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
             file_id,
             user_id,
             file_name,
-            file_size,
-            file_type as i16,
-            mime_type,
-            parent_folder_id
+            blob.size,
+            file_type.file_type as i16,
+            file_type.normalized_mime_type,
+            parent_folder_id,
+            blob.hash
         )
             .fetch_one(&self.db_pool)
             .await
             .map_err(|e| {
                 tracing::error!("Error creating file {}: {}", file_id, e);
                 AppError::InternalError
-            })
-            .map(|row| row.id)
+            });
+
+        // The file owns the reference on the blob, which is dropped if it can't be created
+        if row.is_err() {
+            let _ = self.blob_service.release(&blob.hash).await;
+        }
+
+        row.map(|row| row.id)
     }
 
     /// Move file that is known to exist for the current user
@@ -516,6 +538,102 @@ impl FileService {
         Ok(result)
     }
 
+    pub async fn get_file_by_name(
+        &self,
+        user_id: UserId,
+        parent_folder_id: Option<i64>,
+        file_name: &str,
+    ) -> Result<Option<FileModel>, AppError> {
+        sqlx::query_as!(
+            FileModel,
+            "SELECT * FROM files WHERE file_name = $1
+             AND user_id = $2
+             AND parent_folder_id IS NOT DISTINCT FROM $3
+             AND deleted_at IS NULL
+             LIMIT 1",
+            file_name,
+            user_id,
+            parent_folder_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting file {} by name: {}", file_name, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_child_files(
+        &self,
+        user_id: UserId,
+        parent_folder_id: Option<i64>,
+    ) -> Result<Vec<FileModel>, AppError> {
+        sqlx::query_as!(
+            FileModel,
+            "SELECT * FROM files WHERE user_id = $1
+             AND parent_folder_id IS NOT DISTINCT FROM $2
+             AND deleted_at IS NULL
+             ORDER BY file_name",
+            user_id,
+            parent_folder_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting child files of {:?}: {}", parent_folder_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Moves and renames the file at once, the destination is expected to be free
+    pub async fn relocate_file(
+        &self,
+        user_id: UserId,
+        file_id: i64,
+        file_name: &str,
+        parent_folder_id: Option<i64>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE files SET file_name = $1, parent_folder_id = $2 WHERE id = $3 AND user_id = $4",
+            file_name,
+            parent_folder_id,
+            file_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error relocating file {}: {}", file_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    /// Takes another reference on the content of the file, e.g. to create a copy of it
+    pub async fn copy_content(&self, file: &FileModel) -> Result<StoredBlob, AppError> {
+        match &file.blob_hash {
+            Some(hash) => {
+                self.blob_service.add_reference(hash).await?;
+                Ok(StoredBlob {
+                    hash: hash.clone(),
+                    size: file.file_size,
+                })
+            }
+            None => {
+                let mut reader = self
+                    .storage
+                    .get(&file.content_key())
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Error reading file {} from storage: {}", file.id, e);
+                        AppError::InternalError
+                    })?;
+                self.blob_service.store(&mut reader).await
+            }
+        }
+    }
+
     pub async fn mark_file_for_deletion(
         &self,
         file_id: i64,
@@ -535,14 +653,22 @@ impl FileService {
         Ok(())
     }
 
+    /// Puts the file back into its folder, or into the root if the folder is in the bin
     pub async fn restore_file(&self, file_id: i64) -> Result<(), AppError> {
-        sqlx::query!("UPDATE files SET deleted_at = null WHERE id = $1", file_id)
-            .execute(&self.db_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Error restoring file {} from deletion: {}", file_id, e);
-                AppError::InternalError
-            })?;
+        sqlx::query!(
+            "UPDATE files f
+             SET deleted_at       = null,
+                 deleted_with     = null,
+                 parent_folder_id = (SELECT p.id FROM folder p WHERE p.id = f.parent_folder_id AND p.deleted_at IS NULL)
+             WHERE f.id = $1",
+            file_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error restoring file {} from deletion: {}", file_id, e);
+            AppError::InternalError
+        })?;
         Ok(())
     }
 
@@ -557,31 +683,75 @@ impl FileService {
         Ok(())
     }
 
+    pub async fn get_format_sources(
+        &self,
+        file_ids: &[i64],
+    ) -> Result<Vec<FormatSource>, AppError> {
+        sqlx::query!(
+            "SELECT id, file_type, mime_type, file_name, blob_hash FROM files WHERE id = ANY($1)",
+            file_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| FormatSource {
+                    file_id: row.id,
+                    file_type: FileType::from(row.file_type),
+                    content_key: storage::content_key(row.id, row.blob_hash.as_deref()),
+                    mime_type: row.mime_type,
+                    file_name: row.file_name,
+                })
+                .collect()
+        })
+        .map_err(|e| {
+            tracing::error!("Error getting content keys of files: {}", e);
+            AppError::InternalError
+        })
+    }
+
     pub async fn permanently_delete_file(
         &self,
         file_id: i64,
         file_type: Option<FileType>,
     ) -> Result<(), AppError> {
-        if let Some(FileType::Image) = file_type {
+        if let Some(FileType::Image | FileType::Video | FileType::Document) = file_type {
             self.delete_formats_from_file_id(file_id).await?;
         }
 
-        tokio::fs::remove_file(self.upload_path.join(file_id.to_string()))
-            .await
-            .map_err(|e| {
-                tracing::error!("Error deleting file {} from storage: {}", file_id, e);
-                AppError::InternalError
-            })?;
+        self.version_service.delete_versions_for_file(file_id).await?;
 
-        sqlx::query!("DELETE FROM files WHERE id = $1", file_id)
-            .execute(&self.db_pool)
-            .await
-            .map_err(|e| {
-                tracing::error!("Error deleting file {} from database: {}", file_id, e);
-                AppError::InternalError
-            })?;
+        let deleted = sqlx::query!(
+            "DELETE FROM files WHERE id = $1 RETURNING blob_hash",
+            file_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting file {} from database: {}", file_id, e);
+            AppError::InternalError
+        })?;
 
-        Ok(())
+        let Some(deleted) = deleted else {
+            return Ok(());
+        };
+
+        self.release_content(file_id, deleted.blob_hash).await
+    }
+
+    /// Removes the stored content of a file, shared blobs are only removed with their last reference
+    async fn release_content(&self, file_id: i64, blob_hash: Option<String>) -> Result<(), AppError> {
+        match blob_hash {
+            Some(hash) => self.blob_service.release(&hash).await,
+            None => self
+                .storage
+                .delete(&storage::file_key(file_id))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error deleting file {} from storage: {}", file_id, e);
+                    AppError::InternalError
+                }),
+        }
     }
 
     pub async fn delete_formats_from_file_id(&self, file_id: i64) -> Result<(), AppError> {
@@ -604,16 +774,15 @@ impl FileService {
             return Ok(());
         }
 
-        let formats_folder_path = self.upload_path.join("formats");
-
         for format_model in formats {
-            let format_path = formats_folder_path.join(ImageService::make_image_format_name(
+            let format_key = storage::format_key(&ImageService::make_format_name(
                 file_id,
                 format_model.format,
+                format_model.codec,
             ));
 
-            // Delete image format from disk
-            let _ = tokio::fs::remove_file(&format_path).await.map_err(|e| {
+            // Delete image format from storage
+            let _ = self.storage.delete(&format_key).await.map_err(|e| {
                 tracing::error!("Error while deleting image format: {}", e);
             });
 
@@ -663,40 +832,209 @@ impl FileService {
         Ok(())
     }
 
-    pub async fn update_file_size(&self, file_id: i64, size: i64) -> Result<(), AppError> {
+    pub async fn update_file_content(&self, file: &FileModel, content: String) -> Result<(), AppError> {
+        // Blobs can be shared with other files, so new content is always stored as a new blob
+        let blob = self.blob_service.store_bytes(content.into_bytes()).await?;
+
+        self.replace_file_content(
+            file,
+            blob,
+            file.file_type,
+            file.mime_type.clone(),
+        )
+        .await
+    }
+
+    /// Replaces the content of a file with a stored blob, the previous content is kept as a version.
+    /// The file takes over the reference on the blob.
+    pub async fn replace_file_content(
+        &self,
+        file: &FileModel,
+        blob: StoredBlob,
+        file_type: FileType,
+        mime_type: String,
+    ) -> Result<(), AppError> {
+        let swapped = self
+            .swap_content(file, &blob.hash, blob.size, file_type, mime_type, None)
+            .await;
+
+        if swapped.is_err() {
+            let _ = self.blob_service.release(&blob.hash).await;
+        }
+
+        swapped
+    }
+
+    /// Makes a previous version the current content of the file, the current content becomes a version
+    pub async fn restore_version(
+        &self,
+        file: &FileModel,
+        version: &FileVersionModel,
+    ) -> Result<FileType, AppError> {
+        let mut file_type = FileService::get_file_type(&version.mime_type, &file.file_name).file_type;
+
+        if file_type == FileType::Image && version.file_size as u64 > FILE_SIZE_LIMIT {
+            file_type = FileType::LargeImage;
+        }
+
+        self.swap_content(
+            file,
+            &version.blob_hash,
+            version.file_size,
+            file_type,
+            version.mime_type.clone(),
+            Some(version.id),
+        )
+        .await?;
+
+        Ok(file_type)
+    }
+
+    /// Archives the current content of the file as a version and points the file at the new blob.
+    /// A restored version is removed in the same transaction, its blob reference moves to the file.
+    async fn swap_content(
+        &self,
+        file: &FileModel,
+        blob_hash: &str,
+        file_size: i64,
+        file_type: FileType,
+        mime_type: String,
+        restored_version_id: Option<i64>,
+    ) -> Result<(), AppError> {
+        // Files from before deduplication are stored by id and moved into a blob to be versioned
+        let (previous_hash, converted) = match &file.blob_hash {
+            Some(hash) => (hash.clone(), false),
+            None => {
+                let mut reader = self
+                    .storage
+                    .get(&storage::file_key(file.id))
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Error reading file {} from storage: {}", file.id, e);
+                        AppError::InternalError
+                    })?;
+                (self.blob_service.store(&mut reader).await?.hash, true)
+            }
+        };
+
+        let version_id = self.sf.next_id().map_err(|_| AppError::InternalError)? as i64;
+
+        let swapped = self
+            .swap_content_transaction(
+                file,
+                version_id,
+                &previous_hash,
+                blob_hash,
+                file_size,
+                file_type,
+                mime_type,
+                restored_version_id,
+            )
+            .await;
+
+        if let Err(e) = swapped {
+            if converted {
+                let _ = self.blob_service.release(&previous_hash).await;
+            }
+            return Err(e);
+        }
+
+        if converted {
+            let _ = self.storage.delete(&storage::file_key(file.id)).await;
+        }
+
+        // Formats of the previous content are outdated, callers queue new ones for the new content
+        if let FileType::Image | FileType::Video | FileType::Document = file.file_type {
+            self.delete_formats_from_file_id(file.id).await?;
+        }
+
+        self.version_service.apply_retention(Some(file.user_id)).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn swap_content_transaction(
+        &self,
+        file: &FileModel,
+        version_id: i64,
+        previous_hash: &str,
+        blob_hash: &str,
+        file_size: i64,
+        file_type: FileType,
+        mime_type: String,
+        restored_version_id: Option<i64>,
+    ) -> Result<(), AppError> {
+        let mut transaction = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        // The version takes over the reference the file held on the previous blob
         sqlx::query!(
-            "UPDATE files SET file_size = $1 WHERE id = $2",
-            size,
-            file_id
+            "INSERT INTO file_versions (id, file_id, user_id, blob_hash, file_size, mime_type, modified_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            version_id,
+            file.id,
+            file.user_id,
+            previous_hash,
+            file.file_size,
+            file.mime_type,
+            file.updated_at
         )
-        .execute(&self.db_pool)
+        .execute(&mut *transaction)
         .await
         .map_err(|e| {
-            tracing::error!("Error while updating file size: {}", e);
+            tracing::error!("Error creating version of file {}: {}", file.id, e);
             AppError::InternalError
         })?;
-        Ok(())
-    }
 
-    pub async fn update_file_content(&self, file_id: i64, content: String) -> Result<(), AppError> {
-        let file_path = self.upload_path.join(file_id.to_string());
-        let mut file = OpenOptions::new()
-            .write(true)
-            .open(file_path)
+        // Replacing a file in the bin brings it back, like an upload of a new file would
+        let updated = sqlx::query!(
+            "UPDATE files SET blob_hash = $1, file_size = $2, file_type = $3, mime_type = $4, deleted_at = NULL
+             WHERE id = $5 AND blob_hash IS NOT DISTINCT FROM $6",
+            blob_hash,
+            file_size,
+            file_type as i16,
+            mime_type,
+            file.id,
+            file.blob_hash
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error while updating file content: {}", e);
+            AppError::InternalError
+        })?;
+
+        if updated.rows_affected() != 1 {
+            return Err(AppError::DataConflict {
+                error: "File content was changed in the meantime".to_string(),
+            });
+        }
+
+        if let Some(restored_version_id) = restored_version_id {
+            let deleted = sqlx::query!(
+                "DELETE FROM file_versions WHERE id = $1 AND file_id = $2",
+                restored_version_id,
+                file.id
+            )
+            .execute(&mut *transaction)
             .await
             .map_err(|e| {
-                tracing::error!("Error while updating file content: {}", e);
+                tracing::error!("Error removing restored version {}: {}", restored_version_id, e);
                 AppError::InternalError
             })?;
 
-        file.write_all(content.as_bytes()).await.map_err(|e| {
-            tracing::error!("Error while updating file content: {}", e);
-            AppError::InternalError
-        })?;
-
-        self.update_file_size(file_id, content.len() as i64).await?;
+            if deleted.rows_affected() != 1 {
+                return Err(AppError::DataConflict {
+                    error: "Version was removed in the meantime".to_string(),
+                });
+            }
+        }
 
-        Ok(())
+        transaction.commit().await.map_err(|e| {
+            tracing::error!("Error committing content of file {}: {}", file.id, e);
+            AppError::InternalError
+        })
     }
 
     pub async fn startup_prepare(&self) {
@@ -715,6 +1053,15 @@ impl FileService {
     }
 }
 
+/// A file the formats and the search index are generated from
+pub struct FormatSource {
+    pub file_id: i64,
+    pub file_type: FileType,
+    pub mime_type: String,
+    pub file_name: String,
+    pub content_key: String,
+}
+
 pub struct GetFileTypeResponse {
     pub file_type: FileType,
     pub normalized_mime_type: String,
@@ -754,7 +1101,7 @@ const TYPES_BY_EXTENSION: [(&str, FileType, &str); 5] = [
     (".txt", FileType::Editable, "text/plain"),
 ];
 
-const TYPES_BY_MIME: [(&str, FileType); 45] = [
+const TYPES_BY_MIME: [(&str, FileType); 44] = [
     ("image/gif", FileType::Image),
     ("image/jpeg", FileType::Image),
     ("image/png", FileType::Image),
@@ -795,7 +1142,6 @@ const TYPES_BY_MIME: [(&str, FileType); 45] = [
     ("application/zip", FileType::Archive),
     ("application/tar", FileType::Archive),
     ("application/rar", FileType::Archive),
-    ("application-7z", FileType::Archive),
     ("application/x-bzip", FileType::Archive),
     ("application/x-bzip2", FileType::Archive),
     ("application/x-gzip", FileType::Archive),
@@ -2,6 +2,7 @@ use sonyflake::Sonyflake;
 use sqlx::QueryBuilder;
 
 use crate::db::{KosmosDb, KosmosDbResult, KosmosPool};
+use crate::model::internal::file_type::FileType;
 use crate::model::folder::{
     DeletionDirectory, Directory, DirectoryWithShare, FolderModel, SimpleDirectory,
 };
@@ -34,6 +35,8 @@ impl FolderService {
         query.push(" AND parent_id IS NOT DISTINCT FROM ");
         query.push_bind(parent_id);
 
+        query.push(" AND deleted_at IS NULL");
+
         query.push(" ORDER BY CASE WHEN favorite = true THEN 0 ELSE 1 END, ");
 
         let sort_by = search.get_sort_by();
@@ -90,7 +93,7 @@ impl FolderService {
     pub async fn get_favorites(&self, user_id: UserId) -> Result<Vec<FolderModel>, AppError> {
         sqlx::query_as!(
             FolderModel,
-            "SELECT * FROM folder WHERE user_id = $1 AND favorite = true",
+            "SELECT * FROM folder WHERE user_id = $1 AND favorite = true AND deleted_at IS NULL",
             user_id
         )
         .fetch_all(&self.db_pool)
@@ -106,7 +109,7 @@ impl FolderService {
         parent_id: &Option<i64>,
     ) -> Result<Vec<FolderModel>, AppError> {
         sqlx::query_as::<_, FolderModel>(
-            "SELECT * FROM folder WHERE parent_id IS NOT DISTINCT FROM $1",
+            "SELECT * FROM folder WHERE parent_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL",
         )
         .bind(parent_id)
         .fetch_all(&self.db_pool)
@@ -119,7 +122,11 @@ impl FolderService {
     }
 
     pub async fn get_folder(&self, folder_id: i64) -> Result<FolderModel, AppError> {
-        sqlx::query_as!(FolderModel, "SELECT * FROM folder WHERE id = $1", folder_id)
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT * FROM folder WHERE id = $1 AND deleted_at IS NULL",
+            folder_id
+        )
             .fetch_one(&self.db_pool)
             .await
             .map_err(|e| {
@@ -234,7 +241,7 @@ impl FolderService {
         folder_name: &String,
         destination_folder_id: Option<i64>,
     ) -> Result<bool, AppError> {
-        sqlx::query!("SELECT id FROM folder WHERE folder_name = $1 AND parent_id IS NOT DISTINCT FROM $2 LIMIT 1", folder_name, destination_folder_id)
+        sqlx::query!("SELECT id FROM folder WHERE folder_name = $1 AND parent_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL LIMIT 1", folder_name, destination_folder_id)
             .fetch_optional(&self.db_pool)
             .await
             .map_err(|e| {
@@ -281,7 +288,7 @@ impl FolderService {
         parent_folder_id: Option<i64>,
     ) -> Result<Option<i64>, AppError> {
         let result = sqlx::query!(
-            "SELECT id FROM folder WHERE folder_name = $1 AND user_id = $2 AND parent_id IS NOT DISTINCT FROM $3 LIMIT 1",
+            "SELECT id FROM folder WHERE folder_name = $1 AND user_id = $2 AND parent_id IS NOT DISTINCT FROM $3 AND deleted_at IS NULL LIMIT 1",
             folder_name,
             user_id,
             parent_folder_id
@@ -296,24 +303,69 @@ impl FolderService {
         Ok(result)
     }
 
-    pub async fn check_folder_contains_elements(&self, folder_id: i64) -> Result<bool, AppError> {
-        let result = sqlx::query!(
-            "SELECT COUNT(*) FROM files WHERE parent_folder_id = $1 AND deleted_at IS NULL",
-            folder_id
+    pub async fn get_folder_by_name(
+        &self,
+        user_id: UserId,
+        parent_id: Option<i64>,
+        folder_name: &str,
+    ) -> Result<Option<FolderModel>, AppError> {
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT * FROM folder WHERE folder_name = $1 AND user_id = $2 AND parent_id IS NOT DISTINCT FROM $3 AND deleted_at IS NULL LIMIT 1",
+            folder_name,
+            user_id,
+            parent_id
         )
-        .fetch_one(&self.db_pool)
+        .fetch_optional(&self.db_pool)
         .await
         .map_err(|e| {
-            tracing::error!(
-                "Error checking if folder {} contains elements: {}",
-                folder_id,
-                e
-            );
+            tracing::error!("Error getting folder {} by name: {}", folder_name, e);
             AppError::InternalError
-        })?
-        .count
-        .unwrap_or(0);
-        Ok(result > 0)
+        })
+    }
+
+    pub async fn get_child_folders(
+        &self,
+        user_id: UserId,
+        parent_id: Option<i64>,
+    ) -> Result<Vec<FolderModel>, AppError> {
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT * FROM folder WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL ORDER BY folder_name",
+            user_id,
+            parent_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting child folders of {:?}: {}", parent_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Moves and renames the folder at once, the destination is expected to be free
+    pub async fn relocate_folder(
+        &self,
+        user_id: UserId,
+        folder_id: i64,
+        folder_name: &str,
+        parent_id: Option<i64>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE folder SET folder_name = $1, parent_id = $2 WHERE id = $3 AND user_id = $4",
+            folder_name,
+            parent_id,
+            folder_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error relocating folder {}: {}", folder_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
     }
 
     pub async fn check_folder_exists_by_id(
@@ -323,7 +375,7 @@ impl FolderService {
     ) -> Result<Option<FolderModel>, AppError> {
         let result = sqlx::query_as!(
             FolderModel,
-            "SELECT * from folder WHERE id = $1 AND user_id = $2",
+            "SELECT * from folder WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
             folder_id,
             user_id
         )
@@ -374,6 +426,208 @@ impl FolderService {
         Ok(structure)
     }
 
+    /// Ids of the folder and all folders below it, including the ones in the bin
+    async fn get_subtree_ids(&self, folder_id: i64, user_id: UserId) -> Result<Vec<i64>, AppError> {
+        sqlx::query_scalar!(
+            r#"WITH RECURSIVE subtree AS (SELECT f.id
+                                          FROM folder f
+                                          WHERE f.id = $1
+                                            AND f.user_id = $2
+                                          UNION ALL
+                                          SELECT f.id
+                                          FROM folder f
+                                                   JOIN subtree s ON f.parent_id = s.id)
+               SELECT id AS "id!" FROM subtree"#,
+            folder_id,
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting subtree of folder {}: {}", folder_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_deleted_folder(
+        &self,
+        folder_id: i64,
+        user_id: UserId,
+    ) -> Result<Option<FolderModel>, AppError> {
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT * FROM folder WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL",
+            folder_id,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting deleted folder {}: {}", folder_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Folders moved to the bin on their own, or the ones in a folder in the bin if a parent is given
+    pub async fn get_deleted_folders(
+        &self,
+        user_id: UserId,
+        parent_id: Option<i64>,
+    ) -> Result<Vec<FolderModel>, AppError> {
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT * FROM folder
+             WHERE user_id = $1
+               AND deleted_at IS NOT NULL
+               AND (($2::BIGINT IS NULL AND deleted_with IS NULL) OR parent_id = $2)
+             ORDER BY deleted_at DESC",
+            user_id,
+            parent_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting deleted folders for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Moves the folder with everything below it to the bin. Items which are already in the bin
+    /// keep their own deletion, so restoring the folder doesn't bring them back.
+    pub async fn mark_folder_for_deletion(
+        &self,
+        folder_id: i64,
+        user_id: UserId,
+    ) -> Result<(), AppError> {
+        let subtree = self.get_subtree_ids(folder_id, user_id).await?;
+
+        let mut tx = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "UPDATE folder
+             SET deleted_at   = now(),
+                 deleted_with = CASE WHEN id = $1 THEN NULL ELSE $1 END
+             WHERE id = ANY($2) AND deleted_at IS NULL",
+            folder_id,
+            &subtree
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error marking folder {} for deletion: {}", folder_id, e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "UPDATE files SET deleted_at = now(), deleted_with = $1
+             WHERE parent_folder_id = ANY($2) AND deleted_at IS NULL",
+            folder_id,
+            &subtree
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error marking files of folder {} for deletion: {}", folder_id, e);
+            AppError::InternalError
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            tracing::error!("Error committing deletion of folder {}: {}", folder_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Restores the folder with the items it was moved to the bin with. It goes back to its
+    /// parent, or to the root if the parent is gone or in the bin itself.
+    pub async fn restore_folder(&self, folder: &FolderModel) -> Result<(), AppError> {
+        let parent_id = match folder.parent_id {
+            Some(parent_id) => self
+                .check_folder_exists_by_id(parent_id, folder.user_id)
+                .await?
+                .map(|parent| parent.id),
+            None => None,
+        };
+
+        if self
+            .check_folder_exists_by_name(&folder.folder_name, folder.user_id, parent_id)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::DataConflict {
+                error: format!("Folder {} already exists where it is restored to", folder.folder_name),
+            });
+        }
+
+        let deleted_with = folder.deleted_with.unwrap_or(folder.id);
+        let subtree = self.get_subtree_ids(folder.id, folder.user_id).await?;
+
+        let mut tx = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "UPDATE folder
+             SET deleted_at   = NULL,
+                 deleted_with = NULL,
+                 parent_id    = CASE WHEN id = $1 THEN $4 ELSE parent_id END
+             WHERE id = ANY($2) AND (id = $1 OR deleted_with = $3)",
+            folder.id,
+            &subtree,
+            deleted_with,
+            parent_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error restoring folder {}: {}", folder.id, e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "UPDATE files SET deleted_at = NULL, deleted_with = NULL
+             WHERE parent_folder_id = ANY($1) AND deleted_with = $2",
+            &subtree,
+            deleted_with
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error restoring files of folder {}: {}", folder.id, e);
+            AppError::InternalError
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            tracing::error!("Error committing restore of folder {}: {}", folder.id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Removes the folder, its subfolders and all files in them for good
+    pub async fn permanently_delete_folder(
+        &self,
+        folder_id: i64,
+        user_id: UserId,
+        file_service: &FileService,
+    ) -> Result<(), AppError> {
+        let structure = self.get_deletion_directories(folder_id, user_id).await?;
+
+        for folder in structure {
+            for (file_id, file_type) in folder.file_ids.iter().zip(folder.file_types.iter()) {
+                file_service
+                    .permanently_delete_file(*file_id, Some(FileType::new(*file_type)))
+                    .await?;
+            }
+
+            self.delete_folder(folder.id).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn delete_folder(&self, folder_id: i64) -> Result<(), AppError> {
         sqlx::query!("DELETE FROM folder WHERE id = $1", folder_id)
             .execute(&self.db_pool)
@@ -476,6 +730,9 @@ impl FolderService {
             "WITH RECURSIVE directories AS (SELECT f.id,
                                       f.folder_name,
                                       f.user_id,
+                                      f.color,
+                                      f.created_at,
+                                      f.updated_at,
                                       ARRAY []::TEXT[] AS path
                                FROM folder f
                                WHERE f.id = ANY (",
@@ -498,15 +755,22 @@ impl FolderService {
                     SELECT f.id,
                                       f.folder_name,
                                       f.user_id,
+                                      f.color,
+                                      f.created_at,
+                                      f.updated_at,
                                       d.path || d.folder_name
                                FROM folder f
-                                        JOIN directories d ON f.parent_id = d.id)
+                                        JOIN directories d ON f.parent_id = d.id
+                               WHERE f.deleted_at IS NULL)
         SELECT d.*,
                COALESCE(ARRAY_AGG(f.id) FILTER (WHERE f.id IS NOT NULL), ARRAY []::BIGINT[])             AS files,
-               COALESCE(ARRAY_AGG(f.file_name) FILTER (WHERE f.file_name IS NOT NULL), ARRAY []::TEXT[]) AS file_names
+               COALESCE(ARRAY_AGG(f.file_name) FILTER (WHERE f.file_name IS NOT NULL), ARRAY []::TEXT[]) AS file_names,
+               COALESCE(ARRAY_AGG(f.blob_hash) FILTER (WHERE f.id IS NOT NULL), ARRAY []::TEXT[])         AS file_blob_hashes,
+               COALESCE(ARRAY_AGG(f.file_size) FILTER (WHERE f.id IS NOT NULL), ARRAY []::BIGINT[])       AS file_sizes,
+               COALESCE(ARRAY_AGG(f.updated_at) FILTER (WHERE f.id IS NOT NULL), ARRAY []::TIMESTAMPTZ[]) AS file_updated_at
         FROM directories d
-                 LEFT JOIN files f ON f.parent_folder_id = d.id
-        GROUP BY d.folder_name, d.user_id, d.id, d.path"
+                 LEFT JOIN files f ON f.parent_folder_id = d.id AND f.deleted_at IS NULL
+        GROUP BY d.folder_name, d.user_id, d.id, d.color, d.created_at, d.updated_at, d.path"
         );
 
         query.sql().into()
@@ -1,22 +1,56 @@
+use std::future::Future;
 use std::io::Cursor;
-use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
 
 use exif::{In, Tag};
 use futures::future;
-use image::{DynamicImage, EncodableLayout, ExtendedColorType, ImageError, ImageReader, RgbImage};
+use futures::FutureExt;
+use image::{
+    DynamicImage, EncodableLayout, ExtendedColorType, ImageEncoder, ImageError, ImageReader,
+    RgbImage,
+};
+use image::codecs::avif::AvifEncoder;
 use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
 use itertools::Itertools;
+use lazy_static::lazy_static;
 use sonyflake::Sonyflake;
-use sqlx::types::JsonValue;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use tokio::io::AsyncReadExt;
 
 use crate::db::KosmosPool;
+use crate::model::internal::file_type::FileType;
+use crate::model::internal::excerpt_codec::ExcerptCodec;
+use crate::model::internal::image_codec::ImageCodec;
 use crate::model::internal::preview_status::PreviewStatus;
 use crate::model::internal::image_format::ImageFormat;
-use crate::model::internal::operation_type::OperationType;
 use crate::model::internal::operation_status::OperationStatus;
 use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
 use crate::state::AppState;
+use crate::storage;
+use crate::storage::KosmosStorage;
+use crate::document::KosmosPageRenderer;
+use crate::services::file_service::FormatSource;
+use crate::video::KosmosFrameExtractor;
+
+/// Layout of the storyboard of a video
+const SPRITE_COLUMNS: u32 = 5;
+const SPRITE_ROWS: u32 = 2;
+
+const PDF_MIME_TYPE: &str = "application/pdf";
+/// Only the start of a document is read for its excerpt
+const EXCERPT_BYTES: u64 = 16 * 1024;
+const EXCERPT_LINES: usize = 40;
+const EXCERPT_THEME: &str = "InspiredGitHub";
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+}
 
 #[derive(Debug)]
 pub struct ImageServiceResizeError {
@@ -43,57 +77,261 @@ pub enum ImageServiceErrorKind {
     ImageGuessFormatError { io_error: std::io::Error },
     ImageDecodeError { image_error: ImageError },
     ExifReadError { exif_error: exif::Error },
+    VideoFrameError { io_error: std::io::Error },
+    PageRenderError { io_error: std::io::Error },
+    ExcerptError { syntect_error: syntect::Error },
 }
 
 #[derive(Clone)]
 pub struct ImageFormatInsert {
     format: i16,
+    codec: i16,
     file_id: i64,
     width: i32,
     height: i32,
 }
 
+/// Renditions generated for every image, set through `IMAGE_FORMATS` (e.g.
+/// `thumbnail:256,preview:1280,large:2560`) and `IMAGE_CODECS` (e.g. `jpeg,webp`).
+/// Only JPEG thumbnails are generated unless more are configured.
+pub struct ImageRenditionConfig {
+    pub formats: Vec<(ImageFormat, u32)>,
+    pub codecs: Vec<ImageCodec>,
+}
+
+impl ImageRenditionConfig {
+    fn from_env() -> Self {
+        let mut formats = std::env::var("IMAGE_FORMATS")
+            .unwrap_or("thumbnail".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let (name, width) = match entry.split_once(':') {
+                    Some((name, width)) => (name, width.parse::<u32>().ok()),
+                    None => (entry, None),
+                };
+
+                let format = ImageFormat::format_by_name(name);
+                if format.is_none() {
+                    tracing::warn!("Unknown image format {} in IMAGE_FORMATS", name);
+                }
+
+                format.map(|format| (format, width.unwrap_or(format.width_by_format())))
+            })
+            .unique_by(|(format, _)| *format as i16)
+            .collect::<Vec<_>>();
+
+        let mut codecs = std::env::var("IMAGE_CODECS")
+            .unwrap_or("jpeg".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| {
+                let codec = ImageCodec::codec_by_name(name);
+                if codec.is_none() {
+                    tracing::warn!("Unknown image codec {} in IMAGE_CODECS", name);
+                }
+                codec
+            })
+            .unique_by(|codec| *codec as i16)
+            .collect::<Vec<_>>();
+
+        // Thumbnails in JPEG are what every client falls back to
+        if !formats.iter().any(|(format, _)| *format == ImageFormat::Thumbnail) {
+            formats.insert(0, (ImageFormat::Thumbnail, ImageFormat::Thumbnail.width_by_format()));
+        }
+        if !codecs.contains(&ImageCodec::Jpeg) {
+            codecs.insert(0, ImageCodec::Jpeg);
+        }
+
+        ImageRenditionConfig { formats, codecs }
+    }
+}
+
+lazy_static! {
+    pub static ref IMAGE_RENDITIONS: ImageRenditionConfig = ImageRenditionConfig::from_env();
+}
+
 #[derive(Clone)]
 pub struct ImageService {
     db_pool: KosmosPool,
     sf: Sonyflake,
+    storage: KosmosStorage,
+    frame_extractor: Option<KosmosFrameExtractor>,
+    page_renderer: Option<KosmosPageRenderer>,
 }
 
 impl ImageService {
-    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
-        ImageService { db_pool, sf }
+    pub fn new(
+        db_pool: KosmosPool,
+        sf: Sonyflake,
+        storage: KosmosStorage,
+        frame_extractor: Option<KosmosFrameExtractor>,
+        page_renderer: Option<KosmosPageRenderer>,
+    ) -> Self {
+        ImageService {
+            db_pool,
+            sf,
+            storage,
+            frame_extractor,
+            page_renderer,
+        }
+    }
+
+    /// Whether formats can be generated for files of the type
+    pub fn supports_file_type(&self, file_type: FileType) -> bool {
+        match file_type {
+            FileType::Image | FileType::Document => true,
+            FileType::Video => self.frame_extractor.is_some(),
+            _ => false,
+        }
     }
 
-    pub fn make_image_format_name(id: i64, format: ImageFormat) -> String {
-        format!("{}_{}", id, format as i16).as_str().to_string()
+    /// PDFs are documents as well, but can only be rendered if a renderer is available
+    fn supports_source(&self, source: &FormatSource) -> bool {
+        match source.file_type {
+            FileType::Document if source.mime_type == PDF_MIME_TYPE => {
+                self.page_renderer.is_some()
+            }
+            file_type => self.supports_file_type(file_type),
+        }
+    }
+
+    pub fn make_image_format_name(id: i64, format: ImageFormat, codec: ImageCodec) -> String {
+        Self::make_format_name(id, format, codec as i16)
+    }
+
+    pub fn make_excerpt_format_name(id: i64, codec: ExcerptCodec) -> String {
+        Self::make_format_name(id, ImageFormat::Excerpt, codec as i16)
+    }
+
+    /// Name of a stored format by the value of its codec column, which holds an `ImageCodec`
+    /// or for excerpts an `ExcerptCodec`
+    pub fn make_format_name(id: i64, format: ImageFormat, codec: i16) -> String {
+        // JPEG keeps the name from before there were other codecs
+        if codec == ImageCodec::Jpeg as i16 {
+            format!("{}_{}", id, format as i16)
+        } else {
+            format!("{}_{}_{}", id, format as i16, codec)
+        }
+    }
+
+    /// Codecs a format of a file is available in
+    pub async fn get_codecs(
+        &self,
+        file_id: i64,
+        format: ImageFormat,
+    ) -> Result<Vec<ImageCodec>, AppError> {
+        sqlx::query_scalar!(
+            "SELECT codec FROM image_formats WHERE file_id = $1 AND format = $2",
+            file_id,
+            format as i16
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting codecs of file {}: {}", file_id, e);
+            AppError::InternalError
+        })
+        .map(|codecs| codecs.into_iter().map(ImageCodec::from).collect())
+    }
+
+    /// Images and videos lacking any of the configured renditions, e.g. after adding a format
+    /// or codec, and documents without any format
+    pub async fn get_files_missing_formats(&self) -> Result<Vec<(UserId, i64)>, AppError> {
+        let (formats, codecs): (Vec<i16>, Vec<i16>) = IMAGE_RENDITIONS
+            .formats
+            .iter()
+            .cartesian_product(IMAGE_RENDITIONS.codecs.iter())
+            .map(|((format, _), codec)| (*format as i16, *codec as i16))
+            .unzip();
+
+        let file_types = [FileType::Image, FileType::Video]
+            .into_iter()
+            .filter(|file_type| self.supports_file_type(*file_type))
+            .map(|file_type| file_type as i16)
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            "SELECT f.id, f.user_id
+             FROM files f
+             WHERE f.file_type = ANY($1)
+               AND EXISTS (SELECT 1
+                           FROM UNNEST($2::SMALLINT[], $3::SMALLINT[]) AS r(format, codec)
+                           WHERE NOT EXISTS (SELECT 1
+                                             FROM image_formats i
+                                             WHERE i.file_id = f.id
+                                               AND i.format = r.format
+                                               AND i.codec = r.codec))
+                OR (f.file_type = $4
+                 AND ($5 OR f.mime_type <> $6)
+                 AND NOT EXISTS (SELECT 1 FROM image_formats i WHERE i.file_id = f.id))",
+            &file_types[..],
+            &formats[..],
+            &codecs[..],
+            FileType::Document as i16,
+            self.page_renderer.is_some(),
+            PDF_MIME_TYPE
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting images missing formats: {}", e);
+            AppError::InternalError
+        })
+        .map(|rows| rows.into_iter().map(|row| (row.user_id, row.id)).collect())
     }
 
     pub async fn generate_all_formats(
         &self,
         file_ids: Vec<i64>,
-        user_id: i64,
         state: Arc<AppState>,
+        operation_id: i64,
         started_by_operation: Option<i64>,
     ) -> Result<(), AppError> {
         let total_files = file_ids.len();
         let mut pending_inserts: Vec<ImageFormatInsert> = Vec::with_capacity(total_files);
 
-        let upload_location = std::env::var("UPLOAD_LOCATION").unwrap();
+        let (sources, unsupported): (Vec<_>, Vec<_>) = state
+            .file_service
+            .get_format_sources(&file_ids)
+            .await?
+            .into_iter()
+            .partition(|source| self.supports_source(source));
 
-        let operation = state
+        let total_progress = total_files as i32;
+        let mut done_progress = unsupported.len() as i32;
+        let _ = state
             .operation_service
-            .create_operation(
-                user_id,
-                OperationType::ImageProcessing,
-                OperationStatus::Pending,
-                Some(JsonValue::from(file_ids.clone())),
-            )
-            .await?;
+            .update_progress(operation_id, done_progress, total_progress, 0, None)
+            .await;
 
-        let mut pending_insert_handles = file_ids
+        let mut pending_insert_handles = sources
             .into_iter()
-            .map(|id| Self::generate_image_sizes(id, &upload_location))
-            .map(Box::pin)
+            .map(|source| {
+                let file_name = source.file_name.clone();
+                let handle = match source.file_type {
+                    FileType::Video => Box::pin(Self::generate_video_formats(
+                        source.file_id,
+                        source.content_key,
+                        &self.storage,
+                        self.frame_extractor.clone(),
+                    ))
+                        as Pin<Box<dyn Future<Output = _> + Send + '_>>,
+                    FileType::Document => Box::pin(Self::generate_document_formats(
+                        source,
+                        &self.storage,
+                        self.page_renderer.clone(),
+                    )),
+                    _ => Box::pin(Self::generate_image_sizes(
+                        source.file_id,
+                        source.content_key,
+                        &self.storage,
+                    )),
+                };
+                handle.map(move |result| (file_name, result)).boxed()
+            })
             .collect::<Vec<_>>();
 
         println!("Created {} handles", pending_insert_handles.len());
@@ -102,11 +340,13 @@ impl ImageService {
         let mut successes = vec![];
 
         while !pending_insert_handles.is_empty() {
-            match future::select_all(pending_insert_handles).await {
-                (Ok(val), _, remaining) => {
-                    println!("Done, {} left", remaining.len());
+            let ((file_name, result), _, remaining) =
+                future::select_all(pending_insert_handles).await;
+            pending_insert_handles = remaining;
+
+            match result {
+                Ok(val) => {
                     pending_inserts.extend(val.clone());
-                    pending_insert_handles = remaining;
 
                     match val.get(0) {
                         Some(row) => {
@@ -115,20 +355,32 @@ impl ImageService {
                         None => {}
                     }
                 }
-                (Err(id), _, remaining) => {
-                    pending_insert_handles = remaining;
+                Err(id) => {
                     failures.push(id);
                 }
             }
+
+            done_progress += 1;
+            let _ = state
+                .operation_service
+                .update_progress(
+                    operation_id,
+                    done_progress,
+                    total_progress,
+                    failures.len() as i32,
+                    Some(file_name),
+                )
+                .await;
         }
 
         if !failures.is_empty() {
             tracing::error!("Failed to generate formats {:?}", failures);
         }
 
-        let (ids, formats, file_ids, widths, heights): (
+        let (ids, formats, codecs, file_ids, widths, heights): (
             Vec<i64>,
             Vec<i16>,
+            Vec<i16>,
             Vec<i64>,
             Vec<i32>,
             Vec<i32>,
@@ -138,6 +390,7 @@ impl ImageService {
                 (
                     self.sf.next_id().unwrap() as i64,
                     row.format,
+                    row.codec,
                     row.file_id,
                     row.width,
                     row.height,
@@ -145,9 +398,12 @@ impl ImageService {
             })
             .multiunzip();
 
-        sqlx::query!("INSERT INTO image_formats (id, format, file_id, width, height) SELECT * FROM UNNEST($1::BIGINT[], $2::SMALLINT[], $3::BIGINT[], $4::INT[], $5::INT[])",
+        // Reprocessed images overwrite their existing formats
+        sqlx::query!("INSERT INTO image_formats (id, format, codec, file_id, width, height) SELECT * FROM UNNEST($1::BIGINT[], $2::SMALLINT[], $3::SMALLINT[], $4::BIGINT[], $5::INT[], $6::INT[])
+                      ON CONFLICT (file_id, format, codec) DO UPDATE SET width = excluded.width, height = excluded.height",
             &ids[..],
             &formats[..],
+            &codecs[..],
             &file_ids[..],
             &widths[..],
             &heights[..]
@@ -161,39 +417,17 @@ impl ImageService {
 
         println!("Failure: {}, total: {}", failures.len(), file_ids.len());
 
-        if failures.len() == total_files {
+        let all_failed = total_files > 0 && failures.len() == total_files;
+
+        if started_by_operation.is_some() && !all_failed {
             state
                 .operation_service
                 .update_operation(
-                    operation.id,
-                    OperationStatus::Unrecoverable,
-                    Some("Failed to generate all formats".to_string()),
+                    started_by_operation.unwrap(),
+                    OperationStatus::Recovered,
+                    Some("Format generation recovered".to_string()),
                 )
                 .await?;
-        } else {
-            state
-                .operation_service
-                .update_operation(operation.id, OperationStatus::Success, None)
-                .await?;
-        }
-
-        if started_by_operation.is_some() {
-            let status = if failures.len() == total_files {
-                OperationStatus::Unrecoverable
-            } else {
-                OperationStatus::Recovered
-            };
-
-            let result = if failures.len() == total_files {
-                Some("Failed to generate all formats".to_string())
-            } else {
-                Some("Format generation recovered".to_string())
-            };
-
-            state
-                .operation_service
-                .update_operation(started_by_operation.unwrap(), status, result)
-                .await?;
         }
 
         println!("Done generating");
@@ -213,18 +447,33 @@ impl ImageService {
             .update_preview_status_for_file_ids(&successes, PreviewStatus::Ready)
             .await?;
 
+        let unsupported_file_ids = unsupported
+            .iter()
+            .map(|source| source.file_id)
+            .collect::<Vec<_>>();
+
+        state
+            .file_service
+            .update_preview_status_for_file_ids(&unsupported_file_ids, PreviewStatus::Unavailable)
+            .await?;
+
+        // Lets the job queue retry the whole batch
+        if all_failed {
+            return Err(AppError::InternalError);
+        }
+
         Ok(())
     }
 
     pub async fn generate_image_sizes(
         file_id: i64,
-        upload_location: &String,
+        content_key: String,
+        storage: &KosmosStorage,
     ) -> Result<Vec<ImageFormatInsert>, ImageServiceResizeError> {
         println!("Starting with {}", file_id);
-        let original_image_path = Path::new(&upload_location).join(file_id.to_string());
-        let image_formats_path = Path::new(&upload_location).join("formats");
 
-        let image_buff = tokio::fs::read(original_image_path.clone())
+        let image_buff = storage
+            .get_bytes(&content_key)
             .await
             .map_err(|e| ImageServiceResizeError {
                 file_id,
@@ -232,9 +481,211 @@ impl ImageService {
                 kind: ImageServiceErrorKind::ImageLoadError { io_error: e },
             })?;
 
-        let mut cursor = Cursor::new(&image_buff);
+        let image = Self::decode_image(file_id, &image_buff)?;
+        let image = Self::apply_exif_orientation(&image, &image_buff);
+
+        let format_inserts = Self::render_formats(file_id, &image, storage).await?;
 
-        let image = ImageReader::new(&mut cursor)
+        println!("Done with {}", file_id);
+
+        Ok(format_inserts)
+    }
+
+    /// Renders the formats from the poster frame, plus a storyboard to preview the video with
+    pub async fn generate_video_formats(
+        file_id: i64,
+        content_key: String,
+        storage: &KosmosStorage,
+        frame_extractor: Option<KosmosFrameExtractor>,
+    ) -> Result<Vec<ImageFormatInsert>, ImageServiceResizeError> {
+        let video_error = |e: std::io::Error| ImageServiceResizeError {
+            file_id,
+            format: ImageFormat::Thumbnail,
+            kind: ImageServiceErrorKind::VideoFrameError { io_error: e },
+        };
+
+        let frame_extractor = frame_extractor.ok_or_else(|| {
+            video_error(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "No frame extractor available",
+            ))
+        })?;
+
+        let local_copy = storage::local_copy(storage, &content_key)
+            .await
+            .map_err(|e| ImageServiceResizeError {
+                file_id,
+                format: ImageFormat::Thumbnail,
+                kind: ImageServiceErrorKind::ImageLoadError { io_error: e },
+            })?;
+
+        let poster_buff = frame_extractor
+            .poster_frame(&local_copy.path)
+            .await
+            .map_err(video_error)?;
+        let poster = Self::decode_image(file_id, &poster_buff)?;
+
+        let mut format_inserts = Self::render_formats(file_id, &poster, storage).await?;
+
+        let sprite_buff = frame_extractor
+            .sprite(
+                &local_copy.path,
+                SPRITE_COLUMNS,
+                SPRITE_ROWS,
+                ImageFormat::Sprite.width_by_format(),
+            )
+            .await
+            .map_err(|e| {
+                ImageServiceErrorKind::VideoFrameError { io_error: e }
+                    .into_error(file_id, ImageFormat::Sprite)
+            })?;
+        let sprite = Self::decode_image(file_id, &sprite_buff)?.to_rgb8();
+
+        for codec in &IMAGE_RENDITIONS.codecs {
+            let response =
+                Self::save_resized_image(file_id, ImageFormat::Sprite, *codec, &sprite, storage)
+                    .await
+                    .map_err(|e| e.into_error(file_id, ImageFormat::Sprite))?;
+
+            format_inserts.push(response)
+        }
+
+        Ok(format_inserts)
+    }
+
+    /// PDFs are rendered from their first page, other documents get an excerpt as HTML with
+    /// highlighting and as plain text
+    pub async fn generate_document_formats(
+        source: FormatSource,
+        storage: &KosmosStorage,
+        page_renderer: Option<KosmosPageRenderer>,
+    ) -> Result<Vec<ImageFormatInsert>, ImageServiceResizeError> {
+        let file_id = source.file_id;
+
+        if source.mime_type == PDF_MIME_TYPE {
+            let page_error = |e: std::io::Error| ImageServiceResizeError {
+                file_id,
+                format: ImageFormat::Thumbnail,
+                kind: ImageServiceErrorKind::PageRenderError { io_error: e },
+            };
+
+            let page_renderer = page_renderer.ok_or_else(|| {
+                page_error(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "No page renderer available",
+                ))
+            })?;
+
+            let local_copy = storage::local_copy(storage, &source.content_key)
+                .await
+                .map_err(|e| ImageServiceResizeError {
+                    file_id,
+                    format: ImageFormat::Thumbnail,
+                    kind: ImageServiceErrorKind::ImageLoadError { io_error: e },
+                })?;
+
+            let max_size = IMAGE_RENDITIONS
+                .formats
+                .iter()
+                .map(|(_, max_size)| *max_size)
+                .max()
+                .unwrap_or(ImageFormat::Thumbnail.width_by_format());
+
+            let page_buff = page_renderer
+                .first_page(&local_copy.path, max_size)
+                .await
+                .map_err(page_error)?;
+            let page = Self::decode_image(file_id, &page_buff)?;
+
+            return Self::render_formats(file_id, &page, storage).await;
+        }
+
+        let load_error = |e: std::io::Error| ImageServiceResizeError {
+            file_id,
+            format: ImageFormat::Excerpt,
+            kind: ImageServiceErrorKind::ImageLoadError { io_error: e },
+        };
+
+        let size = storage.size(&source.content_key).await.map_err(load_error)?;
+        let mut buff = vec![];
+        storage
+            .get_range(&source.content_key, 0, size.min(EXCERPT_BYTES))
+            .await
+            .map_err(load_error)?
+            .read_to_end(&mut buff)
+            .await
+            .map_err(load_error)?;
+
+        let max_width = ImageFormat::Excerpt.width_by_format() as usize;
+        let lines = String::from_utf8_lossy(&buff)
+            .lines()
+            .take(EXCERPT_LINES)
+            .map(|line| line.chars().take(max_width).collect::<String>())
+            .collect::<Vec<_>>();
+
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let excerpt = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+
+        let syntax = Self::find_syntax(&source.file_name, &source.mime_type);
+        let html = highlighted_html_for_string(
+            &excerpt,
+            &SYNTAX_SET,
+            syntax,
+            &THEME_SET.themes[EXCERPT_THEME],
+        )
+        .map_err(|e| {
+            ImageServiceErrorKind::ExcerptError { syntect_error: e }
+                .into_error(file_id, ImageFormat::Excerpt)
+        })?;
+
+        let mut format_inserts = vec![];
+
+        for (codec, content) in [(ExcerptCodec::Html, html), (ExcerptCodec::Text, excerpt)] {
+            let key = storage::format_key(&Self::make_excerpt_format_name(file_id, codec));
+
+            storage
+                .put_bytes(&key, content.into_bytes())
+                .await
+                .map_err(|e| {
+                    ImageServiceErrorKind::ResizeImageFileSaveError { io_error: e }
+                        .into_error(file_id, ImageFormat::Excerpt)
+                })?;
+
+            format_inserts.push(ImageFormatInsert {
+                format: ImageFormat::Excerpt as i16,
+                codec: codec as i16,
+                file_id,
+                width: width as i32,
+                height: lines.len() as i32,
+            });
+        }
+
+        Ok(format_inserts)
+    }
+
+    /// The extension is more specific than the MIME type, e.g. for JSON
+    fn find_syntax(file_name: &str, mime_type: &str) -> &'static SyntaxReference {
+        let extension_by_mime = match mime_type {
+            "application/json" => "json",
+            "text/x-python" => "py",
+            "text/x-rust" => "rs",
+            "text/x-c" => "c",
+            "text/x-java" => "java",
+            "text/x-ruby" => "rb",
+            _ => "txt",
+        };
+
+        std::path::Path::new(file_name)
+            .extension()
+            .and_then(|extension| SYNTAX_SET.find_syntax_by_extension(&extension.to_string_lossy()))
+            .or_else(|| SYNTAX_SET.find_syntax_by_extension(extension_by_mime))
+            .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text())
+    }
+
+    fn decode_image(file_id: i64, image_buff: &[u8]) -> Result<DynamicImage, ImageServiceResizeError> {
+        let mut cursor = Cursor::new(image_buff);
+
+        ImageReader::new(&mut cursor)
             .with_guessed_format()
             .map_err(|e| ImageServiceResizeError {
                 file_id,
@@ -246,53 +697,73 @@ impl ImageService {
                 file_id,
                 format: ImageFormat::Thumbnail,
                 kind: ImageServiceErrorKind::ImageDecodeError { image_error: e },
-            })?;
+            })
+    }
 
+    async fn render_formats(
+        file_id: i64,
+        image: &DynamicImage,
+        storage: &KosmosStorage,
+    ) -> Result<Vec<ImageFormatInsert>, ImageServiceResizeError> {
         let mut format_inserts: Vec<ImageFormatInsert> = vec![];
 
-        for format in ImageFormat::IMAGE_FORMATS {
-            let resize_image = Self::resize_image_and_convert_to_jpg(format, &image, &image_buff)
-                .map_err(|e| e.into_error(file_id, format))?;
+        for (format, max_size) in &IMAGE_RENDITIONS.formats {
+            let resize_image = Self::resize_image(*max_size, image);
 
-            let response =
-                Self::save_resized_image(file_id, format, resize_image, &image_formats_path)
-                    .await
-                    .map_err(|e| e.into_error(file_id, format))?;
+            for codec in &IMAGE_RENDITIONS.codecs {
+                let response =
+                    Self::save_resized_image(file_id, *format, *codec, &resize_image, storage)
+                        .await
+                        .map_err(|e| e.into_error(file_id, *format))?;
 
-            format_inserts.push(response)
+                format_inserts.push(response)
+            }
         }
 
-        println!("Done with {}", file_id);
-
         Ok(format_inserts)
     }
 
     async fn save_resized_image(
         file_id: i64,
         format: ImageFormat,
-        resized_image: RgbImage,
-        formats_folder_path: &PathBuf,
+        codec: ImageCodec,
+        resized_image: &RgbImage,
+        storage: &KosmosStorage,
     ) -> Result<ImageFormatInsert, ImageServiceErrorKind> {
-        let image_format_path =
-            Path::new(formats_folder_path).join(Self::make_image_format_name(file_id, format));
-
-        let image_format_path = image_format_path.to_str().unwrap();
+        let image_format_key =
+            storage::format_key(&Self::make_image_format_name(file_id, format, codec));
 
         let format_height = resized_image.height();
         let format_width = resized_image.width();
 
         let mut buff = vec![];
 
-        JpegEncoder::new(&mut buff)
-            .encode(
+        let encoded = match codec {
+            ImageCodec::Jpeg => JpegEncoder::new(&mut buff).write_image(
                 resized_image.as_bytes(),
                 format_width,
                 format_height,
                 ExtendedColorType::Rgb8,
-            )
-            .map_err(|image_error| ImageServiceErrorKind::ResizeImageSaveError { image_error })?;
+            ),
+            // The WebP encoder of image only supports lossless output
+            ImageCodec::WebP => WebPEncoder::new_lossless(&mut buff).write_image(
+                resized_image.as_bytes(),
+                format_width,
+                format_height,
+                ExtendedColorType::Rgb8,
+            ),
+            ImageCodec::Avif => AvifEncoder::new_with_speed_quality(&mut buff, 8, 70).write_image(
+                resized_image.as_bytes(),
+                format_width,
+                format_height,
+                ExtendedColorType::Rgb8,
+            ),
+        };
 
-        tokio::fs::write(image_format_path, buff)
+        encoded.map_err(|image_error| ImageServiceErrorKind::ResizeImageSaveError { image_error })?;
+
+        storage
+            .put_bytes(&image_format_key, buff)
             .await
             .map_err(|e| ImageServiceErrorKind::ResizeImageFileSaveError { io_error: e })?;
 
@@ -301,16 +772,51 @@ impl ImageService {
             height: format_height as i32,
             file_id,
             format: format as i16,
+            codec: codec as i16,
         })
     }
 
-    fn resize_image_and_convert_to_jpg(
-        format: ImageFormat,
-        image: &DynamicImage,
-        image_buff: &[u8],
-    ) -> Result<RgbImage, ImageServiceErrorKind> {
-        let max_size = format.width_by_format();
+    /// Renders a format of an image which is not stored as a file, e.g. an entry of an
+    /// archive. Nothing is stored, the format is rendered as JPEG on every request.
+    pub fn render_format_on_demand(image_buff: &[u8], format: ImageFormat) -> Result<Vec<u8>, AppError> {
+        let max_size = IMAGE_RENDITIONS
+            .formats
+            .iter()
+            .find(|(rendition, _)| *rendition == format)
+            .map_or(format.width_by_format(), |(_, max_size)| *max_size);
+
+        let image = Self::decode_image(0, image_buff).map_err(|_| AppError::UnprocessableEntity {
+            error: "Image can't be decoded".to_string(),
+        })?;
+        let image = Self::apply_exif_orientation(&image, image_buff);
+        let resized_image = Self::resize_image(max_size, &image);
 
+        let mut buff = vec![];
+        JpegEncoder::new(&mut buff)
+            .write_image(
+                resized_image.as_bytes(),
+                resized_image.width(),
+                resized_image.height(),
+                ExtendedColorType::Rgb8,
+            )
+            .map_err(|e| {
+                tracing::error!("Error encoding image format: {}", e);
+                AppError::InternalError
+            })?;
+
+        Ok(buff)
+    }
+
+    /// Images smaller than the format are kept at their size instead of being upscaled
+    fn resize_image(max_size: u32, image: &DynamicImage) -> RgbImage {
+        if image.width() <= max_size && image.height() <= max_size {
+            return image.to_rgb8();
+        }
+
+        image.thumbnail(max_size, max_size).to_rgb8()
+    }
+
+    fn apply_exif_orientation(image: &DynamicImage, image_buff: &[u8]) -> DynamicImage {
         let mut cursor = Cursor::new(image_buff);
 
         let exif_reader = exif::Reader::new();
@@ -322,7 +828,7 @@ impl ImageService {
         };
 
         // Rotate image if exif orientation is not 1
-        let image = if let Some(orientation) = exif_orientation {
+        if let Some(orientation) = exif_orientation {
             match orientation.get_uint(0) {
                 Some(v @ 1..=8) => match v {
                     1 => image.clone(),
@@ -339,8 +845,6 @@ impl ImageService {
             }
         } else {
             image.clone()
-        };
-
-        Ok(image.thumbnail(max_size, max_size).to_rgb8())
+        }
     }
 }
@@ -0,0 +1,363 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use sonyflake::Sonyflake;
+use sqlx::types::JsonValue;
+use tokio::sync::Notify;
+use crate::db::KosmosPool;
+use crate::model::internal::job_type::JobType;
+use crate::model::internal::operation_status::OperationStatus;
+use crate::model::job::{
+    ArchiveExtractionPayload, BinPurgePayload, ContentIndexingPayload, ImageProcessingPayload,
+    JobModel,
+};
+use crate::model::operation::OperationModel;
+use crate::response::error_handling::AppError;
+use crate::runtimes::IMAGE_PROCESSING_RUNTIME;
+use crate::services::operation_service::OperationService;
+use crate::services::session_service::UserId;
+use crate::state::AppState;
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+const BACKOFF_BASE_SECONDS: i64 = 30;
+const BACKOFF_MAX_SECONDS: i64 = 60 * 60;
+/// Workers look for due retries at least this often, new jobs wake them up directly
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct JobService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+    operation_service: OperationService,
+    notify: Arc<Notify>,
+}
+
+impl JobService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake, operation_service: OperationService) -> Self {
+        JobService {
+            db_pool,
+            sf,
+            operation_service,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Queues a job, the returned operation reports its progress to the user
+    pub async fn enqueue(
+        &self,
+        user_id: UserId,
+        job_type: JobType,
+        payload: JsonValue,
+        metadata: Option<JsonValue>,
+    ) -> Result<OperationModel, AppError> {
+        let operation = self
+            .operation_service
+            .create_operation(
+                user_id,
+                job_type.operation_type(),
+                OperationStatus::Pending,
+                metadata,
+            )
+            .await?;
+
+        sqlx::query!(
+            "INSERT INTO jobs (id, operation_id, job_type, payload, max_attempts) VALUES ($1, $2, $3, $4, $5)",
+            self.sf.next_id().map_err(|_| AppError::InternalError)? as i64,
+            operation.id,
+            job_type as i16,
+            payload,
+            DEFAULT_MAX_ATTEMPTS
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error enqueuing job: {}", e);
+            AppError::InternalError
+        })?;
+
+        self.notify.notify_one();
+
+        Ok(operation)
+    }
+
+    pub async fn enqueue_image_processing(
+        &self,
+        user_id: UserId,
+        file_ids: Vec<i64>,
+        started_by_operation: Option<i64>,
+    ) -> Result<OperationModel, AppError> {
+        let metadata = JsonValue::from(file_ids.clone());
+        let payload = serde_json::to_value(ImageProcessingPayload {
+            user_id,
+            file_ids,
+            started_by_operation,
+        })
+        .map_err(|e| {
+            tracing::error!("Error serializing job payload: {}", e);
+            AppError::InternalError
+        })?;
+
+        self.enqueue(user_id, JobType::ImageProcessing, payload, Some(metadata))
+            .await
+    }
+
+    pub async fn enqueue_content_indexing(
+        &self,
+        user_id: UserId,
+        file_ids: Vec<i64>,
+    ) -> Result<OperationModel, AppError> {
+        let metadata = JsonValue::from(file_ids.clone());
+        let payload = serde_json::to_value(ContentIndexingPayload { user_id, file_ids })
+            .map_err(|e| {
+                tracing::error!("Error serializing job payload: {}", e);
+                AppError::InternalError
+            })?;
+
+        self.enqueue(user_id, JobType::ContentIndexing, payload, Some(metadata))
+            .await
+    }
+
+    pub async fn enqueue_bin_purge(
+        &self,
+        user_id: UserId,
+        file_ids: Vec<i64>,
+        folder_ids: Vec<i64>,
+    ) -> Result<OperationModel, AppError> {
+        let metadata = JsonValue::from(file_ids.clone());
+        let payload = serde_json::to_value(BinPurgePayload {
+            user_id,
+            file_ids,
+            folder_ids,
+        })
+        .map_err(|e| {
+            tracing::error!("Error serializing job payload: {}", e);
+            AppError::InternalError
+        })?;
+
+        self.enqueue(user_id, JobType::BinPurge, payload, Some(metadata))
+            .await
+    }
+
+    pub async fn enqueue_archive_extraction(
+        &self,
+        user_id: UserId,
+        file_id: i64,
+        parent_folder_id: Option<i64>,
+    ) -> Result<OperationModel, AppError> {
+        let metadata = JsonValue::from(vec![file_id]);
+        let payload = serde_json::to_value(ArchiveExtractionPayload {
+            user_id,
+            file_id,
+            parent_folder_id,
+        })
+        .map_err(|e| {
+            tracing::error!("Error serializing job payload: {}", e);
+            AppError::InternalError
+        })?;
+
+        self.enqueue(user_id, JobType::ArchiveExtraction, payload, Some(metadata))
+            .await
+    }
+
+    /// Locks the next due job, concurrent workers skip jobs which are already locked
+    async fn claim_job(&self) -> Result<Option<JobModel>, AppError> {
+        sqlx::query_as!(
+            JobModel,
+            "UPDATE jobs SET locked_at = now(), attempts = attempts + 1
+             WHERE id = (
+                SELECT id FROM jobs
+                WHERE dead_at IS NULL AND locked_at IS NULL AND run_at <= now()
+                ORDER BY run_at
+                FOR UPDATE SKIP LOCKED
+                LIMIT 1
+             )
+             RETURNING *"
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error claiming job: {}", e);
+            AppError::InternalError
+        })
+    }
+
+    async fn complete_job(&self, job: &JobModel) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM jobs WHERE id = $1", job.id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error completing job {}: {}", job.id, e);
+                AppError::InternalError
+            })?;
+
+        self.operation_service
+            .update_operation(job.operation_id, OperationStatus::Success, None)
+            .await
+    }
+
+    /// Reschedules the job with exponential backoff, or dead-letters it once all attempts are used up
+    async fn fail_job(&self, job: &JobModel, error: String) -> Result<(), AppError> {
+        if job.attempts >= job.max_attempts {
+            sqlx::query!(
+                "UPDATE jobs SET locked_at = NULL, last_error = $1, dead_at = now() WHERE id = $2",
+                error,
+                job.id
+            )
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error dead-lettering job {}: {}", job.id, e);
+                AppError::InternalError
+            })?;
+
+            return self
+                .operation_service
+                .update_operation(job.operation_id, OperationStatus::Unrecoverable, Some(error))
+                .await;
+        }
+
+        let backoff = (BACKOFF_BASE_SECONDS << (job.attempts - 1).clamp(0, 16)).min(BACKOFF_MAX_SECONDS);
+
+        sqlx::query!(
+            "UPDATE jobs SET locked_at = NULL, last_error = $1, run_at = now() + make_interval(secs => $2) WHERE id = $3",
+            error,
+            backoff as f64,
+            job.id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error rescheduling job {}: {}", job.id, e);
+            AppError::InternalError
+        })?;
+
+        self.operation_service
+            .update_operation(
+                job.operation_id,
+                OperationStatus::Pending,
+                Some(format!(
+                    "Attempt {} of {} failed, retrying: {}",
+                    job.attempts, job.max_attempts, error
+                )),
+            )
+            .await
+    }
+
+    async fn run_job(job: &JobModel, state: &AppState) -> Result<(), String> {
+        match job.job_type {
+            JobType::ImageProcessing => {
+                let payload: ImageProcessingPayload =
+                    serde_json::from_value(job.payload.clone()).map_err(|e| e.to_string())?;
+
+                state
+                    .image_service
+                    .generate_all_formats(
+                        payload.file_ids,
+                        Arc::new(state.clone()),
+                        job.operation_id,
+                        payload.started_by_operation,
+                    )
+                    .await
+                    .map_err(|e| format!("Failed to generate all formats: {}", e))
+            }
+            JobType::ContentIndexing => {
+                let payload: ContentIndexingPayload =
+                    serde_json::from_value(job.payload.clone()).map_err(|e| e.to_string())?;
+
+                let sources = state
+                    .file_service
+                    .get_format_sources(&payload.file_ids)
+                    .await
+                    .map_err(|e| format!("Failed to load files: {}", e))?;
+
+                state
+                    .search_service
+                    .index_sources(sources, &state.operation_service, job.operation_id)
+                    .await
+                    .map_err(|e| format!("Failed to index all files: {}", e))
+            }
+            JobType::BinPurge => {
+                let payload: BinPurgePayload =
+                    serde_json::from_value(job.payload.clone()).map_err(|e| e.to_string())?;
+
+                state
+                    .bin_service
+                    .purge(&payload, state, job.operation_id)
+                    .await
+                    .map_err(|e| format!("Failed to purge all items: {}", e))
+            }
+            JobType::ArchiveExtraction => {
+                let payload: ArchiveExtractionPayload =
+                    serde_json::from_value(job.payload.clone()).map_err(|e| e.to_string())?;
+
+                state
+                    .archive_service
+                    .extract(&payload, state, job.operation_id)
+                    .await
+                    .map_err(|e| format!("Failed to extract all entries: {}", e))
+            }
+        }
+    }
+
+    async fn work(&self, state: AppState) {
+        loop {
+            let job = match self.claim_job().await {
+                Ok(Some(job)) => job,
+                Ok(None) | Err(_) => {
+                    let _ = tokio::time::timeout(POLL_INTERVAL, self.notify.notified()).await;
+                    continue;
+                }
+            };
+
+            tracing::debug!("Running job {} (attempt {})", job.id, job.attempts);
+
+            // A panicking job only ends its own task, it counts as a failed attempt
+            let run = tokio::spawn({
+                let job = job.clone();
+                let state = state.clone();
+                async move { Self::run_job(&job, &state).await }
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("Job panicked: {}", e)));
+
+            let result = match run {
+                Ok(()) => self.complete_job(&job).await,
+                Err(error) => {
+                    tracing::warn!("Job {} failed: {}", job.id, error);
+                    self.fail_job(&job, error).await
+                }
+            };
+
+            if result.is_err() {
+                tracing::error!("Error finishing job {}", job.id);
+            }
+        }
+    }
+
+    /// Releases jobs of workers which didn't survive a restart and starts the workers
+    pub async fn startup_prepare(&self, state: &AppState) {
+        let _ = sqlx::query!("UPDATE jobs SET locked_at = NULL WHERE locked_at IS NOT NULL")
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error preparing jobs for startup: {}", e);
+                AppError::InternalError
+            });
+
+        let default_workers = 2;
+        let workers = env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|workers| workers.parse::<usize>().ok())
+            .unwrap_or(default_workers);
+
+        for _ in 0..workers {
+            let job_service = self.clone();
+            let state = state.clone();
+            IMAGE_PROCESSING_RUNTIME.spawn(async move {
+                job_service.work(state).await;
+            });
+        }
+
+        tracing::info!("Started {} job workers", workers);
+    }
+}
@@ -10,3 +10,16 @@ pub mod usage_service;
 pub mod search_service;
 pub mod album_service;
 pub mod passkey_service;
+pub mod upload_service;
+pub mod blob_service;
+pub mod version_service;
+pub mod app_password_service;
+pub mod dav_service;
+pub mod api_token_service;
+pub mod active_session_service;
+pub mod totp_service;
+pub mod oidc_service;
+pub mod job_service;
+pub mod tag_service;
+pub mod bin_service;
+pub mod archive_service;
@@ -0,0 +1,394 @@
+use std::sync::Arc;
+
+use base64::prelude::*;
+use chrono::Utc;
+use hyper::body::to_bytes;
+use hyper::client::HttpConnector;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::{Body, Client, Method, Request};
+use hyper_tls::HttpsConnector;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use sonyflake::Sonyflake;
+use tokio::sync::OnceCell;
+
+use crate::db::KosmosPool;
+use crate::model::oidc::{
+    OidcIdTokenClaims, OidcLinkModel, OidcLoginState, OidcProviderMetadata, OidcTokenResponse,
+};
+use crate::model::role::Role;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+const OIDC_RANDOM_LENGTH: usize = 64;
+
+/// Login through an OpenID Connect identity provider, only available if `OIDC_ISSUER` is set
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Public clients only use PKCE
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// Creates a user on the first login of an unknown subject
+    pub auto_provision: bool,
+    pub username_claim: String,
+    /// Claim the role of the user is derived from on every login, e.g. `groups`
+    pub role_claim: Option<String>,
+    /// Value of the role claim which makes a user admin
+    pub admin_role: String,
+    /// Where the browser is sent after the login
+    pub login_redirect: String,
+}
+
+impl OidcConfig {
+    pub fn from_env() -> Option<Self> {
+        let issuer = std::env::var("OIDC_ISSUER").ok()?;
+
+        Some(OidcConfig {
+            issuer: issuer.trim_end_matches('/').to_string(),
+            client_id: std::env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID must be set"),
+            client_secret: std::env::var("OIDC_CLIENT_SECRET").ok(),
+            redirect_uri: std::env::var("OIDC_REDIRECT_URI").expect("OIDC_REDIRECT_URI must be set"),
+            scopes: std::env::var("OIDC_SCOPES").unwrap_or("openid profile email".to_string()),
+            auto_provision: std::env::var("OIDC_AUTO_PROVISION").unwrap_or("false".to_string())
+                == "true",
+            username_claim: std::env::var("OIDC_USERNAME_CLAIM")
+                .unwrap_or("preferred_username".to_string()),
+            role_claim: std::env::var("OIDC_ROLE_CLAIM").ok(),
+            admin_role: std::env::var("OIDC_ADMIN_ROLE").unwrap_or("admin".to_string()),
+            login_redirect: std::env::var("OIDC_LOGIN_REDIRECT").unwrap_or("/".to_string()),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct OidcService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+    config: Option<OidcConfig>,
+    client: Client<HttpsConnector<HttpConnector>>,
+    metadata: Arc<OnceCell<OidcProviderMetadata>>,
+}
+
+fn random_string() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(OIDC_RANDOM_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+fn provider_error() -> AppError {
+    AppError::BadRequest {
+        error: Some("Login with the identity provider failed".to_string()),
+    }
+}
+
+impl OidcService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
+        OidcService {
+            db_pool,
+            sf,
+            config: OidcConfig::from_env(),
+            client: Client::builder().build(HttpsConnector::new()),
+            metadata: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn config(&self) -> Result<&OidcConfig, AppError> {
+        self.config.as_ref().ok_or(AppError::NotFound {
+            error: "OIDC login is not configured".to_string(),
+        })
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(&self, request: Request<Body>) -> Result<T, AppError> {
+        let uri = request.uri().clone();
+
+        let response = self.client.request(request).await.map_err(|e| {
+            tracing::error!("Error requesting {} from identity provider: {}", uri, e);
+            provider_error()
+        })?;
+
+        let status = response.status();
+        let body = to_bytes(response.into_body()).await.map_err(|e| {
+            tracing::error!("Error reading response of {}: {}", uri, e);
+            provider_error()
+        })?;
+
+        if !status.is_success() {
+            tracing::error!(
+                "Identity provider responded to {} with {}: {}",
+                uri,
+                status,
+                String::from_utf8_lossy(&body)
+            );
+            return Err(provider_error());
+        }
+
+        serde_json::from_slice(&body).map_err(|e| {
+            tracing::error!("Error parsing response of {}: {}", uri, e);
+            provider_error()
+        })
+    }
+
+    /// The discovery document is loaded on first use and kept afterward
+    async fn metadata(&self) -> Result<&OidcProviderMetadata, AppError> {
+        let config = self.config()?;
+
+        self.metadata
+            .get_or_try_init(|| async {
+                let request = Request::get(format!(
+                    "{}/.well-known/openid-configuration",
+                    config.issuer
+                ))
+                .body(Body::empty())
+                .map_err(|_| AppError::InternalError)?;
+
+                let metadata: OidcProviderMetadata = self.fetch_json(request).await?;
+
+                if metadata.issuer.trim_end_matches('/') != config.issuer {
+                    tracing::error!(
+                        "Issuer {} of the discovery document does not match {}",
+                        metadata.issuer,
+                        config.issuer
+                    );
+                    return Err(provider_error());
+                }
+
+                Ok(metadata)
+            })
+            .await
+    }
+
+    /// URL of the identity provider the browser is sent to, together with the state to check
+    /// the callback against
+    pub async fn start_login(&self) -> Result<(String, OidcLoginState), AppError> {
+        let config = self.config()?;
+        let metadata = self.metadata().await?;
+
+        let login_state = OidcLoginState {
+            state: random_string(),
+            nonce: random_string(),
+            code_verifier: random_string(),
+        };
+
+        let code_challenge =
+            BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(login_state.code_verifier.as_bytes()));
+
+        let query = serde_urlencoded::to_string([
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("redirect_uri", &config.redirect_uri),
+            ("scope", &config.scopes),
+            ("state", &login_state.state),
+            ("nonce", &login_state.nonce),
+            ("code_challenge", &code_challenge),
+            ("code_challenge_method", "S256"),
+        ])
+        .map_err(|_| AppError::InternalError)?;
+
+        let separator = if metadata.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        Ok((
+            format!("{}{}{}", metadata.authorization_endpoint, separator, query),
+            login_state,
+        ))
+    }
+
+    /// Exchanges the code of the callback and returns the validated claims of the ID token.
+    /// The token comes straight from the token endpoint of the issuer, so the TLS connection
+    /// authenticates it instead of its signature.
+    pub async fn finish_login(
+        &self,
+        code: &str,
+        login_state: &OidcLoginState,
+    ) -> Result<OidcIdTokenClaims, AppError> {
+        let config = self.config()?;
+        let metadata = self.metadata().await?;
+
+        let form = serde_urlencoded::to_string([
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &config.redirect_uri),
+            ("client_id", &config.client_id),
+            ("code_verifier", &login_state.code_verifier),
+        ])
+        .map_err(|_| AppError::InternalError)?;
+
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(&metadata.token_endpoint)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+
+        if let Some(client_secret) = &config.client_secret {
+            let credentials = serde_urlencoded::to_string([("", &config.client_id)])
+                .and_then(|id| {
+                    serde_urlencoded::to_string([("", client_secret)])
+                        .map(|secret| format!("{}:{}", &id[1..], &secret[1..]))
+                })
+                .map_err(|_| AppError::InternalError)?;
+
+            request = request.header(
+                AUTHORIZATION,
+                format!("Basic {}", BASE64_STANDARD.encode(credentials)),
+            );
+        }
+
+        let request = request
+            .body(Body::from(form))
+            .map_err(|_| AppError::InternalError)?;
+
+        let token_response: OidcTokenResponse = self.fetch_json(request).await?;
+
+        let payload = token_response
+            .id_token
+            .split('.')
+            .nth(1)
+            .and_then(|payload| BASE64_URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok())
+            .ok_or_else(|| {
+                tracing::error!("Malformed ID token from identity provider");
+                provider_error()
+            })?;
+
+        let claims: OidcIdTokenClaims = serde_json::from_slice(&payload).map_err(|e| {
+            tracing::error!("Error parsing ID token claims: {}", e);
+            provider_error()
+        })?;
+
+        let audience_matches = match &claims.aud {
+            serde_json::Value::String(audience) => audience == &config.client_id,
+            serde_json::Value::Array(audiences) => audiences
+                .iter()
+                .any(|audience| audience.as_str() == Some(config.client_id.as_str())),
+            _ => false,
+        };
+
+        if claims.iss.trim_end_matches('/') != config.issuer
+            || !audience_matches
+            || claims.exp <= Utc::now().timestamp()
+            || claims.nonce.as_deref() != Some(login_state.nonce.as_str())
+        {
+            tracing::error!("ID token of subject {} failed validation", claims.sub);
+            return Err(provider_error());
+        }
+
+        Ok(claims)
+    }
+
+    /// Role of the user as stated by the configured claim, `None` if no claim is configured
+    pub fn get_role_from_claims(&self, claims: &OidcIdTokenClaims) -> Result<Option<Role>, AppError> {
+        let config = self.config()?;
+
+        let Some(role_claim) = &config.role_claim else {
+            return Ok(None);
+        };
+
+        let is_admin = match claims.claims.get(role_claim) {
+            Some(serde_json::Value::String(role)) => role == &config.admin_role,
+            Some(serde_json::Value::Array(roles)) => roles
+                .iter()
+                .any(|role| role.as_str() == Some(config.admin_role.as_str())),
+            _ => false,
+        };
+
+        Ok(Some(if is_admin { Role::Admin } else { Role::User }))
+    }
+
+    pub fn get_username_from_claims(&self, claims: &OidcIdTokenClaims) -> Result<Option<String>, AppError> {
+        let config = self.config()?;
+
+        Ok(claims
+            .claims
+            .get(&config.username_claim)
+            .and_then(|username| username.as_str())
+            .map(str::to_string))
+    }
+
+    pub async fn get_link(
+        &self,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<OidcLinkModel>, AppError> {
+        sqlx::query_as!(
+            OidcLinkModel,
+            "SELECT * FROM oidc_links WHERE issuer = $1 AND subject = $2",
+            issuer,
+            subject
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting OIDC link of subject {}: {}", subject, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_links(&self, user_id: UserId) -> Result<Vec<OidcLinkModel>, AppError> {
+        sqlx::query_as!(
+            OidcLinkModel,
+            "SELECT * FROM oidc_links WHERE user_id = $1 ORDER BY created_at DESC",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting OIDC links of user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn create_link(
+        &self,
+        user_id: UserId,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<(), AppError> {
+        let id = self.sf.next_id().map_err(|_| AppError::InternalError)? as i64;
+
+        sqlx::query!(
+            "INSERT INTO oidc_links (id, user_id, issuer, subject) VALUES ($1, $2, $3, $4)",
+            id,
+            user_id,
+            issuer,
+            subject
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error linking subject {} to user {}: {}", subject, user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn delete_link(&self, user_id: UserId, link_id: i64) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            "DELETE FROM oidc_links WHERE id = $1 AND user_id = $2",
+            link_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting OIDC link {}: {}", link_id, e);
+            AppError::InternalError
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound {
+                error: "Link not found".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ use crate::response::error_handling::AppError;
 use crate::services::session_service::UserId;
 use sonyflake::Sonyflake;
 use sqlx::types::JsonValue;
+use tokio::sync::broadcast;
 use crate::model::internal::operation_status::OperationStatus;
 use crate::model::internal::operation_type::OperationType;
 
@@ -11,11 +12,30 @@ use crate::model::internal::operation_type::OperationType;
 pub struct OperationService {
     db_pool: KosmosPool,
     sf: Sonyflake,
+    events: broadcast::Sender<OperationModel>,
 }
 
+/// Updates which a subscriber didn't receive yet are dropped once this many are buffered
+const EVENT_CAPACITY: usize = 256;
+
 impl OperationService {
     pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
-        OperationService { db_pool, sf }
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        OperationService {
+            db_pool,
+            sf,
+            events,
+        }
+    }
+
+    /// Receives every created or updated operation of all users
+    pub fn subscribe(&self) -> broadcast::Receiver<OperationModel> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, operation: OperationModel) {
+        // Sending only fails if nobody is subscribed
+        let _ = self.events.send(operation);
     }
 
     pub async fn create_operation(
@@ -40,6 +60,7 @@ impl OperationService {
                 tracing::error!("Error creating operation: {}", e);
                 AppError::InternalError
             })?;
+        self.publish(operation.clone());
         Ok(operation)
     }
 
@@ -52,33 +73,60 @@ impl OperationService {
         let has_ended = operation_status != OperationStatus::Pending
             && operation_status != OperationStatus::Interrupted;
 
-        if has_ended {
-            sqlx::query!(
-                "UPDATE operations SET operation_status = $1, result = $2, ended_at = now() WHERE id = $3",
+        let operation = if has_ended {
+            sqlx::query_as!(
+                OperationModel,
+                "UPDATE operations SET operation_status = $1, result = $2, ended_at = now() WHERE id = $3 RETURNING *",
                 operation_status as i16,
                 result,
                 operation_id
             )
-            .execute(&self.db_pool)
+            .fetch_one(&self.db_pool)
             .await
-            .map_err(|e| {
-                tracing::error!("Error updating operation {}: {}", operation_id, e);
-                AppError::InternalError
-            })?;
         } else {
-            sqlx::query!(
-                "UPDATE operations SET operation_status = $1, result = $2 WHERE id = $3",
+            sqlx::query_as!(
+                OperationModel,
+                "UPDATE operations SET operation_status = $1, result = $2 WHERE id = $3 RETURNING *",
                 operation_status as i16,
                 result,
                 operation_id
             )
-            .execute(&self.db_pool)
+            .fetch_one(&self.db_pool)
             .await
-            .map_err(|e| {
-                tracing::error!("Error updating operation {}: {}", operation_id, e);
-                AppError::InternalError
-            })?;
         }
+        .map_err(|e| {
+            tracing::error!("Error updating operation {}: {}", operation_id, e);
+            AppError::InternalError
+        })?;
+        self.publish(operation);
+        Ok(())
+    }
+
+    /// Records how many of the operation's items are done, `item` names the one finished last
+    pub async fn update_progress(
+        &self,
+        operation_id: i64,
+        done: i32,
+        total: i32,
+        errors: i32,
+        item: Option<String>,
+    ) -> Result<(), AppError> {
+        let operation = sqlx::query_as!(
+            OperationModel,
+            "UPDATE operations SET progress_done = $1, progress_total = $2, progress_errors = $3, progress_item = $4 WHERE id = $5 RETURNING *",
+            done,
+            total,
+            errors,
+            item,
+            operation_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error updating progress of operation {}: {}", operation_id, e);
+            AppError::InternalError
+        })?;
+        self.publish(operation);
         Ok(())
     }
 
@@ -154,7 +202,8 @@ impl OperationService {
 
     pub async fn startup_prepare(&self) {
         let _ = sqlx::query!(
-            "UPDATE operations SET operation_status = $1 WHERE operation_status = $2",
+            "UPDATE operations SET operation_status = $1 WHERE operation_status = $2
+             AND NOT EXISTS (SELECT 1 FROM jobs WHERE jobs.operation_id = operations.id AND jobs.dead_at IS NULL)",
             OperationStatus::Interrupted as i16,
             OperationStatus::Pending as i16
         )
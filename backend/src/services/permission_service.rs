@@ -3,7 +3,7 @@ use crate::model::user::UserModel;
 use crate::response::error_handling::AppError;
 use crate::services::session_service::SessionService;
 use crate::services::user_service::UserService;
-use tower_sessions::Session;
+use crate::session::AuthSession;
 
 #[derive(Clone)]
 pub struct PermissionService {
@@ -19,7 +19,7 @@ impl PermissionService {
 
     pub async fn verify_permission(
         &self,
-        session: &Session,
+        session: &AuthSession,
         permission: Permission,
     ) -> Result<UserModel, AppError> {
         let user_id = SessionService::check_logged_in(&session).await?;
@@ -39,7 +39,7 @@ impl PermissionService {
 
     pub async fn verify_permissions(
         &self,
-        session: &Session,
+        session: &AuthSession,
         permissions: Vec<Permission>,
     ) -> Result<UserModel, AppError> {
         let user_id = SessionService::check_logged_in(&session).await?;
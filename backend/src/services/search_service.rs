@@ -1,19 +1,49 @@
-use crate::db::KosmosPool;
+use std::collections::HashMap;
+use tokio::io::AsyncReadExt;
+use chrono::NaiveDate;
+use sqlx::QueryBuilder;
+use crate::db::{KosmosDb, KosmosPool};
+use crate::document::KosmosTextExtractor;
 use crate::model::file::{FileModel, FileModelDTO};
 use crate::model::folder::{FolderModel, FolderModelDTO};
+use crate::model::internal::file_type::FileType;
 use crate::response::error_handling::AppError;
+use crate::routes::api::v1::auth::file::{SortByFiles, SortOrder};
+use crate::services::file_service::FormatSource;
+use crate::services::operation_service::OperationService;
 use crate::services::session_service::UserId;
+use crate::storage;
+use crate::storage::KosmosStorage;
+use crate::utils::search_query::{Comparison, SearchFilter, SearchFlag, SearchQuery, SearchTerm};
 use serde::Serialize;
 use ts_rs::TS;
 
+const PDF_MIME_TYPE: &str = "application/pdf";
+/// Only the start of large files is indexed, a tsvector can't exceed 1MB
+const CONTENT_INDEX_BYTES: usize = 256 * 1024;
+/// Bounds of the match in a snippet, replaced by `<mark>` after escaping the snippet
+const SNIPPET_START: char = '\u{E000}';
+const SNIPPET_STOP: char = '\u{E001}';
+
 #[derive(Clone)]
 pub struct SearchService {
     db_pool: KosmosPool,
+    storage: KosmosStorage,
+    text_extractor: Option<KosmosTextExtractor>,
+}
+
+pub struct SearchOptions {
+    /// Sorted by relevance if not set
+    pub sort_by: Option<SortByFiles>,
+    pub sort_order: Option<SortOrder>,
+    pub limit: i64,
+    pub page: i64,
 }
 
 pub struct ExplorerSearchResponse {
     pub files: Vec<FileModel>,
     pub folders: Vec<FolderModel>,
+    pub snippets: HashMap<i64, String>,
 }
 
 #[derive(Serialize, TS)]
@@ -21,79 +51,474 @@ pub struct ExplorerSearchResponse {
 pub struct ExplorerSearchDTO {
     pub files: Vec<FileModelDTO>,
     pub folders: Vec<FolderModelDTO>,
+    /// HTML excerpts of the matched content by file id, matches are wrapped in `<mark>`
+    pub snippets: HashMap<String, String>,
 }
 
 impl SearchService {
-    pub fn new(db_pool: KosmosPool) -> Self {
-        SearchService { db_pool }
+    pub fn new(
+        db_pool: KosmosPool,
+        storage: KosmosStorage,
+        text_extractor: Option<KosmosTextExtractor>,
+    ) -> Self {
+        SearchService {
+            db_pool,
+            storage,
+            text_extractor,
+        }
+    }
+
+    /// Files of these types are searchable by their content
+    pub fn supports_file_type(file_type: FileType) -> bool {
+        matches!(file_type, FileType::Editable | FileType::Document)
     }
 
+    /// Without a sort, files matching by name come first, followed by the best content matches
     pub async fn search_files(
         &self,
         user_id: &UserId,
-        query: &String,
+        query: &SearchQuery,
+        options: &SearchOptions,
     ) -> Result<Vec<FileModel>, AppError> {
-        sqlx::query_as!(
-            FileModel,
-            "SELECT *
-            FROM files
-            WHERE user_id = $1
-              AND deleted_at IS NULL
-              AND file_name
-                ILIKE '%' || $2 || '%'
-            ORDER BY updated_at DESC
-            LIMIT 25",
-            user_id,
-            query
-        )
-        .fetch_all(&self.db_pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to search files: {}", e);
-            AppError::InternalError
-        })
+        let content_query = query.content_query();
+        let mut builder: QueryBuilder<KosmosDb> = QueryBuilder::new(
+            "SELECT f.* FROM files f LEFT JOIN file_contents c ON c.file_id = f.id WHERE f.user_id = ",
+        );
+        builder.push_bind(*user_id);
+        builder.push(" AND f.deleted_at IS NULL");
+
+        if !query.terms.is_empty() {
+            builder.push(" AND (");
+            Self::push_name_match(&mut builder, "f.file_name", &query.terms);
+            builder.push(" OR c.content_tsv @@ websearch_to_tsquery('simple', ");
+            builder.push_bind(content_query.clone());
+            builder.push("))");
+        }
+
+        for filter in &query.filters {
+            match filter {
+                SearchFilter::Type { file_types, .. } => {
+                    builder.push(" AND f.file_type = ANY(");
+                    builder.push_bind(
+                        file_types
+                            .iter()
+                            .map(|file_type| *file_type as i16)
+                            .collect::<Vec<_>>(),
+                    );
+                    builder.push(")");
+                }
+                SearchFilter::Size(comparison, size) => {
+                    builder.push(" AND f.file_size ");
+                    builder.push(Self::comparison_operator(*comparison));
+                    builder.push_bind(*size);
+                }
+                SearchFilter::Modified(comparison, date) => {
+                    Self::push_date_match(&mut builder, "f.updated_at", *comparison, *date);
+                }
+                SearchFilter::In(segments) => {
+                    Self::push_folder_match(&mut builder, "f.parent_folder_id", *user_id, segments);
+                }
+                SearchFilter::Is(SearchFlag::Favorite) => {
+                    builder.push(" AND f.favorite");
+                }
+                SearchFilter::Is(SearchFlag::Shared) => {
+                    builder.push(" AND EXISTS (SELECT 1 FROM shares s WHERE s.file_id = f.id)");
+                }
+                SearchFilter::Ext(extensions) => {
+                    builder.push(" AND f.file_name ILIKE ANY(");
+                    builder.push_bind(
+                        extensions
+                            .iter()
+                            .map(|ext| format!("%.{}", Self::escape_like(ext)))
+                            .collect::<Vec<_>>(),
+                    );
+                    builder.push(")");
+                }
+                SearchFilter::Tag(names) => {
+                    builder.push(
+                        " AND EXISTS (SELECT 1 FROM files_on_tag ft JOIN tags t ON t.id = ft.tag_id WHERE ft.file_id = f.id AND LOWER(t.name) = ANY(",
+                    );
+                    builder.push_bind(names.clone());
+                    builder.push("))");
+                }
+            }
+        }
+
+        builder.push(" ORDER BY ");
+        match &options.sort_by {
+            Some(SortByFiles::Name) => builder.push("LOWER(f.file_name)"),
+            Some(SortByFiles::FileSize) => builder.push("f.file_size"),
+            Some(SortByFiles::CreatedAt) => builder.push("f.created_at"),
+            Some(SortByFiles::UpdatedAt) => builder.push("f.updated_at"),
+            None if !query.terms.is_empty() => {
+                builder.push("(");
+                Self::push_name_match(&mut builder, "f.file_name", &query.terms);
+                builder.push(")::INT + COALESCE(ts_rank(c.content_tsv, websearch_to_tsquery('simple', ");
+                builder.push_bind(content_query);
+                builder.push(")), 0)")
+            }
+            None => builder.push("f.updated_at"),
+        };
+        Self::push_order_and_page(&mut builder, options);
+
+        builder
+            .build_query_as::<FileModel>()
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to search files: {}", e);
+                AppError::InternalError
+            })
     }
 
     pub async fn search_folders(
         &self,
         user_id: &UserId,
-        query: &String,
+        query: &SearchQuery,
+        options: &SearchOptions,
     ) -> Result<Vec<FolderModel>, AppError> {
-        sqlx::query_as!(
-            FolderModel,
-            "SELECT *
-            FROM folder
-            WHERE user_id = $1
-            AND folder_name
-                ILIKE '%' || $2 || '%'
-            ORDER BY updated_at DESC
-            LIMIT 25",
-            user_id,
-            query
+        let mut builder: QueryBuilder<KosmosDb> =
+            QueryBuilder::new("SELECT fo.* FROM folder fo WHERE fo.user_id = ");
+        builder.push_bind(*user_id);
+        builder.push(" AND fo.deleted_at IS NULL");
+
+        if !query.terms.is_empty() {
+            builder.push(" AND ");
+            Self::push_name_match(&mut builder, "fo.folder_name", &query.terms);
+        }
+
+        for filter in &query.filters {
+            match filter {
+                SearchFilter::Modified(comparison, date) => {
+                    Self::push_date_match(&mut builder, "fo.updated_at", *comparison, *date);
+                }
+                SearchFilter::In(segments) => {
+                    Self::push_folder_match(&mut builder, "fo.parent_id", *user_id, segments);
+                }
+                SearchFilter::Is(SearchFlag::Favorite) => {
+                    builder.push(" AND fo.favorite");
+                }
+                SearchFilter::Is(SearchFlag::Shared) => {
+                    builder.push(" AND EXISTS (SELECT 1 FROM shares s WHERE s.folder_id = fo.id)");
+                }
+                SearchFilter::Tag(names) => {
+                    builder.push(
+                        " AND EXISTS (SELECT 1 FROM folders_on_tag ft JOIN tags t ON t.id = ft.tag_id WHERE ft.folder_id = fo.id AND LOWER(t.name) = ANY(",
+                    );
+                    builder.push_bind(names.clone());
+                    builder.push("))");
+                }
+                // Filters which exclude folders altogether are handled by the caller
+                SearchFilter::Type { .. } | SearchFilter::Size(..) | SearchFilter::Ext(_) => {}
+            }
+        }
+
+        builder.push(" ORDER BY ");
+        match &options.sort_by {
+            Some(SortByFiles::Name) | Some(SortByFiles::FileSize) => {
+                builder.push("LOWER(fo.folder_name)")
+            }
+            Some(SortByFiles::CreatedAt) => builder.push("fo.created_at"),
+            Some(SortByFiles::UpdatedAt) | None => builder.push("fo.updated_at"),
+        };
+        Self::push_order_and_page(&mut builder, options);
+
+        builder
+            .build_query_as::<FolderModel>()
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to search folders: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    /// Every term has to be part of the name
+    fn push_name_match(builder: &mut QueryBuilder<KosmosDb>, column: &str, terms: &[SearchTerm]) {
+        builder.push("(");
+        for (index, term) in terms.iter().enumerate() {
+            if index > 0 {
+                builder.push(" AND ");
+            }
+            let (SearchTerm::Word(text) | SearchTerm::Phrase(text)) = term;
+            builder.push(column);
+            builder.push(" ILIKE ");
+            builder.push_bind(format!("%{}%", Self::escape_like(text)));
+        }
+        builder.push(")");
+    }
+
+    /// Dates cover the whole day, so `<` is before and `>` after it
+    fn push_date_match(
+        builder: &mut QueryBuilder<KosmosDb>,
+        column: &str,
+        comparison: Comparison,
+        date: NaiveDate,
+    ) {
+        let next_day = date.succ_opt().unwrap_or(date);
+        let bounds = match comparison {
+            Comparison::Less => (None, Some(date)),
+            Comparison::LessOrEqual => (None, Some(next_day)),
+            Comparison::Equal => (Some(date), Some(next_day)),
+            Comparison::GreaterOrEqual => (Some(date), None),
+            Comparison::Greater => (Some(next_day), None),
+        };
+
+        if let Some(start) = bounds.0 {
+            builder.push(format!(" AND {} >= ", column));
+            builder.push_bind(start);
+        }
+        if let Some(end) = bounds.1 {
+            builder.push(format!(" AND {} < ", column));
+            builder.push_bind(end);
+        }
+    }
+
+    /// Matches items in the folder at the path or any of its subfolders
+    fn push_folder_match(
+        builder: &mut QueryBuilder<KosmosDb>,
+        column: &str,
+        user_id: UserId,
+        segments: &[String],
+    ) {
+        // Everything is inside the root
+        if segments.is_empty() {
+            return;
+        }
+
+        builder.push(format!(" AND {} IN (", column));
+        builder.push(
+            "WITH RECURSIVE path AS (SELECT p.id, 1 AS depth FROM folder p WHERE p.parent_id IS NULL AND p.deleted_at IS NULL AND p.user_id = ",
+        );
+        builder.push_bind(user_id);
+        builder.push(" AND p.folder_name = ");
+        builder.push_bind(segments[0].clone());
+        builder.push(
+            " UNION ALL SELECT p.id, path.depth + 1 FROM folder p JOIN path ON p.parent_id = path.id WHERE p.deleted_at IS NULL AND p.folder_name = (",
+        );
+        builder.push_bind(segments.to_vec());
+        builder.push("::TEXT[])[path.depth + 1]), subtree AS (SELECT id FROM path WHERE depth = ");
+        builder.push_bind(segments.len() as i32);
+        builder.push(
+            " UNION ALL SELECT p.id FROM folder p JOIN subtree ON p.parent_id = subtree.id) SELECT id FROM subtree)",
+        );
+    }
+
+    fn push_order_and_page(builder: &mut QueryBuilder<KosmosDb>, options: &SearchOptions) {
+        // Relevance is sorted best first unless asked otherwise
+        let ascending = match &options.sort_order {
+            Some(order) => order == &SortOrder::Asc,
+            None => options.sort_by.is_some(),
+        };
+        builder.push(if ascending { " ASC" } else { " DESC" });
+        builder.push(" LIMIT ");
+        builder.push_bind(options.limit);
+        builder.push(" OFFSET ");
+        builder.push_bind(options.page * options.limit);
+    }
+
+    fn comparison_operator(comparison: Comparison) -> &'static str {
+        match comparison {
+            Comparison::Less => " < ",
+            Comparison::LessOrEqual => " <= ",
+            Comparison::Equal => " = ",
+            Comparison::GreaterOrEqual => " >= ",
+            Comparison::Greater => " > ",
+        }
+    }
+
+    fn escape_like(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+
+    /// Highlighted excerpts of the files whose content matches the query
+    pub async fn get_snippets(
+        &self,
+        file_ids: &[i64],
+        query: &String,
+    ) -> Result<HashMap<i64, String>, AppError> {
+        let options = format!(
+            "StartSel={}, StopSel={}, MaxWords=24, MinWords=8, MaxFragments=2, FragmentDelimiter=…",
+            SNIPPET_START, SNIPPET_STOP
+        );
+
+        let rows = sqlx::query!(
+            r#"SELECT c.file_id, ts_headline('simple', c.content, q, $3) AS "snippet!"
+            FROM file_contents c, websearch_to_tsquery('simple', $2) q
+            WHERE c.file_id = ANY($1)
+              AND c.content_tsv @@ q"#,
+            file_ids,
+            query,
+            options
         )
         .fetch_all(&self.db_pool)
         .await
         .map_err(|e| {
-            tracing::error!("Failed to search folders: {}", e);
+            tracing::error!("Failed to get search snippets: {}", e);
             AppError::InternalError
-        })
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let snippet = quick_xml::escape::escape(row.snippet.trim())
+                    .replace(SNIPPET_START, "<mark>")
+                    .replace(SNIPPET_STOP, "</mark>");
+                (row.file_id, snippet)
+            })
+            .collect())
+    }
+
+    /// Replaces the indexed content of a file
+    pub async fn index_content(&self, file_id: i64, content: &str) -> Result<(), AppError> {
+        let mut end = content.len().min(CONTENT_INDEX_BYTES);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        // Postgres text can't hold NUL
+        let content = content[..end].replace('\0', "");
+
+        sqlx::query!(
+            "INSERT INTO file_contents (file_id, content) VALUES ($1, $2)
+             ON CONFLICT (file_id) DO UPDATE SET content = excluded.content",
+            file_id,
+            content
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error indexing content of file {}: {}", file_id, e);
+            AppError::InternalError
+        })?;
+        Ok(())
+    }
+
+    pub async fn delete_content(&self, file_id: i64) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM file_contents WHERE file_id = $1", file_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting indexed content of file {}: {}", file_id, e);
+                AppError::InternalError
+            })?;
+        Ok(())
+    }
+
+    /// Text of the file, `None` if it has no text that can be indexed
+    async fn extract_content(&self, source: &FormatSource) -> std::io::Result<Option<String>> {
+        if !Self::supports_file_type(source.file_type) {
+            return Ok(None);
+        }
+
+        if source.mime_type == PDF_MIME_TYPE {
+            let Some(text_extractor) = &self.text_extractor else {
+                return Ok(None);
+            };
+
+            let local_copy = storage::local_copy(&self.storage, &source.content_key).await?;
+            return text_extractor.text(&local_copy.path).await.map(Some);
+        }
+
+        let size = self.storage.size(&source.content_key).await?;
+        let mut buff = vec![];
+        self.storage
+            .get_range(&source.content_key, 0, size.min(CONTENT_INDEX_BYTES as u64))
+            .await?
+            .read_to_end(&mut buff)
+            .await?;
+
+        // Binary content labeled as text isn't worth indexing
+        if buff.contains(&0) {
+            return Ok(None);
+        }
+
+        Ok(Some(String::from_utf8_lossy(&buff).to_string()))
+    }
+
+    /// Indexes the content of the files, fails if none of them could be read
+    pub async fn index_sources(
+        &self,
+        sources: Vec<FormatSource>,
+        operation_service: &OperationService,
+        operation_id: i64,
+    ) -> Result<(), AppError> {
+        let total = sources.len() as i32;
+        let mut failures = 0;
+
+        for (done, source) in sources.into_iter().enumerate() {
+            let indexed = match self.extract_content(&source).await {
+                Ok(Some(content)) => self.index_content(source.file_id, &content).await,
+                Ok(None) => self.delete_content(source.file_id).await,
+                Err(e) => {
+                    tracing::error!("Error extracting content of file {}: {}", source.file_id, e);
+                    Err(AppError::InternalError)
+                }
+            };
+
+            if indexed.is_err() {
+                failures += 1;
+            }
+
+            let _ = operation_service
+                .update_progress(
+                    operation_id,
+                    done as i32 + 1,
+                    total,
+                    failures,
+                    Some(source.file_name),
+                )
+                .await;
+        }
+
+        if total > 0 && failures == total {
+            return Err(AppError::InternalError);
+        }
+
+        Ok(())
     }
 
     pub async fn search_explorer(
         &self,
         user_id: UserId,
         query: String,
+        options: SearchOptions,
     ) -> Result<ExplorerSearchResponse, AppError> {
+        let query = SearchQuery::parse(&query)?;
+
         if query.is_empty() {
             return Ok(ExplorerSearchResponse {
                 files: vec![],
                 folders: vec![],
+                snippets: HashMap::new(),
             });
         };
 
+        let files = if query.includes_files() {
+            self.search_files(&user_id, &query, &options).await?
+        } else {
+            vec![]
+        };
+
+        let folders = if query.includes_folders() {
+            self.search_folders(&user_id, &query, &options).await?
+        } else {
+            vec![]
+        };
+
+        let snippets = if query.terms.is_empty() {
+            HashMap::new()
+        } else {
+            let file_ids = files.iter().map(|file| file.id).collect::<Vec<_>>();
+            self.get_snippets(&file_ids, &query.content_query()).await?
+        };
+
         Ok(ExplorerSearchResponse {
-            files: self.search_files(&user_id, &query).await?,
-            folders: self.search_folders(&user_id, &query).await?,
+            files,
+            folders,
+            snippets,
         })
     }
 }
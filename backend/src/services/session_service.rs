@@ -1,13 +1,31 @@
-use crate::constants::SESSION_USER_ID_KEY;
+use crate::constants::{
+    SESSION_OIDC_LOGIN_KEY, SESSION_PENDING_TWO_FACTOR_KEY, SESSION_USER_ID_KEY,
+};
+use crate::model::oidc::OidcLoginState;
 use crate::response::error_handling::AppError;
+use crate::session::AuthSession;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use tower_sessions::Session;
 use webauthn_rs::prelude::{DiscoverableAuthentication, PasskeyRegistration};
 
+/// Seconds the second factor of a login can be entered in
+const PENDING_TWO_FACTOR_TIMEOUT: i64 = 5 * 60;
+const MAX_TWO_FACTOR_ATTEMPTS: u8 = 5;
+
 #[derive(Clone)]
 pub struct SessionService;
 
 pub type UserId = i64;
 
+/// A login whose password was correct, but which still needs the second factor
+#[derive(Serialize, Deserialize)]
+pub struct PendingTwoFactor {
+    pub user_id: UserId,
+    started_at: i64,
+    attempts: u8,
+}
+
 impl SessionService {
     /// Asynchronously retrieves the user ID from the session.
     ///
@@ -25,11 +43,28 @@ impl SessionService {
         id.unwrap_or_else(|_| None)
     }
 
-    /// Asynchronously checks if a user is logged in based on the session.
+    /// Asynchronously retrieves the user ID from the API token of the request, or else from the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - A reference to the session and API token of the request.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Option<UserId>` representing the user ID if the request is authenticated, otherwise returns `None`.
+    ///
+    pub async fn get_auth_user_id(session: &AuthSession) -> Option<UserId> {
+        match session.token_user_id() {
+            Some(user_id) => Some(user_id),
+            None => SessionService::get_user_id(session).await,
+        }
+    }
+
+    /// Asynchronously checks if a user is logged in based on the API token or the session.
     ///
     /// # Arguments
     ///
-    /// * `session` - A reference to the session to check for logged-in status.
+    /// * `session` - A reference to the session and API token to check for logged-in status.
     ///
     /// # Returns
     ///
@@ -39,8 +74,8 @@ impl SessionService {
     ///
     /// Returns an `AppError::NotLoggedIn` error if the user is not logged in.
     ///
-    pub async fn check_logged_in(session: &Session) -> Result<UserId, AppError> {
-        let id = SessionService::get_user_id(session).await;
+    pub async fn check_logged_in(session: &AuthSession) -> Result<UserId, AppError> {
+        let id = SessionService::get_auth_user_id(session).await;
         match id {
             Some(user) => Ok(user),
             None => Err(AppError::NotLoggedIn)?,
@@ -133,4 +168,109 @@ impl SessionService {
             .await
             .unwrap();
     }
+
+    /// Logs the session in as the user. The session gets a new ID, so an ID which was known before
+    /// the login can't be used to take over the logged-in session.
+    pub async fn log_in(session: &Session, user_id: UserId) -> Result<(), AppError> {
+        session.cycle_id().await.map_err(|e| {
+            tracing::error!("Failed to cycle session id: {}", e);
+            AppError::InternalError
+        })?;
+
+        session
+            .insert(SESSION_USER_ID_KEY, user_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to insert session: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    pub async fn set_pending_two_factor(session: &Session, user_id: UserId) -> Result<(), AppError> {
+        let pending = PendingTwoFactor {
+            user_id,
+            started_at: Utc::now().timestamp(),
+            attempts: 0,
+        };
+
+        session
+            .insert(SESSION_PENDING_TWO_FACTOR_KEY, pending)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to insert pending two-factor login: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    pub async fn get_pending_two_factor(session: &Session) -> Result<PendingTwoFactor, AppError> {
+        let pending = session
+            .get::<PendingTwoFactor>(SESSION_PENDING_TWO_FACTOR_KEY)
+            .await
+            .unwrap_or(None)
+            .filter(|pending| Utc::now().timestamp() - pending.started_at < PENDING_TWO_FACTOR_TIMEOUT);
+
+        match pending {
+            None => Err(AppError::BadRequest {
+                error: Some("No pending login".to_string()),
+            }),
+            Some(pending) => Ok(pending),
+        }
+    }
+
+    /// Counts a wrong code, the login has to start over after too many of them. The number of
+    /// codes a user can try overall is limited by the TOTP service.
+    pub async fn record_failed_two_factor(
+        session: &Session,
+        mut pending: PendingTwoFactor,
+    ) -> Result<(), AppError> {
+        pending.attempts += 1;
+
+        if pending.attempts >= MAX_TWO_FACTOR_ATTEMPTS {
+            return SessionService::clear_pending_two_factor(session).await;
+        }
+
+        session
+            .insert(SESSION_PENDING_TWO_FACTOR_KEY, pending)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to update pending two-factor login: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    pub async fn clear_pending_two_factor(session: &Session) -> Result<(), AppError> {
+        session
+            .remove::<PendingTwoFactor>(SESSION_PENDING_TWO_FACTOR_KEY)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                tracing::error!("Failed to remove pending two-factor login: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    pub async fn set_oidc_login(session: &Session, state: OidcLoginState) -> Result<(), AppError> {
+        session
+            .insert(SESSION_OIDC_LOGIN_KEY, state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to insert OIDC login state: {}", e);
+                AppError::InternalError
+            })
+    }
+
+    /// The state can only be used for a single callback
+    pub async fn take_oidc_login(session: &Session) -> Result<OidcLoginState, AppError> {
+        let state = session
+            .remove::<OidcLoginState>(SESSION_OIDC_LOGIN_KEY)
+            .await
+            .unwrap_or(None);
+
+        match state {
+            None => Err(AppError::BadRequest {
+                error: Some("OIDC login state not found".to_string()),
+            }),
+            Some(state) => Ok(state),
+        }
+    }
 }
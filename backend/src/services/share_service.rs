@@ -108,6 +108,8 @@ impl ShareService {
             "SELECT DISTINCT ON (f.id) f.*, s.uuid as share_uuid, u.username as share_target_username",
         );
 
+        let is_folder = share_type == AccessShareItemType::Folder;
+
         match share_type {
             AccessShareItemType::File | AccessShareItemType::Zip => query.push(
                 " FROM files f
@@ -128,6 +130,10 @@ impl ShareService {
 
         query.push_bind(user_id);
 
+        if is_folder {
+            query.push(" AND f.deleted_at IS NULL");
+        }
+
         query.build().sql().into()
     }
 
@@ -432,9 +438,10 @@ impl ShareService {
         let share = sqlx::query_as!(
             ShareModel,
             "INSERT INTO shares
-            (id, user_id, share_type, folder_id, access_limit, password, expires_at)
+            (id, user_id, share_type, folder_id, access_limit, password, expires_at,
+             share_mode, upload_size_limit, upload_count_limit)
             VALUES
-            ($1, $2, $3, $4, $5, $6, $7)
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             RETURNING *",
             self.sf.next_id().map_err(|e| {
                 tracing::error!("Error creating share id: {}", e);
@@ -445,7 +452,10 @@ impl ShareService {
             folder_id,
             data.limit,
             data.password,
-            data.expires_at
+            data.expires_at,
+            data.mode as i16,
+            data.upload_size_limit,
+            data.upload_count_limit
         )
         .fetch_one(&self.db_pool)
         .await
@@ -611,6 +621,47 @@ impl ShareService {
         Ok(())
     }
 
+    /// Counts an upload through the share, `false` if it would exceed the upload limits
+    pub async fn add_share_upload(&self, share_id: i64, size: i64) -> Result<bool, AppError> {
+        let updated = sqlx::query!(
+            "UPDATE shares
+            SET uploaded_size = uploaded_size + $1, uploaded_count = uploaded_count + 1
+            WHERE id = $2
+            AND (upload_size_limit IS NULL OR uploaded_size + $1 <= upload_size_limit)
+            AND (upload_count_limit IS NULL OR uploaded_count + 1 <= upload_count_limit)",
+            size,
+            share_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error counting share upload: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Takes back an upload counted with `add_share_upload` which could not be stored
+    pub async fn remove_share_upload(&self, share_id: i64, size: i64) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE shares
+            SET uploaded_size = GREATEST(uploaded_size - $1, 0),
+                uploaded_count = GREATEST(uploaded_count - 1, 0)
+            WHERE id = $2",
+            size,
+            share_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error taking back share upload: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
     pub async fn handle_share_access(&self, share_id: i64) {
         let _ = sqlx::query!(
             "UPDATE shares SET last_access = now() WHERE id = $1",
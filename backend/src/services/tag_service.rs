@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use itertools::Itertools;
+use sonyflake::Sonyflake;
+use crate::db::KosmosPool;
+use crate::model::file::{FileModel, FileModelDTO};
+use crate::model::folder::{FolderModel, FolderModelDTO};
+use crate::model::tag::{TagModel, TagModelDTO};
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+
+#[derive(Clone)]
+pub struct TagService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+}
+
+fn tag_exists(name: &str) -> AppError {
+    AppError::DataConflict {
+        error: format!("Tag {} already exists", name),
+    }
+}
+
+impl TagService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
+        TagService { db_pool, sf }
+    }
+
+    pub async fn get_tags(&self, user_id: UserId) -> Result<Vec<TagModel>, AppError> {
+        sqlx::query_as!(
+            TagModel,
+            "SELECT * FROM tags WHERE user_id = $1 ORDER BY LOWER(name)",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting tags for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_tag(&self, user_id: UserId, tag_id: i64) -> Result<TagModel, AppError> {
+        sqlx::query_as!(
+            TagModel,
+            "SELECT * FROM tags WHERE id = $1 AND user_id = $2",
+            tag_id,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting tag {}: {}", tag_id, e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::NotFound {
+            error: "Tag not found".to_string(),
+        })
+    }
+
+    pub async fn create_tag(
+        &self,
+        user_id: UserId,
+        name: &str,
+        color: Option<&str>,
+    ) -> Result<TagModel, AppError> {
+        sqlx::query_as!(
+            TagModel,
+            "INSERT INTO tags (id, user_id, name, color) VALUES ($1, $2, $3, $4) RETURNING *",
+            self.sf.next_id().map_err(|_| AppError::InternalError)? as i64,
+            user_id,
+            name,
+            color
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => tag_exists(name),
+            e => {
+                tracing::error!("Error creating tag for user {}: {}", user_id, e);
+                AppError::InternalError
+            }
+        })
+    }
+
+    pub async fn update_tag(
+        &self,
+        user_id: UserId,
+        tag_id: i64,
+        name: &str,
+        color: Option<&str>,
+    ) -> Result<TagModel, AppError> {
+        sqlx::query_as!(
+            TagModel,
+            "UPDATE tags SET name = $1, color = $2 WHERE id = $3 AND user_id = $4 RETURNING *",
+            name,
+            color,
+            tag_id,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => tag_exists(name),
+            e => {
+                tracing::error!("Error updating tag {}: {}", tag_id, e);
+                AppError::InternalError
+            }
+        })?
+        .ok_or(AppError::NotFound {
+            error: "Tag not found".to_string(),
+        })
+    }
+
+    pub async fn delete_tag(&self, user_id: UserId, tag_id: i64) -> Result<(), AppError> {
+        sqlx::query!(
+            "DELETE FROM tags WHERE id = $1 AND user_id = $2",
+            tag_id,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting tag {}: {}", tag_id, e);
+            AppError::InternalError
+        })
+        .map(|_| ())
+    }
+
+    /// Links every tag to every item, items and tags of other users are skipped
+    pub async fn tag_items(
+        &self,
+        user_id: UserId,
+        tag_ids: &[i64],
+        file_ids: &[i64],
+        folder_ids: &[i64],
+    ) -> Result<(), AppError> {
+        let mut tx = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "INSERT INTO files_on_tag (tag_id, file_id)
+             SELECT t.id, f.id FROM tags t, files f
+             WHERE t.id = ANY($1) AND t.user_id = $3 AND f.id = ANY($2) AND f.user_id = $3
+             ON CONFLICT DO NOTHING",
+            tag_ids,
+            file_ids,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error tagging files for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "INSERT INTO folders_on_tag (tag_id, folder_id)
+             SELECT t.id, f.id FROM tags t, folder f
+             WHERE t.id = ANY($1) AND t.user_id = $3 AND f.id = ANY($2) AND f.user_id = $3
+             ON CONFLICT DO NOTHING",
+            tag_ids,
+            folder_ids,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error tagging folders for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            tracing::error!("Error committing tags: {}", e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn untag_items(
+        &self,
+        user_id: UserId,
+        tag_ids: &[i64],
+        file_ids: &[i64],
+        folder_ids: &[i64],
+    ) -> Result<(), AppError> {
+        let mut tx = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "DELETE FROM files_on_tag ft USING tags t
+             WHERE t.id = ft.tag_id AND t.user_id = $3 AND ft.tag_id = ANY($1) AND ft.file_id = ANY($2)",
+            tag_ids,
+            file_ids,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error untagging files for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!(
+            "DELETE FROM folders_on_tag ft USING tags t
+             WHERE t.id = ft.tag_id AND t.user_id = $3 AND ft.tag_id = ANY($1) AND ft.folder_id = ANY($2)",
+            tag_ids,
+            folder_ids,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error untagging folders for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        tx.commit().await.map_err(|e| {
+            tracing::error!("Error committing tags: {}", e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_files_by_tag(&self, tag_id: i64) -> Result<Vec<FileModel>, AppError> {
+        sqlx::query_as!(
+            FileModel,
+            "SELECT f.*
+                FROM files_on_tag ft
+                INNER JOIN files f ON f.id = ft.file_id
+                WHERE ft.tag_id = $1
+                  AND f.deleted_at IS NULL
+                ORDER BY LOWER(f.file_name)",
+            tag_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting files for tag {}: {}", tag_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_folders_by_tag(&self, tag_id: i64) -> Result<Vec<FolderModel>, AppError> {
+        sqlx::query_as!(
+            FolderModel,
+            "SELECT f.*
+                FROM folders_on_tag ft
+                INNER JOIN folder f ON f.id = ft.folder_id
+                WHERE ft.tag_id = $1
+                  AND f.deleted_at IS NULL
+                ORDER BY LOWER(f.folder_name)",
+            tag_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting folders for tag {}: {}", tag_id, e);
+            AppError::InternalError
+        })
+    }
+
+    async fn get_tags_by_file(&self, file_ids: &[i64]) -> Result<HashMap<i64, Vec<TagModel>>, AppError> {
+        let rows = sqlx::query!(
+            "SELECT ft.file_id, t.*
+                FROM files_on_tag ft
+                INNER JOIN tags t ON t.id = ft.tag_id
+                WHERE ft.file_id = ANY($1)
+                ORDER BY LOWER(t.name)",
+            file_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting tags of files: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.file_id,
+                    TagModel {
+                        id: row.id,
+                        user_id: row.user_id,
+                        name: row.name,
+                        color: row.color,
+                        created_at: row.created_at,
+                        updated_at: row.updated_at,
+                    },
+                )
+            })
+            .into_group_map())
+    }
+
+    async fn get_tags_by_folder(
+        &self,
+        folder_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<TagModel>>, AppError> {
+        let rows = sqlx::query!(
+            "SELECT ft.folder_id, t.*
+                FROM folders_on_tag ft
+                INNER JOIN tags t ON t.id = ft.tag_id
+                WHERE ft.folder_id = ANY($1)
+                ORDER BY LOWER(t.name)",
+            folder_ids
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting tags of folders: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.folder_id,
+                    TagModel {
+                        id: row.id,
+                        user_id: row.user_id,
+                        name: row.name,
+                        color: row.color,
+                        created_at: row.created_at,
+                        updated_at: row.updated_at,
+                    },
+                )
+            })
+            .into_group_map())
+    }
+
+    /// Files as DTOs including their tags, which are only shown to the owner
+    pub async fn tag_files(&self, files: Vec<FileModel>) -> Result<Vec<FileModelDTO>, AppError> {
+        let file_ids = files.iter().map(|file| file.id).collect::<Vec<_>>();
+        let mut tags = self.get_tags_by_file(&file_ids).await?;
+
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let file_tags = tags.remove(&file.id).unwrap_or_default();
+                let mut dto = FileModelDTO::from(file);
+                dto.tags = file_tags.into_iter().map(TagModelDTO::from).collect();
+                dto
+            })
+            .collect())
+    }
+
+    /// Folders as DTOs including their tags, which are only shown to the owner
+    pub async fn tag_folders(
+        &self,
+        folders: Vec<FolderModel>,
+    ) -> Result<Vec<FolderModelDTO>, AppError> {
+        let folder_ids = folders.iter().map(|folder| folder.id).collect::<Vec<_>>();
+        let mut tags = self.get_tags_by_folder(&folder_ids).await?;
+
+        Ok(folders
+            .into_iter()
+            .map(|folder| {
+                let folder_tags = tags.remove(&folder.id).unwrap_or_default();
+                let mut dto = FolderModelDTO::from(folder);
+                dto.tags = folder_tags.into_iter().map(TagModelDTO::from).collect();
+                dto
+            })
+            .collect())
+    }
+}
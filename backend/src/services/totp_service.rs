@@ -0,0 +1,319 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sonyflake::Sonyflake;
+
+use crate::db::KosmosPool;
+use crate::model::totp::TotpModel;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+use crate::utils::totp;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+/// Failed codes after which checking is paused, the pause doubles every time up to a day
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+const LOCKOUT_SECONDS: f64 = 5.0 * 60.0;
+const MAX_LOCKOUT_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+
+#[derive(Clone)]
+pub struct TotpService {
+    db_pool: KosmosPool,
+    sf: Sonyflake,
+}
+
+impl TotpService {
+    pub fn new(db_pool: KosmosPool, sf: Sonyflake) -> Self {
+        TotpService { db_pool, sf }
+    }
+
+    /// Recovery codes are compared without formatting and case
+    fn hash_recovery_code(code: &str) -> String {
+        let normalized: String = code
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        format!("{:x}", Sha256::digest(normalized.as_bytes()))
+    }
+
+    pub async fn get_totp(&self, user_id: UserId) -> Result<Option<TotpModel>, AppError> {
+        sqlx::query_as!(
+            TotpModel,
+            "SELECT * FROM totp WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting TOTP of user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn is_enabled(&self, user_id: UserId) -> Result<bool, AppError> {
+        Ok(self
+            .get_totp(user_id)
+            .await?
+            .is_some_and(|totp| totp.enabled))
+    }
+
+    /// Creates a new secret, which only takes effect after a first code was verified
+    pub async fn start_enrollment(&self, user_id: UserId) -> Result<String, AppError> {
+        if self.is_enabled(user_id).await? {
+            return Err(AppError::DataConflict {
+                error: "Two-factor authentication is already enabled".to_string(),
+            });
+        }
+
+        let secret = totp::generate_secret();
+
+        sqlx::query!(
+            "INSERT INTO totp (user_id, secret) VALUES ($1, $2)
+             ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL",
+            user_id,
+            secret
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error starting TOTP enrollment of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(secret)
+    }
+
+    /// Checks the code against the secret and marks its time step as used
+    async fn use_code(&self, totp_model: &TotpModel, code: &str) -> Result<bool, AppError> {
+        let Some(step) = totp::verify_code(&totp_model.secret, code)? else {
+            return Ok(false);
+        };
+
+        let result = sqlx::query!(
+            "UPDATE totp SET last_used_step = $1
+             WHERE user_id = $2 AND (last_used_step IS NULL OR last_used_step < $1)",
+            step,
+            totp_model.user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error using TOTP code of user {}: {}", totp_model.user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn use_recovery_code(&self, user_id: UserId, code: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE totp_recovery_codes SET used_at = now()
+             WHERE id = (SELECT id
+                         FROM totp_recovery_codes
+                         WHERE user_id = $1
+                           AND code_hash = $2
+                           AND used_at IS NULL
+                         LIMIT 1)",
+            user_id,
+            Self::hash_recovery_code(code)
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error using recovery code of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Confirms the enrollment with a first code and returns the recovery codes
+    pub async fn enable(&self, user_id: UserId, code: &str) -> Result<Vec<String>, AppError> {
+        let totp_model = self.get_totp(user_id).await?.ok_or(AppError::BadRequest {
+            error: Some("Two-factor authentication enrollment not started".to_string()),
+        })?;
+
+        if totp_model.enabled {
+            return Err(AppError::DataConflict {
+                error: "Two-factor authentication is already enabled".to_string(),
+            });
+        }
+
+        if !self.use_code(&totp_model, code).await? {
+            return Err(AppError::Forbidden {
+                error: Some("Invalid code".to_string()),
+            });
+        }
+
+        sqlx::query!("UPDATE totp SET enabled = true WHERE user_id = $1", user_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error enabling TOTP of user {}: {}", user_id, e);
+                AppError::InternalError
+            })?;
+
+        self.regenerate_recovery_codes(user_id).await
+    }
+
+    /// Checks a code of the authenticator app or an unused recovery code of the user. Guesses
+    /// are limited per user, so starting new logins doesn't allow more of them.
+    pub async fn verify(&self, user_id: UserId, code: &str) -> Result<bool, AppError> {
+        let Some(totp_model) = self.get_totp(user_id).await?.filter(|totp| totp.enabled) else {
+            return Ok(false);
+        };
+
+        if !self.count_attempt(user_id).await? {
+            return Err(AppError::Forbidden {
+                error: Some("Too many invalid codes, try again later".to_string()),
+            });
+        }
+
+        let is_valid = self.use_code(&totp_model, code).await?
+            || self.use_recovery_code(user_id, code).await?;
+
+        if is_valid {
+            self.reset_attempts(user_id).await?;
+        }
+
+        Ok(is_valid)
+    }
+
+    /// Counts the attempt before the code is checked, so concurrent guesses can't pass the limit.
+    /// `false` while checking is paused.
+    async fn count_attempt(&self, user_id: UserId) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE totp
+             SET failed_attempts = failed_attempts + 1,
+                 locked_until = CASE
+                     WHEN (failed_attempts + 1) % $1 = 0
+                         THEN now() + make_interval(secs => LEAST(
+                             $2 * POWER(2, (failed_attempts + 1) / $1 - 1), $3))
+                     ELSE locked_until
+                     END
+             WHERE user_id = $4
+               AND (locked_until IS NULL OR locked_until <= now())",
+            MAX_FAILED_ATTEMPTS,
+            LOCKOUT_SECONDS,
+            MAX_LOCKOUT_SECONDS,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error counting TOTP attempt of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn reset_attempts(&self, user_id: UserId) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE totp SET failed_attempts = 0, locked_until = NULL WHERE user_id = $1",
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error resetting TOTP attempts of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    /// Replaces all recovery codes of the user, the plain codes are only returned here
+    pub async fn regenerate_recovery_codes(&self, user_id: UserId) -> Result<Vec<String>, AppError> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let code: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(RECOVERY_CODE_LENGTH)
+                    .map(|c| char::from(c).to_ascii_lowercase())
+                    .collect();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect();
+
+        let mut ids = Vec::with_capacity(codes.len());
+        for _ in &codes {
+            ids.push(self.sf.next_id().map_err(|_| AppError::InternalError)? as i64);
+        }
+
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| Self::hash_recovery_code(code))
+            .collect();
+
+        let mut transaction = self.db_pool.begin().await.map_err(|e| {
+            tracing::error!("Error starting transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting recovery codes of user {}: {}", user_id, e);
+                AppError::InternalError
+            })?;
+
+        sqlx::query!(
+            "INSERT INTO totp_recovery_codes (id, user_id, code_hash)
+             SELECT UNNEST($1::BIGINT[]), $2, UNNEST($3::TEXT[])",
+            &ids,
+            user_id,
+            &hashes
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating recovery codes of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        transaction.commit().await.map_err(|e| {
+            tracing::error!("Error committing transaction: {}", e);
+            AppError::InternalError
+        })?;
+
+        Ok(codes)
+    }
+
+    pub async fn get_remaining_recovery_codes(&self, user_id: UserId) -> Result<i64, AppError> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM totp_recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+            user_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error counting recovery codes of user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Removes the second factor of the user, including the recovery codes
+    pub async fn disable(&self, user_id: UserId) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM totp_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting recovery codes of user {}: {}", user_id, e);
+                AppError::InternalError
+            })?;
+
+        sqlx::query!("DELETE FROM totp WHERE user_id = $1", user_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting TOTP of user {}: {}", user_id, e);
+                AppError::InternalError
+            })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::db::KosmosPool;
+use crate::model::upload::TusUploadModel;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::UserId;
+use crate::storage;
+
+/// Days after which an upload that has not received any data is discarded
+const STALE_UPLOAD_DAYS: i32 = 7;
+const STALE_UPLOAD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+pub struct UploadService {
+    db_pool: KosmosPool,
+    /// Uploads which are receiving data right now
+    active_uploads: Arc<Mutex<HashSet<i64>>>,
+}
+
+/// Appending to the upload is reserved for its holder until it is dropped
+pub struct UploadClaim {
+    upload_id: i64,
+    active_uploads: Arc<Mutex<HashSet<i64>>>,
+}
+
+impl Drop for UploadClaim {
+    fn drop(&mut self) {
+        if let Ok(mut active_uploads) = self.active_uploads.lock() {
+            active_uploads.remove(&self.upload_id);
+        }
+    }
+}
+
+impl UploadService {
+    pub fn new(db_pool: KosmosPool) -> Self {
+        UploadService {
+            db_pool,
+            active_uploads: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Partial uploads are kept in the local temp folder until they are complete
+    pub fn get_partial_upload_path(upload_id: i64) -> PathBuf {
+        storage::temp_location().join(format!("tus_{}.part", upload_id))
+    }
+
+    pub async fn create_upload(
+        &self,
+        upload_id: i64,
+        user_id: UserId,
+        file_name: String,
+        mime_type: String,
+        parent_folder_id: Option<i64>,
+        upload_length: i64,
+    ) -> Result<TusUploadModel, AppError> {
+        tokio::fs::File::create(Self::get_partial_upload_path(upload_id))
+            .await
+            .map_err(|e| {
+                tracing::error!("Error creating partial upload file {}: {}", upload_id, e);
+                AppError::InternalError
+            })?;
+
+        sqlx::query_as!(
+            TusUploadModel,
+            "INSERT INTO tus_uploads (id, user_id, file_name, mime_type, parent_folder_id, upload_length)
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            upload_id,
+            user_id,
+            file_name,
+            mime_type,
+            parent_folder_id,
+            upload_length
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating upload {} for user {}: {}", upload_id, user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_upload(
+        &self,
+        upload_id: i64,
+        user_id: UserId,
+    ) -> Result<TusUploadModel, AppError> {
+        sqlx::query_as!(
+            TusUploadModel,
+            "SELECT * FROM tus_uploads WHERE id = $1 AND user_id = $2",
+            upload_id,
+            user_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting upload {}: {}", upload_id, e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::NotFound {
+            error: "Upload not found".to_string(),
+        })
+    }
+
+    /// Only one request at a time may append to an upload, `None` if another one already does
+    pub fn claim_upload(&self, upload_id: i64) -> Option<UploadClaim> {
+        let mut active_uploads = self.active_uploads.lock().ok()?;
+
+        if !active_uploads.insert(upload_id) {
+            return None;
+        }
+
+        Some(UploadClaim {
+            upload_id,
+            active_uploads: self.active_uploads.clone(),
+        })
+    }
+
+    /// Moves the offset forward, returns false if the offset was changed in the meantime
+    pub async fn update_upload_offset(
+        &self,
+        upload_id: i64,
+        expected_offset: i64,
+        upload_offset: i64,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            "UPDATE tus_uploads SET upload_offset = $1 WHERE id = $2 AND upload_offset = $3",
+            upload_offset,
+            upload_id,
+            expected_offset
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error updating offset of upload {}: {}", upload_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_upload(&self, upload_id: i64) -> Result<(), AppError> {
+        sqlx::query!("DELETE FROM tus_uploads WHERE id = $1", upload_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error deleting upload {}: {}", upload_id, e);
+                AppError::InternalError
+            })?;
+
+        let _ = tokio::fs::remove_file(Self::get_partial_upload_path(upload_id)).await;
+
+        Ok(())
+    }
+
+    /// Storage which is reserved by unfinished uploads of the user
+    pub async fn get_reserved_storage(&self, user_id: UserId) -> Result<i64, AppError> {
+        sqlx::query_scalar!(
+            "SELECT COALESCE(SUM(upload_length), 0)::BIGINT FROM tus_uploads WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map(|sum| sum.unwrap_or(0))
+        .map_err(|e| {
+            tracing::error!("Error getting reserved storage for user {}: {}", user_id, e);
+            AppError::InternalError
+        })
+    }
+
+    /// Discards uploads without new data for a while, which frees the storage they reserve
+    async fn remove_stale_uploads(&self) -> Result<usize, AppError> {
+        let active_uploads = self
+            .active_uploads
+            .lock()
+            .map(|active_uploads| active_uploads.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        let stale_uploads = sqlx::query_scalar!(
+            "DELETE FROM tus_uploads
+             WHERE updated_at < now() - make_interval(days => $1) AND NOT (id = ANY($2))
+             RETURNING id",
+            STALE_UPLOAD_DAYS,
+            &active_uploads
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error removing stale uploads: {}", e);
+            AppError::InternalError
+        })?;
+
+        for upload_id in &stale_uploads {
+            let _ = tokio::fs::remove_file(Self::get_partial_upload_path(*upload_id)).await;
+        }
+
+        Ok(stale_uploads.len())
+    }
+
+    pub async fn startup_prepare(&self) {
+        let removed = self.remove_stale_uploads().await.unwrap_or_default();
+        tracing::info!("Removed {} stale uploads", removed);
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(STALE_UPLOAD_INTERVAL);
+            // The first tick completes immediately, stale uploads were just removed
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let _ = service.remove_stale_uploads().await;
+            }
+        });
+    }
+}
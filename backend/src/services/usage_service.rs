@@ -15,6 +15,7 @@ impl UsageService {
         UsageService { db_pool }
     }
 
+    /// Usage is the logical size of the files, even if their content is shared through a blob
     fn get_storage_query(user_id: UserId, marked_deleted: Option<bool>) -> String {
         let mut query: QueryBuilder<KosmosDb> =
             QueryBuilder::new("SELECT SUM(file_size), COUNT(id) FROM files WHERE user_id = ");
@@ -46,6 +47,24 @@ impl UsageService {
             })
     }
 
+    /// Previous versions count towards the storage of a user as well
+    pub async fn get_version_storage_usage(
+        &self,
+        user_id: UserId,
+    ) -> Result<UsageSumData, AppError> {
+        sqlx::query_as!(
+            UsageSumData,
+            "SELECT SUM(file_size), COUNT(id) FROM file_versions WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching user version storage usage: {}", e);
+            AppError::InternalError
+        })
+    }
+
     pub async fn get_file_type_stats(
         &self,
         user_id: UserId,
@@ -1,3 +1,4 @@
+use crate::constants::FALLBACK_STORAGE_LIMIT;
 use crate::db::KosmosDbResult;
 use crate::model::role::Role;
 use crate::model::user::UserModel;
@@ -6,9 +7,16 @@ use crate::services::session_service::{SessionService, UserId};
 use crate::KosmosPool;
 use serde::{Deserialize, Serialize};
 use sonyflake::Sonyflake;
-use tower_sessions::Session;
+use crate::session::AuthSession;
+use crate::utils::auth;
+use chrono::Utc;
 use validator::Validate;
 
+/// Failed passwords after which checking is paused, the pause doubles every time up to a day
+const MAX_FAILED_LOGIN_ATTEMPTS: i32 = 5;
+const LOGIN_LOCKOUT_SECONDS: f64 = 5.0 * 60.0;
+const MAX_LOGIN_LOCKOUT_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+
 #[derive(Deserialize)]
 pub struct AccountUpdatePayload {
     pub username: Option<String>,
@@ -41,6 +49,82 @@ impl UserService {
         UserService { db_pool, sf }
     }
 
+    /// Checks the account password when logging in, failed attempts are counted across the API
+    /// and WebDAV and pause the checking for a while
+    pub async fn verify_login_password(
+        &self,
+        user: &UserModel,
+        password: &str,
+    ) -> Result<bool, AppError> {
+        if user
+            .login_locked_until
+            .is_some_and(|locked_until| locked_until > Utc::now())
+        {
+            return Err(AppError::Forbidden {
+                error: Some("Too many invalid passwords, try again later".to_string()),
+            });
+        }
+
+        let is_valid = auth::verify_password(password, &user.password_hash)?;
+
+        if !is_valid {
+            self.count_failed_login(user.id).await?;
+        } else if user.failed_login_attempts > 0 {
+            self.reset_failed_logins(user.id).await?;
+        }
+
+        Ok(is_valid)
+    }
+
+    async fn count_failed_login(&self, user_id: UserId) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users
+             SET failed_login_attempts = failed_login_attempts + 1,
+                 login_locked_until = CASE
+                     WHEN (failed_login_attempts + 1) % $1 = 0
+                         THEN now() + make_interval(secs => LEAST(
+                             $2 * POWER(2, (failed_login_attempts + 1) / $1 - 1), $3))
+                     ELSE login_locked_until
+                     END
+             WHERE id = $4",
+            MAX_FAILED_LOGIN_ATTEMPTS,
+            LOGIN_LOCKOUT_SECONDS,
+            MAX_LOGIN_LOCKOUT_SECONDS,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error counting failed login of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    async fn reset_failed_logins(&self, user_id: UserId) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users SET failed_login_attempts = 0, login_locked_until = NULL WHERE id = $1",
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error resetting failed logins of user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        Ok(())
+    }
+
+    /// Storage limit of users which sign up on their own
+    pub fn default_storage_limit() -> i64 {
+        match std::env::var("DEFAULT_STORAGE_LIMIT") {
+            Ok(env) => env.parse::<i64>().unwrap_or(FALLBACK_STORAGE_LIMIT),
+            Err(_) => FALLBACK_STORAGE_LIMIT,
+        }
+    }
+
     pub async fn create_user(
         &self,
         username: String,
@@ -87,9 +171,9 @@ impl UserService {
 
     pub async fn check_user_optional(
         &self,
-        session: &Session,
+        session: &AuthSession,
     ) -> Result<Option<UserModel>, AppError> {
-        let user_id = SessionService::get_user_id(session).await;
+        let user_id = SessionService::get_auth_user_id(session).await;
 
         match user_id {
             Some(user) => self.get_user(user).await,
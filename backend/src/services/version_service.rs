@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use crate::db::KosmosPool;
+use crate::model::version::FileVersionModel;
+use crate::response::error_handling::AppError;
+use crate::services::blob_service::BlobService;
+use crate::services::session_service::UserId;
+
+const VERSION_RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+pub struct VersionService {
+    db_pool: KosmosPool,
+    blob_service: BlobService,
+}
+
+impl VersionService {
+    pub fn new(db_pool: KosmosPool, blob_service: BlobService) -> Self {
+        VersionService {
+            db_pool,
+            blob_service,
+        }
+    }
+
+    pub async fn get_versions(&self, file_id: i64) -> Result<Vec<FileVersionModel>, AppError> {
+        sqlx::query_as!(
+            FileVersionModel,
+            "SELECT * FROM file_versions WHERE file_id = $1 ORDER BY created_at DESC",
+            file_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting versions of file {}: {}", file_id, e);
+            AppError::InternalError
+        })
+    }
+
+    pub async fn get_version(
+        &self,
+        file_id: i64,
+        version_id: i64,
+    ) -> Result<FileVersionModel, AppError> {
+        sqlx::query_as!(
+            FileVersionModel,
+            "SELECT * FROM file_versions WHERE id = $1 AND file_id = $2",
+            version_id,
+            file_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting version {}: {}", version_id, e);
+            AppError::InternalError
+        })?
+        .ok_or(AppError::NotFound {
+            error: "Version not found".to_string(),
+        })
+    }
+
+    pub async fn delete_version(&self, version_id: i64) -> Result<(), AppError> {
+        let deleted = sqlx::query_scalar!(
+            "DELETE FROM file_versions WHERE id = $1 RETURNING blob_hash",
+            version_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting version {}: {}", version_id, e);
+            AppError::InternalError
+        })?;
+
+        if let Some(blob_hash) = deleted {
+            self.blob_service.release(&blob_hash).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_versions_for_file(&self, file_id: i64) -> Result<(), AppError> {
+        let deleted = sqlx::query_scalar!(
+            "DELETE FROM file_versions WHERE file_id = $1 RETURNING blob_hash",
+            file_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error deleting versions of file {}: {}", file_id, e);
+            AppError::InternalError
+        })?;
+
+        for blob_hash in deleted {
+            self.blob_service.release(&blob_hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes versions exceeding the retention policy of the user, or of all users if none is given
+    pub async fn apply_retention(&self, user_id: Option<UserId>) -> Result<(), AppError> {
+        let expired = sqlx::query_scalar!(
+            "SELECT v.id
+             FROM (SELECT fv.id,
+                          fv.user_id,
+                          fv.created_at,
+                          ROW_NUMBER() OVER (PARTITION BY fv.file_id ORDER BY fv.created_at DESC) AS position
+                   FROM file_versions fv
+                   WHERE $1::BIGINT IS NULL OR fv.user_id = $1) v
+                      JOIN users u ON u.id = v.user_id
+             WHERE (u.version_retention_count IS NOT NULL AND v.position > u.version_retention_count)
+                OR (u.version_retention_days IS NOT NULL
+                 AND v.created_at < now() - make_interval(days => u.version_retention_days))",
+            user_id
+        )
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting expired versions: {}", e);
+            AppError::InternalError
+        })?;
+
+        for version_id in expired {
+            self.delete_version(version_id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_retention(
+        &self,
+        user_id: UserId,
+        retention_count: Option<i32>,
+        retention_days: Option<i32>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users SET version_retention_count = $1, version_retention_days = $2 WHERE id = $3",
+            retention_count,
+            retention_days,
+            user_id
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error updating version retention for user {}: {}", user_id, e);
+            AppError::InternalError
+        })?;
+
+        self.apply_retention(Some(user_id)).await
+    }
+
+    pub async fn startup_prepare(&self) {
+        let _ = self.apply_retention(None).await;
+        tracing::info!("Applied version retention");
+
+        // Versions also expire by age while a file is not edited
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(VERSION_RETENTION_INTERVAL);
+            // The first tick completes immediately, the retention above already ran
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+                let _ = service.apply_retention(None).await;
+            }
+        });
+    }
+}
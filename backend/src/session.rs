@@ -1,16 +1,274 @@
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
-use tower_sessions::cookie::time::Duration;
-use crate::constants::SESSION_NAME;
+use std::net::SocketAddr;
+use std::ops::Deref;
 
-pub type KosmosSession = SessionManagerLayer<MemoryStore>;
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts, OriginalUri, Request};
+use axum::http::header::{AUTHORIZATION, USER_AGENT};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+use tower_sessions::cookie::SameSite;
+use tower_sessions::session::{Id, Record};
+use tower_sessions::{session_store, Expiry, Session, SessionManagerLayer, SessionStore};
+use crate::constants::{
+    SESSION_IP_ADDRESS_KEY, SESSION_NAME, SESSION_USER_AGENT_KEY, SESSION_USER_ID_KEY,
+};
+use crate::db::KosmosPool;
+use crate::response::error_handling::AppError;
+use crate::services::session_service::{SessionService, UserId};
+use crate::state::AppState;
 
-pub fn init() -> KosmosSession {
-    let store = MemoryStore::default();
+pub type KosmosSession = SessionManagerLayer<PostgresSessionStore>;
 
+pub fn init(db_pool: &KosmosPool) -> KosmosSession {
+    let store = PostgresSessionStore::new(db_pool.clone());
+
+    // Saving on every request keeps the inactivity expiry and the last activity up to date.
+    // Lax is needed for the cookie to be sent on the redirect back from an identity provider.
     let session_layer = SessionManagerLayer::new(store)
         .with_name(SESSION_NAME)
         .with_secure(false)
+        .with_same_site(SameSite::Lax)
+        .with_always_save(true)
         .with_expiry(Expiry::OnInactivity(Duration::days(30)));
 
     session_layer
 }
+
+/// Stores sessions in the database, so they survive restarts of the server
+#[derive(Clone, Debug)]
+pub struct PostgresSessionStore {
+    db_pool: KosmosPool,
+}
+
+fn backend_error(e: sqlx::Error) -> session_store::Error {
+    tracing::error!("Error accessing session store: {}", e);
+    session_store::Error::Backend(e.to_string())
+}
+
+fn to_utc(date: OffsetDateTime) -> DateTime<Utc> {
+    DateTime::from_timestamp(date.unix_timestamp(), date.nanosecond()).unwrap_or_default()
+}
+
+impl PostgresSessionStore {
+    pub fn new(db_pool: KosmosPool) -> Self {
+        PostgresSessionStore { db_pool }
+    }
+
+    fn data_string(record: &Record, key: &str) -> Option<String> {
+        record
+            .data
+            .get(key)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    }
+}
+
+#[async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let data = serde_json::to_value(&record.data)
+            .map_err(|e| session_store::Error::Encode(e.to_string()))?;
+
+        loop {
+            let result = sqlx::query!(
+                "INSERT INTO sessions (id, user_id, data, user_agent, ip_address, expires_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (id) DO NOTHING",
+                record.id.to_string(),
+                record.data.get(SESSION_USER_ID_KEY).and_then(|value| value.as_i64()),
+                data,
+                Self::data_string(record, SESSION_USER_AGENT_KEY),
+                Self::data_string(record, SESSION_IP_ADDRESS_KEY),
+                to_utc(record.expiry_date)
+            )
+            .execute(&self.db_pool)
+            .await
+            .map_err(backend_error)?;
+
+            if result.rows_affected() > 0 {
+                return Ok(());
+            }
+
+            // Session ID collision mitigation
+            record.id = Id::default();
+        }
+    }
+
+    /// Only updates sessions which still exist, a request which was running while its session
+    /// was revoked or logged out doesn't bring it back
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_value(&record.data)
+            .map_err(|e| session_store::Error::Encode(e.to_string()))?;
+
+        let result = sqlx::query!(
+            "UPDATE sessions
+             SET user_id      = $2,
+                 data         = $3,
+                 user_agent   = $4,
+                 ip_address   = $5,
+                 expires_at   = $6,
+                 last_seen_at = now()
+             WHERE id = $1",
+            record.id.to_string(),
+            record.data.get(SESSION_USER_ID_KEY).and_then(|value| value.as_i64()),
+            data,
+            Self::data_string(record, SESSION_USER_AGENT_KEY),
+            Self::data_string(record, SESSION_IP_ADDRESS_KEY),
+            to_utc(record.expiry_date)
+        )
+        .execute(&self.db_pool)
+        .await
+        .map_err(backend_error)?;
+
+        if result.rows_affected() == 0 {
+            tracing::debug!("Session {} no longer exists and is not saved", record.id);
+        }
+
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let session = sqlx::query!(
+            "SELECT data, expires_at FROM sessions WHERE id = $1 AND expires_at > now()",
+            session_id.to_string()
+        )
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(backend_error)?;
+
+        let Some(session) = session else {
+            return Ok(None);
+        };
+
+        let expiry_date = OffsetDateTime::from_unix_timestamp(session.expires_at.timestamp())
+            .map_err(|e| session_store::Error::Decode(e.to_string()))?;
+
+        Ok(Some(Record {
+            id: *session_id,
+            data: serde_json::from_value(session.data)
+                .map_err(|e| session_store::Error::Decode(e.to_string()))?,
+            expiry_date,
+        }))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sqlx::query!("DELETE FROM sessions WHERE id = $1", session_id.to_string())
+            .execute(&self.db_pool)
+            .await
+            .map_err(backend_error)?;
+
+        Ok(())
+    }
+}
+
+/// Records the device and address a logged-in session was last used from, which includes the
+/// request that logged in
+pub async fn track_session_client(
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    session: Session,
+    request: Request,
+    next: Next,
+) -> Response {
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    // Behind a reverse proxy the peer is the proxy itself, the header can be set by any client
+    // though and is only used if the server is known to run behind a proxy
+    let trust_proxy_headers =
+        std::env::var("TRUST_PROXY_HEADERS").unwrap_or("false".to_string()) == "true";
+
+    let ip_address = request
+        .headers()
+        .get("X-Forwarded-For")
+        .filter(|_| trust_proxy_headers)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|value| value.trim().to_string())
+        .or(connect_info.map(|ConnectInfo(address)| address.ip().to_string()));
+
+    let response = next.run(request).await;
+
+    if SessionService::get_user_id(&session).await.is_some() {
+        let _ = session.insert(SESSION_USER_AGENT_KEY, user_agent).await;
+        let _ = session.insert(SESSION_IP_ADDRESS_KEY, ip_address).await;
+    }
+
+    response
+}
+
+/// The session of a request together with the user of its API token, if it was sent as
+/// `Authorization: Bearer`. Dereferences to the session, so it can be used wherever a
+/// `Session` is expected.
+pub struct AuthSession {
+    session: Session,
+    token_user_id: Option<UserId>,
+}
+
+impl AuthSession {
+    pub fn token_user_id(&self) -> Option<UserId> {
+        self.token_user_id
+    }
+}
+
+impl Deref for AuthSession {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthSession {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(|(_, e)| {
+                tracing::error!("Error extracting session: {}", e);
+                AppError::InternalError
+            })?;
+
+        let Some(token) = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Ok(AuthSession {
+                session,
+                token_user_id: None,
+            });
+        };
+
+        let (user_id, scope) = state
+            .api_token_service
+            .get_token_auth(token.trim())
+            .await?
+            .ok_or(AppError::NotLoggedIn)?;
+
+        // Nested routers only see the rest of the path
+        let path = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.path().to_string(),
+            None => parts.uri.path().to_string(),
+        };
+
+        if !scope.allows(&parts.method, &path) {
+            return Err(AppError::Forbidden {
+                error: Some("Not allowed with the scope of the API token".to_string()),
+            });
+        }
+
+        Ok(AuthSession {
+            session,
+            token_user_id: Some(user_id),
+        })
+    }
+}
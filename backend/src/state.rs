@@ -4,16 +4,32 @@ use webauthn_rs::Webauthn;
 use crate::db::KosmosPool;
 use crate::response::error_handling::AppError;
 use crate::services::album_service::AlbumService;
+use crate::services::archive_service::ArchiveService;
+use crate::services::active_session_service::ActiveSessionService;
+use crate::services::api_token_service::ApiTokenService;
+use crate::services::app_password_service::AppPasswordService;
+use crate::services::blob_service::BlobService;
+use crate::services::dav_service::DavService;
 use crate::services::file_service::FileService;
 use crate::services::folder_service::FolderService;
 use crate::services::image_service::ImageService;
+use crate::services::job_service::JobService;
+use crate::services::oidc_service::OidcService;
 use crate::services::operation_service::OperationService;
 use crate::services::passkey_service::PasskeyService;
 use crate::services::permission_service::PermissionService;
 use crate::services::search_service::SearchService;
 use crate::services::share_service::ShareService;
+use crate::services::tag_service::TagService;
+use crate::services::bin_service::BinService;
+use crate::services::totp_service::TotpService;
+use crate::services::upload_service::UploadService;
 use crate::services::usage_service::UsageService;
 use crate::services::user_service::UserService;
+use crate::services::version_service::VersionService;
+use crate::storage::KosmosStorage;
+use crate::document::{KosmosPageRenderer, KosmosTextExtractor};
+use crate::video::KosmosFrameExtractor;
 
 pub type KosmosState = State<AppState>;
 
@@ -30,6 +46,20 @@ pub struct AppState {
     pub search_service: SearchService,
     pub album_service: AlbumService,
     pub passkey_service: PasskeyService,
+    pub upload_service: UploadService,
+    pub blob_service: BlobService,
+    pub version_service: VersionService,
+    pub app_password_service: AppPasswordService,
+    pub dav_service: DavService,
+    pub api_token_service: ApiTokenService,
+    pub active_session_service: ActiveSessionService,
+    pub totp_service: TotpService,
+    pub oidc_service: OidcService,
+    pub job_service: JobService,
+    pub tag_service: TagService,
+    pub bin_service: BinService,
+    pub archive_service: ArchiveService,
+    pub storage: KosmosStorage,
     pub sf: Sonyflake,
 }
 
@@ -45,19 +75,51 @@ impl AppState {
     }
 }
 
-pub fn init(db: &KosmosPool, webauthn: &Webauthn) -> AppState {
+pub fn init(
+    db: &KosmosPool,
+    webauthn: &Webauthn,
+    storage: &KosmosStorage,
+    frame_extractor: &Option<KosmosFrameExtractor>,
+    page_renderer: &Option<KosmosPageRenderer>,
+    text_extractor: &Option<KosmosTextExtractor>,
+) -> AppState {
     let sf = Sonyflake::new().expect("Failed to initialize Sonyflake");
     let user_service = UserService::new(db.clone(), sf.clone());
-    let file_service = FileService::new(db.clone(), sf.clone());
+    let blob_service = BlobService::new(db.clone(), sf.clone(), storage.clone());
+    let version_service = VersionService::new(db.clone(), blob_service.clone());
+    let file_service = FileService::new(
+        db.clone(),
+        sf.clone(),
+        storage.clone(),
+        blob_service.clone(),
+        version_service.clone(),
+    );
     let folder_service = FolderService::new(db.clone(), sf.clone());
-    let image_service = ImageService::new(db.clone(), sf.clone());
+    let image_service = ImageService::new(
+        db.clone(),
+        sf.clone(),
+        storage.clone(),
+        frame_extractor.clone(),
+        page_renderer.clone(),
+    );
     let operation_service = OperationService::new(db.clone(), sf.clone());
     let share_service = ShareService::new(db.clone(), sf.clone());
     let permission_service = PermissionService::new(user_service.clone());
     let usage_service = UsageService::new(db.clone());
-    let search_service = SearchService::new(db.clone());
+    let search_service = SearchService::new(db.clone(), storage.clone(), text_extractor.clone());
     let album_service = AlbumService::new(db.clone(), sf.clone());
     let passkey_service = PasskeyService::new(db.clone(), webauthn.clone());
+    let upload_service = UploadService::new(db.clone());
+    let app_password_service = AppPasswordService::new(db.clone(), sf.clone());
+    let dav_service = DavService::new(db.clone());
+    let api_token_service = ApiTokenService::new(db.clone(), sf.clone());
+    let active_session_service = ActiveSessionService::new(db.clone());
+    let totp_service = TotpService::new(db.clone(), sf.clone());
+    let oidc_service = OidcService::new(db.clone(), sf.clone());
+    let job_service = JobService::new(db.clone(), sf.clone(), operation_service.clone());
+    let tag_service = TagService::new(db.clone(), sf.clone());
+    let bin_service = BinService::new(db.clone(), job_service.clone());
+    let archive_service = ArchiveService::new(storage.clone());
 
     AppState {
         user_service,
@@ -71,6 +133,20 @@ pub fn init(db: &KosmosPool, webauthn: &Webauthn) -> AppState {
         search_service,
         album_service,
         passkey_service,
+        upload_service,
+        blob_service,
+        version_service,
+        app_password_service,
+        dav_service,
+        api_token_service,
+        active_session_service,
+        totp_service,
+        oidc_service,
+        job_service,
+        tag_service,
+        bin_service,
+        archive_service,
+        storage: storage.clone(),
         sf,
     }
 }
@@ -0,0 +1,124 @@
+use std::io;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+
+use crate::storage::{StorageBackend, StorageReader};
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf) -> Self {
+        LocalStorage { root }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> io::Result<u64> {
+        let path = self.path(key);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = BufWriter::new(File::create(&path).await?);
+
+        let result = match tokio::io::copy(reader, &mut file).await {
+            Ok(len) => file.flush().await.map(|_| len),
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+
+        result
+    }
+
+    async fn get(&self, key: &str) -> io::Result<StorageReader> {
+        let file = File::open(self.path(key)).await?;
+        Ok(Box::pin(file))
+    }
+
+    async fn get_range(&self, key: &str, start: u64, length: u64) -> io::Result<StorageReader> {
+        let mut file = File::open(self.path(key)).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(Box::pin(file.take(length)))
+    }
+
+    async fn size(&self, key: &str) -> io::Result<u64> {
+        tokio::fs::metadata(self.path(key)).await.map(|m| m.len())
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        tokio::fs::remove_file(self.path(key)).await
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool> {
+        tokio::fs::try_exists(self.path(key)).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let path = self.path(to);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::rename(self.path(from), path).await
+    }
+
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>> {
+        // Keys only ever contain a single folder level, so only the prefix's folder is read
+        let (folder, file_prefix) = match prefix.rsplit_once('/') {
+            Some((folder, file_prefix)) => (Some(folder), file_prefix),
+            None => (None, prefix),
+        };
+
+        let directory = match folder {
+            Some(folder) => self.root.join(folder),
+            None => self.root.clone(),
+        };
+
+        let mut keys = vec![];
+        let mut entries = match tokio::fs::read_dir(&directory).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(keys),
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(file_prefix) {
+                continue;
+            }
+            keys.push(match folder {
+                Some(folder) => format!("{}/{}", folder, name),
+                None => name,
+            });
+        }
+
+        Ok(keys)
+    }
+
+    fn local_path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.path(key))
+    }
+}
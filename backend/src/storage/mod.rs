@@ -0,0 +1,154 @@
+use std::io;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncReadExt, BufWriter};
+
+use crate::storage::local::LocalStorage;
+use crate::storage::s3::S3Storage;
+
+pub mod local;
+pub mod s3;
+
+pub type StorageReader = Pin<Box<dyn AsyncRead + Send>>;
+pub type KosmosStorage = Arc<dyn StorageBackend>;
+
+/// Storage for file contents and generated formats.
+///
+/// Objects are addressed by a relative key like `123` or `formats/123_0`.
+/// Missing objects are reported as an `io::ErrorKind::NotFound` error.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Writes all data from the reader to the object, replacing any existing content.
+    /// Returns the amount of bytes written.
+    async fn put(&self, key: &str, reader: &mut (dyn AsyncRead + Send + Unpin))
+        -> io::Result<u64>;
+
+    async fn get(&self, key: &str) -> io::Result<StorageReader>;
+
+    /// Reads `length` bytes of the object starting at `start`.
+    async fn get_range(&self, key: &str, start: u64, length: u64) -> io::Result<StorageReader>;
+
+    async fn size(&self, key: &str) -> io::Result<u64>;
+
+    async fn delete(&self, key: &str) -> io::Result<()>;
+
+    async fn exists(&self, key: &str) -> io::Result<bool>;
+
+    /// Moves an object to a new key, replacing any existing object there.
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+
+    /// Lists all keys starting with the given prefix.
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>>;
+
+    /// Path of the object on the local filesystem, if the backend stores it there.
+    fn local_path(&self, _key: &str) -> Option<PathBuf> {
+        None
+    }
+
+    async fn put_bytes(&self, key: &str, data: Vec<u8>) -> io::Result<u64> {
+        self.put(key, &mut Cursor::new(data)).await
+    }
+
+    async fn get_bytes(&self, key: &str) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![];
+        self.get(key).await?.read_to_end(&mut buffer).await?;
+        Ok(buffer)
+    }
+}
+
+pub fn file_key(file_id: i64) -> String {
+    file_id.to_string()
+}
+
+pub fn format_key(format_name: &str) -> String {
+    format!("formats/{}", format_name)
+}
+
+pub fn blob_key(hash: &str) -> String {
+    format!("blobs/{}", hash)
+}
+
+/// Key an upload is written to until its content hash is known
+pub fn staging_key(id: i64) -> String {
+    format!("temp/{}", id)
+}
+
+/// Key of the content of a file, files stored before deduplication have no blob
+pub fn content_key(file_id: i64, blob_hash: Option<&str>) -> String {
+    match blob_hash {
+        Some(hash) => blob_key(hash),
+        None => file_key(file_id),
+    }
+}
+
+/// Local working directory for temporary data, independent of the storage backend.
+pub fn temp_location() -> PathBuf {
+    let upload_location = std::env::var("UPLOAD_LOCATION").expect("UPLOAD_LOCATION must be set");
+    PathBuf::from(upload_location).join("temp")
+}
+
+/// Path in the temp location which is not used by any other task, the name starts with the prefix
+pub fn unique_temp_path(prefix: &str) -> PathBuf {
+    temp_location().join(format!("{}_{:016x}", prefix, rand::random::<u64>()))
+}
+
+/// A file on the local filesystem holding the content of a stored object.
+/// Copies made for remote backends are removed when this is dropped.
+pub struct LocalCopy {
+    pub path: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for LocalCopy {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub async fn local_copy(storage: &KosmosStorage, key: &str) -> io::Result<LocalCopy> {
+    if let Some(path) = storage.local_path(key) {
+        return Ok(LocalCopy {
+            path,
+            temporary: false,
+        });
+    }
+
+    let path = unique_temp_path(&format!("copy_{}", key.replace('/', "_")));
+
+    let copy = LocalCopy {
+        path,
+        temporary: true,
+    };
+
+    let mut reader = storage.get(key).await?;
+    let mut file = BufWriter::new(tokio::fs::File::create(&copy.path).await?);
+    tokio::io::copy(&mut reader, &mut file).await?;
+    tokio::io::AsyncWriteExt::flush(&mut file).await?;
+
+    Ok(copy)
+}
+
+pub fn init() -> KosmosStorage {
+    let backend = std::env::var("STORAGE_BACKEND").unwrap_or("local".to_string());
+
+    match backend.as_str() {
+        "local" => {
+            let upload_location =
+                std::env::var("UPLOAD_LOCATION").expect("UPLOAD_LOCATION must be set");
+            tracing::info!(name: "bootstrap", "Using local storage at {}", upload_location);
+            Arc::new(LocalStorage::new(PathBuf::from(upload_location)))
+        }
+        "s3" => {
+            let storage = S3Storage::from_env();
+            tracing::info!(name: "bootstrap", "Using S3 storage in bucket {}", storage.bucket_name());
+            Arc::new(storage)
+        }
+        _ => panic!("STORAGE_BACKEND must be either \"local\" or \"s3\""),
+    }
+}
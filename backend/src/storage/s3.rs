@@ -0,0 +1,154 @@
+use std::io;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use s3::command::Command;
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::request::tokio_backend::HyperRequest;
+use s3::request::{DataStream, Request};
+use s3::{Bucket, Region};
+use tokio::io::AsyncRead;
+use tokio_util::io::StreamReader;
+
+use crate::storage::{StorageBackend, StorageReader};
+
+/// Storage in an S3 compatible bucket, e.g. AWS S3 or MinIO
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+}
+
+fn map_s3_error(error: S3Error) -> io::Error {
+    match error {
+        S3Error::HttpFailWithBody(404, _) => io::Error::new(io::ErrorKind::NotFound, "Object not found"),
+        e => io::Error::other(e.to_string()),
+    }
+}
+
+fn stream_to_reader(stream: DataStream) -> StorageReader {
+    Box::pin(StreamReader::new(stream.map_err(map_s3_error)))
+}
+
+impl S3Storage {
+    pub fn from_env() -> Self {
+        let bucket_name = std::env::var("S3_BUCKET").expect("S3_BUCKET must be set");
+        let region_name = std::env::var("S3_REGION").unwrap_or("us-east-1".to_string());
+        let access_key = std::env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set");
+        let secret_key = std::env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set");
+
+        // A custom endpoint is used for S3 compatible services like MinIO
+        let region = match std::env::var("S3_ENDPOINT") {
+            Ok(endpoint) => Region::Custom {
+                region: region_name,
+                endpoint,
+            },
+            Err(_) => region_name.parse().expect("S3_REGION must be a valid region"),
+        };
+
+        let credentials = Credentials::new(Some(&access_key), Some(&secret_key), None, None, None)
+            .expect("Failed to initialize S3 credentials");
+
+        let bucket = Bucket::new(&bucket_name, region, credentials)
+            .expect("Failed to initialize S3 bucket");
+
+        let path_style = std::env::var("S3_PATH_STYLE").unwrap_or("true".to_string()) == "true";
+        let bucket = if path_style {
+            bucket.with_path_style()
+        } else {
+            bucket
+        };
+
+        S3Storage { bucket }
+    }
+
+    pub fn bucket_name(&self) -> String {
+        self.bucket.name()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Storage {
+    async fn put(
+        &self,
+        key: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> io::Result<u64> {
+        self.bucket
+            .put_object_stream(reader, key)
+            .await
+            .map(|response| response.uploaded_bytes() as u64)
+            .map_err(map_s3_error)
+    }
+
+    async fn get(&self, key: &str) -> io::Result<StorageReader> {
+        let response = self
+            .bucket
+            .get_object_stream(key)
+            .await
+            .map_err(map_s3_error)?;
+        Ok(stream_to_reader(response.bytes))
+    }
+
+    async fn get_range(&self, key: &str, start: u64, length: u64) -> io::Result<StorageReader> {
+        if length == 0 {
+            return Ok(Box::pin(io::Cursor::new(vec![])));
+        }
+
+        let command = Command::GetObjectRange {
+            start,
+            end: Some(start + length - 1),
+        };
+        let response = HyperRequest::new(&self.bucket, key, command)
+            .await
+            .map_err(map_s3_error)?
+            .response_data_to_stream()
+            .await
+            .map_err(map_s3_error)?;
+        Ok(stream_to_reader(response.bytes))
+    }
+
+    async fn size(&self, key: &str) -> io::Result<u64> {
+        let (head, _) = self.bucket.head_object(key).await.map_err(map_s3_error)?;
+        Ok(head.content_length.unwrap_or(0) as u64)
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        self.bucket
+            .delete_object(key)
+            .await
+            .map(|_| ())
+            .map_err(map_s3_error)
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool> {
+        match self.bucket.head_object(key).await.map_err(map_s3_error) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// S3 has no rename, the object is copied and the original removed.
+    /// Server side copies are limited to 5 GB by S3.
+    async fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        self.bucket
+            .copy_object_internal(from, to)
+            .await
+            .map_err(map_s3_error)?;
+        self.delete(from).await
+    }
+
+    async fn list(&self, prefix: &str) -> io::Result<Vec<String>> {
+        let pages = self
+            .bucket
+            .list(prefix.to_string(), None)
+            .await
+            .map_err(map_s3_error)?;
+
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| object.key)
+            .collect())
+    }
+}
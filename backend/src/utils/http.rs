@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// More ranges than this are answered with the whole content
+const MAX_RANGES: usize = 32;
+/// Characters which can stay as they are in `filename*` (RFC 5987)
+const FILENAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Inclusive byte range of a content
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn length(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    pub fn content_range(&self, size: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, size)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    /// No range or one which has to be ignored, the whole content is sent
+    Full,
+    /// Sorted ranges which don't overlap
+    Partial(Vec<ByteRange>),
+    /// None of the ranges starts inside the content
+    Unsatisfiable,
+}
+
+/// Date in the IMF-fixdate format used by HTTP headers
+pub fn http_date(date: &DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Download under the name, clients without support for `filename*` get an ASCII version
+pub fn attachment_disposition(file_name: &str) -> String {
+    let fallback = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        utf8_percent_encode(file_name, FILENAME_ENCODE_SET)
+    )
+}
+
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Parses a `Range` header for a content of the given size. Invalid headers are ignored as
+/// RFC 9110 allows, overlapping and adjacent ranges are merged.
+pub fn parse_range(value: &str, size: u64) -> RangeRequest {
+    let value = value.trim();
+    let Some(specs) = value
+        .get(..6)
+        .filter(|unit| unit.eq_ignore_ascii_case("bytes="))
+        .map(|_| &value[6..])
+    else {
+        return RangeRequest::Full;
+    };
+
+    let mut ranges = vec![];
+    let mut spec_count = 0;
+
+    for spec in specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        spec_count += 1;
+
+        let Some((start, end)) = spec.split_once('-') else {
+            return RangeRequest::Full;
+        };
+
+        let range = if start.is_empty() {
+            // Suffix range, the last bytes of the content
+            let Some(suffix) = parse_position(end) else {
+                return RangeRequest::Full;
+            };
+
+            if suffix == 0 || size == 0 {
+                continue;
+            }
+
+            ByteRange {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            }
+        } else {
+            let Some(start) = parse_position(start) else {
+                return RangeRequest::Full;
+            };
+            let end = if end.is_empty() {
+                None
+            } else {
+                match parse_position(end) {
+                    Some(end) if end >= start => Some(end),
+                    _ => return RangeRequest::Full,
+                }
+            };
+
+            if start >= size {
+                continue;
+            }
+
+            ByteRange {
+                start,
+                end: end.unwrap_or(u64::MAX).min(size - 1),
+            }
+        };
+
+        ranges.push(range);
+    }
+
+    if spec_count == 0 || spec_count > MAX_RANGES {
+        return RangeRequest::Full;
+    }
+
+    if ranges.is_empty() {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    RangeRequest::Partial(merged)
+}
+
+fn parse_position(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+/// Whether an `If-None-Match` or `If-Range` list contains the ETag. The weak comparison
+/// ignores the `W/` prefix, the strong one never matches weak tags.
+pub fn etag_matches(header: &str, etag: &str, weak: bool) -> bool {
+    let header = header.trim();
+    if header == "*" {
+        return true;
+    }
+
+    let normalize = |tag: &str| -> Option<String> {
+        let tag = tag.trim();
+        match tag.strip_prefix("W/") {
+            Some(tag) if weak => Some(tag.to_string()),
+            Some(_) => None,
+            None => Some(tag.to_string()),
+        }
+    };
+
+    let Some(etag) = normalize(etag) else {
+        return false;
+    };
+
+    header
+        .split(',')
+        .filter_map(normalize)
+        .any(|tag| tag == etag)
+}
+
+/// Media ranges of an `Accept` header with their quality, lowercased and in header order
+pub fn parse_accept(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media_type = parts.next()?.to_ascii_lowercase();
+            let quality = parts
+                .find_map(|param| param.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            Some((media_type, quality))
+        })
+        .collect()
+}
@@ -1,3 +1,7 @@
 pub mod string;
 pub mod auth;
-pub(crate) mod validation;
\ No newline at end of file
+pub mod totp;
+pub(crate) mod validation;
+pub mod search_query;
+pub mod http;
+pub mod process;
\ No newline at end of file
@@ -0,0 +1,58 @@
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+/// Limit of a single run in seconds, read from the variable `name`
+pub fn timeout_from_env(name: &str, default_seconds: u64) -> Duration {
+    Duration::from_secs(
+        std::env::var(name)
+            .ok()
+            .and_then(|seconds| seconds.parse::<u64>().ok())
+            .unwrap_or(default_seconds),
+    )
+}
+
+/// Whether the binary is installed, checked by letting it print its version with `version_arg`
+pub fn is_available(binary: &Path, version_arg: &str) -> bool {
+    std::process::Command::new(binary)
+        .arg(version_arg)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Runs the command and returns what it wrote to stdout. A run which exits unsuccessfully fails
+/// with its stderr, one taking longer than `timeout` is killed.
+pub async fn run(command: &mut Command, timeout: Duration) -> io::Result<Vec<u8>> {
+    let program = Path::new(command.as_std().get_program())
+        .display()
+        .to_string();
+
+    // The child is killed once the timed out future is dropped
+    let output = tokio::time::timeout(
+        timeout,
+        command.stdin(Stdio::null()).kill_on_drop(true).output(),
+    )
+    .await
+    .map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("{} timed out after {}s", program, timeout.as_secs()),
+        )
+    })??;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
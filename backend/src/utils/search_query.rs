@@ -0,0 +1,300 @@
+use chrono::NaiveDate;
+
+use crate::model::internal::file_type::FileType;
+use crate::response::error_handling::AppError;
+
+/// A parsed search like `report type:document size:>1MB in:"Work/2024"`.
+/// Terms have to match all, values of a filter separated by commas match any.
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<SearchTerm>,
+    pub filters: Vec<SearchFilter>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchTerm {
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchFilter {
+    Type {
+        file_types: Vec<FileType>,
+        folders: bool,
+    },
+    Size(Comparison, i64),
+    Modified(Comparison, NaiveDate),
+    /// Path segments of the folder, its subfolders are included
+    In(Vec<String>),
+    Is(SearchFlag),
+    Ext(Vec<String>),
+    /// Tag names, compared case-insensitively
+    Tag(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchFlag {
+    Favorite,
+    Shared,
+}
+
+struct Token {
+    key: Option<String>,
+    value: String,
+    quoted: bool,
+}
+
+const SIZE_UNITS: [(&str, i64); 5] = [
+    ("tb", 1024 * 1024 * 1024 * 1024),
+    ("gb", 1024 * 1024 * 1024),
+    ("mb", 1024 * 1024),
+    ("kb", 1024),
+    ("b", 1),
+];
+
+fn invalid(error: String) -> AppError {
+    AppError::UnprocessableEntity { error }
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Result<SearchQuery, AppError> {
+        let mut query = SearchQuery::default();
+
+        for token in Self::tokenize(input)? {
+            let Some(key) = token.key else {
+                query.terms.push(if token.quoted {
+                    SearchTerm::Phrase(token.value)
+                } else {
+                    SearchTerm::Word(token.value)
+                });
+                continue;
+            };
+
+            if token.value.is_empty() {
+                return Err(invalid(format!("Filter '{}:' needs a value", key)));
+            }
+
+            let filter = match key.to_lowercase().as_str() {
+                "type" => Self::parse_type(&token.value)?,
+                "size" => {
+                    let (comparison, size) = Self::split_comparison(&token.value);
+                    SearchFilter::Size(comparison, Self::parse_size(size)?)
+                }
+                "modified" => {
+                    let (comparison, date) = Self::split_comparison(&token.value);
+                    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+                        invalid(format!(
+                            "Invalid date '{}', expected e.g. modified:<2024-01-01",
+                            date
+                        ))
+                    })?;
+                    SearchFilter::Modified(comparison, date)
+                }
+                "in" => SearchFilter::In(
+                    token
+                        .value
+                        .split('/')
+                        .filter(|segment| !segment.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                ),
+                "is" => match token.value.to_lowercase().as_str() {
+                    "favorite" => SearchFilter::Is(SearchFlag::Favorite),
+                    "shared" => SearchFilter::Is(SearchFlag::Shared),
+                    other => {
+                        return Err(invalid(format!(
+                            "Unknown value 'is:{}', expected favorite or shared",
+                            other
+                        )))
+                    }
+                },
+                "ext" => SearchFilter::Ext(
+                    token
+                        .value
+                        .split(',')
+                        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                        .filter(|ext| !ext.is_empty())
+                        .collect(),
+                ),
+                "tag" => SearchFilter::Tag(
+                    token
+                        .value
+                        .split(',')
+                        .map(|tag| tag.trim().to_lowercase())
+                        .filter(|tag| !tag.is_empty())
+                        .collect(),
+                ),
+                other => {
+                    return Err(invalid(format!(
+                        "Unknown filter '{}', expected type, size, modified, in, is, ext or tag",
+                        other
+                    )))
+                }
+            };
+
+            query.filters.push(filter);
+        }
+
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.filters.is_empty()
+    }
+
+    /// Terms as a `websearch_to_tsquery` input, phrases stay quoted
+    pub fn content_query(&self) -> String {
+        self.terms
+            .iter()
+            .map(|term| match term {
+                SearchTerm::Word(word) => word.replace('"', ""),
+                SearchTerm::Phrase(phrase) => format!("\"{}\"", phrase.replace('"', "")),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Folders have no type, size or extension, so filtering by them only returns files
+    pub fn includes_folders(&self) -> bool {
+        self.filters.iter().all(|filter| match filter {
+            SearchFilter::Type { folders, .. } => *folders,
+            SearchFilter::Size(..) | SearchFilter::Ext(_) => false,
+            _ => true,
+        })
+    }
+
+    pub fn includes_files(&self) -> bool {
+        self.filters.iter().all(|filter| match filter {
+            SearchFilter::Type { file_types, .. } => !file_types.is_empty(),
+            _ => true,
+        })
+    }
+
+    /// Splits words at whitespace outside of quotes, `key:` before the value makes it a filter
+    fn tokenize(input: &str) -> Result<Vec<Token>, AppError> {
+        let mut tokens = vec![];
+        let mut chars = input.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let mut token = Token {
+                key: None,
+                value: String::new(),
+                quoted: false,
+            };
+            let mut in_quote = false;
+
+            while let Some(c) = chars.next_if(|c| in_quote || !c.is_whitespace()) {
+                match c {
+                    '"' => {
+                        in_quote = !in_quote;
+                        token.quoted = true;
+                    }
+                    ':' if !token.quoted && token.key.is_none() && !token.value.is_empty() => {
+                        token.key = Some(std::mem::take(&mut token.value));
+                    }
+                    c => token.value.push(c),
+                }
+            }
+
+            if in_quote {
+                return Err(invalid("Unterminated quote in search".to_string()));
+            }
+
+            if token.key.is_some() || !token.value.is_empty() {
+                tokens.push(token);
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    fn split_comparison(value: &str) -> (Comparison, &str) {
+        [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| {
+            value
+                .strip_prefix(prefix)
+                .map(|rest| (comparison, rest.trim()))
+        })
+        .unwrap_or((Comparison::Equal, value))
+    }
+
+    fn parse_size(value: &str) -> Result<i64, AppError> {
+        let error = || {
+            invalid(format!(
+                "Invalid size '{}', expected e.g. size:>100MB",
+                value
+            ))
+        };
+
+        let lower = value.to_lowercase();
+        let (number, unit) = SIZE_UNITS
+            .iter()
+            .find_map(|(suffix, unit)| lower.strip_suffix(suffix).map(|number| (number, *unit)))
+            .unwrap_or((lower.as_str(), 1));
+
+        let number = number.trim().parse::<f64>().map_err(|_| error())?;
+
+        if !number.is_finite() || number < 0.0 {
+            return Err(error());
+        }
+
+        Ok((number * unit as f64) as i64)
+    }
+
+    fn parse_type(value: &str) -> Result<SearchFilter, AppError> {
+        let mut file_types = vec![];
+        let mut folders = false;
+
+        for name in value.split(',') {
+            match name.to_lowercase().as_str() {
+                "folder" => folders = true,
+                "image" => file_types.extend([
+                    FileType::Image,
+                    FileType::LargeImage,
+                    FileType::RawImage,
+                ]),
+                "raw" => file_types.push(FileType::RawImage),
+                "video" => file_types.push(FileType::Video),
+                "audio" => file_types.push(FileType::Audio),
+                "document" => file_types.push(FileType::Document),
+                "archive" => file_types.push(FileType::Archive),
+                "text" => file_types.push(FileType::Editable),
+                "other" => file_types.push(FileType::Generic),
+                other => {
+                    return Err(invalid(format!(
+                        "Unknown type '{}', expected image, raw, video, audio, document, archive, text, other or folder",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(SearchFilter::Type {
+            file_types,
+            folders,
+        })
+    }
+}
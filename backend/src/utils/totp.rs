@@ -0,0 +1,80 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::RngCore;
+use sha1::Sha1;
+
+use crate::response::error_handling::AppError;
+
+const TOTP_ISSUER: &str = "Kosmos";
+const TOTP_SECRET_LENGTH: usize = 20;
+const TOTP_DIGITS: u32 = 6;
+const TOTP_PERIOD: i64 = 30;
+/// Codes of the previous and next time step are accepted as well, to allow for clock drift
+const TOTP_SKEW: i64 = 1;
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; TOTP_SECRET_LENGTH];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+/// The `otpauth://` URI which authenticator apps read from a QR code
+pub fn provisioning_uri(secret: &str, username: &str) -> String {
+    let label = utf8_percent_encode(&format!("{}:{}", TOTP_ISSUER, username), NON_ALPHANUMERIC)
+        .to_string();
+
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        label, secret, TOTP_ISSUER, TOTP_DIGITS, TOTP_PERIOD
+    )
+}
+
+/// Code of a time step as defined by RFC 6238, with HMAC-SHA1 as in RFC 4226
+fn generate_code(key: &[u8], step: i64) -> Result<u32, AppError> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).map_err(|e| {
+        tracing::error!("Error creating TOTP hmac: {}", e);
+        AppError::InternalError
+    })?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Ok(binary % 10u32.pow(TOTP_DIGITS))
+}
+
+/// Returns the time step the code belongs to, if it is valid right now
+pub fn verify_code(secret: &str, code: &str) -> Result<Option<i64>, AppError> {
+    let code = code.trim();
+
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let Ok(code) = code.parse::<u32>() else {
+        return Ok(None);
+    };
+
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).map_err(|e| {
+        tracing::error!("Error decoding TOTP secret: {}", e);
+        AppError::InternalError
+    })?;
+
+    let current_step = Utc::now().timestamp() / TOTP_PERIOD;
+
+    for step in current_step - TOTP_SKEW..=current_step + TOTP_SKEW {
+        if generate_code(&key, step)? == code {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
@@ -1,3 +1,4 @@
+use regex::Regex;
 use crate::response::error_handling::AppError;
 use crate::utils::string;
 
@@ -20,4 +21,15 @@ pub fn validate_password(password: &str) -> Result<(), AppError> {
         })?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn validate_color(color: &str) -> Result<(), AppError> {
+    let color_test = Regex::new(r"^#([0-9a-f]{6})$").unwrap();
+
+    if !color_test.is_match(color) {
+        Err(AppError::BadRequest {
+            error: Some("Invalid hex color".to_string()),
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use crate::utils::process;
+use crate::video::FrameExtractor;
+
+/// Frames are seeked to this share of the duration, the very first one is often black
+const POSTER_POSITION: f64 = 0.1;
+
+/// Runs taking longer are killed, sprites of long videos decode the whole stream
+const DEFAULT_TIMEOUT_SECONDS: u64 = 300;
+
+pub struct FfmpegFrameExtractor {
+    ffmpeg: PathBuf,
+    ffprobe: PathBuf,
+    timeout: Duration,
+}
+
+impl FfmpegFrameExtractor {
+    pub fn from_env() -> Self {
+        FfmpegFrameExtractor {
+            ffmpeg: PathBuf::from(std::env::var("FFMPEG_PATH").unwrap_or("ffmpeg".to_string())),
+            ffprobe: PathBuf::from(std::env::var("FFPROBE_PATH").unwrap_or("ffprobe".to_string())),
+            timeout: process::timeout_from_env("FFMPEG_TIMEOUT_SECONDS", DEFAULT_TIMEOUT_SECONDS),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        [&self.ffmpeg, &self.ffprobe]
+            .iter()
+            .all(|binary| process::is_available(binary, "-version"))
+    }
+
+    async fn run(&self, binary: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+        process::run(Command::new(binary).args(args), self.timeout).await
+    }
+
+    /// Duration in seconds, streams without a known duration report none
+    async fn duration(&self, path: &Path) -> io::Result<Option<f64>> {
+        let output = self
+            .run(
+                &self.ffprobe,
+                &[
+                    "-v",
+                    "error",
+                    "-show_entries",
+                    "format=duration",
+                    "-of",
+                    "default=noprint_wrappers=1:nokey=1",
+                    &path.to_string_lossy(),
+                ],
+            )
+            .await?;
+
+        Ok(String::from_utf8_lossy(&output)
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|duration| duration.is_finite() && *duration > 0.0))
+    }
+}
+
+#[async_trait]
+impl FrameExtractor for FfmpegFrameExtractor {
+    async fn poster_frame(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let position = self.duration(path).await?.unwrap_or(0.0) * POSTER_POSITION;
+
+        self.run(
+            &self.ffmpeg,
+            &[
+                "-v",
+                "error",
+                "-ss",
+                &format!("{:.3}", position),
+                "-i",
+                &path.to_string_lossy(),
+                "-frames:v",
+                "1",
+                "-f",
+                "image2pipe",
+                "-vcodec",
+                "png",
+                "-",
+            ],
+        )
+        .await
+    }
+
+    async fn sprite(
+        &self,
+        path: &Path,
+        columns: u32,
+        rows: u32,
+        tile_width: u32,
+    ) -> io::Result<Vec<u8>> {
+        let frames = columns * rows;
+        // Without a duration every frame is taken one second apart
+        let duration = self.duration(path).await?.unwrap_or(frames as f64);
+
+        let filter = format!(
+            "fps={}/{:.3},scale={}:-2,tile={}x{}",
+            frames, duration, tile_width, columns, rows
+        );
+
+        self.run(
+            &self.ffmpeg,
+            &[
+                "-v",
+                "error",
+                "-i",
+                &path.to_string_lossy(),
+                "-vf",
+                &filter,
+                "-frames:v",
+                "1",
+                "-f",
+                "image2pipe",
+                "-vcodec",
+                "png",
+                "-",
+            ],
+        )
+        .await
+    }
+}
@@ -0,0 +1,44 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::video::ffmpeg::FfmpegFrameExtractor;
+
+pub mod ffmpeg;
+
+pub type KosmosFrameExtractor = Arc<dyn FrameExtractor>;
+
+/// Renders still images of videos, which are turned into their formats.
+///
+/// Frames are returned as PNG encoded images.
+#[async_trait]
+pub trait FrameExtractor: Send + Sync {
+    /// A representative frame near the start of the video.
+    async fn poster_frame(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// `columns * rows` frames spread evenly over the video, each scaled to `tile_width`,
+    /// tiled row by row into a single image.
+    async fn sprite(
+        &self,
+        path: &Path,
+        columns: u32,
+        rows: u32,
+        tile_width: u32,
+    ) -> io::Result<Vec<u8>>;
+}
+
+/// Videos only get previews if ffmpeg is installed, its location can be set in `FFMPEG_PATH`
+/// and `FFPROBE_PATH`.
+pub fn init() -> Option<KosmosFrameExtractor> {
+    let extractor = FfmpegFrameExtractor::from_env();
+
+    if !extractor.is_available() {
+        tracing::warn!(name: "bootstrap", "ffmpeg not found, videos get no previews");
+        return None;
+    }
+
+    tracing::info!(name: "bootstrap", "Using ffmpeg for video previews");
+    Some(Arc::new(extractor))
+}